@@ -0,0 +1,134 @@
+use crate::record::{write_cut, CutOutOpts};
+use anyhow::Error;
+use colored::*;
+use filmreel::cut::Register;
+use lazy_static::lazy_static;
+use log::error;
+use serde_json::Value;
+use std::{
+    path::PathBuf,
+    process,
+    sync::{Mutex, Once},
+};
+
+/// The exit code used when a record is interrupted by SIGINT or SIGTERM
+pub const ABORT_EXIT_CODE: i32 = 130;
+
+/// The ignored Cut Variable naming the frame that was in flight when a record was interrupted
+pub const ABORTED_VAR: &str = "aborted";
+
+/// AbortState is the record context needed to dump the cut register upon interruption
+struct AbortState {
+    cut_out: Option<PathBuf>,
+    cut_out_opts: CutOutOpts,
+    reel_name: String,
+    register: Register,
+    frame: Option<String>,
+}
+
+lazy_static! {
+    static ref ABORT_STATE: Mutex<Option<AbortState>> = Mutex::new(None);
+}
+
+/// AbortGuard writes the last known cut register to `--cut-out` and exits with
+/// [`ABORT_EXIT_CODE`] if the process is interrupted while the guard is alive. The in-flight
+/// request is abandoned when the process exits.
+pub struct AbortGuard;
+
+impl AbortGuard {
+    /// Installs the SIGINT and SIGTERM handler, the handler is only registered once per process
+    pub fn install<T: Into<String>>(
+        cut_out: Option<PathBuf>,
+        cut_out_opts: CutOutOpts,
+        reel_name: T,
+        register: &Register,
+    ) -> Result<Self, Error> {
+        static HANDLER: Once = Once::new();
+        let mut result = Ok(());
+        HANDLER.call_once(|| result = ctrlc::set_handler(on_abort));
+        result?;
+
+        set_state(Some(AbortState {
+            cut_out,
+            cut_out_opts,
+            reel_name: reel_name.into(),
+            register: register.clone(),
+            frame: None,
+        }));
+        Ok(Self)
+    }
+
+    /// Records the frame about to be taken alongside the cut register it is taken with
+    pub fn update(&self, frame: &str, register: &Register) {
+        if let Ok(mut state) = ABORT_STATE.lock() {
+            if let Some(state) = state.as_mut() {
+                state.frame = Some(frame.to_string());
+                state.register = register.clone();
+            }
+        }
+    }
+}
+
+impl Drop for AbortGuard {
+    fn drop(&mut self) {
+        set_state(None);
+    }
+}
+
+fn set_state(new_state: Option<AbortState>) {
+    if let Ok(mut state) = ABORT_STATE.lock() {
+        *state = new_state;
+    }
+}
+
+fn on_abort() {
+    if let Ok(state) = ABORT_STATE.lock() {
+        if let Some(state) = state.as_ref() {
+            error!(
+                "{} {}",
+                "Interrupted:".red(),
+                state.frame.as_deref().unwrap_or_default()
+            );
+            if let Err(e) = write_cut(
+                &state.cut_out,
+                state.cut_out_opts,
+                &aborted_register(&state.register, state.frame.as_deref()),
+                &state.reel_name,
+                true,
+            ) {
+                error!("{} {:#}", "Error:".red(), e);
+            }
+        }
+    }
+    process::exit(ABORT_EXIT_CODE);
+}
+
+/// aborted_register marks a copy of the register with the frame that was interrupted, the marker
+/// is an ignored variable so that the register can be reused as an input cut
+fn aborted_register(register: &Register, frame: Option<&str>) -> Register {
+    let mut register = register.clone();
+    let marker = frame.map(Value::from).unwrap_or(Value::Bool(true));
+    register
+        .write_operation(ABORTED_VAR, marker)
+        .expect("valid Cut Variable name");
+    register
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filmreel::register;
+    use serde_json::json;
+
+    #[test]
+    fn test_aborted_register() {
+        let register = register!({"USER_ID" => json!("BIG_BEN")});
+        let aborted = aborted_register(&register, Some("usr.02s.getuser.fr.json"));
+        assert_eq!(
+            Some(&json!("usr.02s.getuser.fr.json")),
+            aborted.get(ABORTED_VAR)
+        );
+        assert_eq!(register.get("USER_ID"), aborted.get("USER_ID"));
+        assert!(filmreel::cut::is_ignored(ABORTED_VAR));
+    }
+}
@@ -0,0 +1,180 @@
+#[cfg(feature = "grpc")]
+use crate::grpc::GrpcurlOutput;
+use crate::{exit_code, generate::Generator, take::Mismatch, ToStringCanonical, ToStringHidden};
+use anyhow::{Context, Error};
+use filmreel::{cut::Register, frame::Frame, FrError};
+use log::error;
+use serde_json::{json, Value};
+use std::{fs, path::Path};
+
+/// write_artifacts dumps the failure context of a frame into the `--artifacts` directory provided,
+/// every file is prefixed with the file stem of the frame filename:
+///
+/// - `<stem>.frame.json`: the hydrated frame, hidden variables are redacted
+/// - `<stem>.cut.json`: a snapshot of the cut register, hidden variables remain hidden
+/// - `<stem>.response.json`: the actual response, if one was received
+/// - `<stem>.grpcurl.stdout.txt` and `<stem>.grpcurl.stderr.txt`: the raw output of `grpcurl`, if
+///   it could not be parsed
+/// - `<stem>.diff.txt`: the uncoloured mismatch diff, if one was produced
+/// - `<stem>.error.txt`: the full redacted error chain, its exit code, the `--seed` needed to replay
+///   generated values, and the description, owner, and link of the frame
+pub fn write_artifacts<P: AsRef<Path>>(
+    dir: P,
+    filename: &str,
+    frame: &Frame,
+    register: &Register,
+    err: &Error,
+    generator: &Generator,
+) -> Result<(), Error> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir).context("unable to create --artifacts directory")?;
+    let stem = filename.trim_end_matches(".fr.json");
+    let write = |suffix: &str, contents: String| -> Result<(), Error> {
+        let path = dir.join(format!("{stem}.{suffix}"));
+        fs::write(&path, contents).context(format!("unable to write artifact => {path:?}"))
+    };
+
+    error!("writing failure artifacts to {}...", dir.to_string_lossy());
+    write("frame.json", register.redact(frame.to_string_canonical()?))?;
+    write("cut.json", register.to_string_hidden()? + "\n")?;
+    if let Some(mismatch) = err.downcast_ref::<Mismatch>() {
+        write("response.json", mismatch.response.clone())?;
+        write("diff.txt", mismatch.diff.clone())?;
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(output) = err.downcast_ref::<GrpcurlOutput>() {
+        write("grpcurl.stdout.txt", register.redact(&output.stdout))?;
+        write("grpcurl.stderr.txt", register.redact(&output.stderr))?;
+    }
+    let mut error_txt = format!(
+        "{}\nexit code: {}\n",
+        register.redact(format!("{err:#}")),
+        exit_code(err)
+    );
+    if let Some(fr_err) = err.chain().find_map(|e| e.downcast_ref::<FrError>()) {
+        error_txt.push_str(&format!("error code: {}\n", fr_err.code()));
+    }
+    if generator.is_used() {
+        error_txt.push_str(&format!("seed: {}\n", generator.seed()));
+    }
+    for (field, value) in [
+        ("description", &frame.meta.description),
+        ("owner", &frame.meta.owner),
+        ("link", &frame.meta.link),
+    ] {
+        if let Some(value) = value {
+            error_txt.push_str(&format!("{field}: {value}\n"));
+        }
+    }
+    write("error.txt", error_txt)?;
+
+    Ok(())
+}
+
+/// error_report describes the error of a frame in the report of a record, along with the raw
+/// output of `grpcurl` when it could not be parsed
+#[cfg_attr(not(feature = "grpc"), allow(unused_mut))]
+pub fn error_report(err: &Error, register: &Register) -> Value {
+    let mut report = json!({
+        "message": register.redact(format!("{err:#}")),
+        "exit_code": exit_code(err),
+    });
+    #[cfg(feature = "grpc")]
+    if let Some(output) = err.downcast_ref::<GrpcurlOutput>() {
+        report["grpcurl"] = json!({
+            "exit_code": output.exit_code,
+            "stdout": register.redact(&output.stdout),
+            "stderr": register.redact(&output.stderr),
+        });
+    }
+    report
+}
+
+/// strip_ansi removes the ANSI escape sequences used to colour terminal output
+pub fn strip_ansi<T: AsRef<str>>(input: T) -> String {
+    let mut output = String::new();
+    let mut chars = input.as_ref().chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\u{1b}' {
+            // skip the Control Sequence Introducer up to and including its final byte
+            for esc in chars.by_ref() {
+                if esc.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        output.push(ch);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(
+            "< left / > right",
+            strip_ansi("\u{1b}[31m<\u{1b}[0m left / \u{1b}[1;32m>\u{1b}[0m right")
+        );
+    }
+
+    #[test]
+    fn test_write_artifacts_redacted() {
+        let dir = std::env::temp_dir().join(format!("dark_artifacts_{}", std::process::id()));
+        let frame: Frame = serde_json::from_value(json!({
+            "protocol": "HTTP",
+            "request": {"uri": "GET /", "header": {"Authorization": "Bearer jWt"}},
+            "response": {"status": 200},
+            "link": "https://wiki.example.com/usr"
+        }))
+        .unwrap();
+        let register = filmreel::register!({"_TOKEN" => "jWt"});
+        let err = anyhow::anyhow!("unexpected token jWt");
+        write_artifacts(
+            &dir,
+            "usr.01s.fr.json",
+            &frame,
+            &register,
+            &err,
+            &Generator::new(None),
+        )
+        .unwrap();
+        let frame_json = fs::read_to_string(dir.join("usr.01s.frame.json")).unwrap();
+        let error_txt = fs::read_to_string(dir.join("usr.01s.error.txt")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(frame_json.contains("Bearer ${_HIDDEN}"));
+        assert!(!frame_json.contains("jWt"));
+        assert!(error_txt.starts_with("unexpected token ${_HIDDEN}\n"));
+        assert!(error_txt.ends_with("link: https://wiki.example.com/usr\n"));
+    }
+
+    #[cfg(feature = "grpc")]
+    #[test]
+    fn test_error_report() {
+        let err = anyhow::anyhow!("expected value at line 1 column 1")
+            .context(GrpcurlOutput::new(
+                "grpcurl response could not be parsed as JSON",
+                Some(0),
+                b"WARNING: token jWt expires soon\n{}",
+                b"",
+            ))
+            .context("usr.01s.createuser.fr.json");
+        let register = filmreel::register!({"_TOKEN" => "jWt"});
+        assert_eq!(
+            json!({
+                "message": "usr.01s.createuser.fr.json: grpcurl response could not be parsed as \
+                            JSON: expected value at line 1 column 1",
+                "exit_code": 1,
+                "grpcurl": {
+                    "exit_code": 0,
+                    "stdout": "WARNING: token ${_HIDDEN} expires soon\n{}",
+                    "stderr": ""
+                }
+            }),
+            error_report(&err, &register)
+        );
+    }
+}
@@ -0,0 +1,753 @@
+use crate::{
+    params::BaseParams, record::init_components, take::read_frame, Coverage, ProtoCoverage,
+};
+use anyhow::{anyhow, Context, Error};
+use filmreel::{
+    cut::Register,
+    frame::Protocol,
+    reel::{MetaFrame, Reel},
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path};
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Operation is a single method and path template of an OpenAPI spec, along with the response
+/// status codes it declares: `"200"`, a `"2XX"` range, or `"default"`
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Operation {
+    pub method: String,
+    pub path: String,
+    pub responses: Vec<String>,
+}
+
+/// OpenApiSpec holds the operations of an OpenAPI 3 or Swagger 2 JSON document along with the
+/// base paths of its servers, a frame path is matched with or without a base path prefix
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct OpenApiSpec {
+    pub base_paths: Vec<String>,
+    pub operations: Vec<Operation>,
+}
+
+impl OpenApiSpec {
+    pub fn from_path(path: &Path) -> Result<Self, Error> {
+        let json = fs::read_to_string(path).context(format!("unable to read {path:?}"))?;
+        let spec: Value =
+            serde_json::from_str(&json).context(format!("{path:?} is not a JSON OpenAPI spec"))?;
+        Self::from_value(&spec).context(format!("{path:?}"))
+    }
+
+    pub fn from_value(spec: &Value) -> Result<Self, Error> {
+        let paths = spec
+            .get("paths")
+            .and_then(Value::as_object)
+            .ok_or_else(|| anyhow!("OpenAPI spec is missing a \"paths\" object"))?;
+        let mut operations = vec![];
+        for (path, item) in paths {
+            for method in METHODS {
+                let responses = match item.get(method) {
+                    Some(op) => op.get("responses").and_then(Value::as_object),
+                    None => continue,
+                };
+                operations.push(Operation {
+                    method: method.to_uppercase(),
+                    path: path.clone(),
+                    responses: responses
+                        .map(|r| r.keys().cloned().collect())
+                        .unwrap_or_default(),
+                });
+            }
+        }
+
+        // OpenAPI 3 server urls and the Swagger 2 basePath
+        let servers = spec.get("servers").and_then(Value::as_array);
+        let mut base_paths: Vec<String> = servers
+            .into_iter()
+            .flatten()
+            .filter_map(|s| s.get("url").and_then(Value::as_str))
+            .chain(spec.get("basePath").and_then(Value::as_str))
+            .map(|url| url_path(url).trim_end_matches('/').to_string())
+            .filter(|path| !path.is_empty())
+            .collect();
+        base_paths.sort();
+        base_paths.dedup();
+        Ok(Self {
+            base_paths,
+            operations,
+        })
+    }
+
+    /// find returns the index of the operation matching the method and path of a frame uri, the
+    /// path template with the most literal segments is preferred: `/users/me` over `/users/{id}`
+    fn find(&self, method: &str, path: &str) -> Option<usize> {
+        let path = path.trim_end_matches('/');
+        let candidates = std::iter::once(path).chain(
+            self.base_paths
+                .iter()
+                .filter_map(|base| path.strip_prefix(base.as_str())),
+        );
+        candidates
+            .flat_map(|path| {
+                self.operations
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, op)| op.method == method)
+                    .filter_map(move |(i, op)| path_matches(&op.path, path).map(|n| (n, i)))
+            })
+            .max_by_key(|(literals, i)| (*literals, std::cmp::Reverse(*i)))
+            .map(|(_, i)| i)
+    }
+}
+
+/// OperationCoverage is an operation of the spec along with the frames exercising it and whether
+/// each declared response status is expected by one of those frames
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct OperationCoverage {
+    pub method: String,
+    pub path: String,
+    pub frames: Vec<String>,
+    pub responses: BTreeMap<String, bool>,
+}
+
+/// CoverageReport is the coverage of the declared responses of an OpenAPI spec by the expected
+/// response statuses of the HTTP frames of a reel, printed by `dark reel coverage`. Frame uris are
+/// hydrated with the Cut Variables of the register that are known before the reel is run, such as
+/// a `${URI_METHOD}` of the cut file.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct CoverageReport {
+    /// the percentage of declared responses exercised
+    pub coverage: f64,
+    pub covered: usize,
+    pub total: usize,
+    pub operations: Vec<OperationCoverage>,
+    /// the declared responses that are not exercised: `"GET /users/{id} 404"`
+    pub untested: Vec<String>,
+    /// the HTTP frames matching no operation of the spec
+    pub unmatched: Vec<String>,
+}
+
+impl CoverageReport {
+    pub fn new(
+        spec: &OpenApiSpec,
+        frames: &[MetaFrame],
+        register: &Register,
+        base_params: &BaseParams,
+    ) -> Result<Self, Error> {
+        let mut operations: Vec<OperationCoverage> = spec
+            .operations
+            .iter()
+            .map(|op| OperationCoverage {
+                method: op.method.clone(),
+                path: op.path.clone(),
+                frames: vec![],
+                responses: op.responses.iter().map(|r| (r.clone(), false)).collect(),
+            })
+            .collect();
+        let mut unmatched = vec![];
+
+        for meta_frame in frames {
+            let filename = meta_frame.get_filename();
+            let frame = read_frame(meta_frame, base_params).context(filename.clone())?;
+            if frame.protocol != Protocol::HTTP {
+                continue;
+            }
+            let uri = hydrate_uri(frame.get_request().get_uri(), register)?;
+            let (method, path) = uri.split_once(' ').unwrap_or(("", &uri));
+            let op = match spec.find(method, uri_path(path.trim())) {
+                Some(i) => &mut operations[i],
+                None => {
+                    unmatched.push(filename);
+                    continue;
+                }
+            };
+            op.frames.push(filename);
+            let statuses = std::iter::once(&frame.response)
+                .chain(frame.response_any_of.iter())
+                .map(|response| response.status);
+            for status in statuses {
+                if let Some(key) = response_key(op.responses.keys(), status) {
+                    op.responses.insert(key, true);
+                }
+            }
+        }
+
+        let mut untested = vec![];
+        let (mut covered, mut total) = (0, 0);
+        for op in operations.iter() {
+            for (response, exercised) in op.responses.iter() {
+                total += 1;
+                match exercised {
+                    true => covered += 1,
+                    false => untested.push(format!("{} {} {}", op.method, op.path, response)),
+                }
+            }
+        }
+        Ok(Self {
+            coverage: percentage(covered, total),
+            covered,
+            total,
+            operations,
+            untested,
+            unmatched,
+        })
+    }
+
+    /// to_html renders the report as a standalone HTML page
+    pub fn to_html(&self) -> String {
+        let mut out = String::from("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\">");
+        out.push_str("<title>OpenAPI coverage</title></head>\n<body>\n");
+        let _ = writeln!(
+            out,
+            "<h1>OpenAPI coverage: {}% ({}/{} responses)</h1>",
+            self.coverage, self.covered, self.total
+        );
+        out.push_str("<table border=\"1\">\n");
+        out.push_str("<tr><th>Operation</th><th>Response</th><th>Frames</th></tr>\n");
+        for op in self.operations.iter() {
+            let frames = escape(&op.frames.join(", "));
+            for (response, exercised) in op.responses.iter() {
+                let _ = writeln!(
+                    out,
+                    "<tr style=\"background:{}\"><td>{} {}</td><td>{}</td><td>{}</td></tr>",
+                    if *exercised { "#dfd" } else { "#fdd" },
+                    op.method,
+                    escape(&op.path),
+                    escape(response),
+                    frames
+                );
+            }
+        }
+        out.push_str("</table>\n");
+        if !self.unmatched.is_empty() {
+            out.push_str("<h2>Frames matching no operation</h2>\n<ul>\n");
+            for filename in self.unmatched.iter() {
+                let _ = writeln!(out, "<li>{}</li>", escape(filename));
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+/// MethodCoverage is an RPC declared by the proto files along with the frames exercising it
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct MethodCoverage {
+    pub method: String,
+    pub frames: Vec<String>,
+}
+
+/// MethodCoverageReport is the coverage of the RPCs declared by proto files by the gRPC frames of
+/// a reel, printed by `dark reel proto-coverage`
+#[derive(Serialize, Debug, PartialEq)]
+pub struct MethodCoverageReport {
+    /// the percentage of declared RPCs exercised
+    pub coverage: f64,
+    pub covered: usize,
+    pub total: usize,
+    pub methods: Vec<MethodCoverage>,
+    /// the declared RPCs that are not exercised: `"helloworld.Greeter/SayHello"`
+    pub untested: Vec<String>,
+    /// the gRPC frames calling no declared RPC
+    pub unmatched: Vec<String>,
+}
+
+impl MethodCoverageReport {
+    pub fn new(
+        declared: Vec<String>,
+        frames: &[MetaFrame],
+        register: &Register,
+        base_params: &BaseParams,
+    ) -> Result<Self, Error> {
+        let mut methods: Vec<MethodCoverage> = declared
+            .into_iter()
+            .map(|method| MethodCoverage {
+                method,
+                frames: vec![],
+            })
+            .collect();
+        let mut unmatched = vec![];
+        for meta_frame in frames {
+            let filename = meta_frame.get_filename();
+            let frame = read_frame(meta_frame, base_params).context(filename.clone())?;
+            if frame.protocol != Protocol::GRPC {
+                continue;
+            }
+            let uri = hydrate_uri(frame.get_request().get_uri(), register)?;
+            let uri = uri.trim().trim_start_matches('/');
+            match methods.iter_mut().find(|m| m.method == uri) {
+                Some(method) => method.frames.push(filename),
+                None => unmatched.push(filename),
+            }
+        }
+
+        let untested: Vec<String> = methods
+            .iter()
+            .filter(|m| m.frames.is_empty())
+            .map(|m| m.method.clone())
+            .collect();
+        let total = methods.len();
+        let covered = total - untested.len();
+        Ok(Self {
+            coverage: percentage(covered, total),
+            covered,
+            total,
+            methods,
+            untested,
+            unmatched,
+        })
+    }
+
+    /// to_html renders the report as a standalone HTML page
+    pub fn to_html(&self) -> String {
+        let mut out = String::from("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\">");
+        out.push_str("<title>Proto coverage</title></head>\n<body>\n");
+        let _ = writeln!(
+            out,
+            "<h1>Proto coverage: {}% ({}/{} methods)</h1>",
+            self.coverage, self.covered, self.total
+        );
+        out.push_str("<table border=\"1\">\n<tr><th>Method</th><th>Frames</th></tr>\n");
+        for method in self.methods.iter() {
+            let _ = writeln!(
+                out,
+                "<tr style=\"background:{}\"><td>{}</td><td>{}</td></tr>",
+                if method.frames.is_empty() {
+                    "#fdd"
+                } else {
+                    "#dfd"
+                },
+                escape(&method.method),
+                escape(&method.frames.join(", "))
+            );
+        }
+        out.push_str("</table>\n");
+        if !self.unmatched.is_empty() {
+            out.push_str("<h2>Frames calling no declared method</h2>\n<ul>\n");
+            for filename in self.unmatched.iter() {
+                let _ = writeln!(out, "<li>{}</li>", escape(filename));
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+/// proto_methods returns the `package.Service/Method` name of every rpc declared by the source of
+/// a proto file
+pub fn proto_methods(source: &str) -> Vec<String> {
+    let tokens = proto_tokens(source);
+    let mut methods = vec![];
+    let mut package = String::new();
+    // the service being declared and the brace depth of its body
+    let mut service: Option<(&str, usize)> = None;
+    let mut depth: usize = 0;
+    let mut iter = tokens.iter().peekable();
+    while let Some(token) = iter.next() {
+        match *token {
+            "{" => depth += 1,
+            "}" => {
+                depth = depth.saturating_sub(1);
+                if service.is_some_and(|(_, body)| body > depth) {
+                    service = None;
+                }
+            }
+            "package" if depth == 0 => {
+                package = iter.next().map_or("", |p| *p).to_string();
+            }
+            "service" if depth == 0 => {
+                service = iter.next().map(|name| (*name, depth + 1));
+            }
+            "rpc" => {
+                if let (Some((name, _)), Some(method)) = (service, iter.next()) {
+                    let service = match package.is_empty() {
+                        true => name.to_string(),
+                        false => format!("{package}.{name}"),
+                    };
+                    methods.push(format!("{service}/{method}"));
+                }
+            }
+            _ => (),
+        }
+    }
+    methods
+}
+
+/// proto_tokens splits the source of a proto file into words and the `{};()=` punctuation,
+/// dropping comments and string literals
+fn proto_tokens(source: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut start: Option<usize> = None;
+    let mut chars = source.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let comment = c == '/' && matches!(chars.peek(), Some((_, '/' | '*')));
+        if c.is_whitespace() || comment || c == '"' || c == '\'' || "{};()=<>,[]".contains(c) {
+            if let Some(s) = start.take() {
+                tokens.push(&source[s..i]);
+            }
+        } else {
+            start.get_or_insert(i);
+            continue;
+        }
+        match c {
+            '/' if comment => {
+                let block = matches!(chars.next(), Some((_, '*')));
+                let mut prev = ' ';
+                for (_, c) in chars.by_ref() {
+                    if (!block && c == '\n') || (block && prev == '*' && c == '/') {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            '"' | '\'' => {
+                let mut escaped = false;
+                for (_, q) in chars.by_ref() {
+                    if q == c && !escaped {
+                        break;
+                    }
+                    escaped = q == '\\' && !escaped;
+                }
+            }
+            _ if !c.is_whitespace() => tokens.push(&source[i..i + c.len_utf8()]),
+            _ => (),
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(&source[s..]);
+    }
+    tokens
+}
+
+fn percentage(covered: usize, total: usize) -> f64 {
+    match total {
+        0 => 100.0,
+        _ => (covered as f64 * 1000.0 / total as f64).round() / 10.0,
+    }
+}
+
+/// hydrate_uri replaces the Cut Variables of a frame uri that are present in the register
+fn hydrate_uri(uri: String, register: &Register) -> Result<String, Error> {
+    let mut hydrated = Value::String(uri.clone());
+    for mat in register.read_match(&uri)? {
+        register.read_operation(mat, &mut hydrated, false)?;
+    }
+    Ok(hydrated.as_str().map_or(uri, String::from))
+}
+
+/// path_matches returns the number of literal segments of the path template if the path matches
+/// it, a `{param}` segment matches any non-empty segment
+fn path_matches(template: &str, path: &str) -> Option<usize> {
+    let template: Vec<&str> = template.trim_end_matches('/').split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    if template.len() != path.len() {
+        return None;
+    }
+    let mut literals = 0;
+    for (t, p) in template.iter().zip(path.iter()) {
+        if !t.contains('{') {
+            if t != p {
+                return None;
+            }
+            literals += usize::from(!t.is_empty());
+            continue;
+        }
+        if p.is_empty() || !segment_matches(t, p) {
+            return None;
+        }
+    }
+    Some(literals)
+}
+
+/// segment_matches matches a path segment holding parameters, such as `{id}.json`, by the literal
+/// text between its parameters
+fn segment_matches(template: &str, segment: &str) -> bool {
+    let literals: Vec<&str> = template
+        .split('{')
+        .enumerate()
+        .map(|(i, part)| match i {
+            0 => part,
+            _ => part.split_once('}').map_or("", |(_, lit)| lit),
+        })
+        .collect();
+    let (first, last) = (literals[0], literals[literals.len() - 1]);
+    if !segment.starts_with(first) || !segment[first.len()..].ends_with(last) {
+        return false;
+    }
+    let mut rest = &segment[first.len()..segment.len() - last.len()];
+    for lit in &literals[1..literals.len() - 1] {
+        match rest.find(lit) {
+            Some(i) => rest = &rest[i + lit.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// response_key returns the declared response matching a status: the exact status code, then its
+/// `"2XX"` range, then `"default"`
+fn response_key<'a, I>(keys: I, status: u32) -> Option<String>
+where
+    I: Iterator<Item = &'a String> + Clone,
+{
+    let code = status.to_string();
+    let range = format!("{}XX", status / 100);
+    let find = |want: &str| keys.clone().find(|k| k.eq_ignore_ascii_case(want)).cloned();
+    find(&code)
+        .or_else(|| find(&range))
+        .or_else(|| find("default"))
+}
+
+/// url_path returns the path of a url, or the string given if it holds no scheme
+fn url_path(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("", |i| &rest[i..]),
+        None => url,
+    }
+}
+
+/// uri_path returns the path of a frame request uri without its query string or fragment
+fn uri_path(uri: &str) -> &str {
+    let path = url_path(uri);
+    path.split(['?', '#']).next().unwrap_or_default()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// reel_frames returns the frames of a reel preceded by the frames of its components, along with
+/// the register of the cut files
+fn reel_frames(
+    reel_path: &Path,
+    reel_name: &str,
+    component: Vec<String>,
+) -> Result<(Vec<MetaFrame>, Register), Error> {
+    let reel = Reel::new(reel_path, reel_name, None)?;
+    let (comp_reels, mut register) = init_components(component)?;
+    let cut_path = reel.get_default_cut_path();
+    if cut_path.is_file() {
+        register.single_merge(Register::try_from(cut_path)?);
+    }
+    let frames: Vec<MetaFrame> = comp_reels.into_iter().flatten().chain(reel).collect();
+    Ok((frames, register))
+}
+
+fn fail_under(coverage: f64, fail_under: Option<u8>) -> Result<(), Error> {
+    match fail_under {
+        Some(min) if coverage < min as f64 => {
+            Err(anyhow!("coverage {}% is below {}%", coverage, min))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn cmd_coverage(cmd: Coverage, base_params: BaseParams) -> Result<(), Error> {
+    let spec = OpenApiSpec::from_path(&cmd.spec)?;
+    let (frames, register) = reel_frames(&cmd.reel_path, &cmd.reel_name, cmd.component)?;
+
+    let report = CoverageReport::new(&spec, &frames, &register, &base_params)?;
+    match cmd.html {
+        true => print!("{}", report.to_html()),
+        false => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    fail_under(report.coverage, cmd.fail_under)
+}
+
+pub fn cmd_proto_coverage(cmd: ProtoCoverage, base_params: BaseParams) -> Result<(), Error> {
+    if base_params.proto.is_empty() {
+        return Err(anyhow!("no --proto files were provided"));
+    }
+    let mut methods = vec![];
+    for path in base_params.proto.iter() {
+        let source = fs::read_to_string(path).context(format!("unable to read {path:?}"))?;
+        methods.extend(proto_methods(&source));
+    }
+    let (frames, register) = reel_frames(&cmd.reel_path, &cmd.reel_name, cmd.component)?;
+
+    let report = MethodCoverageReport::new(methods, &frames, &register, &base_params)?;
+    match cmd.html {
+        true => print!("{}", report.to_html()),
+        false => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    fail_under(report.coverage, cmd.fail_under)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Command;
+    use argh::FromArgs;
+    use filmreel::{frame::Frame, register};
+    use rstest::*;
+    use serde_json::json;
+
+    fn base_params() -> BaseParams {
+        BaseParams::from(&Command::from_args(&["dark"], &["version"]).unwrap())
+    }
+
+    fn frame(uri: &str, status: u32) -> Frame<'static> {
+        serde_json::from_value(json!({
+            "protocol": "HTTP",
+            "request": {"uri": uri},
+            "response": {"status": status}
+        }))
+        .unwrap()
+    }
+
+    #[rstest(
+        template,
+        path,
+        expected,
+        case("/users/{id}", "/users/${USER_ID}", Some(1)),
+        case("/users/me", "/users/me", Some(2)),
+        case("/users/{id}", "/users", None),
+        case("/files/{name}.{ext}", "/files/report.pdf", Some(1)),
+        case("/files/{name}.json", "/files/report.pdf", None)
+    )]
+    fn test_path_matches(template: &str, path: &str, expected: Option<usize>) {
+        assert_eq!(expected, path_matches(template, path));
+    }
+
+    #[test]
+    fn test_coverage_report() {
+        let spec = OpenApiSpec::from_value(&json!({
+            "openapi": "3.0.0",
+            "servers": [{"url": "https://api.example.com/v1"}],
+            "paths": {
+                "/users": {
+                    "post": {"responses": {"201": {}, "400": {}}}
+                },
+                "/users/{id}": {
+                    "parameters": [],
+                    "get": {"responses": {"2XX": {}, "404": {}}},
+                    "delete": {"responses": {"default": {}}}
+                },
+                "/users/me": {
+                    "get": {"responses": {"200": {}}}
+                }
+            }
+        }))
+        .unwrap();
+        assert_eq!(vec!["/v1".to_string()], spec.base_paths);
+
+        let reel = Reel::from_frames(vec![
+            ("usr.01s.create.fr.json", frame("${METHOD} /v1/users", 201)),
+            (
+                "usr.02s.get.fr.json",
+                frame("GET /users/${ID}?fields=email", 200),
+            ),
+            ("usr.03s.me.fr.json", frame("GET /users/me", 200)),
+            ("usr.04s.list.fr.json", frame("GET /users", 200)),
+        ])
+        .unwrap();
+        let frames: Vec<MetaFrame> = reel.into_iter().collect();
+        let report = CoverageReport::new(
+            &spec,
+            &frames,
+            &register!({"METHOD"=>"POST"}),
+            &base_params(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            (3, 6, 50.0),
+            (report.covered, report.total, report.coverage)
+        );
+        assert_eq!(
+            vec![
+                "POST /users 400",
+                "GET /users/{id} 404",
+                "DELETE /users/{id} default",
+            ],
+            report.untested
+        );
+        assert_eq!(vec!["usr.04s.list.fr.json"], report.unmatched);
+        assert!(report
+            .to_html()
+            .contains("<td>GET /users/{id}</td><td>2XX</td>"));
+    }
+
+    #[test]
+    fn test_proto_methods() {
+        let source = r#"
+syntax = "proto3";
+// package commented.out;
+package helloworld.v1;
+
+import "google/api/annotations.proto";
+
+service Greeter {
+  /* rpc Hidden (HelloRequest) returns (HelloReply); */
+  rpc SayHello (HelloRequest) returns (HelloReply) {
+    option (google.api.http) = { get: "/v1/{name}" };
+  }
+  rpc StreamHellos(stream HelloRequest) returns (stream HelloReply);
+}
+
+message HelloRequest {
+  string name = 1;
+  map<string, string> labels = 2;
+}
+
+service Health { rpc Check(HelloRequest) returns (HelloReply); }
+"#;
+        assert_eq!(
+            vec![
+                "helloworld.v1.Greeter/SayHello",
+                "helloworld.v1.Greeter/StreamHellos",
+                "helloworld.v1.Health/Check",
+            ],
+            proto_methods(source)
+        );
+        // an unbalanced closing brace is ignored
+        assert_eq!(
+            vec!["Greeter/SayHello"],
+            proto_methods("} service Greeter { rpc SayHello (A) returns (B); }")
+        );
+    }
+
+    #[test]
+    fn test_method_coverage_report() {
+        let grpc = |uri: &str| -> Frame<'static> {
+            serde_json::from_value(json!({
+                "protocol": "gRPC",
+                "request": {"uri": uri},
+                "response": {"status": 0}
+            }))
+            .unwrap()
+        };
+        let reel = Reel::from_frames(vec![
+            ("hello.01s.say.fr.json", grpc("${SERVICE}/SayHello")),
+            (
+                "hello.02s.check.fr.json",
+                grpc("grpc.health.v1.Health/Check"),
+            ),
+        ])
+        .unwrap();
+        let frames: Vec<MetaFrame> = reel.into_iter().collect();
+        let report = MethodCoverageReport::new(
+            vec![
+                "helloworld.Greeter/SayHello".to_string(),
+                "helloworld.Greeter/SayGoodbye".to_string(),
+            ],
+            &frames,
+            &register!({"SERVICE"=>"helloworld.Greeter"}),
+            &base_params(),
+        )
+        .unwrap();
+        assert_eq!(
+            (1, 2, 50.0),
+            (report.covered, report.total, report.coverage)
+        );
+        assert_eq!(vec!["helloworld.Greeter/SayGoodbye"], report.untested);
+        assert_eq!(vec!["hello.02s.check.fr.json"], report.unmatched);
+    }
+}
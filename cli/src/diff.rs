@@ -0,0 +1,247 @@
+use crate::artifacts::strip_ansi;
+use anyhow::Error;
+use filmreel::response::Response;
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeSet, path::PathBuf, str::FromStr};
+
+/// DiffFormat determines how a response mismatch is reported, provided through `--diff-format`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// the coloured text diff of the expected and actual responses
+    #[default]
+    Text,
+    /// a single line JSON object listing every differing JSON pointer
+    Json,
+}
+
+impl FromStr for DiffFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "unknown diff format \"{s}\", expected one of: text, json"
+            )),
+        }
+    }
+}
+
+/// DiffLimit bounds the size of the text mismatch output so that large payloads keep logs readable,
+/// the full payloads are still written to the `--artifacts` directory
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DiffLimit {
+    /// the number of unchanged lines kept around each changed line, provided through
+    /// `--diff-context`
+    pub context: Option<usize>,
+    /// the maximum number of lines printed per diff or payload, provided through
+    /// `--diff-max-lines`
+    pub max_lines: Option<usize>,
+    /// the `--artifacts` directory that the full payloads are written to
+    pub artifacts: Option<PathBuf>,
+}
+
+impl DiffLimit {
+    /// limit_diff elides the unchanged lines of a [`colored_diff::PrettyDifference`] that are
+    /// further than `context` lines away from a change before capping the diff to `max_lines`
+    pub fn limit_diff<T: AsRef<str>>(&self, diff: T) -> String {
+        let diff = diff.as_ref();
+        let context = match self.context {
+            Some(context) => context,
+            None => return self.limit_lines(diff),
+        };
+        let mut lines = diff.lines();
+        // the "< left / > right" legend is always kept
+        let mut output: Vec<String> = lines.next().map(String::from).into_iter().collect();
+        let lines: Vec<&str> = lines.collect();
+        let changed: Vec<usize> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| !strip_ansi(line).starts_with(' '))
+            .map(|(i, _)| i)
+            .collect();
+
+        let mut elided = 0;
+        for (i, line) in lines.iter().enumerate() {
+            if changed.iter().any(|c| c.abs_diff(i) <= context) {
+                if elided > 0 {
+                    output.push(format!(" ... {elided} unchanged lines"));
+                    elided = 0;
+                }
+                output.push(line.to_string());
+            } else {
+                elided += 1;
+            }
+        }
+        if elided > 0 {
+            output.push(format!(" ... {elided} unchanged lines"));
+        }
+        self.limit_lines(output.join("\n"))
+    }
+
+    /// limit_lines caps the text given to `max_lines`, pointing to the full payloads in place of
+    /// the lines removed
+    pub fn limit_lines<T: AsRef<str>>(&self, text: T) -> String {
+        let text = text.as_ref();
+        let max_lines = match self.max_lines {
+            Some(max_lines) => max_lines,
+            None => return text.to_string(),
+        };
+        let total = text.lines().count();
+        if total <= max_lines {
+            return text.to_string();
+        }
+        let pointer = match &self.artifacts {
+            Some(dir) => format!("the full payloads are written to {}", dir.to_string_lossy()),
+            None => "pass --artifacts <dir> to write the full payloads".to_string(),
+        };
+        let mut output: Vec<&str> = text.lines().take(max_lines).collect();
+        let truncated = format!(" ... {} more lines, {pointer}", total - max_lines);
+        output.push(&truncated);
+        output.join("\n")
+    }
+}
+
+/// json_diff reports a response mismatch as a JSON object holding a JSON Patch style list of the
+/// operations that turn the expected response into the actual response:
+///
+/// ```json
+/// {
+///   "mismatch": "request/response mismatch",
+///   "diff": [{"op": "replace", "path": "/body/id", "expected": 1, "actual": 2}]
+/// }
+/// ```
+pub fn json_diff(msg: &str, expected: &Response, actual: &Response) -> Result<String, Error> {
+    let diff = diff_operations(expected, actual)?;
+    Ok(serde_json::to_string(
+        &json!({"mismatch": msg, "diff": diff}),
+    )?)
+}
+
+/// diff_operations returns the `{"op", "path", "expected", "actual"}` operations of [`json_diff`]
+/// ordered by JSON pointer
+pub fn diff_operations(expected: &Response, actual: &Response) -> Result<Vec<Value>, Error> {
+    let expected = serde_json::to_value(expected)?;
+    let actual = serde_json::to_value(actual)?;
+    let mut diff = vec![];
+    push_diff(String::new(), Some(&expected), Some(&actual), &mut diff);
+    Ok(diff)
+}
+
+fn push_diff(
+    path: String,
+    expected: Option<&Value>,
+    actual: Option<&Value>,
+    diff: &mut Vec<Value>,
+) {
+    match (expected, actual) {
+        (Some(Value::Object(e)), Some(Value::Object(a))) => {
+            let keys: BTreeSet<&String> = e.keys().chain(a.keys()).collect();
+            for key in keys {
+                let pointer = format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"));
+                push_diff(pointer, e.get(key), a.get(key), diff);
+            }
+        }
+        (Some(Value::Array(e)), Some(Value::Array(a))) => {
+            for i in 0..e.len().max(a.len()) {
+                push_diff(format!("{path}/{i}"), e.get(i), a.get(i), diff);
+            }
+        }
+        (e, a) if e == a => (),
+        (e, a) => {
+            let op = match (e, a) {
+                (None, _) => "add",
+                (_, None) => "remove",
+                _ => "replace",
+            };
+            let mut entry = Map::new();
+            entry.insert("op".to_string(), op.into());
+            entry.insert("path".to_string(), path.into());
+            if let Some(e) = e {
+                entry.insert("expected".to_string(), e.clone());
+            }
+            if let Some(a) = a {
+                entry.insert("actual".to_string(), a.clone());
+            }
+            diff.push(Value::Object(entry));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    const DIFF: &str = "< left / > right\n {\n   \"a\": 1,\n   \"b\": 2,\n<  \"c\": 3,\n>  \"c\": 4,\n   \"d\": 5,\n   \"e\": 6\n }";
+
+    #[rstest(
+        context,
+        max_lines,
+        artifacts,
+        expected,
+        case(None, None, None, DIFF),
+        case(
+            Some(1),
+            None,
+            None,
+            "< left / > right\n ... 2 unchanged lines\n   \"b\": 2,\n<  \"c\": 3,\n>  \"c\": 4,\n   \"d\": 5,\n ... 2 unchanged lines"
+        ),
+        case(
+            Some(0),
+            Some(3),
+            Some("./artifacts"),
+            "< left / > right\n ... 3 unchanged lines\n<  \"c\": 3,\n ... 2 more lines, the full payloads are written to ./artifacts"
+        ),
+        case(
+            None,
+            Some(2),
+            None,
+            "< left / > right\n {\n ... 7 more lines, pass --artifacts <dir> to write the full payloads"
+        )
+    )]
+    fn test_limit_diff(
+        context: Option<usize>,
+        max_lines: Option<usize>,
+        artifacts: Option<&str>,
+        expected: &str,
+    ) {
+        let limit = DiffLimit {
+            context,
+            max_lines,
+            artifacts: artifacts.map(PathBuf::from),
+        };
+        assert_eq!(expected, limit.limit_diff(DIFF));
+    }
+
+    #[test]
+    fn test_json_diff() {
+        let expected = Response {
+            body: Some(json!({"id": 1, "tags": ["a"], "a/b": true, "name": "BIG_BEN"})),
+            status: 200,
+            ..Default::default()
+        };
+        let actual = Response {
+            body: Some(json!({"id": 2, "tags": ["a", "b"], "name": "BIG_BEN"})),
+            status: 201,
+            ..Default::default()
+        };
+        let diff: Value = serde_json::from_str(
+            &json_diff("request/response mismatch", &expected, &actual).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            json!({
+                "mismatch": "request/response mismatch",
+                "diff": [
+                    {"op": "remove", "path": "/body/a~1b", "expected": true},
+                    {"op": "replace", "path": "/body/id", "expected": 1, "actual": 2},
+                    {"op": "add", "path": "/body/tags/1", "actual": "b"},
+                    {"op": "replace", "path": "/status", "expected": 200, "actual": 201}
+                ]
+            }),
+            diff
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::guess_json_obj;
+use anyhow::{anyhow, Context, Error};
+use filmreel::{frame::Request, response::Response, utils::select_value};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+/// Echo reads `"request"["echo"]`, asserting that the request headers and body echoed back by a
+/// debugging target such as httpbin match the ones sent, catching proxies and middleware that
+/// mutate requests in transit. `true` reads the echo of an httpbin `/anything` response:
+///
+/// ```json
+/// "echo": true
+/// "echo": {"header": "'response'.'body'.'headers'", "body": "'response'.'body'.'json'"}
+/// ```
+///
+/// Every header sent must be echoed with the same value under a name of any casing, headers added
+/// in transit are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Echo {
+    /// the selector of the echoed header map
+    header: Option<String>,
+    /// the selector of the echoed body
+    body: Option<String>,
+}
+
+impl Echo {
+    pub fn from_request(req: &Request) -> Result<Option<Self>, Error> {
+        match req.get_etc().as_ref().and_then(|etc| etc.get("echo")) {
+            Some(Value::Null | Value::Bool(false)) | None => Ok(None),
+            Some(Value::Bool(true)) => Ok(Some(Self {
+                header: Some("'response'.'body'.'headers'".to_string()),
+                body: Some("'response'.'body'.'json'".to_string()),
+            })),
+            Some(echo) => serde_json::from_value(echo.clone())
+                .map(Some)
+                .context("request[\"echo\"] must be true or an object of: {\"header\", \"body\"}"),
+        }
+    }
+
+    /// verify returns the differences between the header and body sent and the ones echoed by
+    /// the response, the body is only compared when one was sent
+    pub fn verify(
+        &self,
+        header: Option<&str>,
+        body: Option<&Value>,
+        response: &Response,
+    ) -> Result<Vec<String>, Error> {
+        let response = json!({ "response": response });
+        let mut diffs = vec![];
+        if let Some(selector) = &self.header {
+            let sent: Map<String, Value> = match header {
+                Some(h) if guess_json_obj(h) => serde_json::from_str(h)?,
+                Some(_) => {
+                    return Err(anyhow!(
+                        "request[\"echo\"] requires the header to be a key value map"
+                    ))
+                }
+                None => Map::new(),
+            };
+            match select_value(&response, selector) {
+                Ok(Value::Object(echoed)) => {
+                    for (name, value) in sent.iter() {
+                        let echoed_value = echoed
+                            .iter()
+                            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                            .map(|(_, v)| v);
+                        match echoed_value {
+                            Some(v) if as_text(v) == as_text(value) => (),
+                            Some(v) => diffs.push(format!(
+                                "header {name:?} was sent as {value} but echoed as {v}"
+                            )),
+                            None => diffs.push(format!("header {name:?} was not echoed")),
+                        }
+                    }
+                }
+                Ok(v) => diffs.push(format!("{selector} is not a header map: {v}")),
+                Err(_) if sent.is_empty() => (),
+                Err(_) => diffs.push(format!("{selector} did not select the echoed header")),
+            }
+        }
+        if let (Some(selector), Some(body)) = (&self.body, body) {
+            match select_value(&response, selector) {
+                Ok(echoed) if echoed == *body => (),
+                Ok(echoed) => diffs.push(format!("body was sent as {body} but echoed as {echoed}")),
+                Err(_) => diffs.push(format!("{selector} did not select the echoed body")),
+            }
+        }
+        Ok(diffs)
+    }
+}
+
+/// as_text returns a string value as-is and any other value as JSON, headers are echoed as strings
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest(
+        echoed,
+        expected,
+        case(
+            json!({"headers": {"X-Run-Id": "abc", "Via": "proxy"}, "json": {"id": 1}}),
+            vec![]
+        ),
+        case(
+            json!({"headers": {"X-Run-Id": "xyz"}, "json": {"id": 2}}),
+            vec![
+                r#"header "x-run-id" was sent as "abc" but echoed as "xyz""#,
+                r#"body was sent as {"id":1} but echoed as {"id":2}"#
+            ]
+        ),
+        case(
+            json!({"headers": {}}),
+            vec![
+                r#"header "x-run-id" was not echoed"#,
+                "'response'.'body'.'json' did not select the echoed body"
+            ]
+        )
+    )]
+    fn test_verify(echoed: Value, expected: Vec<&str>) {
+        let req: Request =
+            serde_json::from_value(json!({"uri": "POST /anything", "echo": true})).unwrap();
+        let echo = Echo::from_request(&req).unwrap().unwrap();
+        let response = Response {
+            body: Some(echoed),
+            status: 200,
+            ..Default::default()
+        };
+        assert_eq!(
+            expected,
+            echo.verify(
+                Some(r#"{"x-run-id": "abc"}"#),
+                Some(&json!({"id": 1})),
+                &response
+            )
+            .unwrap()
+        );
+    }
+}
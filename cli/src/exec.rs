@@ -0,0 +1,169 @@
+use crate::{dump::RequestDump, params::Params};
+use anyhow::{anyhow, Context, Error};
+use filmreel::{frame::Request, response::Response};
+use serde_json::{json, Value};
+use std::{
+    io::{Read, Write},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// stdin returns the bytes piped to the command, a string body is piped as-is while other bodies
+/// are piped as a line of JSON
+fn stdin(body: Option<Value>) -> Vec<u8> {
+    match body {
+        None => vec![],
+        Some(Value::String(body)) => body.into_bytes(),
+        Some(body) => format!("{body}\n").into_bytes(),
+    }
+}
+
+/// to_body parses the output of the command as JSON, output that is not JSON is kept as a string
+/// and empty output is no body at all
+fn to_body(output: &[u8]) -> Option<Value> {
+    let output = String::from_utf8_lossy(output);
+    if output.trim().is_empty() {
+        return None;
+    }
+    Some(serde_json::from_str(&output).unwrap_or_else(|_| Value::String(output.into_owned())))
+}
+
+/// dump_request describes the command line of a Frame Request, the request body is piped to it
+/// through `printf`
+pub fn dump_request(prm: &Params, req: Request) -> Result<RequestDump, Error> {
+    let mut command: Vec<String> = vec![];
+    if !prm.env.is_empty() {
+        command.push("env".to_string());
+        command.extend(prm.env.iter().map(|(k, v)| format!("{k}={v}")));
+    }
+    let input = String::from_utf8_lossy(&stdin(req.to_val_payload()?)).into_owned();
+    command.extend([
+        "sh".to_string(),
+        "-c".to_string(),
+        r#"printf %s "$1" | sh -c "$2""#.to_string(),
+        "sh".to_string(),
+        input,
+        req.get_uri(),
+    ]);
+    Ok(RequestDump {
+        request: json!({
+            "protocol": "Exec",
+            "uri": req.get_uri(),
+            "body": req.to_val_payload()?,
+        }),
+        command,
+    })
+}
+
+/// request runs the command line held by the uri of a Frame Request with `sh -c`, piping the
+/// request body to its stdin. The response status is the exit code of the command and the response
+/// body is its stdout parsed as JSON, or its stderr if the command failed without any output.
+///
+/// The Cut Variables of the command line are hydrated as single-quoted shell words, see
+/// [`filmreel::cut::Register::shell_quoted`]. The command is killed once the `--timeout` of
+/// darkroom elapses.
+pub fn request<'a>(prm: Params, req: Request) -> Result<Response<'a>, Error> {
+    let uri = req.get_uri();
+    let input = stdin(req.to_val_payload()?);
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&uri)
+        .envs(prm.env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context(format!("failed to execute => {uri}"))?;
+
+    let mut child_stdin = child.stdin.take().expect("piped stdin");
+    // a command that exits without reading its stdin closes the pipe, which is not an error
+    let writer = thread::spawn(move || {
+        let _ = child_stdin.write_all(&input);
+    });
+    let read_pipe = |mut pipe: Box<dyn Read + Send>| {
+        thread::spawn(move || {
+            let mut output = vec![];
+            pipe.read_to_end(&mut output).map(|_| output)
+        })
+    };
+    let stdout = read_pipe(Box::new(child.stdout.take().expect("piped stdout")));
+    let stderr = read_pipe(Box::new(child.stderr.take().expect("piped stderr")));
+
+    let deadline = match prm.timeout {
+        0 => None,
+        timeout => Some(Instant::now() + Duration::from_secs(timeout)),
+    };
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            child.kill()?;
+            child.wait()?;
+            return Err(anyhow!("command timed out after {}s => {uri}", prm.timeout));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let _ = writer.join();
+    let stdout = stdout.join().expect("stdout reader panic")?;
+    let stderr = stderr.join().expect("stderr reader panic")?;
+
+    let code = status
+        .code()
+        .ok_or_else(|| anyhow!("command was terminated by a signal => {uri}"))?;
+    let body = match (code, to_body(&stdout)) {
+        (0, body) | (_, body @ Some(_)) => body,
+        (_, None) => to_body(&stderr),
+    };
+    Ok(Response {
+        body,
+        status: code as u32,
+        content_type: None,
+        header: None,
+        etc: Some(json!({})),
+        validation: None,
+        ttfb: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest(
+        request_json,
+        expected_body,
+        expected_status,
+        case(
+            json!({"uri": "cat", "body": {"name": "BIG BEN"}}),
+            Some(json!({"name": "BIG BEN"})),
+            0
+        ),
+        case(json!({"uri": "tr a-z A-Z", "body": "big ben"}), Some(json!("BIG BEN")), 0),
+        case(json!({"uri": "echo oops >&2; exit 3"}), Some(json!("oops\n")), 3),
+        case(json!({"uri": "echo '[1, 2]'; exit 1"}), Some(json!([1, 2])), 1),
+        case(json!({"uri": "true"}), None, 0)
+    )]
+    fn test_request(request_json: Value, expected_body: Option<Value>, expected_status: u32) {
+        let req: Request = serde_json::from_value(request_json).unwrap();
+        let response = request(Params::default(), req).unwrap();
+        assert_eq!(
+            (expected_body, expected_status),
+            (response.body, response.status)
+        );
+    }
+
+    #[test]
+    fn test_request_timeout() {
+        let req: Request = serde_json::from_value(json!({"uri": "sleep 5"})).unwrap();
+        let prm = Params {
+            timeout: 1,
+            ..Default::default()
+        };
+        let started = Instant::now();
+        assert!(request(prm, req).is_err());
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::diff::diff_operations;
+use anyhow::Error;
+use filmreel::{frame::InstructionSet, response::Response};
+use serde_json::Value;
+use std::fmt::Write;
+
+/// Explanation records why a response was reported as a mismatch, logged through `--explain`:
+///
+/// - the validation rules applied to each selector of the frame response
+/// - the value matched by every write instruction or the reason it failed to match
+/// - the selectors where the expected and actual responses still diverged once validated
+#[derive(Debug, Default, PartialEq)]
+pub struct Explanation {
+    /// the selector and the enabled rules of every validator of the frame response
+    pub validators: Vec<(String, Vec<String>)>,
+    /// the Cut Variable, the selector, and the match of every write instruction
+    pub writes: Vec<(String, String, Result<Option<Value>, String>)>,
+}
+
+impl Explanation {
+    /// new records the validators of the frame response, it must be called before they are
+    /// applied since applying them consumes the validation
+    pub fn new(response: &Response) -> Self {
+        let validators = response
+            .validation
+            .iter()
+            .flatten()
+            .map(|(selector, validator)| (selector.to_string(), validator.rules()))
+            .collect();
+        Self {
+            validators,
+            writes: vec![],
+        }
+    }
+
+    /// record_writes records the outcome of the write instructions against the validated
+    /// responses, before the matched values are hydrated into the frame response
+    pub fn record_writes(
+        &mut self,
+        expected: &Response,
+        set: &InstructionSet,
+        actual: &Response,
+    ) -> Result<(), Error> {
+        self.writes = expected
+            .explain_writes(set, actual)?
+            .into_iter()
+            .map(|(var, selector, mat)| {
+                (
+                    var.to_string(),
+                    selector.to_string(),
+                    mat.map_err(|e| e.to_string()),
+                )
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// render formats the explanation along with the selectors where the expected and actual
+    /// responses diverge
+    pub fn render(&self, expected: &Response, actual: &Response) -> Result<String, Error> {
+        let mut out = String::from("Validators:\n");
+        if self.validators.is_empty() {
+            out.push_str("  none\n");
+        }
+        for (selector, rules) in self.validators.iter() {
+            let rules = match rules.is_empty() {
+                true => "no rules".to_string(),
+                false => rules.join(", "),
+            };
+            let _ = writeln!(out, "  {selector}: {rules}");
+        }
+
+        out.push_str("Write instructions:\n");
+        if self.writes.is_empty() {
+            out.push_str("  none\n");
+        }
+        for (var, selector, mat) in self.writes.iter() {
+            let outcome = match mat {
+                Ok(Some(value)) => format!("matched {value}"),
+                Ok(None) => "no match".to_string(),
+                Err(e) => format!("failed: {e}"),
+            };
+            let _ = writeln!(out, "  ${{{var}}} {selector}: {outcome}");
+        }
+
+        out.push_str("Diverged:\n");
+        let expected_value = serde_json::to_value(expected)?;
+        let actual_value = serde_json::to_value(actual)?;
+        for op in diff_operations(expected, actual)? {
+            let pointer = op["path"].as_str().unwrap_or_default();
+            let _ = write!(
+                out,
+                "  {} {}",
+                op["op"].as_str().unwrap_or_default(),
+                to_selector(pointer, &expected_value, &actual_value)
+            );
+            for side in ["expected", "actual"] {
+                if let Some(value) = op.get(side) {
+                    let _ = write!(out, ", {side} {value}");
+                }
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+/// to_selector converts a JSON pointer into the response into a frame selector such as
+/// `'response'.'body'.'tags'.[1]`, segments of either response holding an array are indices
+fn to_selector(pointer: &str, expected: &Value, actual: &Value) -> String {
+    let mut selector = String::from("'response'");
+    let (mut expected, mut actual) = (Some(expected), Some(actual));
+    for segment in pointer.split('/').skip(1) {
+        let key = segment.replace("~1", "/").replace("~0", "~");
+        let is_array =
+            matches!(expected, Some(Value::Array(_))) || matches!(actual, Some(Value::Array(_)));
+        match key.parse::<usize>() {
+            Ok(i) if is_array => {
+                let _ = write!(selector, ".[{i}]");
+                expected = expected.and_then(|v| v.get(i));
+                actual = actual.and_then(|v| v.get(i));
+            }
+            _ => {
+                let _ = write!(selector, ".'{}'", key.replace('\'', "\\'"));
+                expected = expected.and_then(|v| v.get(&key));
+                actual = actual.and_then(|v| v.get(&key));
+            }
+        }
+    }
+    selector
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filmreel::frame::Frame;
+    use serde_json::json;
+
+    #[test]
+    fn test_explanation() {
+        let frame: Frame = serde_json::from_value(json!({
+            "protocol": "HTTP",
+            "cut": {"to": {"USER_ID": "'response'.'body'.'id'", "NAME": "'response'.'body'.'name'"}},
+            "request": {"uri": "GET /users"},
+            "response": {
+                "body": {"id": "${USER_ID}", "name": "user_${NAME}", "tags": ["a"]},
+                "validation": {"'response'.'body'.'tags'": {"partial": true, "unordered": true}},
+                "status": 200
+            }
+        }))
+        .unwrap();
+        let mut expected = frame.response.clone();
+        let mut actual = Response {
+            body: Some(json!({"id": "BIG_BEN", "name": "ben", "tags": ["b", "c"]})),
+            status: 200,
+            ..Default::default()
+        };
+
+        let mut explanation = Explanation::new(&expected);
+        expected.apply_validation(&mut actual).unwrap();
+        explanation
+            .record_writes(&expected, &frame.cut, &actual)
+            .unwrap();
+        expected.body = Some(json!({"id": "BIG_BEN", "name": "user_${NAME}", "tags": ["a"]}));
+
+        assert_eq!(
+            r#"Validators:
+  'response'.'body'.'tags': partial, unordered
+Write instructions:
+  ${NAME} 'response'.'body'.'name': failed: WriteInstructionError: Frame String templating mismatch
+  ${USER_ID} 'response'.'body'.'id': matched "BIG_BEN"
+Diverged:
+  replace 'response'.'body'.'name', expected "user_${NAME}", actual "ben"
+  replace 'response'.'body'.'tags'.[0], expected "a", actual "b"
+  add 'response'.'body'.'tags'.[1], actual "c"
+"#,
+            explanation.render(&expected, &actual).unwrap()
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{CutCmd, CutSubCommand, Export};
+use anyhow::Error;
+use colored::*;
+use filmreel::cut::Register;
+use log::warn;
+use serde_json::Value;
+use std::str::FromStr;
+
+/// ExportFormat is how the Cut Variables of `dark cut export` are printed, provided through
+/// `--format`:
+///
+/// - `dotenv`: `KEY=value` lines read by dotenv loaders and `docker --env-file`
+/// - `shell`: `export KEY='value'` lines to be sourced by a POSIX shell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Dotenv,
+    Shell,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dotenv" => Ok(Self::Dotenv),
+            "shell" => Ok(Self::Shell),
+            _ => Err(format!(
+                "unknown export format \"{s}\", expected one of: dotenv, shell"
+            )),
+        }
+    }
+}
+
+/// export_register formats every Cut Variable of the register sorted by name, string values are
+/// exported as-is while other values are exported as compact JSON. Variables whose names are not
+/// valid environment variable names are left out.
+pub fn export_register(register: &Register, format: ExportFormat, mask_hidden: bool) -> String {
+    let mut vars: Vec<(&String, &Value)> = register.iter().collect();
+    vars.sort_by_key(|(name, _)| *name);
+
+    let mut out = String::new();
+    for (name, value) in vars {
+        if !is_env_name(name) {
+            warn!(
+                "{} {} is not a valid variable name",
+                "Skipped:".yellow(),
+                name
+            );
+            continue;
+        }
+        let value = match value {
+            _ if mask_hidden && register.is_hidden(name) => "${_HIDDEN}".to_string(),
+            Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        let line = match format {
+            ExportFormat::Dotenv => format!("{name}={}", dotenv_quote(&value)),
+            ExportFormat::Shell => format!("export {name}={}", shell_quote(&value)),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn is_bare(value: &str) -> bool {
+    value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_-./:@%+,".contains(c))
+}
+
+/// dotenv_quote single quotes values holding special characters so that they are read literally,
+/// values holding a single quote or a newline are double quoted with escapes instead
+fn dotenv_quote(value: &str) -> String {
+    if is_bare(value) {
+        return value.to_string();
+    }
+    if !value.contains(['\'', '\n']) {
+        return format!("'{value}'");
+    }
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('$', "\\$");
+    format!("\"{escaped}\"")
+}
+
+/// shell_quote single quotes values holding special characters, closing and reopening the quotes
+/// around single quotes
+fn shell_quote(value: &str) -> String {
+    match is_bare(value) && !value.is_empty() {
+        true => value.to_string(),
+        false => format!("'{}'", value.replace('\'', r"'\''")),
+    }
+}
+
+pub fn cmd_cut(cmd: CutCmd) -> Result<(), Error> {
+    match cmd.nested {
+        CutSubCommand::Export(cmd) => cmd_export(cmd),
+    }
+}
+
+fn cmd_export(cmd: Export) -> Result<(), Error> {
+    let register = Register::try_from(cmd.cut_file)?;
+    print!(
+        "{}",
+        export_register(&register, cmd.format, cmd.mask_hidden)
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filmreel::register;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest(
+        format,
+        mask_hidden,
+        expected,
+        case(
+            ExportFormat::Dotenv,
+            false,
+            r#"COUNT=3
+GREETING='hello world'
+QUOTE="it's \"quoted\"\nnext line"
+URL=http://localhost:8080/users
+USER='{"id":1}'
+_TOKEN='Bearer jWt'
+"#
+        ),
+        case(
+            ExportFormat::Shell,
+            true,
+            r#"export COUNT=3
+export GREETING='${_HIDDEN}'
+export QUOTE='it'\''s "quoted"
+next line'
+export URL=http://localhost:8080/users
+export USER='{"id":1}'
+export _TOKEN='${_HIDDEN}'
+"#
+        )
+    )]
+    fn test_export_register(format: ExportFormat, mask_hidden: bool, expected: &str) {
+        let mut register = register!({
+            "URL" => "http://localhost:8080/users",
+            "GREETING" => "hello world",
+            "QUOTE" => "it's \"quoted\"\nnext line",
+            "COUNT" => 3,
+            "USER" => json!({"id": 1}),
+            "_TOKEN" => "Bearer jWt",
+            "2FA_CODE" => "skipped"
+        });
+        register.hide("GREETING");
+        assert_eq!(expected, export_register(&register, format, mask_hidden));
+    }
+}
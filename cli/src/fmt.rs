@@ -0,0 +1,118 @@
+use crate::{
+    schema::{lint_targets, SchemaKind},
+    Fmt,
+};
+use anyhow::{anyhow, Context, Error};
+use colored::*;
+use filmreel::{utils::strip_jsonc, ToStringCanonical};
+use log::warn;
+use serde_json::Value;
+use std::{fs, path::Path};
+
+/// cmd_fmt rewrites every file provided in the canonical style, with `--check` the files that are
+/// not formatted are only reported and fail the command
+pub fn cmd_fmt(cmd: Fmt) -> Result<(), Error> {
+    let files = lint_targets(&cmd.files);
+    let mut unformatted = 0;
+    for path in files.iter() {
+        let formatted = match format_file(path)? {
+            Some(formatted) => formatted,
+            None => continue,
+        };
+        unformatted += 1;
+        if cmd.check {
+            warn!("{} {}", "Unformatted:".yellow(), path.to_string_lossy());
+            continue;
+        }
+        fs::write(path, formatted).context(format!("fmt write failure => {path:?}"))?;
+        warn!("{} {}", "Formatted:".green(), path.to_string_lossy());
+    }
+    if cmd.check && unformatted > 0 {
+        return Err(anyhow!(
+            "{} of {} files are not formatted, run `dark fmt` to format them",
+            unformatted,
+            files.len()
+        ));
+    }
+    Ok(())
+}
+
+/// format_file returns the canonical contents of a frame, cut, or vreel file, or `None` if the
+/// file is already formatted. Files holding comments are refused since formatting drops them.
+pub fn format_file<P: AsRef<Path>>(path: P) -> Result<Option<String>, Error> {
+    let path = path.as_ref();
+    let kind = SchemaKind::from_path(path).ok_or_else(|| {
+        anyhow!("unable to infer the file format, expected a .fr.json, .cut.json, or .vr.json file => {path:?}")
+    })?;
+    let json_string = fs::read_to_string(path).context(format!("fmt read failure => {path:?}"))?;
+    if strip_jsonc(&json_string) != json_string {
+        return Err(anyhow!(
+            "{path:?} holds comments or trailing commas that formatting would remove"
+        ));
+    }
+    let value: Value =
+        serde_json::from_str(&json_string).context(format!("fmt parse failure => {path:?}"))?;
+    let formatted = format_value(kind, value)?;
+    Ok((formatted != json_string).then_some(formatted))
+}
+
+/// format_value sorts the keys of every object with a 2-space indent and a trailing newline, the
+/// Cut Variables read by the instruction sets of a frame are sorted as well
+pub fn format_value(kind: SchemaKind, mut value: Value) -> Result<String, Error> {
+    if kind == SchemaKind::Frame {
+        for reads in ["/cut/from", "/before/cut/from"] {
+            if let Some(Value::Array(vars)) = value.pointer_mut(reads) {
+                vars.sort_by_key(|var| var.to_string());
+            }
+        }
+    }
+    Ok(value.to_string_canonical()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest(
+        kind,
+        input,
+        expected,
+        case(
+            SchemaKind::Frame,
+            json!({"response": {"status": 200}, "protocol": "HTTP", "cut": {"from": ["USER_ID", "HOST"]}, "request": {"uri": "GET /"}}),
+            r#"{
+  "cut": {
+    "from": [
+      "HOST",
+      "USER_ID"
+    ]
+  },
+  "protocol": "HTTP",
+  "request": {
+    "uri": "GET /"
+  },
+  "response": {
+    "status": 200
+  }
+}
+"#
+        ),
+        case(
+            SchemaKind::VirtualReel,
+            json!({"name": "usr", "frames": ["usr.02s.b.fr.json", "usr.01s.a.fr.json"]}),
+            r#"{
+  "frames": [
+    "usr.02s.b.fr.json",
+    "usr.01s.a.fr.json"
+  ],
+  "name": "usr"
+}
+"#
+        )
+    )]
+    fn test_format_value(kind: SchemaKind, input: Value, expected: &str) {
+        assert_eq!(expected, format_value(kind, input).unwrap());
+    }
+}
@@ -0,0 +1,174 @@
+use chrono::{SecondsFormat, Utc};
+use filmreel::{
+    cut::Register,
+    frame::{Generate, InstructionSet},
+    FrError,
+};
+use serde_json::Value;
+use std::{
+    cell::Cell,
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Generator produces the values of generated Cut Variables, values are reproducible across runs
+/// when the same seed is provided through `--seed`
+#[derive(Debug, Clone)]
+pub struct Generator {
+    seed: u64,
+    state: Cell<u64>,
+    used: Cell<bool>,
+}
+
+impl Generator {
+    /// Creates a new Generator, seeding it with the current system time if no seed is provided
+    pub fn new(seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+        Self {
+            seed,
+            state: Cell::new(seed),
+            used: Cell::new(false),
+        }
+    }
+
+    /// The seed used to initialize the Generator
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns true if any random values were produced by the Generator
+    pub fn is_used(&self) -> bool {
+        self.used.get()
+    }
+
+    // splitmix64: http://xoshiro.di.unimi.it/splitmix64.c
+    fn next_u64(&self) -> u64 {
+        self.used.set(true);
+        let state = self.state.get().wrapping_add(0x9E37_79B9_7F4A_7C15);
+        self.state.set(state);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Produces a single value of the given kind
+    pub fn generate(&self, kind: &Generate) -> Value {
+        match kind {
+            Generate::Uuid => {
+                let (hi, lo) = (self.next_u64(), self.next_u64());
+                // set the version (4) and variant (RFC 4122) bits
+                let hi = (hi & 0xFFFF_FFFF_FFFF_0FFF) | 0x0000_0000_0000_4000;
+                let lo = (lo & 0x3FFF_FFFF_FFFF_FFFF) | 0x8000_0000_0000_0000;
+                Value::String(format!(
+                    "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+                    hi >> 32,
+                    (hi >> 16) & 0xFFFF,
+                    hi & 0xFFFF,
+                    lo >> 48,
+                    lo & 0xFFFF_FFFF_FFFF
+                ))
+            }
+            Generate::Int => Value::from(self.next_u64() as u32),
+            // the current time is not reproducible through the seed
+            Generate::Timestamp => {
+                Value::String(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true))
+            }
+        }
+    }
+
+    /// Writes a newly generated value into the register for every generate instruction present,
+    /// frame-local variables are written into the frame-local overlay of the register
+    pub fn write_into(&self, set: &InstructionSet, register: &mut Register) -> Result<(), FrError> {
+        for (name, kind) in set.generated() {
+            match set.is_local(name) {
+                true => register.write_local(name, self.generate(kind))?,
+                false => register.write_operation(name, self.generate(kind))?,
+            };
+        }
+        Ok(())
+    }
+}
+
+/// random_uuid returns a UUID that is not reproduced through `--seed`, used for the run ID of a
+/// darkroom session and for the idempotency key of a frame
+pub fn random_uuid() -> String {
+    match Generator::new(Some(entropy())).generate(&Generate::Uuid) {
+        Value::String(uuid) => uuid,
+        uuid => uuid.to_string(),
+    }
+}
+
+/// random_u64 returns a number that is not reproduced through `--seed`, used for the jitter of
+/// retry attempts
+pub fn random_u64() -> u64 {
+    Generator::new(Some(entropy())).next_u64()
+}
+
+/// entropy returns a seed that differs across calls made within the same clock tick, even from
+/// concurrent frames, by hashing a process-wide counter with the randomly keyed hasher of std
+fn entropy() -> u64 {
+    static CALLS: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(CALLS.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_generate() {
+        let first = Generator::new(Some(42));
+        let second = Generator::new(Some(42));
+        for kind in [Generate::Uuid, Generate::Int, Generate::Uuid] {
+            assert_eq!(first.generate(&kind), second.generate(&kind));
+        }
+        assert!(first.is_used());
+    }
+
+    #[test]
+    fn test_random_uuid() {
+        let uuids: std::collections::HashSet<String> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| (0..256).map(|_| random_uuid()).collect::<Vec<_>>()))
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|w| w.join().unwrap())
+                .collect()
+        });
+        assert_eq!(4 * 256, uuids.len());
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let generator = Generator::new(Some(7));
+        let timestamp = generator.generate(&Generate::Timestamp);
+        assert!(chrono::DateTime::parse_from_rfc3339(timestamp.as_str().unwrap()).is_ok());
+        assert!(!generator.is_used());
+    }
+
+    #[test]
+    fn test_uuid_format() {
+        let uuid = Generator::new(Some(7)).generate(&Generate::Uuid);
+        let uuid = uuid.as_str().unwrap();
+        let groups: Vec<usize> = uuid.split('-').map(str::len).collect();
+        assert_eq!(vec![8, 4, 4, 4, 12], groups);
+        assert_eq!(Some('4'), uuid.chars().nth(14));
+    }
+}
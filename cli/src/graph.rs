@@ -0,0 +1,250 @@
+use crate::{record::init_components, Graph};
+use anyhow::{anyhow, Context, Error};
+use filmreel::{
+    cut::Register,
+    reel::{MetaFrame, Reel},
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write,
+};
+
+/// Source is where the value of a Cut Variable read by a frame comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// the cut register of the reel and its components
+    Cut,
+    /// the index of the frame that last wrote the variable
+    Frame(usize),
+}
+
+/// ReelGraph holds the frames of a reel in the order they are run, grouped by reel so that
+/// component frames are shown apart from the frames of the reel, along with the Cut Variables
+/// flowing from the frame or cut register that writes them to the later frames that read them
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReelGraph {
+    pub name: String,
+    /// every frame filename in the order it is run
+    pub frames: Vec<String>,
+    /// the reel name and the indices of the frames belonging to that reel
+    pub reels: Vec<(String, Vec<usize>)>,
+    /// the Cut Variables read by a frame and where they are read from
+    pub flows: Vec<(Source, usize, String)>,
+}
+
+impl ReelGraph {
+    /// new builds the graph of the frames provided, variables read without being present in the
+    /// register or written by an earlier frame are left out, `dark lint` reports them
+    pub fn new<T: Into<String>>(
+        name: T,
+        frames: &[MetaFrame],
+        register: &Register,
+    ) -> Result<Self, Error> {
+        let mut graph = Self {
+            name: name.into(),
+            ..Default::default()
+        };
+        let mut writers: HashMap<String, Source> = register
+            .iter()
+            .map(|(var, _)| (var.clone(), Source::Cut))
+            .collect();
+
+        for (i, meta_frame) in frames.iter().enumerate() {
+            let filename = meta_frame.get_filename();
+            let frame = meta_frame.to_frame().context(filename.clone())?;
+            match graph.reels.last_mut() {
+                Some((reel, indices)) if *reel == meta_frame.reel_name => indices.push(i),
+                _ => graph.reels.push((meta_frame.reel_name.clone(), vec![i])),
+            }
+            graph.frames.push(filename);
+
+            let sets: Vec<_> = std::iter::once(&frame.cut)
+                .chain(frame.before.as_ref().map(|b| &b.cut))
+                .collect();
+            let reads: BTreeSet<&str> = sets.iter().flat_map(|set| set.read()).collect();
+            for var in reads {
+                if let Some(source) = writers.get(var) {
+                    graph.flows.push((*source, i, var.to_string()));
+                }
+            }
+            for set in sets {
+                let written = set.written().chain(set.generated().map(|(var, _)| var));
+                for var in written {
+                    writers.insert(var.to_string(), Source::Frame(i));
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    /// to_dot renders the graph in the Graphviz DOT language
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "digraph {:?} {{", self.name);
+        let _ = writeln!(out, "  node [shape=box];");
+        if self
+            .flows
+            .iter()
+            .any(|(source, _, _)| *source == Source::Cut)
+        {
+            let _ = writeln!(out, "  cut [label=\"cut register\", shape=note];");
+        }
+        for (n, (reel, indices)) in self.reels.iter().enumerate() {
+            let _ = writeln!(out, "  subgraph cluster_{n} {{");
+            let _ = writeln!(out, "    label={reel:?};");
+            for i in indices {
+                let _ = writeln!(out, "    f{i} [label={:?}];", self.frames[*i]);
+            }
+            let _ = writeln!(out, "  }}");
+        }
+        for i in 1..self.frames.len() {
+            let _ = writeln!(out, "  f{} -> f{i};", i - 1);
+        }
+        for (source, i, var) in self.flows.iter() {
+            let _ = writeln!(
+                out,
+                "  {} -> f{i} [label={var:?}, style=dashed, color=blue];",
+                source.node()
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// to_mermaid renders the graph as a Mermaid flowchart
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart TD\n");
+        if self
+            .flows
+            .iter()
+            .any(|(source, _, _)| *source == Source::Cut)
+        {
+            let _ = writeln!(out, "  cut[/\"cut register\"/]");
+        }
+        for (n, (reel, indices)) in self.reels.iter().enumerate() {
+            let _ = writeln!(out, "  subgraph reel{n}[\"{reel}\"]");
+            for i in indices {
+                let _ = writeln!(out, "    f{i}[\"{}\"]", self.frames[*i]);
+            }
+            let _ = writeln!(out, "  end");
+        }
+        for i in 1..self.frames.len() {
+            let _ = writeln!(out, "  f{} --> f{i}", i - 1);
+        }
+        for (source, i, var) in self.flows.iter() {
+            let _ = writeln!(out, "  {} -. {var} .-> f{i}", source.node());
+        }
+        out
+    }
+}
+
+impl Source {
+    fn node(&self) -> String {
+        match self {
+            Self::Cut => "cut".to_string(),
+            Self::Frame(i) => format!("f{i}"),
+        }
+    }
+}
+
+pub fn cmd_graph(cmd: Graph) -> Result<(), Error> {
+    if cmd.dot && cmd.mermaid {
+        return Err(anyhow!("only one of --dot or --mermaid can be provided"));
+    }
+    let reel = Reel::new(&cmd.reel_path, &cmd.reel_name, None)?;
+    let (comp_reels, mut register) = init_components(cmd.component)?;
+    let cut_path = reel.get_default_cut_path();
+    if cut_path.is_file() {
+        register.single_merge(Register::try_from(cut_path)?);
+    }
+    let frames: Vec<MetaFrame> = comp_reels.into_iter().flatten().chain(reel).collect();
+
+    let graph = ReelGraph::new(cmd.reel_name, &frames, &register)?;
+    match cmd.mermaid {
+        true => print!("{}", graph.to_mermaid()),
+        false => print!("{}", graph.to_dot()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filmreel::{frame::Frame, register};
+    use serde_json::json;
+
+    fn frame(cut: serde_json::Value) -> Frame<'static> {
+        serde_json::from_value(json!({
+            "protocol": "HTTP",
+            "cut": cut,
+            "request": {"uri": "GET /"},
+            "response": {"status": 200}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reel_graph() {
+        let comp = Reel::from_frames(vec![(
+            "auth.01s.login.fr.json",
+            frame(json!({"from": ["PASSWORD"], "to": {"TOKEN": "'response'.'body'"}})),
+        )])
+        .unwrap();
+        let reel = Reel::from_frames(vec![
+            (
+                "usr.01s.createuser.fr.json",
+                frame(json!({"from": ["TOKEN"], "to": {"USER_ID": "'response'.'body'"}})),
+            ),
+            (
+                "usr.02s.getuser.fr.json",
+                frame(json!({"from": ["TOKEN", "USER_ID", "UNKNOWN"]})),
+            ),
+        ])
+        .unwrap();
+        let frames: Vec<MetaFrame> = comp.into_iter().chain(reel).collect();
+        let graph = ReelGraph::new("usr", &frames, &register!({"PASSWORD"=> "hunter2"})).unwrap();
+
+        assert_eq!(
+            r#"digraph "usr" {
+  node [shape=box];
+  cut [label="cut register", shape=note];
+  subgraph cluster_0 {
+    label="auth";
+    f0 [label="auth.01s.login.fr.json"];
+  }
+  subgraph cluster_1 {
+    label="usr";
+    f1 [label="usr.01s.createuser.fr.json"];
+    f2 [label="usr.02s.getuser.fr.json"];
+  }
+  f0 -> f1;
+  f1 -> f2;
+  cut -> f0 [label="PASSWORD", style=dashed, color=blue];
+  f0 -> f1 [label="TOKEN", style=dashed, color=blue];
+  f0 -> f2 [label="TOKEN", style=dashed, color=blue];
+  f1 -> f2 [label="USER_ID", style=dashed, color=blue];
+}
+"#,
+            graph.to_dot()
+        );
+        assert_eq!(
+            r#"flowchart TD
+  cut[/"cut register"/]
+  subgraph reel0["auth"]
+    f0["auth.01s.login.fr.json"]
+  end
+  subgraph reel1["usr"]
+    f1["usr.01s.createuser.fr.json"]
+    f2["usr.02s.getuser.fr.json"]
+  end
+  f0 --> f1
+  f1 --> f2
+  cut -. PASSWORD .-> f0
+  f0 -. TOKEN .-> f1
+  f0 -. TOKEN .-> f2
+  f1 -. USER_ID .-> f2
+"#,
+            graph.to_mermaid()
+        );
+    }
+}
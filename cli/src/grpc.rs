@@ -1,10 +1,28 @@
-use crate::params::{iter_path_args, Params};
+use crate::{
+    dump::{parse_header, RequestDump},
+    params::{iter_path_args, Params},
+};
 use anyhow::{anyhow, Context, Error};
-use filmreel::{frame::Request, response::Response};
+use filmreel::{
+    frame::{Request, StreamMode},
+    response::Response,
+};
 use lazy_static::lazy_static;
 use serde::Deserialize;
-use serde_json::json;
-use std::{collections::HashMap, ffi::OsString, path::PathBuf, process::Command};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    io,
+    path::PathBuf,
+    process::{ExitStatus, Stdio},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    process::Command,
+    time::{timeout_at, Instant},
+};
 
 /// Checks to see if grpcurl is in the system path
 pub fn validate_grpcurl() -> Result<(), Error> {
@@ -17,19 +35,27 @@ pub fn validate_grpcurl() -> Result<(), Error> {
     Ok(())
 }
 
-/// request parses a Frame Request and a Params object to send a gRPC payload using `grpcurl`
-/// the command line tool
-pub fn request<'a>(prm: Params, req: Request) -> Result<Response<'a>, Error> {
-    validate_grpcurl().context("grpcurl request failure")?;
-
+/// grpcurl_args builds the arguments of the `grpcurl` invocation for a Frame Request
+fn grpcurl_args(prm: &Params, req: &Request) -> Result<Vec<OsString>, Error> {
     let mut flags: Vec<OsString> = vec![OsString::from("-format-error")];
 
-    if !prm.tls {
+    // TLS files imply a TLS connection
+    if !prm.tls && prm.tls_files.is_empty() {
         flags.push(OsString::from("-plaintext"));
     }
+    let tls_files = [
+        ("-cacert", &prm.tls_files.cacert),
+        ("-cert", &prm.tls_files.cert),
+        ("-key", &prm.tls_files.key),
+    ];
+    for (flag, path) in tls_files {
+        if let Some(path) = path {
+            flags.extend([OsString::from(flag), OsString::from(path)]);
+        }
+    }
 
     // prepend "-import-path" to every protos PathBuf provided
-    if let Some(proto_path) = prm.proto_path {
+    if let Some(proto_path) = &prm.proto_path {
         flags.extend(iter_path_args(
             OsString::from("-import-path"),
             proto_path.iter().map(OsString::from),
@@ -37,7 +63,7 @@ pub fn request<'a>(prm: Params, req: Request) -> Result<Response<'a>, Error> {
     }
 
     // prepend "-proto" to every protos PathBuf provided
-    if let Some(protos) = prm.proto {
+    if let Some(protos) = &prm.proto {
         flags.extend(iter_path_args(
             OsString::from("-proto"),
             protos.iter().map(OsString::from),
@@ -57,39 +83,260 @@ pub fn request<'a>(prm: Params, req: Request) -> Result<Response<'a>, Error> {
         }
     };
 
-    let req_cmd = Command::new("grpcurl")
-        .args(flags)
-        .arg("-connect-timeout")
-        .arg(format!("{:.1}", prm.timeout as f32))
-        .arg("-d")
-        .arg(req.to_payload()?)
-        .arg(&prm.address)
-        .arg(req.get_uri())
-        .output()
+    // bidirectional streams are written to stdin as the server replies
+    let data: OsString = match req.get_stream()? {
+        Some((StreamMode::Bidi, _)) => "@".into(),
+        Some((StreamMode::Client, messages)) => stream_payload(messages)?.into(),
+        None => req.to_payload()?.into(),
+    };
+    flags.extend([
+        OsString::from("-connect-timeout"),
+        format!("{:.1}", prm.timeout as f32).into(),
+        OsString::from("-d"),
+        data,
+        OsString::from(&prm.address),
+        req.get_uri().into(),
+    ]);
+    Ok(flags)
+}
+
+/// stream_payload concatenates the messages of a client stream, `grpcurl` sends every JSON
+/// object of the `-d` data as a separate message
+fn stream_payload(messages: &[Value]) -> Result<String, Error> {
+    let messages = messages
+        .iter()
+        .map(serde_json::to_string_pretty)
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(messages.join("\n"))
+}
+
+/// parse_messages parses every JSON message written to the stdout of `grpcurl`, a server stream
+/// writes one JSON object per message received
+fn parse_messages(stdout: &[u8]) -> Result<Vec<Value>, Error> {
+    serde_json::Deserializer::from_slice(stdout)
+        .into_iter::<Value>()
+        .collect::<Result<Vec<Value>, _>>()
+        .map_err(Error::from)
+}
+
+/// exchange runs a bidirectional stream, writing each message to the stdin of `grpcurl` once
+/// the message received in reply to the previous one has been read from its stdout. Messages
+/// sent by the server after the last message are read until the stream is closed.
+///
+/// The exchange fails once the `timeout` in seconds elapses without a reply, stderr is drained
+/// concurrently so that `grpcurl` never blocks on a full pipe.
+async fn exchange(
+    mut cmd: Command,
+    messages: &[Value],
+    timeout: u64,
+) -> Result<(Vec<Value>, ExitStatus, Vec<u8>), Error> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
         .context("failed to execute grpcurl process")?;
+    let mut stdin = child.stdin.take();
+    let mut replies = Replies::new(child.stdout.take().expect("piped stdout"));
+    let mut stderr = child.stderr.take().expect("piped stderr");
+    let stderr = tokio::spawn(async move {
+        let mut buf = vec![];
+        let _ = stderr.read_to_end(&mut buf).await;
+        buf
+    });
+
+    let deadline = (timeout > 0).then(|| Instant::now() + Duration::from_secs(timeout));
+    let mut pending = messages.iter();
+    let mut received = vec![];
+    let failure = loop {
+        if let Some(input) = stdin.as_mut() {
+            // a closed stdin means grpcurl exited, its exit status reports why, stdin is closed
+            // in turn once every message was written
+            let written = match pending.next() {
+                Some(message) => match input.write_all(format!("{message}\n").as_bytes()).await {
+                    Ok(()) => input.flush().await,
+                    Err(e) => Err(e),
+                },
+                None => Err(io::ErrorKind::UnexpectedEof.into()),
+            };
+            if written.is_err() {
+                stdin = None;
+            }
+        }
+        let reply = match deadline {
+            Some(deadline) => match timeout_at(deadline, replies.next()).await {
+                Ok(reply) => reply,
+                Err(_) => {
+                    break Some((
+                        "grpcurl bidirectional stream timed out",
+                        anyhow!("no reply was received within {timeout}s"),
+                    ))
+                }
+            },
+            None => replies.next().await,
+        };
+        match reply {
+            Some(Ok(reply)) => received.push(reply),
+            Some(Err(e)) => break Some(("grpcurl response could not be parsed as JSON", e.into())),
+            None => break None,
+        }
+    };
+    drop(stdin);
+    if failure.is_some() {
+        let _ = child.start_kill();
+    }
+    let status = child.wait().await?;
+    let stderr = stderr.await.unwrap_or_default();
+    match failure {
+        Some((msg, err)) => Err(err.context(GrpcurlOutput::new(
+            msg,
+            status.code(),
+            &replies.stdout,
+            &stderr,
+        ))),
+        None => Ok((received, status, stderr)),
+    }
+}
+
+/// Replies parses the JSON messages written to the stdout of `grpcurl` as they are read, keeping
+/// every byte read so that the raw output can be reported when it could not be parsed
+struct Replies<R> {
+    reader: R,
+    stdout: Vec<u8>,
+    /// the number of bytes of stdout holding the messages parsed so far
+    parsed: usize,
+    closed: bool,
+}
 
-    let response = match req_cmd.status.code() {
+impl<R: AsyncRead + Unpin> Replies<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            stdout: vec![],
+            parsed: 0,
+            closed: false,
+        }
+    }
+
+    /// next returns the next message received, reading stdout until a whole message was written,
+    /// None is returned once stdout is closed
+    async fn next(&mut self) -> Option<Result<Value, serde_json::Error>> {
+        loop {
+            let mut stream =
+                serde_json::Deserializer::from_slice(&self.stdout[self.parsed..]).into_iter();
+            match stream.next() {
+                // the message is incomplete until more of it is read
+                Some(Err(e)) if e.is_eof() && !self.closed => (),
+                None if !self.closed => (),
+                Some(reply) => {
+                    self.parsed += stream.byte_offset();
+                    return Some(reply);
+                }
+                None => return None,
+            }
+            let mut buf = [0; 4096];
+            match self.reader.read(&mut buf).await {
+                Ok(n) if n > 0 => self.stdout.extend_from_slice(&buf[..n]),
+                _ => self.closed = true,
+            }
+        }
+    }
+}
+
+/// dump_request describes the `grpcurl` invocation for a Frame Request without running it
+pub fn dump_request(prm: &Params, req: Request) -> Result<RequestDump, Error> {
+    let mut command: Vec<String> = vec![];
+    if !prm.env.is_empty() {
+        command.push("env".to_string());
+        command.extend(prm.env.iter().map(|(k, v)| format!("{k}={v}")));
+    }
+    command.push("grpcurl".to_string());
+    command.extend(
+        grpcurl_args(prm, &req)?
+            .into_iter()
+            .map(|arg| arg.to_string_lossy().into_owned()),
+    );
+    Ok(RequestDump {
+        request: json!({
+            "protocol": "gRPC",
+            "address": prm.address,
+            "uri": req.get_uri(),
+            "header": prm.header.as_deref().map(parse_header),
+            "body": req.to_val_payload()?,
+        }),
+        command,
+    })
+}
+
+/// request parses a Frame Request and a Params object to send a gRPC payload using `grpcurl`
+/// the command line tool
+///
+/// Every request spawns its own `grpcurl` process and thus its own connection, channels cannot be
+/// kept open across the frames of a record until gRPC requests are sent by a native client.
+pub async fn request<'a>(prm: Params<'_>, req: Request) -> Result<Response<'a>, Error> {
+    validate_grpcurl().context("grpcurl request failure")?;
+
+    let mut cmd = Command::new("grpcurl");
+    cmd.envs(prm.env.iter().map(|(k, v)| (k, v)))
+        .args(grpcurl_args(&prm, &req)?);
+
+    let stream = req.get_stream()?;
+    let bidi = matches!(stream, Some((StreamMode::Bidi, _)));
+    let (received, status, stderr) = match stream {
+        Some((StreamMode::Bidi, messages)) => exchange(cmd, messages, prm.timeout).await?,
+        _ => {
+            let output = cmd
+                .kill_on_drop(true)
+                .output()
+                .await
+                .context("failed to execute grpcurl process")?;
+            let received = match output.status.success() {
+                true => parse_messages(&output.stdout).map_err(|e| {
+                    e.context(GrpcurlOutput::new(
+                        "grpcurl response could not be parsed as JSON",
+                        output.status.code(),
+                        &output.stdout,
+                        &output.stderr,
+                    ))
+                })?,
+                false => vec![],
+            };
+            (received, output.status, output.stderr)
+        }
+    };
+
+    let response = match status.code() {
         Some(0) => Response {
-            body: serde_json::from_slice(&req_cmd.stdout)?,
+            // streamed responses hold the array of messages received, a bidirectional stream
+            // responds with an array even when a single message is received
+            body: match (bidi, received.len()) {
+                (false, 1) => received.into_iter().next(),
+                _ => Some(Value::Array(received)),
+            },
             status: 0,
+            content_type: None,
+            header: None,
             etc: Some(json!({})),
             validation: None,
+            ttfb: None,
         },
         Some(_) => {
-            let err: ResponseError = serde_json::from_slice(&req_cmd.stderr).map_err(|_| {
-                // if we fail to map to a serde struct, stringingfy stderr bytes and cast to anyhow error
-                String::from_utf8(req_cmd.stderr)
-                    .map_err(Error::from)
-                    .map(|v| anyhow!(v))
-                    .context("grpcurl error")
-                    .unwrap_or_else(|e| e)
+            let err: ResponseError = serde_json::from_slice(&stderr).map_err(|_| {
+                // if we fail to map to a serde struct, keep the raw output of grpcurl in the error
+                anyhow!(String::from_utf8_lossy(&stderr).trim().to_string()).context(
+                    GrpcurlOutput::new("grpcurl error", status.code(), &[], &stderr),
+                )
             })?;
             // create frame response from deserialized grpcurl error
             Response {
                 body: Some(serde_json::Value::String(err.message)),
                 status: err.code,
+                content_type: None,
+                header: None,
                 etc: Some(json!({})),
                 validation: None,
+                ttfb: None,
             }
         }
         None => return Err(anyhow!("grpcurl response code was <None>")),
@@ -97,6 +344,41 @@ pub fn request<'a>(prm: Params, req: Request) -> Result<Response<'a>, Error> {
     Ok(response)
 }
 
+/// GrpcurlOutput holds the raw output of a `grpcurl` invocation that could not be parsed so that
+/// it can be retrieved from the returned [`Error`] with `Error::downcast_ref` and written to the
+/// failure artifacts
+#[derive(Debug, PartialEq, Eq)]
+pub struct GrpcurlOutput {
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    msg: &'static str,
+}
+
+impl GrpcurlOutput {
+    pub(crate) fn new(
+        msg: &'static str,
+        exit_code: Option<i32>,
+        stdout: &[u8],
+        stderr: &[u8],
+    ) -> Self {
+        Self {
+            exit_code,
+            stdout: String::from_utf8_lossy(stdout).into_owned(),
+            stderr: String::from_utf8_lossy(stderr).into_owned(),
+            msg,
+        }
+    }
+}
+
+impl std::fmt::Display for GrpcurlOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl std::error::Error for GrpcurlOutput {}
+
 #[derive(Debug, Deserialize, PartialEq)]
 struct ResponseError {
     code: u32,
@@ -106,6 +388,7 @@ struct ResponseError {
 #[cfg(test)]
 mod serde_tests {
     use super::*;
+    use filmreel::builder::RequestBuilder;
 
     const INTERNAL_ERROR: &str = r#"{
   "code": 13,
@@ -139,4 +422,77 @@ mod serde_tests {
             json_struct
         );
     }
+
+    #[test]
+    fn test_parse_messages() {
+        let stdout = b"{\n  \"n\": 1\n}\n{\n  \"n\": 2\n}\n";
+        assert_eq!(
+            vec![json!({"n": 1}), json!({"n": 2})],
+            parse_messages(stdout).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_stream_args() {
+        let prm = Params {
+            address: "localhost:8080".to_string(),
+            tls: true,
+            ..Default::default()
+        };
+        let data = |req: Request| {
+            let args = grpcurl_args(&prm, &req).unwrap();
+            let i = args.iter().position(|arg| arg == "-d").unwrap();
+            args[i + 1].to_string_lossy().into_owned()
+        };
+        let client = RequestBuilder::new("pkg.Svc/Upload")
+            .body(json!([{"n": 1}, {"n": 2}]))
+            .build();
+        assert_eq!("{\n  \"n\": 1\n}\n{\n  \"n\": 2\n}", data(client));
+        let bidi = RequestBuilder::new("pkg.Svc/Chat")
+            .body(json!([{"n": 1}]))
+            .stream(StreamMode::Bidi)
+            .build();
+        assert_eq!("@", data(bidi));
+    }
+
+    #[test]
+    fn test_exchange() {
+        let sh = |script: &str| {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", script]);
+            cmd
+        };
+        let exchange = |cmd: Command, messages: &[Value], timeout: u64| {
+            crate::transport::runtime().block_on(exchange(cmd, messages, timeout))
+        };
+        let messages = [json!({"n": 1}), json!({"n": 2})];
+
+        // a server replying one to one while flooding stderr
+        let script = "head -c 1000000 /dev/zero >&2; cat; echo '{\"n\": 3}'";
+        let (received, status, stderr) = exchange(sh(script), &messages, 5).unwrap();
+        assert_eq!(
+            vec![json!({"n": 1}), json!({"n": 2}), json!({"n": 3})],
+            received
+        );
+        assert!(status.success());
+        assert_eq!(1_000_000, stderr.len());
+
+        // a server that never replies
+        let started = std::time::Instant::now();
+        let err = exchange(sh("read line; exec sleep 5"), &messages, 1).unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert_eq!(
+            "grpcurl bidirectional stream timed out",
+            err.downcast_ref::<GrpcurlOutput>().unwrap().to_string()
+        );
+
+        // a server replying with invalid JSON
+        let err = exchange(sh("read line; echo 'not json'"), &messages, 5).unwrap_err();
+        let output = err.downcast_ref::<GrpcurlOutput>().unwrap();
+        assert_eq!(
+            "grpcurl response could not be parsed as JSON",
+            output.to_string()
+        );
+        assert_eq!("not json\n", output.stdout);
+    }
 }
@@ -0,0 +1,152 @@
+use crate::{
+    coverage::{cmd_coverage, cmd_proto_coverage},
+    graph::cmd_graph,
+    ls::cmd_ls,
+    params::BaseParams,
+    Hash, ReelCmd, ReelSubCommand, ToStringCanonical,
+};
+use anyhow::{Context, Error};
+use colored::*;
+use filmreel::reel::{MetaFrame, Reel};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// ReelHash holds the content hash of every frame of a reel and of the reel as a whole, printed by
+/// `dark reel hash` and read back by `dark record --changed-since`:
+///
+/// ```json
+/// {
+///   "reel": "usr",
+///   "hash": "<sha256 of the frame hashes>",
+///   "frames": {"usr.01s.createuser.fr.json": "<sha256>"}
+/// }
+/// ```
+///
+/// A frame is hashed from its JSON content with sorted keys, formatting changes do not change the
+/// hash.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct ReelHash {
+    pub reel: String,
+    pub hash: String,
+    pub frames: BTreeMap<String, String>,
+}
+
+impl ReelHash {
+    pub fn new<T: Into<String>>(reel_name: T, frames: &[MetaFrame]) -> Result<Self, Error> {
+        let mut reel_hasher = Sha256::new();
+        let mut hashes = BTreeMap::new();
+        // the reel hash follows the sequence of the frames rather than their sorted names
+        for meta_frame in frames {
+            let filename = meta_frame.get_filename();
+            let hash = frame_hash(meta_frame).context(filename.clone())?;
+            reel_hasher.update(format!("{hash}  {filename}\n"));
+            hashes.insert(filename, hash);
+        }
+        Ok(Self {
+            reel: reel_name.into(),
+            hash: hex(reel_hasher.finalize()),
+            frames: hashes,
+        })
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).context(format!("unable to read {path:?}"))?;
+        serde_json::from_str(&contents).context(format!("{path:?} is not a reel hash file"))
+    }
+
+    /// changed returns the frames whose hash differs from the hash recorded for the same filename,
+    /// frames absent from the recorded hashes are always returned
+    pub fn changed(&self, frames: Vec<MetaFrame>) -> Result<Vec<MetaFrame>, Error> {
+        let mut changed = vec![];
+        for meta_frame in frames {
+            let filename = meta_frame.get_filename();
+            let hash = frame_hash(&meta_frame).context(filename.clone())?;
+            if self.frames.get(&filename) == Some(&hash) {
+                warn!("{} {}", "Unchanged:".yellow(), filename);
+                continue;
+            }
+            changed.push(meta_frame);
+        }
+        Ok(changed)
+    }
+}
+
+/// frame_hash returns the hex sha256 digest of the canonical JSON of a frame file, or of the
+/// in-memory frame
+fn frame_hash(meta_frame: &MetaFrame) -> Result<String, Error> {
+    let value: Value = match &meta_frame.frame {
+        Some(frame) => serde_json::to_value(frame)?,
+        None => serde_json::from_str(&filmreel::read_json_file(&meta_frame.path)?)?,
+    };
+    Ok(hex(Sha256::digest(value.to_string_canonical()?)))
+}
+
+fn hex<T: AsRef<[u8]>>(digest: T) -> String {
+    digest.as_ref().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn cmd_reel(cmd: ReelCmd, base_params: BaseParams) -> Result<(), Error> {
+    match cmd.nested {
+        ReelSubCommand::Ls(cmd) => cmd_ls(cmd),
+        ReelSubCommand::Hash(cmd) => cmd_hash(cmd),
+        ReelSubCommand::Graph(cmd) => cmd_graph(cmd),
+        ReelSubCommand::Coverage(cmd) => cmd_coverage(cmd, base_params),
+        ReelSubCommand::ProtoCoverage(cmd) => cmd_proto_coverage(cmd, base_params),
+    }
+}
+
+fn cmd_hash(cmd: Hash) -> Result<(), Error> {
+    let reel = Reel::new(&cmd.reel_path, &cmd.reel_name, None)?;
+    let frames: Vec<MetaFrame> = reel.into_iter().collect();
+    print!(
+        "{}",
+        ReelHash::new(cmd.reel_name, &frames)?.to_string_canonical()?
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reel_hash_changed() {
+        let dir = std::env::temp_dir().join(format!("darkroom_hash_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, contents: &str| {
+            fs::write(dir.join(name), contents).unwrap();
+            MetaFrame::try_from(&dir.join(name)).unwrap()
+        };
+        let first = write(
+            "usr.01s.createuser.fr.json",
+            r#"{"protocol": "HTTP", "request": {"uri": "POST /users"}, "response": {"status": 200}}"#,
+        );
+        let second = write(
+            "usr.02s.getuser.fr.json",
+            r#"{"protocol": "HTTP", "request": {"uri": "GET /users"}, "response": {"status": 200}}"#,
+        );
+        let recorded = ReelHash::new("usr", &[first, second]).unwrap();
+        assert_eq!(2, recorded.frames.len());
+
+        // reformatting a frame keeps its hash
+        let first = write(
+            "usr.01s.createuser.fr.json",
+            r#"{"response": {"status": 200},
+                "request": {"uri": "POST /users"}, "protocol": "HTTP"}"#,
+        );
+        let second = write(
+            "usr.02s.getuser.fr.json",
+            r#"{"protocol": "HTTP", "request": {"uri": "GET /users/1"}, "response": {"status": 200}}"#,
+        );
+        let changed = recorded.changed(vec![first, second]).unwrap();
+        assert_eq!(
+            vec![dir.join("usr.02s.getuser.fr.json")],
+            changed.into_iter().map(|m| m.path).collect::<Vec<_>>()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,23 +1,55 @@
-use crate::params::Params;
+use crate::{
+    dump::RequestDump,
+    params::{DnsServer, Params, TlsFiles},
+};
 use anyhow::{anyhow, Context, Error};
-use filmreel::{frame::Request, response::Response};
-use http::header::HeaderMap;
+use encoding_rs::{Encoding, UTF_8};
+use filmreel::{
+    frame::Request,
+    response::{media_type, Response},
+};
+use futures_util::TryStreamExt;
+use hickory_resolver::{
+    config::{NameServerConfig, ResolverConfig},
+    name_server::TokioConnectionProvider,
+    proto::xfer::Protocol,
+    TokioResolver,
+};
+use http::header::{self, HeaderMap};
 use log::warn;
-use reqwest::{blocking::*, Method};
-use serde_json::{json, Value};
-use std::{collections::HashMap, time::Duration};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect::Policy,
+    Certificate, Client, Identity, Method, NoProxy, Proxy, RequestBuilder,
+};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::{
+    collections::HashMap,
+    fs, io,
+    net::{SocketAddr, ToSocketAddrs},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tokio_util::io::StreamReader;
 use url::Url;
 
+/// PATH_SEGMENT percent-encodes every character outside of the RFC 3986 unreserved set so that a
+/// path parameter can never introduce a `/`, `?` or `#` into the request uri
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
 /// build_request parses a Frame Request and a Params object to send a HTTP payload using reqwest
-pub fn build_request(prm: &Params, req: Request) -> Result<RequestBuilder, Error> {
+pub fn build_request(client: &Client, prm: &Params, req: Request) -> Result<RequestBuilder, Error> {
     let method: Method;
     let endpoint: Url;
 
-    let timeout = match prm.timeout {
-        0 => None,
-        _ => Some(Duration::from_secs(prm.timeout)),
-    };
-
     match &req
         .get_uri()
         .splitn(2, ' ')
@@ -27,9 +59,10 @@ pub fn build_request(prm: &Params, req: Request) -> Result<RequestBuilder, Error
         [method_str, tail_str] => {
             method = Method::from_bytes(method_str.as_bytes())?;
             let entrypoint = &prm.address;
+            let tail = expand_path_params(tail_str, &req)?;
             endpoint = Url::parse(entrypoint)
                 .context(format!("base url: {entrypoint}"))?
-                .join(tail_str)
+                .join(&tail)
                 .context(format!(
                     "base url: {entrypoint}, This is the case if the scheme and ':' delimiter are not followed by a '/',
 such as 'data:' mailto: URLs, and localhost without a leading http:// or https://"
@@ -40,10 +73,7 @@ such as 'data:' mailto: URLs, and localhost without a leading http:// or https:/
         }
     };
 
-    let mut builder = Client::builder()
-        .timeout(timeout)
-        .build()?
-        .request(method, endpoint);
+    let mut builder = client.request(method, endpoint);
     if let Some(b) = req.to_val_payload()? {
         builder = builder.body(b.to_string());
     }
@@ -56,7 +86,9 @@ such as 'data:' mailto: URLs, and localhost without a leading http:// or https:/
         }
 
         match etc.get("query") {
-            Some(Value::Object(f)) => builder = builder.query(&f),
+            Some(Value::Object(q)) => {
+                builder = builder.query(&build_query(q, QueryEncoding::from_etc(&etc)?)?)
+            }
             Some(Value::Null) | None => (),
             _ => return Err(anyhow!("request[\"query\"] must be a key value map")),
         }
@@ -68,6 +100,547 @@ such as 'data:' mailto: URLs, and localhost without a leading http:// or https:/
     Ok(builder)
 }
 
+/// dump_request describes the HTTP request built for a Frame Request as JSON and as a `curl`
+/// command without sending it
+pub fn dump_request(prm: &Params, req: Request) -> Result<RequestDump, Error> {
+    let client = build_client(prm, follow_location(&req)?)?;
+    let request = build_request(&client, prm, req)?.build()?;
+    let mut command: Vec<String> = vec![
+        "curl".to_string(),
+        "-X".to_string(),
+        request.method().to_string(),
+        request.url().to_string(),
+    ];
+    let tls_files = [
+        ("--cacert", &prm.tls_files.cacert),
+        ("--cert", &prm.tls_files.cert),
+        ("--key", &prm.tls_files.key),
+    ];
+    for (flag, path) in tls_files {
+        if let Some(path) = path {
+            command.extend([flag.to_string(), path.to_string_lossy().into_owned()]);
+        }
+    }
+    if let Some(proxy) = &prm.proxy {
+        command.extend(["--proxy".to_string(), proxy.clone()]);
+    }
+    match &prm.dns {
+        Some(DnsServer::Nameserver(addr)) => {
+            command.extend(["--dns-servers".to_string(), addr.to_string()])
+        }
+        Some(DnsServer::Https(url)) => command.extend(["--doh-url".to_string(), url.clone()]),
+        None => (),
+    }
+    let mut header = Map::new();
+    for (key, value) in request.headers() {
+        let value = value
+            .to_str()
+            .context(format!("header {key} is not valid ASCII"))?;
+        command.extend(["-H".to_string(), format!("{key}: {value}")]);
+        header.insert(key.to_string(), value.into());
+    }
+    let body = request
+        .body()
+        .and_then(|b| b.as_bytes())
+        .map(|b| String::from_utf8_lossy(b).into_owned());
+    if let Some(body) = &body {
+        // curl would otherwise send a form Content-Type that the original request did not have
+        if !request.headers().contains_key(header::CONTENT_TYPE) {
+            command.extend(["-H".to_string(), "Content-Type:".to_string()]);
+        }
+        command.extend(["--data-raw".to_string(), body.clone()]);
+    }
+    Ok(RequestDump {
+        request: json!({
+            "method": request.method().as_str(),
+            "url": request.url().as_str(),
+            "header": header,
+            // JSON bodies are kept as JSON for readability
+            "body": body.map(|b| serde_json::from_str(&b).unwrap_or(Value::String(b))),
+        }),
+        command,
+    })
+}
+
+/// expand_path_params substitutes every `{name}` placeholder of the uri path with the URL-encoded
+/// value of `"request"["path_params"]["name"]`:
+///
+/// ```json
+/// "request": {
+///   "uri": "GET /users/{id}",
+///   "path_params": {"id": "${USER_ID}"}
+/// }
+/// ```
+///
+/// the path is left untouched when no `"path_params"` are present
+fn expand_path_params(path: &str, req: &Request) -> Result<String, Error> {
+    let etc = req.get_etc();
+    let params = match etc.as_ref().and_then(|etc| etc.get("path_params")) {
+        Some(Value::Object(p)) => p,
+        Some(Value::Null) | None => return Ok(path.to_string()),
+        _ => return Err(anyhow!("request[\"path_params\"] must be a key value map")),
+    };
+
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| anyhow!("unclosed path parameter in request uri => {path}"))?;
+        let name = &rest[start + 1..end];
+        let value = match params.get(name) {
+            Some(Value::String(s)) => s.clone(),
+            Some(v @ (Value::Number(_) | Value::Bool(_))) => v.to_string(),
+            Some(_) => {
+                return Err(anyhow!(
+                    "request[\"path_params\"][\"{name}\"] must be a string, number, or boolean"
+                ))
+            }
+            None => {
+                return Err(anyhow!(
+                    "missing path parameter \"{name}\" for request uri => {path}"
+                ))
+            }
+        };
+        expanded.push_str(&rest[..start]);
+        expanded.extend(utf8_percent_encode(&value, PATH_SEGMENT));
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+
+    let unused: Vec<&String> = params
+        .keys()
+        .filter(|k| !path.contains(&format!("{{{k}}}")))
+        .collect();
+    if !unused.is_empty() {
+        return Err(anyhow!(
+            "request[\"path_params\"] {unused:?} not found in request uri => {path}"
+        ));
+    }
+    Ok(expanded)
+}
+
+/// QueryEncoding determines how array values of `"request"["query"]` are encoded, it is read from
+/// `"request"["query_encoding"]`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum QueryEncoding {
+    /// `?id=1&id=2`
+    #[default]
+    Repeat,
+    /// `?id=1,2`
+    Comma,
+}
+
+impl QueryEncoding {
+    fn from_etc(etc: &Value) -> Result<Self, Error> {
+        match etc.get("query_encoding") {
+            Some(Value::String(s)) if s == "repeat" => Ok(Self::Repeat),
+            Some(Value::String(s)) if s == "comma" => Ok(Self::Comma),
+            Some(Value::Null) | None => Ok(Self::default()),
+            _ => Err(anyhow!(
+                "request[\"query_encoding\"] must be one of: \"repeat\", \"comma\""
+            )),
+        }
+    }
+}
+
+/// build_query flattens `"request"["query"]` into URL query pairs, nested objects use bracket
+/// notation and arrays follow the [`QueryEncoding`] given:
+///
+/// ```json
+/// "query": {"filter": {"age": {"gt": 21}}, "id": [1, 2]}
+/// ```
+///
+/// is encoded as `?filter[age][gt]=21&id=1&id=2`, null values are omitted
+fn build_query(
+    query: &Map<String, Value>,
+    encoding: QueryEncoding,
+) -> Result<Vec<(String, String)>, Error> {
+    let mut pairs = vec![];
+    for (key, value) in query {
+        push_query_pair(&mut pairs, key.clone(), value, encoding)?;
+    }
+    Ok(pairs)
+}
+
+fn push_query_pair(
+    pairs: &mut Vec<(String, String)>,
+    key: String,
+    value: &Value,
+    encoding: QueryEncoding,
+) -> Result<(), Error> {
+    match value {
+        Value::Null => (),
+        Value::Object(map) => {
+            for (k, v) in map {
+                push_query_pair(pairs, format!("{key}[{k}]"), v, encoding)?;
+            }
+        }
+        Value::Array(arr) => {
+            let values = arr
+                .iter()
+                .map(|v| query_scalar(&key, v))
+                .collect::<Result<Vec<String>, Error>>()?;
+            match encoding {
+                QueryEncoding::Repeat => pairs.extend(values.into_iter().map(|v| (key.clone(), v))),
+                QueryEncoding::Comma => pairs.push((key, values.join(","))),
+            }
+        }
+        v => pairs.push((key.clone(), query_scalar(&key, v)?)),
+    }
+    Ok(())
+}
+
+fn query_scalar(key: &str, value: &Value) -> Result<String, Error> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(_) | Value::Bool(_) => Ok(value.to_string()),
+        _ => Err(anyhow!(
+            "request[\"query\"] array values must be strings, numbers, or booleans => {key}"
+        )),
+    }
+}
+
+/// ClientCache holds the clients built by a take or record keyed by the settings they were built
+/// with, so that the connections and TLS sessions of a client are reused by later frames
+#[derive(Debug, Default)]
+pub struct ClientCache(Mutex<HashMap<ClientKey, Client>>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientKey {
+    timeout: u64,
+    proxy: Option<String>,
+    dns: Option<DnsServer>,
+    tls_files: TlsFiles,
+    follow: bool,
+}
+
+impl ClientCache {
+    /// get returns the client built with the settings of the Params, building it on first use
+    pub fn get(&self, prm: &Params, follow: bool) -> Result<Client, Error> {
+        let key = ClientKey {
+            timeout: prm.timeout,
+            proxy: prm.proxy.clone(),
+            dns: prm.dns.clone(),
+            tls_files: prm.tls_files.clone(),
+            follow,
+        };
+        let mut clients = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(client) = clients.get(&key) {
+            return Ok(client.clone());
+        }
+        let client = build_client(prm, follow)?;
+        clients.insert(key, client.clone());
+        Ok(client)
+    }
+}
+
+/// build_client constructs an asynchronous client using the request timeout given, automatic
+/// redirects are disabled for follow so that the first hop can be captured by follow_location
+pub fn build_client(prm: &Params, follow: bool) -> Result<Client, Error> {
+    let redirect = match follow {
+        true => Policy::none(),
+        false => Policy::default(),
+    };
+    let mut builder = Client::builder().redirect(redirect);
+    if prm.timeout > 0 {
+        builder = builder.timeout(Duration::from_secs(prm.timeout));
+    }
+    if let Some(proxy) = &prm.proxy {
+        builder = builder.proxy(
+            Proxy::all(proxy)
+                .context(format!("invalid --proxy {proxy}"))?
+                .no_proxy(NoProxy::from_env()),
+        );
+    }
+    if let Some(dns) = &prm.dns {
+        builder = builder.dns_resolver(Arc::new(DnsResolver::new(dns)?));
+    }
+    let read = |path: &PathBuf| fs::read(path).context(format!("unable to read {path:?}"));
+    if let Some(cacert) = &prm.tls_files.cacert {
+        builder = builder.add_root_certificate(
+            Certificate::from_pem(&read(cacert)?).context("invalid --cacert certificate")?,
+        );
+    }
+    if let (Some(cert), Some(key)) = (&prm.tls_files.cert, &prm.tls_files.key) {
+        builder = builder.identity(
+            Identity::from_pkcs8_pem(&read(cert)?, &read(key)?)
+                .context("invalid --cert certificate or PKCS#8 --key")?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
+/// DnsResolver resolves the hostnames of HTTP requests with the `--dns` nameserver
+struct DnsResolver(TokioResolver);
+
+impl DnsResolver {
+    fn new(dns: &DnsServer) -> Result<Self, Error> {
+        let mut config = ResolverConfig::new();
+        match dns {
+            DnsServer::Nameserver(addr) => {
+                config.add_name_server(NameServerConfig::new(*addr, Protocol::Udp));
+                config.add_name_server(NameServerConfig::new(*addr, Protocol::Tcp));
+            }
+            DnsServer::Https(url) => {
+                let url = Url::parse(url).context(format!("invalid --dns url {url}"))?;
+                let host = url
+                    .host_str()
+                    .ok_or_else(|| anyhow!("--dns url {url} is missing a host"))?;
+                // the DNS-over-HTTPS endpoint itself is reached through the system resolver
+                let addr = (host.trim_matches(['[', ']']), url.port().unwrap_or(443))
+                    .to_socket_addrs()
+                    .context(format!("unable to resolve the --dns host {host}"))?
+                    .next()
+                    .ok_or_else(|| anyhow!("unable to resolve the --dns host {host}"))?;
+                let mut server = NameServerConfig::new(addr, Protocol::Https);
+                server.tls_dns_name = Some(host.trim_matches(['[', ']']).to_string());
+                server.http_endpoint = Some(url.path().to_string());
+                config.add_name_server(server);
+            }
+        }
+        Ok(Self(
+            TokioResolver::builder_with_config(config, TokioConnectionProvider::default()).build(),
+        ))
+    }
+}
+
+impl Resolve for DnsResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.0.clone();
+        Box::pin(async move {
+            let lookup = resolver.lookup_ip(name.as_str()).await?;
+            // the port of the request uri replaces the port of resolved addresses
+            let addrs: Addrs = Box::new(
+                lookup
+                    .into_iter()
+                    .map(|ip| SocketAddr::new(ip, 0))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            );
+            Ok(addrs)
+        })
+    }
+}
+
+/// follow_location reads the `"request"["follow_location"]` flag, when true the Location header of
+/// the response is followed with a GET request and both hops are exposed in the frame response:
+///
+/// ```json
+/// "response": {
+///   "body": "<body of the GET response>",
+///   "redirect": {"status": 201, "location": "http://host/users/1", "body": "<body of the first response>"},
+///   "status": 200
+/// }
+/// ```
+fn follow_location(req: &Request) -> Result<bool, Error> {
+    match req
+        .get_etc()
+        .as_ref()
+        .and_then(|etc| etc.get("follow_location"))
+    {
+        Some(Value::Bool(follow)) => Ok(*follow),
+        Some(Value::Null) | None => Ok(false),
+        _ => Err(anyhow!("request[\"follow_location\"] must be a boolean")),
+    }
+}
+
+/// retry_connect reads the `"request"["retry_connect"]` flag, opting a request in or out of the
+/// `--retry-connect` retries that are otherwise only made for GET and HEAD requests
+fn retry_connect(req: &Request) -> Result<bool, Error> {
+    match req
+        .get_etc()
+        .as_ref()
+        .and_then(|etc| etc.get("retry_connect"))
+    {
+        Some(Value::Bool(retry)) => Ok(*retry),
+        Some(Value::Null) | None => Ok(matches!(
+            req.get_uri().split(' ').next(),
+            Some("GET" | "HEAD")
+        )),
+        _ => Err(anyhow!("request[\"retry_connect\"] must be a boolean")),
+    }
+}
+
+/// send_retrying resends a request up to `retries` times while it fails before any response is
+/// received, waiting 100ms before the first retry and twice as long before each following one
+async fn send_retrying(builder: RequestBuilder, retries: u32) -> Result<reqwest::Response, Error> {
+    let mut delay = Duration::from_millis(100);
+    for retry in 1..=retries {
+        // a streamed body cannot be cloned and is only sent once
+        let Some(attempt) = builder.try_clone() else {
+            break;
+        };
+        match attempt.send().await {
+            Err(e) if is_connection_failure(&e) => {
+                warn!(
+                    "connection failure, retry {retry}/{retries} in {}ms: {e}",
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            response => return Ok(response?),
+        }
+    }
+    Ok(builder.send().await?)
+}
+
+/// is_connection_failure is true for a refused connection, a DNS failure, or a connection closed
+/// before a response was received, timeouts and HTTP error statuses are never retried
+fn is_connection_failure(e: &reqwest::Error) -> bool {
+    if e.is_connect() {
+        return true;
+    }
+    let mut source = std::error::Error::source(e);
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<io::Error>() {
+            return matches!(
+                io.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::UnexpectedEof
+            );
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// SseOpts reads `"request"["sse"]`, the bounds of the events collected from a
+/// `text/event-stream` response, events are otherwise collected until the server closes the
+/// stream:
+///
+/// ```json
+/// "sse": {"max_events": 3, "timeout": 2.5}
+/// ```
+///
+/// `timeout` is the number of seconds after which the events received so far are kept, the
+/// events received before the client `--timeout` elapses are kept as well
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct SseOpts {
+    max_events: Option<usize>,
+    timeout: Option<f64>,
+}
+
+impl SseOpts {
+    fn from_request(req: &Request) -> Result<Self, Error> {
+        let opts: Self = match req.get_etc().as_ref().and_then(|etc| etc.get("sse")) {
+            Some(Value::Null) | None => return Ok(Self::default()),
+            Some(sse) => serde_json::from_value(sse.clone())
+                .context("request[\"sse\"] must be an object of: {\"max_events\", \"timeout\"}")?,
+        };
+        if opts
+            .timeout
+            .is_some_and(|secs| !secs.is_finite() || secs < 0.0)
+        {
+            return Err(anyhow!(
+                "request[\"sse\"][\"timeout\"] must be a non-negative number of seconds"
+            ));
+        }
+        Ok(opts)
+    }
+}
+
+/// SseEvent accumulates the fields of an event of a `text/event-stream` until the blank line
+/// dispatching it
+#[derive(Debug, Default)]
+struct SseEvent {
+    event: Option<String>,
+    id: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseEvent {
+    /// push_line reads a line of the stream, returning the event dispatched by a blank line as
+    /// `{"event", "id", "data"}` where `"event"` and `"id"` are only present when sent and
+    /// `"data"` is parsed as JSON when possible
+    fn push_line(&mut self, line: &str) -> Option<Value> {
+        if line.is_empty() {
+            let event = std::mem::take(self);
+            // events without data are not dispatched
+            if event.data.is_empty() {
+                return None;
+            }
+            let data = event.data.join("\n");
+            let mut value = json!({
+                "data": serde_json::from_str(&data).unwrap_or(Value::String(data)),
+            });
+            if let Some(name) = event.event {
+                value["event"] = Value::String(name);
+            }
+            if let Some(id) = event.id {
+                value["id"] = Value::String(id);
+            }
+            return Some(value);
+        }
+        // lines starting with a colon are comments such as keep-alive pings
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value).to_string();
+        match field {
+            "event" => self.event = Some(value),
+            "id" => self.id = Some(value),
+            "data" => self.data.push(value),
+            _ => (),
+        }
+        None
+    }
+}
+
+/// collect_events reads the events of a `text/event-stream` until the stream is closed or one of
+/// the bounds of the SseOpts is reached
+async fn collect_events<R>(reader: R, opts: SseOpts) -> Result<Vec<Value>, Error>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let deadline = opts
+        .timeout
+        .map(|secs| tokio::time::Instant::now() + Duration::from_secs_f64(secs));
+    let mut lines = reader.lines();
+    let mut event = SseEvent::default();
+    let mut events = vec![];
+    while opts.max_events.is_none_or(|max| events.len() < max) {
+        let line = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, lines.next_line()).await {
+                Ok(line) => line,
+                // the timeout elapsed
+                Err(_) => break,
+            },
+            None => lines.next_line().await,
+        };
+        match line {
+            Ok(Some(line)) => events.extend(event.push_line(&line)),
+            // the stream was closed
+            Ok(None) => break,
+            Err(e) if is_timeout(&e) => {
+                warn!(
+                    "text/event-stream timed out, keeping the {} events received",
+                    events.len()
+                );
+                break;
+            }
+            Err(e) => return Err(Error::from(e).context("text/event-stream read failure")),
+        }
+    }
+    Ok(events)
+}
+
+/// is_timeout returns true if a read of the response body failed because the client timeout
+/// elapsed
+fn is_timeout(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::TimedOut
+        || err
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<reqwest::Error>())
+            .is_some_and(reqwest::Error::is_timeout)
+}
+
 /// build_header constructs a header map from the header arg passed in from a ::Take or ::Record struct
 fn build_header(header: &str) -> Result<HeaderMap, Error> {
     let map: HashMap<String, String> = serde_json::from_str(header)?;
@@ -77,11 +650,79 @@ fn build_header(header: &str) -> Result<HeaderMap, Error> {
     }
 }
 
-// request is used by run_request to send an http request and deserialize the returned data
-// into a Response struct
-pub fn request<'a>(prm: Params, req: Request) -> Result<Response<'a>, Error> {
-    let response = build_request(&prm, req)?.send()?;
+// request is used by NetworkTransport to send an http request and deserialize the returned data
+// into a Response struct, the time to first byte is measured until the response headers of the
+// first hop are received
+pub async fn request<'a>(
+    clients: &ClientCache,
+    prm: Params<'_>,
+    req: Request,
+) -> Result<Response<'a>, Error> {
+    let follow = follow_location(&req)?;
+    let sse = SseOpts::from_request(&req)?;
+    let retries = match retry_connect(&req)? {
+        true => prm.retry_connect,
+        false => 0,
+    };
+    let builder = build_request(&clients.get(&prm, follow)?, &prm, req)?;
+    let sent = Instant::now();
+    let response = send_retrying(builder, retries).await?;
+    let ttfb = Some(sent.elapsed());
+    if !follow {
+        return to_response(response, sse)
+            .await
+            .map(|response| Response { ttfb, ..response });
+    }
+
+    let location = response
+        .headers()
+        .get(header::LOCATION)
+        .ok_or_else(|| anyhow!("follow_location: response is missing a Location header"))?
+        .to_str()
+        .context("follow_location: Location header is not valid ASCII")?;
+    let location = response.url().join(location).context(format!(
+        "follow_location: invalid Location header => {location}"
+    ))?;
+    let mut builder = clients.get(&prm, false)?.get(location.clone());
+    if let Some(h) = &prm.header {
+        builder = builder.headers(build_header(h)?);
+    }
+
+    let first = to_response(response, sse).await?;
+    let mut redirect = json!({"status": first.status, "location": location.as_str()});
+    if let Some(body) = first.body {
+        redirect["body"] = body;
+    }
+    let mut followed = to_response(send_retrying(builder, retries).await?, sse).await?;
+    followed.etc = Some(json!({ "redirect": redirect }));
+    followed.ttfb = ttfb;
+    Ok(followed)
+}
+
+/// to_response deserializes a reqwest response into a frame Response, the events of a
+/// `text/event-stream` response are collected into an array body
+async fn to_response<'a>(response: reqwest::Response, sse: SseOpts) -> Result<Response<'a>, Error> {
     let status = response.status().as_u16() as u32;
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .map(|v| v.to_str().map(String::from))
+        .transpose()
+        .context("http::request Content-Type header is not valid ASCII")?;
+    let header = Some(response_header(response.headers()));
+    if content_type.as_deref().map(media_type).as_deref() == Some("text/event-stream") {
+        // a failed read keeps the reqwest error as its source so that timeouts are recognized
+        let stream = StreamReader::new(response.bytes_stream().map_err(io::Error::other));
+        return Ok(Response {
+            body: Some(Value::Array(collect_events(stream, sse).await?)),
+            content_type,
+            header,
+            etc: Some(json!({})),
+            validation: None,
+            ttfb: None,
+            status,
+        });
+    }
     // reqwest.Response is a private Option<Value> field so we rely on
     // the Response.content_length() method to get the exact body byte size
     let response_body: Option<Value> = match response.content_length() {
@@ -90,25 +731,99 @@ pub fn request<'a>(prm: Params, req: Request) -> Result<Response<'a>, Error> {
             warn!("unable to determine Response body content length");
             None
         }
-        Some(_) => response
-            .json()
-            .context("http::request response.json() decode failure")?,
+        Some(_) => parse_body(content_type.as_deref(), &response.bytes().await?)?,
     };
 
     Ok(Response {
-        // TODO add response headers
         body: response_body,
+        content_type,
+        header,
         etc: Some(json!({})),
         validation: None,
+        ttfb: None,
         status,
     })
 }
 
+/// response_header collects the response headers keyed by their lowercase name, a header sent more
+/// than once holds the array of its values. Values that are not valid UTF-8 are decoded lossily.
+fn response_header(headers: &HeaderMap) -> Map<String, Value> {
+    let mut header = Map::new();
+    for name in headers.keys() {
+        let mut values: Vec<Value> = headers
+            .get_all(name)
+            .iter()
+            .map(|v| String::from_utf8_lossy(v.as_bytes()).into())
+            .collect();
+        let value = match values.len() {
+            1 => values.remove(0),
+            _ => Value::Array(values),
+        };
+        header.insert(name.to_string(), value);
+    }
+    header
+}
+
+/// parse_body deserializes the response body according to its declared Content-Type:
+/// `text/*` bodies are kept as a JSON string, any other body must be valid JSON. The body is
+/// decoded from the `charset` parameter of the Content-Type or its byte order mark, UTF-8 being
+/// assumed otherwise.
+fn parse_body(content_type: Option<&str>, bytes: &[u8]) -> Result<Option<Value>, Error> {
+    let text = decode_body(content_type, bytes)?;
+    let declared = match content_type {
+        Some(ct) => media_type(ct),
+        None => {
+            return serde_json::from_str(&text)
+                .context("response body is not valid JSON and no Content-Type was declared")
+        }
+    };
+    if declared.starts_with("text/") {
+        return Ok(Some(Value::String(text)));
+    }
+    serde_json::from_str(&text).context(format!(
+        "response body could not be parsed as JSON for Content-Type: {declared}"
+    ))
+}
+
+/// decode_body decodes the response body into a string using the byte order mark of the body,
+/// falling back to the `charset` parameter of the Content-Type and then UTF-8
+fn decode_body(content_type: Option<&str>, bytes: &[u8]) -> Result<String, Error> {
+    let (encoding, bytes) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_len)) => (encoding, &bytes[bom_len..]),
+        None => match content_type.and_then(charset) {
+            Some(label) => (
+                Encoding::for_label(label.as_bytes())
+                    .ok_or_else(|| anyhow!("response body has an unsupported charset: {label}"))?,
+                bytes,
+            ),
+            None => (UTF_8, bytes),
+        },
+    };
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(String::from)
+        .ok_or_else(|| anyhow!("response body is not valid {} text", encoding.name()))
+}
+
+/// charset returns the `charset` parameter of a Content-Type, stripped of any quotes
+fn charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use http::header;
+    use crate::transport::runtime;
     use rstest::*;
+    use std::{
+        io::{BufRead, BufReader},
+        thread,
+    };
 
     fn case_build_header(case: u32) -> HeaderMap {
         let mut header = HeaderMap::new();
@@ -137,4 +852,356 @@ mod tests {
     fn test_build_header(string_header: &str, expected: HeaderMap) {
         assert_eq!(expected, build_header(string_header).unwrap());
     }
+
+    #[rstest(
+        proxy,
+        expected,
+        case(None, true),
+        case(Some("http://proxy:3128"), true),
+        case(Some("socks5h://proxy:1080"), true),
+        case(Some("not a url"), false)
+    )]
+    fn test_build_client_proxy(proxy: Option<&str>, expected: bool) {
+        let prm = Params {
+            proxy: proxy.map(String::from),
+            ..Default::default()
+        };
+        assert_eq!(expected, build_client(&prm, true).is_ok());
+    }
+
+    #[test]
+    fn test_dns_resolver() {
+        // a nameserver answering every A query with 127.0.0.1 and any other query with no records
+        let dns = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dns_addr = dns.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0; 512];
+            while let Ok((_, peer)) = dns.recv_from(&mut buf) {
+                let mut end = 12;
+                while buf[end] != 0 {
+                    end += buf[end] as usize + 1;
+                }
+                end += 5;
+                let is_a = buf[end - 4..end - 2] == [0, 1];
+                let mut reply =
+                    [&buf[..2], &[0x81, 0x80, 0, 1, 0, is_a as u8, 0, 0, 0, 0]].concat();
+                reply.extend_from_slice(&buf[12..end]);
+                if is_a {
+                    reply.extend_from_slice(&[
+                        0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 127, 0, 0, 1,
+                    ]);
+                }
+                dns.send_to(&reply, peer).unwrap();
+            }
+        });
+        let http = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = http.local_addr().unwrap().port();
+        thread::spawn(move || {
+            use std::io::Write;
+            let (mut stream, _) = http.accept().unwrap();
+            // the request ends with the blank line closing its headers
+            BufReader::new(&stream)
+                .lines()
+                .find(|line| line.as_ref().unwrap().is_empty());
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+        });
+
+        let prm = Params {
+            dns: Some(DnsServer::Nameserver(dns_addr)),
+            ..Default::default()
+        };
+        let response = runtime()
+            .block_on(
+                build_client(&prm, true)
+                    .unwrap()
+                    .get(format!("http://service.internal:{port}/"))
+                    .send(),
+            )
+            .unwrap();
+        assert_eq!(204, response.status().as_u16());
+    }
+
+    #[test]
+    fn test_client_cache() {
+        use std::io::Write;
+
+        // a server answering every request of a single kept alive connection
+        let http = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = http.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = http.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            loop {
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    line.clear();
+                }
+                if line.is_empty() {
+                    break;
+                }
+                stream
+                    .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                    .unwrap();
+            }
+        });
+
+        let clients = ClientCache::default();
+        let prm = Params {
+            timeout: 5,
+            address: format!("http://127.0.0.1:{port}"),
+            ..Default::default()
+        };
+        let req: Request = serde_json::from_value(json!({"uri": "GET /"})).unwrap();
+        for _ in 0..2 {
+            let response = runtime()
+                .block_on(request(&clients, prm.clone(), req.clone()))
+                .unwrap();
+            assert_eq!(204, response.status);
+        }
+        assert_eq!(1, clients.0.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_response_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::LOCATION, "/users/BIG_BEN".parse().unwrap());
+        headers.append(header::SET_COOKIE, "a=1".parse().unwrap());
+        headers.append(header::SET_COOKIE, "b=2".parse().unwrap());
+        assert_eq!(
+            json!({"location": "/users/BIG_BEN", "set-cookie": ["a=1", "b=2"]}),
+            Value::Object(response_header(&headers))
+        );
+    }
+
+    #[rstest(
+        content_type,
+        body,
+        expected,
+        case(Some("application/json; charset=utf-8"), r#"{"a":1}"#, Some(json!({"a": 1}))),
+        case(None, r#"{"a":1}"#, Some(json!({"a": 1}))),
+        case(Some("text/plain"), "{not json", Some(json!("{not json"))),
+        case(Some("application/json"), "{not json", None),
+        case(None, "<html/>", None)
+    )]
+    fn test_parse_body(content_type: Option<&str>, body: &str, expected: Option<Value>) {
+        match expected {
+            Some(v) => assert_eq!(Some(v), parse_body(content_type, body.as_bytes()).unwrap()),
+            None => assert!(parse_body(content_type, body.as_bytes()).is_err()),
+        }
+    }
+
+    #[rstest(
+        content_type,
+        bytes,
+        expected,
+        case(Some("text/plain; charset=ISO-8859-1"), b"caf\xe9".to_vec(), Some(json!("café"))),
+        case(
+            Some(r#"application/json; charset="utf-16le""#),
+            b"{\"a\":\"\xe9\"}".iter().flat_map(|b| [*b, 0]).collect(),
+            Some(json!({"a": "é"}))
+        ),
+        case(
+            Some("application/json"),
+            [&[0xfe, 0xff][..], &br#"{"a":1}"#.iter().flat_map(|b| [0, *b]).collect::<Vec<u8>>()].concat(),
+            Some(json!({"a": 1}))
+        ),
+        case(Some("text/plain"), b"caf\xe9".to_vec(), None),
+        case(Some("text/plain; charset=klingon"), b"qapla'".to_vec(), None)
+    )]
+    fn test_parse_body_charset(
+        content_type: Option<&str>,
+        bytes: Vec<u8>,
+        expected: Option<Value>,
+    ) {
+        match expected {
+            Some(v) => assert_eq!(Some(v), parse_body(content_type, &bytes).unwrap()),
+            None => assert!(parse_body(content_type, &bytes).is_err()),
+        }
+    }
+
+    #[rstest(
+        request,
+        expected,
+        case(r#"{"uri": "POST /users"}"#, Some(false)),
+        case(r#"{"uri": "POST /users", "follow_location": true}"#, Some(true)),
+        case(r#"{"uri": "POST /users", "follow_location": "yes"}"#, None)
+    )]
+    fn test_follow_location(request: &str, expected: Option<bool>) {
+        let request: Request = serde_json::from_str(request).unwrap();
+        assert_eq!(expected, follow_location(&request).ok());
+    }
+
+    #[rstest(
+        request,
+        expected,
+        case(r#"{"uri": "GET /users"}"#, Some(true)),
+        case(r#"{"uri": "HEAD /users"}"#, Some(true)),
+        case(r#"{"uri": "POST /users"}"#, Some(false)),
+        case(r#"{"uri": "PUT /users/1", "retry_connect": true}"#, Some(true)),
+        case(r#"{"uri": "GET /users", "retry_connect": false}"#, Some(false)),
+        case(r#"{"uri": "GET /users", "retry_connect": 3}"#, None)
+    )]
+    fn test_retry_connect(request: &str, expected: Option<bool>) {
+        let request: Request = serde_json::from_str(request).unwrap();
+        assert_eq!(expected, retry_connect(&request).ok());
+    }
+
+    #[test]
+    fn test_send_retrying_refused() {
+        // binding then dropping a listener leaves a local port that refuses connections
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let builder = Client::new().get(format!("http://127.0.0.1:{port}/"));
+        let err = runtime().block_on(send_retrying(builder, 1)).unwrap_err();
+        assert!(is_connection_failure(
+            err.downcast_ref::<reqwest::Error>().unwrap()
+        ));
+    }
+
+    #[rstest(
+        request,
+        expected,
+        case(r#"{"uri": "GET /users/{id}"}"#, Some("/users/{id}")),
+        case(
+            r#"{"uri": "GET /users/{id}", "path_params": {"id": "BIG_BEN"}}"#,
+            Some("/users/BIG_BEN")
+        ),
+        case(
+            r#"{"uri": "GET /users/{id}/posts/{post}", "path_params": {"id": 7, "post": "a b"}}"#,
+            Some("/users/7/posts/a%20b")
+        ),
+        case(
+            r#"{"uri": "GET /users/{id}", "path_params": {"id": "../admin?all=true#"}}"#,
+            Some("/users/..%2Fadmin%3Fall%3Dtrue%23")
+        ),
+        case(r#"{"uri": "GET /users/{id}", "path_params": {}}"#, None),
+        case(r#"{"uri": "GET /users/{id", "path_params": {"id": "1"}}"#, None),
+        case(r#"{"uri": "GET /users", "path_params": {"id": "1"}}"#, None),
+        case(r#"{"uri": "GET /users/{id}", "path_params": {"id": null}}"#, None),
+        case(r#"{"uri": "GET /users/{id}", "path_params": ["1"]}"#, None)
+    )]
+    fn test_expand_path_params(request: &str, expected: Option<&str>) {
+        let request: Request = serde_json::from_str(request).unwrap();
+        let path = request.get_uri();
+        let path = path.split_once(' ').unwrap().1;
+        assert_eq!(
+            expected.map(String::from),
+            expand_path_params(path, &request).ok()
+        );
+    }
+
+    #[rstest(
+        query,
+        encoding,
+        expected,
+        case(json!({"id": 1, "name": "BIG BEN"}), QueryEncoding::Repeat, Some(vec![("id", "1"), ("name", "BIG BEN")])),
+        case(json!({"id": [1, 2], "q": null}), QueryEncoding::Repeat, Some(vec![("id", "1"), ("id", "2")])),
+        case(json!({"id": [1, 2]}), QueryEncoding::Comma, Some(vec![("id", "1,2")])),
+        case(
+            json!({"filter": {"age": {"gt": 21}, "tag": ["a", "b"]}}),
+            QueryEncoding::Repeat,
+            Some(vec![("filter[age][gt]", "21"), ("filter[tag]", "a"), ("filter[tag]", "b")])
+        ),
+        case(json!({"id": [{"a": 1}]}), QueryEncoding::Repeat, None)
+    )]
+    fn test_build_query(
+        query: Value,
+        encoding: QueryEncoding,
+        expected: Option<Vec<(&str, &str)>>,
+    ) {
+        let expected = expected.map(|pairs| {
+            pairs
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<Vec<(String, String)>>()
+        });
+        assert_eq!(
+            expected,
+            build_query(query.as_object().unwrap(), encoding).ok()
+        );
+    }
+
+    const EVENT_STREAM: &str = ": ping\n\nevent: update\nid: 1\ndata: {\"n\": 1}\n\ndata: first\ndata: second\n\nretry: 10\n\ndata: unterminated";
+
+    #[rstest(
+        opts,
+        expected,
+        case(
+            SseOpts::default(),
+            vec![json!({"event": "update", "id": "1", "data": {"n": 1}}), json!({"data": "first\nsecond"})]
+        ),
+        case(
+            SseOpts {max_events: Some(1), timeout: None},
+            vec![json!({"event": "update", "id": "1", "data": {"n": 1}})]
+        )
+    )]
+    fn test_collect_events(opts: SseOpts, expected: Vec<Value>) {
+        let events = runtime().block_on(collect_events(EVENT_STREAM.as_bytes(), opts));
+        assert_eq!(expected, events.unwrap());
+    }
+
+    #[test]
+    fn test_collect_events_timeout() {
+        use std::{
+            pin::Pin,
+            task::{Context, Poll},
+        };
+        use tokio::io::{AsyncRead, AsyncReadExt, BufReader, ReadBuf};
+
+        // a stream that sends a single event before blocking for as long as the server end is open
+        let (stalled, _server) = tokio::io::duplex(64);
+        let opts = SseOpts {
+            max_events: None,
+            timeout: Some(0.2),
+        };
+        let started = Instant::now();
+        let events = runtime()
+            .block_on(collect_events(
+                BufReader::new(b"data: 1\n\n".chain(stalled)),
+                opts,
+            ))
+            .unwrap();
+        assert_eq!(vec![json!({"data": 1})], events);
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        // a stream whose read times out after a single event
+        struct TimedOut;
+        impl AsyncRead for TimedOut {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                _: &mut Context<'_>,
+                _: &mut ReadBuf<'_>,
+            ) -> Poll<io::Result<()>> {
+                Poll::Ready(Err(io::ErrorKind::TimedOut.into()))
+            }
+        }
+        let events = runtime()
+            .block_on(collect_events(
+                BufReader::new(b"data: 1\n\n".chain(TimedOut)),
+                SseOpts::default(),
+            ))
+            .unwrap();
+        assert_eq!(vec![json!({"data": 1})], events);
+    }
+
+    #[rstest(
+        request,
+        expected,
+        case(r#"{"uri": "GET /events"}"#, Some(SseOpts::default())),
+        case(
+            r#"{"uri": "GET /events", "sse": {"max_events": 2, "timeout": 1.5}}"#,
+            Some(SseOpts {max_events: Some(2), timeout: Some(1.5)})
+        ),
+        case(r#"{"uri": "GET /events", "sse": {"max": 2}}"#, None),
+        case(r#"{"uri": "GET /events", "sse": {"timeout": -1}}"#, None)
+    )]
+    fn test_sse_opts(request: &str, expected: Option<SseOpts>) {
+        let request: Request = serde_json::from_str(request).unwrap();
+        assert_eq!(expected, SseOpts::from_request(&request).ok());
+    }
 }
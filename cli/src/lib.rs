@@ -1,25 +1,73 @@
-use crate::params::BaseParams;
-use anyhow::{anyhow, Error};
+use crate::{
+    cookies::CookieJar,
+    diff::{DiffFormat, DiffLimit},
+    dump::LogRequests,
+    export::ExportFormat,
+    generate::{random_uuid, Generator},
+    metrics::MetricsTarget,
+    params::{env_proxy, BaseParams, DnsServer, TlsFiles},
+    record::{CutAssignment, CutOutMode, CutOutOpts},
+    schema::SchemaKind,
+    storage::{is_cut_file, RemoteCut},
+    transport::NetworkTransport,
+};
+use anyhow::{anyhow, Context, Error};
 use argh::FromArgs;
 //                             >:(      Colour
+#[cfg(feature = "pretty")]
 use colored_json::{prelude::*, Color as Colour, Style, Styler};
 use serde::Serialize;
-use std::{fs, path::PathBuf};
+use serde_json::Map;
+use std::{fs, path::PathBuf, sync::Arc};
 
 #[cfg(feature = "man")]
 use crate::man::Man;
 
+pub mod abort;
+pub mod artifacts;
+pub mod cookies;
+pub mod coverage;
+pub mod diff;
+pub mod dump;
+pub mod echo;
+#[cfg(feature = "exec")]
+pub mod exec;
+pub mod explain;
+pub mod export;
+pub mod fmt;
+pub mod generate;
+pub mod graph;
+#[cfg(feature = "grpc")]
 pub mod grpc;
+pub mod hash;
+#[cfg(feature = "http")]
 pub mod http;
+pub mod ls;
+pub mod metrics;
+pub mod migrate;
+pub mod oauth;
 pub mod params;
+pub mod quarantine;
 pub mod record;
+pub mod repl;
+pub mod schema;
+#[cfg(feature = "http")]
+pub mod selftest;
+#[cfg(feature = "sql")]
+pub mod sql;
+pub mod status;
+pub mod storage;
 pub mod take;
+#[cfg(feature = "tcp")]
+pub mod tcp;
+pub mod transport;
 
 #[cfg(feature = "man")]
 mod man;
 
 pub use filmreel::{
-    FrError, Frame, MetaFrame, Reel, Register, ToStringHidden, ToStringPretty, VirtualReel,
+    ErrorCode, FrError, Frame, MetaFrame, Reel, Register, ToStringCanonical, ToStringHidden,
+    ToStringPretty, VirtualReel,
 };
 
 pub struct Logger;
@@ -59,6 +107,11 @@ pub struct Command {
     #[argh(switch, short = 'v')]
     verbose: bool,
 
+    /// request and response bodies larger than this many bytes are summarized by their size, sha256
+    /// digest, and first lines in verbose output, 0 prints every body in full [default: 4096]
+    #[argh(option, default = "4096", arg_name = "bytes")]
+    verbose_body_limit: usize,
+
     /// fallback address passed to the specified protocol
     #[argh(positional)]
     address: Option<String>,
@@ -67,10 +120,107 @@ pub struct Command {
     #[argh(option, short = 'H')]
     header: Option<String>,
 
-    /// output of final cut file
+    /// add the run ID of the session, readable by frames as ${@RUN_ID}, to the headers of every
+    /// request under the name provided: --run-id-header X-Request-Id
+    #[argh(option, arg_name = "name")]
+    run_id_header: Option<String>,
+
+    /// store the cookies set by HTTP responses and send them with the subsequent HTTP requests of
+    /// the session
+    #[argh(switch)]
+    cookie_jar: bool,
+
+    /// output of the cookies stored once the session ends, implies --cookie-jar
+    #[argh(option, arg_name = "file")]
+    cookie_out: Option<PathBuf>,
+
+    /// output of final cut file, a directory, or an s3://, gs://, or https:// URL
     #[argh(option, arg_name = "file")]
     cut_out: Option<PathBuf>,
 
+    /// create the missing parent directories of --cut-out
+    #[argh(switch)]
+    mkdir: bool,
+
+    /// copy an existing --cut-out file to <file>.<timestamp>.bak before it is overwritten
+    #[argh(switch)]
+    cut_backup: bool,
+
+    /// how the final register is written to an existing --cut-out file: overwrite or merge
+    /// [default: overwrite]
+    #[argh(option, default = "CutOutMode::Overwrite", arg_name = "mode")]
+    cut_out_mode: CutOutMode,
+
+    /// output directory for the failure context of a frame: hydrated frame, actual response, cut
+    /// register, and diff
+    #[argh(option, arg_name = "dir")]
+    artifacts: Option<PathBuf>,
+
+    /// export per-frame durations and statuses of a record to an OpenMetrics text file or to a
+    /// Prometheus pushgateway http(s) address
+    #[argh(option, arg_name = "file|url")]
+    metrics: Option<MetricsTarget>,
+
+    /// output directory for the hydrated request of every failed frame as JSON and as a curl or
+    /// grpcurl command
+    #[argh(option, arg_name = "dir")]
+    dump_failures: Option<PathBuf>,
+
+    /// shell command run when a frame fails a record, such as to capture server logs, with the
+    /// failure described by the DARK_REEL, DARK_FRAME, DARK_RUN_ID, DARK_ERROR, DARK_EXIT_CODE,
+    /// DARK_ARTIFACTS, DARK_DUMP_FAILURES, and DARK_TAKE_OUT environment variables
+    #[argh(option, arg_name = "cmd")]
+    on_failure: Option<String>,
+
+    /// log a preview of every request sent that leaves out secrets, sanitized logs the method,
+    /// the uri without query values, the header names, and the body size
+    #[argh(option, arg_name = "mode")]
+    log_requests: Option<LogRequests>,
+
+    /// format of response mismatch output: text or json [default: text]
+    #[argh(option, default = "DiffFormat::Text", arg_name = "format")]
+    diff_format: DiffFormat,
+
+    /// number of unchanged lines shown around each changed line of a mismatch diff
+    #[argh(option, arg_name = "n")]
+    diff_context: Option<usize>,
+
+    /// maximum number of lines printed per mismatch diff or payload, the full payloads are written
+    /// to --artifacts
+    #[argh(option, arg_name = "n")]
+    diff_max_lines: Option<usize>,
+
+    /// explain a response mismatch: the validators applied to each selector, the outcome of every
+    /// write instruction, and the selectors where the expected and actual responses diverged
+    #[argh(switch)]
+    explain: bool,
+
+    /// number of requests sent at the same time: with --concurrent-attempts, the attempts of a
+    /// retried frame and, with --concurrent-frames, consecutive independent frames of a record,
+    /// frames that read the writes of a prior frame, generate variables, or promote headers are
+    /// taken on their own [default: 1]
+    #[argh(option, default = "1", arg_name = "n")]
+    concurrency: usize,
+
+    /// take consecutive independent frames of a record concurrently under --concurrency, the
+    /// requests of a batch are sent even if an earlier frame of the batch fails
+    #[argh(switch)]
+    concurrent_frames: bool,
+
+    /// send the retry attempts of GET, HEAD, and OPTIONS frames concurrently under --concurrency,
+    /// each once its interval has elapsed rather than once the attempt before it was answered,
+    /// attempts already sent when the take ends are not cancelled
+    #[argh(switch)]
+    concurrent_attempts: bool,
+
+    /// seed for generated cut variables, reusing the seed of a previous run reproduces its values
+    #[argh(option, arg_name = "n")]
+    seed: Option<u64>,
+
+    /// fail on frame fields that are neither part of the frame format nor a supported extension
+    #[argh(switch)]
+    strict: bool,
+
     /// interactive frame sequence transitions
     #[argh(switch, short = 'i')]
     interactive: bool,
@@ -79,6 +229,40 @@ pub struct Command {
     #[argh(switch)]
     tls: bool,
 
+    /// proxy of HTTP requests such as http://proxy:3128 or socks5h://proxy:1080, read from the
+    /// HTTPS_PROXY or ALL_PROXY environment variables when absent, hosts listed in NO_PROXY are
+    /// reached directly
+    #[argh(option, arg_name = "url")]
+    proxy: Option<String>,
+
+    /// number of times an HTTP request is resent after a connection reset, refused connection, or
+    /// DNS failure, only GET and HEAD requests are resent unless "retry_connect" is set in the
+    /// frame request, unlike frame attempts no response is ever compared [default: 0]
+    #[argh(option, default = "0", arg_name = "n")]
+    retry_connect: u32,
+
+    /// nameserver that the hostnames of HTTP requests are resolved with: an ip[:port] queried
+    /// over UDP and TCP, or a DNS-over-HTTPS url such as https://1.1.1.1/dns-query
+    #[argh(option, arg_name = "server")]
+    dns: Option<DnsServer>,
+
+    /// OAuth2 client-credentials config of the access token fetched into the cut register before
+    /// the first frame, the <reel_name>.oauth.json file of the reel directory is read otherwise
+    #[argh(option, arg_name = "file")]
+    oauth_config: Option<PathBuf>,
+
+    /// PEM certificate authority that server certificates are verified against
+    #[argh(option, arg_name = "file")]
+    cacert: Option<PathBuf>,
+
+    /// PEM client certificate for mutual TLS, provided along with --key
+    #[argh(option, arg_name = "file")]
+    cert: Option<PathBuf>,
+
+    /// PEM private key of the --cert client certificate, PKCS#8 for HTTP requests
+    #[argh(option, arg_name = "file")]
+    key: Option<PathBuf>,
+
     /// the path to a directory from which proto sources can be imported, for use with --proto flags.
     #[argh(option, arg_name = "dir")]
     proto_dir: Vec<PathBuf>,
@@ -87,6 +271,11 @@ pub struct Command {
     #[argh(option, short = 'p', arg_name = "file")]
     proto: Vec<PathBuf>,
 
+    /// match the field names of gRPC response bodies that differ only by casing, such as the
+    /// user_id proto name and the userId JSON name
+    #[argh(switch)]
+    normalize_field_names: bool,
+
     #[argh(subcommand)]
     pub nested: SubCommand,
 }
@@ -97,13 +286,99 @@ impl Command {
             timeout: 30,
             timestamp: false,
             tls: self.tls,
+            tls_files: self.tls_files(),
+            proxy: self.proxy.clone().or_else(env_proxy),
+            retry_connect: self.retry_connect,
+            concurrency: self.concurrency,
+            concurrent_frames: self.concurrent_frames,
+            concurrent_attempts: self.concurrent_attempts,
+            dns: self.dns.clone(),
+            oauth_config: self.oauth_config.clone(),
+            oauth: None,
             header: self.header.clone(),
             address: self.address.clone(),
             proto_path: self.proto_dir.clone(),
             proto: self.proto.clone(),
+            normalize_field_names: self.normalize_field_names,
             cut_out: self.cut_out.clone(),
+            cut_out_opts: self.cut_out_opts(),
+            artifacts: self.artifacts.clone(),
+            dump_failures: self.dump_failures.clone(),
+            on_failure: self.on_failure.clone(),
+            log_requests: self.log_requests,
+            generator: Generator::new(self.seed),
+            diff_format: self.diff_format,
+            diff_limit: self.diff_limit(),
+            explain: self.explain,
+            metrics: self.metrics.clone(),
+            session_header: Map::new(),
+            run_id: random_uuid(),
+            run_id_header: self.run_id_header.clone(),
+            cookie_jar: (self.cookie_jar || self.cookie_out.is_some())
+                .then(|| Arc::new(CookieJar::default())),
+            cookie_out: self.cookie_out.clone(),
+            transport: Arc::new(NetworkTransport::default()),
+            progress: None,
+            strict: self.strict,
             interactive: self.interactive,
             verbose: self.verbose,
+            verbose_body_limit: self.verbose_body_limit,
+        }
+    }
+
+    pub fn tls_files(&self) -> TlsFiles {
+        TlsFiles {
+            cacert: self.cacert.clone(),
+            cert: self.cert.clone(),
+            key: self.key.clone(),
+        }
+    }
+
+    /// validate ensures that --cut-out can be written to before any frame is run so that a
+    /// misconfigured run does not fail only once the cut register is written
+    pub fn validate(&self) -> Result<(), Error> {
+        let cut_out = match &self.cut_out {
+            Some(cut_out) if !cut_out.is_dir() && RemoteCut::from_path(cut_out).is_none() => {
+                cut_out
+            }
+            _ => return Ok(()),
+        };
+        // a trailing separator names the directory that the hidden cut file is written to
+        let dir = match cut_out
+            .to_string_lossy()
+            .ends_with(std::path::MAIN_SEPARATOR)
+        {
+            true => cut_out.as_path(),
+            false => match cut_out.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => return Ok(()),
+            },
+        };
+        if dir.is_dir() {
+            return Ok(());
+        }
+        if self.mkdir && !dir.exists() {
+            return fs::create_dir_all(dir)
+                .context(format!("unable to create --cut-out directory {dir:?}"));
+        }
+        Err(anyhow!(
+            "--cut-out directory {} must be a valid directory, pass --mkdir to create it",
+            dir.to_string_lossy()
+        ))
+    }
+
+    fn cut_out_opts(&self) -> CutOutOpts {
+        CutOutOpts {
+            backup: self.cut_backup,
+            mode: self.cut_out_mode,
+        }
+    }
+
+    fn diff_limit(&self) -> DiffLimit {
+        DiffLimit {
+            context: self.diff_context,
+            max_lines: self.diff_max_lines,
+            artifacts: self.artifacts.clone(),
         }
     }
 
@@ -131,9 +406,20 @@ pub enum SubCommand {
     Version(Version),
     Take(Take),
     Record(Record),
+    RecordAll(RecordAll),
     #[cfg(feature = "man")]
     Man(Man),
     VirtualRecord(VirtualRecord),
+    Schema(Schema),
+    Lint(Lint),
+    Migrate(Migrate),
+    Fmt(Fmt),
+    Repl(Repl),
+    Reel(ReelCmd),
+    Cut(CutCmd),
+    ValidateResponse(ValidateResponse),
+    #[cfg(feature = "http")]
+    Selftest(Selftest),
 }
 
 /// Returns CARGO_PKG_VERSION
@@ -145,6 +431,266 @@ pub struct Version {
     version: bool,
 }
 
+/// Runs a bundled reel against an embedded local server to verify the installation without
+/// network access
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "selftest")]
+pub struct Selftest {}
+
+/// Emits the JSON Schema of a filmReel file format for use by editors and other tooling
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "schema")]
+#[argh(example = "Write the frame schema to a file:
+    $ dark schema frame > frame.schema.json")]
+pub struct Schema {
+    /// the file format to emit a schema for: frame, cut, or vreel
+    #[argh(positional)]
+    kind: SchemaKind,
+}
+
+/// Validates filmReel files against the JSON Schema of their file format
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "lint")]
+#[argh(
+    example = "Lint every file in a reel directory:
+    $ dark lint ./test_data/post.*",
+    example = "Report diagnostics to an editor whenever a file of the directory changes:
+    $ dark lint --watch --json ./test_data"
+)]
+pub struct Lint {
+    /// frame (.fr.json), cut (.cut.json), or vreel (.vr.json) files to validate, or directories
+    /// holding them
+    #[argh(positional)]
+    files: Vec<PathBuf>,
+
+    /// print the diagnostics of every file as a single line of JSON
+    #[argh(switch)]
+    json: bool,
+
+    /// lint the files again whenever they change, directories are rescanned for new files
+    #[argh(switch, short = 'w')]
+    watch: bool,
+}
+
+/// Upgrades frames to the current frame version, rewriting them in place
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "migrate")]
+#[argh(example = "Upgrade every frame in a reel directory:
+    $ dark migrate ./test_data/*.fr.json")]
+pub struct Migrate {
+    /// frame (.fr.json) files to migrate
+    #[argh(positional)]
+    files: Vec<PathBuf>,
+
+    /// report the frames that would be migrated without rewriting them
+    #[argh(switch, short = 'n')]
+    dry_run: bool,
+}
+
+/// Rewrites frame, cut, and vreel files in the canonical style: sorted object keys, sorted Cut
+/// Variable reads, and a 2-space indent
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "fmt")]
+#[argh(
+    example = "Format every file in a reel directory:
+    $ dark fmt ./test_data",
+    example = "Fail a pre-commit hook on unformatted files:
+    $ dark fmt --check ./test_data"
+)]
+pub struct Fmt {
+    /// frame (.fr.json), cut (.cut.json), or vreel (.vr.json) files to format, or directories
+    /// holding them
+    #[argh(positional)]
+    files: Vec<PathBuf>,
+
+    /// report the files that are not formatted without rewriting them, failing if there are any
+    #[argh(switch)]
+    check: bool,
+}
+
+/// Reports on the frames of a reel
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "reel")]
+pub struct ReelCmd {
+    #[argh(subcommand)]
+    nested: ReelSubCommand,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand)]
+pub enum ReelSubCommand {
+    Ls(Ls),
+    Hash(Hash),
+    Graph(Graph),
+    Coverage(Coverage),
+    ProtoCoverage(ProtoCoverage),
+}
+
+/// Lists the frames of a reel in the order they are run along with their request, description,
+/// owner, and link
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "ls")]
+#[argh(example = "List the frames of the post reel:
+    $ dark reel ls ./test_data post")]
+pub struct Ls {
+    /// directory path where frames are to be found
+    #[argh(positional)]
+    reel_path: PathBuf,
+
+    /// name of the reel, used to find corresponding frames for the path provided
+    #[argh(positional)]
+    reel_name: String,
+}
+
+/// Prints the content hash of every frame of a reel and of the whole reel as JSON
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "hash")]
+#[argh(
+    example = "Record the frame hashes of the post reel, then run only the frames changed since:
+    $ dark reel hash ./test_data post > post.hash.json
+    $ dark record --changed-since post.hash.json ./test_data post"
+)]
+pub struct Hash {
+    /// directory path where frames are to be found
+    #[argh(positional)]
+    reel_path: PathBuf,
+
+    /// name of the reel, used to find corresponding frames for the path provided
+    #[argh(positional)]
+    reel_name: String,
+}
+
+/// Prints the frame order and the Cut Variable flow of a reel as a Graphviz or Mermaid graph
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "graph")]
+#[argh(example = "Render the post reel and its user component as an SVG:
+    $ dark reel graph --component './test_data&usr' ./test_data post | dot -Tsvg > post.svg")]
+pub struct Graph {
+    /// directory path where frames are to be found
+    #[argh(positional)]
+    reel_path: PathBuf,
+
+    /// name of the reel, used to find corresponding frames for the path provided
+    #[argh(positional)]
+    reel_name: String,
+
+    /// repeatable component reel pattern using an ampersand separator: --component "<dir>&<reel_name>"
+    #[argh(option, short = 'b')]
+    component: Vec<String>,
+
+    /// print a Graphviz DOT graph, the default
+    #[argh(switch)]
+    dot: bool,
+
+    /// print a Mermaid flowchart
+    #[argh(switch)]
+    mermaid: bool,
+}
+
+/// Reports the operations and response statuses of an OpenAPI spec that are exercised by the HTTP
+/// frames of a reel, along with the percentage of declared responses covered
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "coverage")]
+#[argh(
+    example = "List the untested responses of the usr reel:
+    $ dark reel coverage ./openapi.json ./test_data usr | jq .untested",
+    example = "Write an HTML report, failing below 80% coverage:
+    $ dark reel coverage --html --fail-under 80 ./openapi.json ./test_data usr > coverage.html"
+)]
+pub struct Coverage {
+    /// an OpenAPI 3 or Swagger 2 spec in JSON
+    #[argh(positional)]
+    spec: PathBuf,
+
+    /// directory path where frames are to be found
+    #[argh(positional)]
+    reel_path: PathBuf,
+
+    /// name of the reel, used to find corresponding frames for the path provided
+    #[argh(positional)]
+    reel_name: String,
+
+    /// repeatable component reel pattern using an ampersand separator: --component "<dir>&<reel_name>"
+    #[argh(option, short = 'b')]
+    component: Vec<String>,
+
+    /// print an HTML report rather than JSON
+    #[argh(switch)]
+    html: bool,
+
+    /// fail if the percentage of covered responses is below this value
+    #[argh(option, arg_name = "percent")]
+    fail_under: Option<u8>,
+}
+
+/// Reports the RPCs declared by the --proto files that are exercised by the gRPC frames of a reel,
+/// along with the percentage of declared RPCs covered
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "proto-coverage")]
+#[argh(example = "List the untested RPCs of the grpc reel:
+    $ dark --proto ./protos/greeter.proto reel proto-coverage ./test_data grpc | jq .untested")]
+pub struct ProtoCoverage {
+    /// directory path where frames are to be found
+    #[argh(positional)]
+    reel_path: PathBuf,
+
+    /// name of the reel, used to find corresponding frames for the path provided
+    #[argh(positional)]
+    reel_name: String,
+
+    /// repeatable component reel pattern using an ampersand separator: --component "<dir>&<reel_name>"
+    #[argh(option, short = 'b')]
+    component: Vec<String>,
+
+    /// print an HTML report rather than JSON
+    #[argh(switch)]
+    html: bool,
+
+    /// fail if the percentage of covered RPCs is below this value
+    #[argh(option, arg_name = "percent")]
+    fail_under: Option<u8>,
+}
+
+/// Works with the Cut Variables of a cut file
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "cut")]
+pub struct CutCmd {
+    #[argh(subcommand)]
+    nested: CutSubCommand,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand)]
+pub enum CutSubCommand {
+    Export(Export),
+}
+
+/// Prints the Cut Variables of a cut file as `KEY=value` lines for steps of a pipeline that do not
+/// run darkroom
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "export")]
+#[argh(
+    example = "Load the variables captured by a record into the environment of a shell:
+    $ eval \"$(dark cut export --format shell ./out/post.cut.json)\"",
+    example = "Write a dotenv file without the values of hidden variables:
+    $ dark cut export --mask-hidden post.cut.json > post.env"
+)]
+pub struct Export {
+    /// cut file (.cut.json) holding the variables to export
+    #[argh(positional)]
+    cut_file: PathBuf,
+
+    /// the format of the exported lines: dotenv (`KEY=value`, the default) or shell
+    /// (`export KEY='value'`)
+    #[argh(option, default = "ExportFormat::Dotenv", arg_name = "format")]
+    format: ExportFormat,
+
+    /// replace the values of hidden variables, those prefixed with an underscore, with
+    /// `${_HIDDEN}`
+    #[argh(switch)]
+    mask_hidden: bool,
+}
+
 /// Takes a single frame, emitting the request then validating the returned response
 #[derive(FromArgs, PartialEq, Eq, Debug)]
 #[argh(subcommand, name = "take")]
@@ -157,7 +703,7 @@ pub struct Take {
     #[argh(positional)]
     frame: PathBuf,
 
-    /// filepath of input cut file
+    /// filepath or s3://, gs://, or https:// URL of input cut file
     #[argh(option, short = 'c')]
     cut: Option<PathBuf>,
 
@@ -174,6 +720,30 @@ pub struct Take {
     merge_cuts: Vec<String>,
 }
 
+/// Validates a frame against a saved response without sending its request, running the hydration,
+/// validation, and write instructions of a take
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "validate-response")]
+#[argh(
+    example = "Iterate on the validation of a frame against the response of a previous take:
+    $ dark take -o post.tk.json ./test_data/post.01s.body.fr.json
+    $ dark validate-response ./test_data/post.01s.body.fr.json post.tk.json"
+)]
+pub struct ValidateResponse {
+    /// path of the frame to validate
+    #[argh(positional)]
+    frame: PathBuf,
+
+    /// path of the saved response: a response object, or a frame or take file holding a
+    /// "response" object
+    #[argh(positional)]
+    response: PathBuf,
+
+    /// filepath of merge cuts, the cut file of the frame is read when none are provided
+    #[argh(positional)]
+    merge_cuts: Vec<String>,
+}
+
 /// Attempts to play through an entire Reel sequence running a take for every frame in the sequence
 #[derive(FromArgs, PartialEq, Eq, Debug)]
 #[argh(subcommand, name = "record")]
@@ -192,7 +762,7 @@ pub struct Record {
     #[argh(positional)]
     reel_name: String,
 
-    /// filepath of input cut file
+    /// filepath or s3://, gs://, or https:// URL of input cut file
     #[argh(option, short = 'c')]
     cut: Option<PathBuf>,
 
@@ -204,7 +774,8 @@ pub struct Record {
     #[argh(positional)]
     merge_cuts: Vec<String>,
 
-    /// output directory for successful takes
+    /// output directory holding a subdirectory per run with the successful takes, the final
+    /// cut, and the report of the run
     #[argh(option, short = 'o')]
     take_out: Option<PathBuf>,
 
@@ -212,17 +783,97 @@ pub struct Record {
     #[argh(option, short = 'r')]
     range: Option<String>,
 
+    /// wall-clock budget of the record such as 90s, 10m, or 1h, remaining frames are skipped once
+    /// exceeded with the exception of teardown frames
+    #[argh(option)]
+    max_duration: Option<String>,
+
     /// client request timeout in seconds, --timeout 0 disables request timeout [default: 30]
     #[argh(option, short = 't', default = "30")]
     timeout: u64,
 
-    /// print timestamp at take start, error return, and reel completion
+    /// print timestamp at take start, error return, and reel completion, along with the start and
+    /// end timestamps and the duration of every frame
     #[argh(switch, short = 's')]
     timestamp: bool,
 
     /// print total time elapsed from record start to completion
     #[argh(switch, short = 'd')]
     duration: bool,
+
+    /// only run the frames whose content changed since the hash file written by `dark reel hash`,
+    /// component reels are always run
+    #[argh(option, arg_name = "hashfile")]
+    changed_since: Option<PathBuf>,
+
+    /// only run the frames whose file name, or vreel name, matches the glob pattern, where `*`
+    /// matches any characters and `?` a single one: --filter "*user*"
+    #[argh(option, arg_name = "glob")]
+    filter: Option<String>,
+
+    /// repeatable or comma separated tag, only the frames holding any of the tags are run:
+    /// --tags smoke
+    #[argh(option, arg_name = "tag")]
+    tags: Vec<String>,
+
+    /// repeatable or comma separated tag, the frames holding any of the tags are skipped:
+    /// --skip-tags slow
+    #[argh(option, arg_name = "tag")]
+    skip_tags: Vec<String>,
+}
+
+/// Records several reels in one invocation, each reel running with its own cut file, followed by
+/// a summary of the reels that passed and failed
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "record-all")]
+#[argh(
+    example = "Record every reel found under ./test_data:
+    $ dark record-all ./test_data",
+    example = "Record the post and grpc reels, stopping at the first failure:
+    $ dark record-all --fail-fast \"./test_data&post\" \"./test_data&grpc\""
+)]
+pub struct RecordAll {
+    /// reels to record: a "<dir>&<reel_name>" pattern or a directory searched recursively for
+    /// frames, hidden directories are skipped
+    #[argh(positional, arg_name = "reel")]
+    reels: Vec<String>,
+
+    /// output directory holding a <reel dir>/<reel name> subdirectory per reel with the runs of the
+    /// reel
+    #[argh(option, short = 'o')]
+    take_out: Option<PathBuf>,
+
+    /// client request timeout in seconds, --timeout 0 disables request timeout [default: 30]
+    #[argh(option, short = 't', default = "30")]
+    timeout: u64,
+
+    /// print timestamp at take start, error return, and reel completion, along with the start and
+    /// end timestamps and the duration of every frame
+    #[argh(switch, short = 's')]
+    timestamp: bool,
+
+    /// print total time elapsed of every reel
+    #[argh(switch, short = 'd')]
+    duration: bool,
+
+    /// skip the remaining reels once a reel fails
+    #[argh(switch)]
+    fail_fast: bool,
+}
+
+impl RecordAll {
+    /// validate ensures that reels were provided and that the output directory exists
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.reels.is_empty() {
+            return Err(anyhow!("at least one <reel> must be provided"));
+        }
+        if let Some(output) = &self.take_out {
+            if !output.is_dir() {
+                return Err(anyhow!("<output> must be a valid directory"));
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Attempts to play through an entire VirtualReel sequence running a take for every frame in the sequence
@@ -230,13 +881,15 @@ pub struct Record {
 #[argh(subcommand, name = "vrecord")]
 #[argh(example = "Run the post reel in a v-reel setup:
 $ {command_name} ./test_data/post.vr.json
-$ {command_name} ./test_data/alt_post.vr.json")]
+$ {command_name} ./test_data/alt_post.vr.json
+$ {command_name} ./test_data/post.vr.json --merge-cut ./test_data/post.cut.json --set USER_ID=BIG_BEN")]
 pub struct VirtualRecord {
     /// filepath or json string of VirtualReel
     #[argh(positional)]
     vreel: String,
 
-    /// output directory for successful takes
+    /// output directory holding a subdirectory per run with the successful takes, the final
+    /// cut, and the report of the run
     #[argh(option, short = 'o')]
     take_out: Option<PathBuf>,
 
@@ -244,13 +897,79 @@ pub struct VirtualRecord {
     #[argh(option, short = 't', default = "30")]
     timeout: u64,
 
-    /// print timestamp at take start, error return, and reel completion
+    /// print timestamp at take start, error return, and reel completion, along with the start and
+    /// end timestamps and the duration of every frame
     #[argh(switch, short = 's')]
     timestamp: bool,
 
     /// print total time elapsed from record start to completion
     #[argh(switch, short = 'd')]
     duration: bool,
+
+    /// repeatable filepath or json string of a cut merged into the cut of the VirtualReel
+    #[argh(option, arg_name = "cut")]
+    merge_cut: Vec<String>,
+
+    /// repeatable Cut Variable written after the merge cuts, the value is read as JSON when valid
+    /// and as a string otherwise: --set USER_ID=BIG_BEN --set COUNT=3
+    #[argh(option, arg_name = "KEY=VAL")]
+    set: Vec<CutAssignment>,
+
+    /// only run the frames whose file name, or vreel name, matches the glob pattern, where `*`
+    /// matches any characters and `?` a single one: --filter "*user*"
+    #[argh(option, arg_name = "glob")]
+    filter: Option<String>,
+
+    /// repeatable or comma separated tag, only the frames holding any of the tags are run:
+    /// --tags smoke
+    #[argh(option, arg_name = "tag")]
+    tags: Vec<String>,
+
+    /// repeatable or comma separated tag, the frames holding any of the tags are skipped:
+    /// --skip-tags slow
+    #[argh(option, arg_name = "tag")]
+    skip_tags: Vec<String>,
+}
+
+/// Runs frames one at a time against a long-lived cut register from an interactive prompt
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+#[argh(subcommand, name = "repl")]
+#[argh(
+    example = "Explore the post reel, saving the session cut to ./test_data/.post.cut.json:
+    $ dark repl ./test_data post"
+)]
+pub struct Repl {
+    /// directory path where frames and (if no explicit cut is provided) the cut are to be found
+    #[argh(positional)]
+    reel_path: PathBuf,
+
+    /// name of the reel, used to find corresponding frames for the path provided
+    #[argh(positional)]
+    reel_name: String,
+
+    /// filepath or s3://, gs://, or https:// URL of input cut file
+    #[argh(option, short = 'c')]
+    cut: Option<PathBuf>,
+
+    /// client request timeout in seconds, --timeout 0 disables request timeout [default: 30]
+    #[argh(option, short = 't', default = "30")]
+    timeout: u64,
+}
+
+impl Repl {
+    /// Returns expected cut filename in the given directory with the provided reel name
+    pub fn get_cut_file(&self) -> PathBuf {
+        if let Some(cut) = &self.cut {
+            return cut.clone();
+        }
+
+        self.reel_path.join(format!("{}.cut.json", self.reel_name))
+    }
+
+    /// Returns the period prefixed path of the cut file that the session is saved to
+    pub fn get_cut_copy(&self) -> PathBuf {
+        self.reel_path.join(format!(".{}.cut.json", self.reel_name))
+    }
 }
 
 impl Take {
@@ -267,7 +986,7 @@ impl Take {
         }
 
         let cut_file = self.get_cut_file()?;
-        if !cut_file.is_file() {
+        if !is_cut_file(&cut_file) {
             return Err(anyhow!(
                 "{} must be a valid file",
                 cut_file.to_string_lossy()
@@ -298,7 +1017,7 @@ impl Record {
         }
 
         if let Some(cut) = &self.cut {
-            if !cut.is_file() {
+            if !is_cut_file(cut) {
                 return Err(anyhow!("<cut> must be a valid file"));
             }
         } else {
@@ -315,6 +1034,12 @@ impl Record {
                 return Err(anyhow!("<output> must be a valid directory"));
             }
         }
+
+        if let Some(hashfile) = &self.changed_since {
+            if !hashfile.is_file() {
+                return Err(anyhow!("--changed-since must be a valid file"));
+            }
+        }
         Ok(())
     }
 
@@ -335,7 +1060,7 @@ impl Record {
 }
 
 impl VirtualRecord {
-    pub fn init(&self) -> Result<VirtualReel, Error> {
+    pub fn init(&self) -> Result<VirtualReel<'_>, Error> {
         let mut vreel = if guess_json_obj(&self.vreel) {
             serde_json::from_str(&self.vreel)?
         } else {
@@ -355,6 +1080,7 @@ impl VirtualRecord {
 }
 
 /// get_styler returns the custom syntax values for stdout json
+#[cfg(feature = "pretty")]
 fn get_styler() -> Styler {
     Styler {
         bool_value: Style::new(Colour::Magenta),
@@ -375,9 +1101,7 @@ where
     T: ?Sized + Serialize,
 {
     fn to_coloured_tk_json(&self) -> Result<String, FrError> {
-        Ok(self
-            .to_string_pretty()?
-            .to_colored_json_with_styler(ColorMode::default().eval(), get_styler())?)
+        colour_json(self.to_string_pretty()?)
     }
 }
 
@@ -385,17 +1109,29 @@ trait ToTakeHiddenColouredJson: ToTakeColouredJson {
     fn to_hidden_tk_json(&self) -> Result<String, FrError>;
 }
 
-impl<T> ToTakeHiddenColouredJson for T
-where
-    T: ?Sized + Serialize,
-{
+impl ToTakeHiddenColouredJson for Register {
     fn to_hidden_tk_json(&self) -> Result<String, FrError> {
-        Ok(self
-            .to_string_hidden()?
-            .to_colored_json_with_styler(ColorMode::default().eval(), get_styler())?)
+        colour_json(self.to_string_hidden()?)
     }
 }
 
+#[cfg(feature = "pretty")]
+fn colour_json(json: String) -> Result<String, FrError> {
+    Ok(json.to_colored_json_with_styler(ColorMode::default().eval(), get_styler())?)
+}
+
+/// JSON is printed uncoloured without the "pretty" feature
+#[cfg(not(feature = "pretty"))]
+fn colour_json(json: String) -> Result<String, FrError> {
+    Ok(json)
+}
+
+/// missing_feature is the error returned when a frame needs a cargo feature that darkroom was
+/// built without
+pub(crate) fn missing_feature(feature: &str) -> Error {
+    anyhow!("darkroom was built without the \"{feature}\" feature")
+}
+
 // try to see if a given string *might* be json
 pub fn guess_json_obj<T: AsRef<str>>(input: T) -> bool {
     let obj = input
@@ -406,3 +1142,56 @@ pub fn guess_json_obj<T: AsRef<str>>(input: T) -> bool {
 
     obj.starts_with("{\"") && obj[2..].contains("\":") && obj.ends_with('}')
 }
+
+/// exit_code maps an error to a stable process exit code: `2` for a response mismatch, `10..=20` for
+/// the [`filmreel::ErrorCode`] of a filmReel error and `1` for any other failure. An interrupted
+/// record exits with [`abort::ABORT_EXIT_CODE`] from its signal handler instead
+pub fn exit_code(err: &Error) -> i32 {
+    if let Some(fr_err) = err.chain().find_map(|e| e.downcast_ref::<FrError>()) {
+        return match fr_err.code() {
+            ErrorCode::FrameParse => 10,
+            ErrorCode::ReelParse => 11,
+            ErrorCode::ReadInstruction => 12,
+            ErrorCode::WriteInstruction => 13,
+            ErrorCode::SelectorNotFound => 14,
+            ErrorCode::TypeMismatch => 15,
+            ErrorCode::Serde => 16,
+            ErrorCode::Parse => 17,
+            ErrorCode::File => 18,
+            ErrorCode::FileMissing => 19,
+            ErrorCode::SelectorSyntax => 20,
+            _ => 1,
+        };
+    }
+    if err.downcast_ref::<take::Mismatch>().is_some() {
+        return 2;
+    }
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest(
+        cut_out,
+        expected,
+        case(None, true),
+        case(Some("./out.cut.json"), true),
+        case(Some("out.cut.json"), true),
+        case(Some("./src"), true),
+        case(Some("./missing_dir/out.cut.json"), false),
+        case(Some("./missing_dir/"), false),
+        case(Some("./Cargo.toml/out.cut.json"), false)
+    )]
+    fn test_validate(cut_out: Option<&str>, expected: bool) {
+        let mut args = vec![];
+        if let Some(cut_out) = cut_out {
+            args.extend(["--cut-out", cut_out]);
+        }
+        args.push("version");
+        let cmd = Command::from_args(&["dark"], &args).unwrap();
+        assert_eq!(expected, cmd.validate().is_ok());
+    }
+}
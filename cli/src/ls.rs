@@ -0,0 +1,75 @@
+use crate::Ls;
+use anyhow::{Context, Error};
+use filmreel::reel::{MetaFrame, Reel};
+use std::fmt::Write;
+
+/// list returns a line per frame holding its filename and request uri, followed by an indented
+/// line per description, owner, and link of the frame
+pub fn list(frames: &[MetaFrame]) -> Result<String, Error> {
+    let mut out = String::new();
+    for meta_frame in frames {
+        let filename = meta_frame.get_filename();
+        let frame = meta_frame.to_frame().context(filename.clone())?;
+        let _ = writeln!(out, "{filename}  {}", frame.get_request().get_uri());
+        for (field, value) in [
+            ("description", &frame.meta.description),
+            ("owner", &frame.meta.owner),
+            ("link", &frame.meta.link),
+        ] {
+            if let Some(value) = value {
+                let _ = writeln!(out, "    {field}: {value}");
+            }
+        }
+    }
+    Ok(out)
+}
+
+pub fn cmd_ls(cmd: Ls) -> Result<(), Error> {
+    let reel = Reel::new(&cmd.reel_path, &cmd.reel_name, None)?;
+    let frames: Vec<MetaFrame> = reel.into_iter().collect();
+    print!("{}", list(&frames)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filmreel::frame::Frame;
+    use serde_json::json;
+
+    #[test]
+    fn test_list() {
+        let frame = |uri: &str, meta: serde_json::Value| -> Frame<'static> {
+            let mut frame = json!({
+                "protocol": "HTTP",
+                "request": {"uri": uri},
+                "response": {"status": 200}
+            });
+            frame
+                .as_object_mut()
+                .unwrap()
+                .extend(meta.as_object().unwrap().clone());
+            serde_json::from_value(frame).unwrap()
+        };
+        let reel = Reel::from_frames(vec![
+            (
+                "usr.01s.createuser.fr.json",
+                frame(
+                    "POST /users",
+                    json!({"description": "creates the user", "owner": "@users-team"}),
+                ),
+            ),
+            ("usr.02s.getuser.fr.json", frame("GET /users", json!({}))),
+        ])
+        .unwrap();
+        let frames: Vec<MetaFrame> = reel.into_iter().collect();
+        assert_eq!(
+            "usr.01s.createuser.fr.json  POST /users
+    description: creates the user
+    owner: @users-team
+usr.02s.getuser.fr.json  GET /users
+",
+            list(&frames).unwrap()
+        );
+    }
+}
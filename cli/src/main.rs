@@ -1,13 +1,33 @@
 use anyhow::Error;
+#[cfg(feature = "http")]
+use darkroom::selftest::cmd_selftest;
 use darkroom::{
-    record::{cmd_record, cmd_vrecord},
-    take::cmd_take,
+    exit_code,
+    export::cmd_cut,
+    fmt::cmd_fmt,
+    hash::cmd_reel,
+    migrate::cmd_migrate,
+    record::{cmd_record, cmd_record_all, cmd_vrecord},
+    repl::cmd_repl,
+    schema::{cmd_lint, cmd_schema},
+    take::{cmd_take, cmd_validate_response},
     version, Command, Logger, Opts, SubCommand,
 };
-use std::io::{self, Write};
+use std::{
+    io::{self, Write},
+    process,
+};
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {e:?}");
+        process::exit(exit_code(&e));
+    }
+}
 
-fn main() -> Result<(), Error> {
+fn run() -> Result<(), Error> {
     let args: Command = argh::from_env();
+    args.validate()?;
 
     let opts: Opts = Opts::new(&args);
     let base_params = args.base_params();
@@ -43,6 +63,20 @@ fn main() -> Result<(), Error> {
             cmd.validate()?;
             cmd_record(cmd, base_params.clone()).map_err(err_ts)
         }
+        SubCommand::RecordAll(cmd) => {
+            cmd.validate()?;
+            cmd_record_all(cmd, base_params.clone()).map_err(err_ts)
+        }
         SubCommand::VirtualRecord(cmd) => cmd_vrecord(cmd, base_params.clone()).map_err(err_ts),
+        SubCommand::Schema(cmd) => cmd_schema(cmd),
+        SubCommand::Lint(cmd) => cmd_lint(cmd),
+        SubCommand::Migrate(cmd) => cmd_migrate(cmd),
+        SubCommand::Fmt(cmd) => cmd_fmt(cmd),
+        SubCommand::Repl(cmd) => cmd_repl(cmd, base_params.clone()),
+        SubCommand::Reel(cmd) => cmd_reel(cmd, base_params.clone()),
+        SubCommand::Cut(cmd) => cmd_cut(cmd),
+        SubCommand::ValidateResponse(cmd) => cmd_validate_response(cmd, base_params.clone()),
+        #[cfg(feature = "http")]
+        SubCommand::Selftest(cmd) => cmd_selftest(cmd, base_params.clone()),
     }
 }
@@ -0,0 +1,315 @@
+use anyhow::{Context, Error};
+use chrono::{DateTime, SecondsFormat, Utc};
+use colored::*;
+use filmreel::frame::Metadata;
+#[cfg(feature = "http")]
+use log::debug;
+use log::error;
+#[cfg(feature = "http")]
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::{fmt::Write, fs, path::PathBuf, time::Duration};
+
+/// The job label used when pushing to a Prometheus pushgateway
+#[cfg(feature = "http")]
+const PUSHGATEWAY_JOB: &str = "darkroom";
+
+/// FrameStatus is the outcome of a single frame of a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameStatus {
+    Passed,
+    Failed,
+    /// a failed frame with a warning severity
+    Warning,
+    /// a failed frame listed in the quarantine file of the reel
+    Quarantined,
+    /// a frame skipped after the `--max-duration` budget was exceeded
+    Skipped,
+}
+
+impl FrameStatus {
+    const ALL: [Self; 5] = [
+        Self::Passed,
+        Self::Failed,
+        Self::Warning,
+        Self::Quarantined,
+        Self::Skipped,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Passed => "passed",
+            Self::Failed => "failed",
+            Self::Warning => "warning",
+            Self::Quarantined => "quarantined",
+            Self::Skipped => "skipped",
+        }
+    }
+}
+
+/// MetricsTarget is where the metrics of a record are exported to, provided through `--metrics`:
+///
+/// - an `http://` or `https://` Prometheus pushgateway address, the metrics are pushed to
+///   `<address>/metrics/job/darkroom/reel/<reel_name>` unless the address already names a job
+/// - any other value is the path of an OpenMetrics text file that is overwritten
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetricsTarget {
+    Pushgateway(String),
+    File(PathBuf),
+}
+
+impl std::str::FromStr for MetricsTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("http://") || s.starts_with("https://") {
+            return Ok(Self::Pushgateway(s.trim_end_matches('/').to_string()));
+        }
+        Ok(Self::File(PathBuf::from(s)))
+    }
+}
+
+/// RecordMetrics collects the duration and status of every frame run by a record
+#[derive(Debug, Default)]
+pub struct RecordMetrics {
+    reel_name: String,
+    frames: Vec<(String, FrameStatus, Duration)>,
+    /// the time at which every frame ended, frames are pushed as soon as they end
+    ended: Vec<DateTime<Utc>>,
+    /// the index of a frame and the report of its error, see [`crate::artifacts::error_report`]
+    errors: Vec<(usize, Value)>,
+    /// the index of a frame and its description, owner, and link, frames without any are left out
+    metadata: Vec<(usize, Metadata)>,
+}
+
+impl RecordMetrics {
+    pub fn new<T: Into<String>>(reel_name: T) -> Self {
+        Self {
+            reel_name: reel_name.into(),
+            frames: vec![],
+            ended: vec![],
+            errors: vec![],
+            metadata: vec![],
+        }
+    }
+
+    /// annotate attaches the metadata of a frame to the next frame pushed, only the JSON report of
+    /// the record holds the metadata
+    pub fn annotate(&mut self, meta: &Metadata) {
+        if !meta.is_empty() {
+            self.metadata.push((self.frames.len(), meta.clone()));
+        }
+    }
+
+    pub fn push<T: Into<String>>(&mut self, frame: T, status: FrameStatus, duration: Duration) {
+        self.frames.push((frame.into(), status, duration));
+        self.ended.push(Utc::now());
+    }
+
+    /// push_error pushes a frame along with the report of the error it failed with, only the
+    /// JSON report of the record holds the error
+    pub fn push_error<T: Into<String>>(
+        &mut self,
+        frame: T,
+        status: FrameStatus,
+        duration: Duration,
+        error: Value,
+    ) {
+        self.errors.push((self.frames.len(), error));
+        self.push(frame, status, duration);
+    }
+
+    /// render formats the collected metrics in the Prometheus text exposition format, or in the
+    /// OpenMetrics text format if openmetrics is true
+    pub fn render(&self, openmetrics: bool) -> String {
+        let reel = escape_label(&self.reel_name);
+        let mut out = String::new();
+        // OpenMetrics counter families are named without the _total suffix of their samples
+        let counter_family = match openmetrics {
+            true => "darkroom_frames",
+            false => "darkroom_frames_total",
+        };
+
+        let _ = writeln!(
+            out,
+            "# HELP darkroom_frame_duration_seconds Wall-clock duration of a frame.\n\
+             # TYPE darkroom_frame_duration_seconds gauge"
+        );
+        for (frame, _, duration) in self.frames.iter() {
+            let _ = writeln!(
+                out,
+                "darkroom_frame_duration_seconds{{reel=\"{reel}\",frame=\"{}\"}} {:.3}",
+                escape_label(frame),
+                duration.as_secs_f64()
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP darkroom_frame_status Outcome of a frame, 1 for the status of the frame.\n\
+             # TYPE darkroom_frame_status gauge"
+        );
+        for (frame, status, _) in self.frames.iter() {
+            let _ = writeln!(
+                out,
+                "darkroom_frame_status{{reel=\"{reel}\",frame=\"{}\",status=\"{}\"}} 1",
+                escape_label(frame),
+                status.as_str()
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP {counter_family} Frames run by the record by status.\n\
+             # TYPE {counter_family} counter"
+        );
+        for status in FrameStatus::ALL {
+            let count = self.frames.iter().filter(|(_, s, _)| *s == status).count();
+            let _ = writeln!(
+                out,
+                "darkroom_frames_total{{reel=\"{reel}\",status=\"{}\"}} {count}",
+                status.as_str()
+            );
+        }
+
+        if openmetrics {
+            out.push_str("# EOF\n");
+        }
+        out
+    }
+
+    /// report returns the JSON report of the record written to its run directory, passed is false
+    /// when the record was aborted by a failed or skipped frame
+    pub fn report(&self, run_id: &str, passed: bool) -> Value {
+        let frames: Vec<Value> = self
+            .frames
+            .iter()
+            .enumerate()
+            .map(|(i, (frame, status, duration))| {
+                let ended = self.ended[i];
+                let started = ended - chrono::Duration::from_std(*duration).unwrap_or_default();
+                let mut report = json!({
+                    "frame": frame,
+                    "status": status.as_str(),
+                    "started_at": started.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    "ended_at": ended.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    "duration_ms": duration.as_millis() as u64,
+                });
+                if let Some((_, meta)) = self.metadata.iter().find(|(j, _)| *j == i) {
+                    for (field, value) in [
+                        ("description", &meta.description),
+                        ("owner", &meta.owner),
+                        ("link", &meta.link),
+                    ] {
+                        if let Some(value) = value {
+                            report[field] = json!(value);
+                        }
+                    }
+                }
+                if let Some((_, error)) = self.errors.iter().find(|(j, _)| *j == i) {
+                    report["error"] = error.clone();
+                }
+                report
+            })
+            .collect();
+        json!({
+            "reel": self.reel_name,
+            "run_id": run_id,
+            "result": match passed {
+                true => "passed",
+                false => "failed",
+            },
+            "frames": frames,
+        })
+    }
+
+    /// export writes or pushes the collected metrics, a failed export is logged rather than
+    /// failing the record
+    pub fn export(&self, target: &Option<MetricsTarget>) {
+        let result = match target {
+            Some(MetricsTarget::Pushgateway(address)) => self.push_to(address),
+            Some(MetricsTarget::File(path)) => fs::write(path, self.render(true))
+                .context(format!("unable to write --metrics file {path:?}")),
+            None => return,
+        };
+        if let Err(e) = result {
+            error!("{} {:#}", "Metrics:".red(), e);
+        }
+    }
+
+    #[cfg(not(feature = "http"))]
+    fn push_to(&self, _address: &str) -> Result<(), Error> {
+        Err(crate::missing_feature("http"))
+    }
+
+    #[cfg(feature = "http")]
+    fn push_to(&self, address: &str) -> Result<(), Error> {
+        let url = match address.contains("/metrics/job/") {
+            true => address.to_string(),
+            false => format!(
+                "{address}/metrics/job/{PUSHGATEWAY_JOB}/reel/{}",
+                percent_encoding::utf8_percent_encode(
+                    &self.reel_name,
+                    percent_encoding::NON_ALPHANUMERIC
+                )
+            ),
+        };
+        debug!("pushing metrics to {url}...");
+        let request = Client::new()
+            .put(&url)
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(self.render(false));
+        crate::transport::runtime()
+            .block_on(request.send())
+            .context(format!("PUT {url} failure"))?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut metrics = RecordMetrics::new("usr");
+        metrics.push(
+            "usr.01s.createuser.fr.json",
+            FrameStatus::Passed,
+            Duration::from_millis(1250),
+        );
+        metrics.push(
+            "usr.02s.getuser.fr.json",
+            FrameStatus::Failed,
+            Duration::from_millis(3),
+        );
+        assert_eq!(
+            r#"# HELP darkroom_frame_duration_seconds Wall-clock duration of a frame.
+# TYPE darkroom_frame_duration_seconds gauge
+darkroom_frame_duration_seconds{reel="usr",frame="usr.01s.createuser.fr.json"} 1.250
+darkroom_frame_duration_seconds{reel="usr",frame="usr.02s.getuser.fr.json"} 0.003
+# HELP darkroom_frame_status Outcome of a frame, 1 for the status of the frame.
+# TYPE darkroom_frame_status gauge
+darkroom_frame_status{reel="usr",frame="usr.01s.createuser.fr.json",status="passed"} 1
+darkroom_frame_status{reel="usr",frame="usr.02s.getuser.fr.json",status="failed"} 1
+# HELP darkroom_frames Frames run by the record by status.
+# TYPE darkroom_frames counter
+darkroom_frames_total{reel="usr",status="passed"} 1
+darkroom_frames_total{reel="usr",status="failed"} 1
+darkroom_frames_total{reel="usr",status="warning"} 0
+darkroom_frames_total{reel="usr",status="quarantined"} 0
+darkroom_frames_total{reel="usr",status="skipped"} 0
+# EOF
+"#,
+            metrics.render(true)
+        );
+    }
+}
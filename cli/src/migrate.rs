@@ -0,0 +1,163 @@
+use crate::{fmt::format_value, schema::SchemaKind, Migrate};
+use anyhow::{anyhow, Context, Error};
+use colored::*;
+use filmreel::frame::{Frame, FRAME_VERSION};
+use log::warn;
+use serde_json::{Map, Value};
+use std::{fs, path::Path};
+
+/// A Migration upgrades the raw JSON of a frame by a single version
+type Migration = fn(&mut Map<String, Value>) -> Result<(), Error>;
+
+/// `MIGRATIONS[n]` upgrades a frame from version `n` to version `n + 1`
+const MIGRATIONS: [Migration; FRAME_VERSION as usize] = [migrate_v0];
+
+/// Unversioned frames placed retry attempts at the top level of the frame rather than in the
+/// request object
+fn migrate_v0(frame: &mut Map<String, Value>) -> Result<(), Error> {
+    let attempts = match frame.remove("attempts") {
+        Some(attempts) => attempts,
+        None => return Ok(()),
+    };
+    let request = frame
+        .get_mut("request")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| anyhow!("frame is missing a request object"))?;
+    if request.contains_key("attempts") {
+        return Err(anyhow!(
+            "frame has attempts in both the frame and the request"
+        ));
+    }
+    request.insert("attempts".to_string(), attempts);
+    Ok(())
+}
+
+/// cmd_migrate upgrades every frame provided to the current frame version, cut files are a flat
+/// map of Cut Variables and are left untouched
+pub fn cmd_migrate(cmd: Migrate) -> Result<(), Error> {
+    for path in cmd.files.iter() {
+        match migrate_file(path, cmd.dry_run)? {
+            Some(from) => warn!(
+                "{} {} (v{} -> v{})",
+                "Migrated:".green(),
+                path.to_string_lossy(),
+                from,
+                FRAME_VERSION
+            ),
+            None => warn!("{} {}", "Current:".yellow(), path.to_string_lossy()),
+        }
+    }
+    Ok(())
+}
+
+/// migrate_file rewrites a frame in place, returning the version it was migrated from or `None`
+/// if the frame is already current
+pub fn migrate_file<P: AsRef<Path>>(path: P, dry_run: bool) -> Result<Option<u32>, Error> {
+    let path = path.as_ref();
+    let json_string =
+        fs::read_to_string(path).context(format!("migrate read failure => {path:?}"))?;
+    let value: Value =
+        serde_json::from_str(&json_string).context(format!("migrate parse failure => {path:?}"))?;
+    let (from, migrated) = migrate_value(value).context(format!("{path:?}"))?;
+    if from == FRAME_VERSION {
+        return Ok(None);
+    }
+    // parse the migrated frame to ensure it is valid, the JSON itself is written so that fields
+    // the Frame does not serialize, such as response validation, are kept
+    Frame::new(&migrated.to_string()).context(format!("{path:?}"))?;
+    if !dry_run {
+        fs::write(path, format_value(SchemaKind::Frame, migrated)?)
+            .context(format!("migrate write failure => {path:?}"))?;
+    }
+    Ok(Some(from))
+}
+
+/// migrate_value applies every migration needed to bring the frame to the current version,
+/// returning the version the frame was migrated from alongside the migrated frame
+pub fn migrate_value(mut value: Value) -> Result<(u32, Value), Error> {
+    let frame = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("frame must be a JSON object"))?;
+    let from = match frame.get("version") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .map(|v| v as u32)
+            .ok_or_else(|| anyhow!("frame version must be a positive integer"))?,
+    };
+    if from > FRAME_VERSION {
+        return Err(anyhow!(
+            "frame version {from} is newer than the supported version {FRAME_VERSION}"
+        ));
+    }
+    for migration in MIGRATIONS[from as usize..].iter() {
+        migration(frame)?;
+    }
+    frame.insert("version".to_string(), FRAME_VERSION.into());
+    Ok((from, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest(
+        input,
+        expected,
+        case(
+            json!({"protocol": "HTTP", "attempts": {"times": 2, "ms": 50}, "request": {"uri": "GET /"}}),
+            (0, json!({"version": 1, "protocol": "HTTP", "request": {"uri": "GET /", "attempts": {"times": 2, "ms": 50}}}))
+        ),
+        case(
+            json!({"protocol": "HTTP", "request": {"uri": "GET /"}}),
+            (0, json!({"version": 1, "protocol": "HTTP", "request": {"uri": "GET /"}}))
+        ),
+        case(
+            json!({"version": 1, "protocol": "HTTP", "request": {"uri": "GET /"}}),
+            (1, json!({"version": 1, "protocol": "HTTP", "request": {"uri": "GET /"}}))
+        )
+    )]
+    fn test_migrate_value(input: Value, expected: (u32, Value)) {
+        assert_eq!(expected, migrate_value(input).unwrap());
+    }
+
+    #[rstest(
+        input,
+        case(json!({"version": 2, "protocol": "HTTP", "request": {"uri": "GET /"}})),
+        case(json!({"version": "1", "protocol": "HTTP", "request": {"uri": "GET /"}})),
+        case(json!({"protocol": "HTTP", "attempts": {"times": 2, "ms": 50}, "request": {"uri": "GET /", "attempts": {"times": 2, "ms": 50}}}))
+    )]
+    fn test_migrate_value_err(input: Value) {
+        assert!(migrate_value(input).is_err());
+    }
+
+    #[test]
+    fn test_migrate_file_validation() {
+        let path =
+            std::env::temp_dir().join(format!("dark_migrate_{}.fr.json", std::process::id()));
+        let frame = json!({
+            "protocol": "HTTP",
+            "attempts": {"times": 2, "ms": 50},
+            "request": {"uri": "GET /"},
+            "response": {
+                "status": 200,
+                "body": {"items": []},
+                "validation": {"'response'.'body'.'items'": {"unordered": true}}
+            }
+        });
+        fs::write(&path, frame.to_string()).unwrap();
+        assert_eq!(Some(0), migrate_file(&path, false).unwrap());
+        let migrated: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            json!({"'response'.'body'.'items'": {"unordered": true}}),
+            migrated["response"]["validation"]
+        );
+        assert_eq!(
+            json!({"times": 2, "ms": 50}),
+            migrated["request"]["attempts"]
+        );
+    }
+}
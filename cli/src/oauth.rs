@@ -0,0 +1,317 @@
+use crate::params::{BaseParams, Params};
+use anyhow::{anyhow, Context, Error};
+use colored::*;
+use filmreel::{cut::Register, frame::Frame};
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// OAuthConfig is the OAuth2 client-credentials grant of an access token fetched before the first
+/// frame is taken, provided through `--oauth-config` or the `<reel_name>.oauth.json` file of the
+/// reel directory:
+///
+/// ```json
+/// {
+///   "token_url": "https://auth.example.com/oauth/token",
+///   "client_id": "${CLIENT_ID}",
+///   "client_secret": "${_CLIENT_SECRET}",
+///   "scope": "users:read users:write"
+/// }
+/// ```
+///
+/// Every string is hydrated with the cut register. The access token is written to the hidden cut
+/// variable `"variable"`, `ACCESS_TOKEN` by default, and is fetched again when a frame reading it
+/// gets a 401 response, the frame is then sent once more with the new token. The token endpoint
+/// is reached with the `--proxy`, `--dns`, and TLS settings of the frames.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct OAuthConfig {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub audience: Option<String>,
+    /// how the client credentials are sent to the token endpoint [default: basic]
+    #[serde(default)]
+    pub client_auth: ClientAuth,
+    #[serde(default = "default_variable")]
+    pub variable: String,
+}
+
+/// ClientAuth is how the client credentials are sent to the token endpoint:
+///
+/// - `"basic"`: as an HTTP Basic Authorization header
+/// - `"body"`: as the `client_id` and `client_secret` form fields
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientAuth {
+    #[default]
+    Basic,
+    Body,
+}
+
+fn default_variable() -> String {
+    "ACCESS_TOKEN".to_string()
+}
+
+impl OAuthConfig {
+    pub fn from_path(path: &Path) -> Result<Self, Error> {
+        let json = fs::read_to_string(path).context(format!("unable to read {path:?}"))?;
+        serde_json::from_str(&json).context(format!("invalid OAuth config {path:?}"))
+    }
+
+    /// hydrated returns the config with the Cut Variables of every string replaced by their
+    /// values in the register
+    fn hydrated(&self, register: &Register) -> Result<Self, Error> {
+        let hydrate = |field: &str, value: &str| -> Result<String, Error> {
+            let mut hydrated = Value::String(value.to_string());
+            for mat in register.read_match(value)? {
+                register.read_operation(mat, &mut hydrated, false)?;
+            }
+            match hydrated {
+                Value::String(s) => Ok(s),
+                _ => Err(anyhow!("OAuth config \"{field}\" must hydrate to a string")),
+            }
+        };
+        let optional = |field: &str, value: &Option<String>| {
+            value.as_deref().map(|v| hydrate(field, v)).transpose()
+        };
+        Ok(Self {
+            token_url: hydrate("token_url", &self.token_url)?,
+            client_id: hydrate("client_id", &self.client_id)?,
+            client_secret: hydrate("client_secret", &self.client_secret)?,
+            scope: optional("scope", &self.scope)?,
+            audience: optional("audience", &self.audience)?,
+            client_auth: self.client_auth,
+            variable: self.variable.clone(),
+        })
+    }
+
+    /// form returns the form fields of the token request
+    #[cfg_attr(not(feature = "http"), allow(dead_code))]
+    fn form(&self) -> Vec<(&str, &str)> {
+        let mut form = vec![("grant_type", "client_credentials")];
+        if self.client_auth == ClientAuth::Body {
+            form.extend([
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+            ]);
+        }
+        form.extend(self.scope.as_deref().map(|scope| ("scope", scope)));
+        form.extend(self.audience.as_deref().map(|aud| ("audience", aud)));
+        form
+    }
+
+    /// fetch_into requests an access token from the token endpoint, writing it to the cut
+    /// variable of the config which is hidden from takes and logs
+    pub fn fetch_into(&self, register: &mut Register, prm: &Params) -> Result<(), Error> {
+        let config = self.hydrated(register)?;
+        let token = config
+            .fetch(prm)
+            .context(format!("OAuth token request to {}", config.token_url))?;
+        register.write_operation(&self.variable, Value::String(token))?;
+        register.hide(&self.variable);
+        info!(
+            "{} access token written to ${{{}}}",
+            "OAuth:".yellow(),
+            self.variable
+        );
+        Ok(())
+    }
+
+    #[cfg(not(feature = "http"))]
+    fn fetch(&self, _prm: &Params) -> Result<String, Error> {
+        Err(crate::missing_feature("http"))
+    }
+
+    #[cfg(feature = "http")]
+    fn fetch(&self, prm: &Params) -> Result<String, Error> {
+        let mut request = crate::http::build_client(prm, false)?
+            .post(&self.token_url)
+            .form(&self.form());
+        if self.client_auth == ClientAuth::Basic {
+            request = request.basic_auth(&self.client_id, Some(&self.client_secret));
+        }
+        let body: Value = crate::transport::runtime().block_on(async {
+            let response = request.send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(anyhow!("token endpoint responded with {status}"));
+            }
+            Ok::<_, Error>(response.json().await?)
+        })?;
+        body.get("access_token")
+            .and_then(Value::as_str)
+            .map(String::from)
+            .ok_or_else(|| anyhow!("token response is missing a string \"access_token\""))
+    }
+}
+
+/// init_oauth reads the OAuth config of `--oauth-config`, falling back to the reel config file
+/// given, and fetches the access token into the register before the first frame is taken. The
+/// config is kept in the BaseParams so that the token can be refreshed.
+pub fn init_oauth(
+    base_params: &mut BaseParams,
+    register: &mut Register,
+    reel_config: Option<PathBuf>,
+) -> Result<(), Error> {
+    let path = match (&base_params.oauth_config, reel_config) {
+        (Some(path), _) => path.clone(),
+        (None, Some(path)) if path.is_file() => path,
+        _ => return Ok(()),
+    };
+    let config = OAuthConfig::from_path(&path)?;
+    let prm = Params {
+        timeout: base_params.timeout,
+        tls_files: base_params.tls_files.clone(),
+        proxy: base_params.proxy.clone(),
+        dns: base_params.dns.clone(),
+        ..Default::default()
+    };
+    config.fetch_into(register, &prm)?;
+    base_params.oauth = Some(config);
+    Ok(())
+}
+
+/// refresh_oauth fetches a new access token after a 401 response to a frame reading the token,
+/// returning true if the frame should be sent again
+pub fn refresh_oauth(
+    base_params: &BaseParams,
+    register: &mut Register,
+    frame: &Frame,
+    status: u32,
+) -> Result<bool, Error> {
+    let config = match &base_params.oauth {
+        Some(config) if status == 401 => config,
+        _ => return Ok(false),
+    };
+    if !frame.cut.read().any(|var| var == config.variable) {
+        return Ok(false);
+    }
+    warn!(
+        "{} 401 response, refreshing ${{{}}}",
+        "OAuth:".yellow(),
+        config.variable
+    );
+    let prm = base_params.init(frame.get_request())?;
+    config.fetch_into(register, &prm)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filmreel::register;
+    use serde_json::json;
+
+    #[test]
+    fn test_hydrated_form() {
+        let config: OAuthConfig = serde_json::from_value(json!({
+            "token_url": "${AUTH_URL}/oauth/token",
+            "client_id": "darkroom",
+            "client_secret": "${_SECRET}",
+            "scope": "users:read",
+            "client_auth": "body"
+        }))
+        .unwrap();
+        assert_eq!("ACCESS_TOKEN", config.variable);
+        let register = register!({
+            "AUTH_URL" => "http://localhost:8080",
+            "_SECRET" => "hunter2"
+        });
+        let hydrated = config.hydrated(&register).unwrap();
+        assert_eq!("http://localhost:8080/oauth/token", hydrated.token_url);
+        assert_eq!(
+            vec![
+                ("grant_type", "client_credentials"),
+                ("client_id", "darkroom"),
+                ("client_secret", "hunter2"),
+                ("scope", "users:read"),
+            ],
+            hydrated.form()
+        );
+        let basic = OAuthConfig {
+            client_auth: ClientAuth::Basic,
+            ..hydrated
+        };
+        assert_eq!(
+            vec![
+                ("grant_type", "client_credentials"),
+                ("scope", "users:read")
+            ],
+            basic.form()
+        );
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_fetch_into() {
+        use std::io::{BufRead, BufReader, Read, Write};
+
+        // a token endpoint answering a single request with the form it was sent as the token
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut len = 0;
+            let mut authorized = false;
+            for line in reader.by_ref().lines() {
+                let line = line.unwrap();
+                if line.is_empty() {
+                    break;
+                }
+                let (name, value) = line.split_once(": ").unwrap_or_default();
+                match name.to_lowercase().as_str() {
+                    "content-length" => len = value.parse().unwrap(),
+                    "authorization" => authorized = value == "Basic ZGFya3Jvb206aHVudGVyMg==",
+                    _ => (),
+                }
+            }
+            let mut form = vec![0; len];
+            reader.read_exact(&mut form).unwrap();
+            let body = json!({"access_token": format!("{authorized}&{}", String::from_utf8(form).unwrap())});
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                body.to_string().len()
+            )
+            .unwrap();
+        });
+
+        let config: OAuthConfig = serde_json::from_value(json!({
+            "token_url": format!("http://127.0.0.1:{port}/oauth/token"),
+            "client_id": "darkroom",
+            "client_secret": "hunter2"
+        }))
+        .unwrap();
+        let mut register = Register::new();
+        let prm = Params {
+            timeout: 5,
+            ..Default::default()
+        };
+        config.fetch_into(&mut register, &prm).unwrap();
+        assert_eq!(
+            Some(&json!("true&grant_type=client_credentials")),
+            register.get("ACCESS_TOKEN")
+        );
+        assert!(register.is_hidden("ACCESS_TOKEN"));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config = serde_json::from_value::<OAuthConfig>(json!({
+            "token_url": "http://localhost:8080/oauth/token",
+            "client_id": "darkroom",
+            "client_secret": "hunter2",
+            "grant_type": "password"
+        }));
+        assert!(config.is_err());
+    }
+}
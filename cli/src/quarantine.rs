@@ -0,0 +1,87 @@
+use anyhow::{Context, Error};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// The filename looked for in a reel directory when initializing a [`Quarantine`]
+pub const QUARANTINE_FILE: &str = "quarantine.json";
+
+/// Quarantine holds the frames whose failures are tolerated until a given expiry date, keyed by
+/// frame filename:
+///
+/// ```json
+/// {
+///   "usr.03s.flaky.fr.json": {
+///     "expires": "2024-01-31",
+///     "reason": "upstream fix pending"
+///   }
+/// }
+/// ```
+#[derive(Deserialize, Default, Debug, PartialEq)]
+pub struct Quarantine(BTreeMap<String, QuarantineEntry>);
+
+/// QuarantineEntry represents a single quarantined frame
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct QuarantineEntry {
+    /// the last day (inclusive) that a failure of the frame is tolerated
+    pub expires: NaiveDate,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl Quarantine {
+    /// Looks for a `quarantine.json` file in the provided directory, an empty [`Quarantine`] is
+    /// returned if the file is not present
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let path = dir.as_ref().join(QUARANTINE_FILE);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let json_string =
+            fs::read_to_string(&path).context(format!("quarantine read failure => {path:?}"))?;
+        serde_json::from_str(&json_string).context(format!("quarantine parse failure => {path:?}"))
+    }
+
+    /// Returns the quarantine entry for a given frame filename
+    pub fn get<T: AsRef<str>>(&self, filename: T) -> Option<&QuarantineEntry> {
+        self.0.get(filename.as_ref())
+    }
+}
+
+impl QuarantineEntry {
+    /// Returns true if the quarantine no longer tolerates failures on the given date
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        today > self.expires
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    const QUARANTINE_JSON: &str = r#"
+{
+  "usr.03s.flaky.fr.json": {
+    "expires": "2024-01-31",
+    "reason": "upstream fix pending"
+  }
+}
+    "#;
+
+    #[rstest(
+        today,
+        expected,
+        case("2024-01-30", false),
+        case("2024-01-31", false),
+        case("2024-02-01", true)
+    )]
+    fn test_is_expired(today: &str, expected: bool) {
+        let quarantine: Quarantine = serde_json::from_str(QUARANTINE_JSON).unwrap();
+        let entry = quarantine.get("usr.03s.flaky.fr.json").unwrap();
+        assert_eq!(
+            expected,
+            entry.is_expired(NaiveDate::parse_from_str(today, "%Y-%m-%d").unwrap())
+        );
+    }
+}
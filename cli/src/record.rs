@@ -1,56 +1,274 @@
-use crate::{guess_json_obj, params::BaseParams, take::*, Record, VirtualRecord};
+use crate::{
+    abort::AbortGuard,
+    artifacts::{error_report, write_artifacts},
+    dump::write_failure_dump,
+    exit_code, guess_json_obj,
+    hash::ReelHash,
+    metrics::{FrameStatus, RecordMetrics},
+    oauth::init_oauth,
+    params::BaseParams,
+    quarantine::{Quarantine, QuarantineEntry},
+    status::Progress,
+    storage::{read_register, RemoteCut},
+    take::*,
+    transport::runtime,
+    Record, RecordAll, ToStringHidden, ToStringPretty, VirtualRecord,
+};
 use anyhow::{anyhow, Context, Error};
+use chrono::{DateTime, SecondsFormat, Utc};
 use colored::*;
 use filmreel as fr;
-use fr::{cut::Register, frame::Frame, reel::*, ToStringHidden};
-use log::{debug, error, warn};
+use fr::{
+    cut::{is_ignored, Register},
+    frame::{Frame, InstructionSet, Severity},
+    reel::*,
+};
+use log::{debug, error, info, warn, Level};
+use serde_json::Value;
 use std::{
+    collections::{BTreeSet, HashMap},
     fs,
+    io::Write,
     ops::Range,
-    path::{Path, PathBuf},
-    time::Instant,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
 };
 
 pub struct RecordRunner {
     duration: bool,
+    max_duration: Option<Duration>,
     reel_name: String,
     take_out: Option<PathBuf>,
     register: Register,
+    quarantine: Quarantine,
     pub frames: Vec<MetaFrame>,
 }
 
+impl RecordRunner {
+    /// Creates a RecordRunner from MetaFrames that may hold in-memory Frames, such as the ones
+    /// produced by [`Reel::from_frames`]
+    pub fn new<T: Into<String>>(reel_name: T, register: Register, frames: Vec<MetaFrame>) -> Self {
+        Self {
+            duration: false,
+            max_duration: None,
+            reel_name: reel_name.into(),
+            take_out: None,
+            register,
+            quarantine: Quarantine::default(),
+            frames,
+        }
+    }
+}
+
 pub fn cmd_record(cmd: Record, mut base_params: BaseParams) -> Result<(), Error> {
     base_params.timeout = cmd.timeout;
     base_params.timestamp = cmd.timestamp;
 
-    let mut cut_register = Register::try_from(cmd.get_cut_file())?;
+    let mut cut_register = read_register(cmd.get_cut_file())?;
     let frame_range = match cmd.range {
         Some(r) => parse_range(r)?,
         None => None,
     };
+    let max_duration = cmd.max_duration.map(parse_duration).transpose()?;
     let reel = Reel::new(&cmd.reel_path, &cmd.reel_name, frame_range)?;
+    let quarantine = Quarantine::from_dir(&cmd.reel_path)?;
+
+    let mut frames: Vec<MetaFrame> = reel.into_iter().collect();
+    if let Some(hashfile) = &cmd.changed_since {
+        frames = ReelHash::from_path(hashfile)?.changed(frames)?;
+    }
+    frames = FrameFilter::new(cmd.filter, cmd.tags, cmd.skip_tags).apply(frames, &base_params)?;
 
     // #### Component init
-    let (mut comp_reels, mut comp_reg) = init_components(cmd.component)?;
+    let (comp_reels, mut comp_reg) = init_components(cmd.component)?;
     comp_reg.single_merge(cut_register);
-    comp_reels.push(reel);
     cut_register = comp_reg;
 
     // add merge_cuts destructively
     read_into(&mut cut_register, cmd.merge_cuts)?;
+    let reel_oauth = cmd.reel_path.join(format!("{}.oauth.json", cmd.reel_name));
+    init_oauth(&mut base_params, &mut cut_register, Some(reel_oauth))?;
 
     run_record(
         RecordRunner {
             duration: cmd.duration,
+            max_duration,
             reel_name: cmd.reel_name,
             take_out: cmd.take_out,
             register: cut_register,
-            frames: comp_reels.into_iter().flatten().collect(),
+            quarantine,
+            frames: comp_reels.into_iter().flatten().chain(frames).collect(),
         },
         base_params,
     )
 }
 
+/// ReelTarget is a reel recorded by `dark record-all`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ReelTarget {
+    pub dir: PathBuf,
+    pub name: String,
+}
+
+impl ReelTarget {
+    /// take_out_dir returns the output directory of the reel under the `--take-out` directory,
+    /// keyed by the reel directory so that reels of the same name in different directories do not
+    /// share their takes
+    fn take_out_dir(&self, take_out: &Path) -> PathBuf {
+        self.dir
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .fold(take_out.to_path_buf(), |dir, c| dir.join(c))
+            .join(&self.name)
+    }
+}
+
+impl std::fmt::Display for ReelTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}&{}", self.dir.to_string_lossy(), self.name)
+    }
+}
+
+/// reel_targets parses the `<reel>` arguments of `dark record-all` in the order provided, a
+/// directory is replaced by the reels found beneath it
+pub fn reel_targets(reels: &[String]) -> Result<Vec<ReelTarget>, Error> {
+    let mut targets: Vec<ReelTarget> = vec![];
+    for reel in reels {
+        let found = match reel.split_once('&') {
+            Some((dir, name)) => vec![ReelTarget {
+                dir: PathBuf::from(dir),
+                name: name.to_string(),
+            }],
+            None if Path::new(reel).is_dir() => {
+                let mut found = BTreeSet::new();
+                discover_reels(Path::new(reel), &mut found)?;
+                if found.is_empty() {
+                    warn!("{} no frames found in {:?}", "record-all:".yellow(), reel);
+                }
+                found.into_iter().collect()
+            }
+            None => {
+                return Err(anyhow!(
+                    "<reel> must be a directory or a \"<dir>&<reel_name>\" pattern => {}",
+                    reel
+                ))
+            }
+        };
+        for target in found {
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+    }
+    Ok(targets)
+}
+
+/// discover_reels adds the reels of the frames found in the directory and its non-hidden
+/// subdirectories
+fn discover_reels(dir: &Path, found: &mut BTreeSet<ReelTarget>) -> Result<(), Error> {
+    let entries = fs::read_dir(dir).context(format!("unable to read directory {dir:?}"))?;
+    for entry in entries {
+        let path = entry?.path();
+        let file_name = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .unwrap_or_default();
+        if file_name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            discover_reels(&path, found)?;
+        } else if file_name.ends_with(".fr.json") {
+            if let Ok(meta_frame) = MetaFrame::try_from(&path) {
+                found.insert(ReelTarget {
+                    dir: dir.to_path_buf(),
+                    name: meta_frame.reel_name,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// cmd_record_all records every reel of the `<reel>` arguments in turn, each with the cut file
+/// of its directory, then prints a summary of the reels. An error is returned if any reel failed,
+/// carrying the failure with the highest exit code.
+pub fn cmd_record_all(cmd: RecordAll, base_params: BaseParams) -> Result<(), Error> {
+    let targets = reel_targets(&cmd.reels)?;
+    let start = Instant::now();
+    // the reels that ran along with their error, if any
+    let mut results: Vec<(&ReelTarget, Option<Error>)> = vec![];
+    for target in targets.iter() {
+        warn!("{} {}", "Reel:".yellow(), target.to_string().bold());
+        let take_out = cmd
+            .take_out
+            .as_ref()
+            .map(|dir| -> Result<PathBuf, Error> {
+                let dir = target.take_out_dir(dir);
+                fs::create_dir_all(&dir).context(format!("unable to create {dir:?}"))?;
+                Ok(dir)
+            })
+            .transpose()?;
+        let record = Record {
+            reel_path: target.dir.clone(),
+            reel_name: target.name.clone(),
+            cut: None,
+            component: vec![],
+            merge_cuts: vec![],
+            take_out,
+            range: None,
+            max_duration: None,
+            timeout: cmd.timeout,
+            timestamp: cmd.timestamp,
+            duration: cmd.duration,
+            changed_since: None,
+            filter: None,
+            tags: vec![],
+            skip_tags: vec![],
+        };
+        let result = record
+            .validate()
+            .and_then(|_| cmd_record(record, base_params.clone()));
+        let failed = result.is_err();
+        results.push((target, result.err()));
+        if failed && cmd.fail_fast {
+            break;
+        }
+    }
+
+    let failed = results.iter().filter(|(_, err)| err.is_some()).count();
+    let skipped = targets.len() - results.len();
+    warn!("{}", "=======================".green());
+    for (target, err) in results.iter() {
+        match err {
+            None => warn!("{} {}", "PASS".green(), target),
+            Some(e) => warn!("{} {} => {:#}", "FAIL".red(), target, e),
+        }
+    }
+    for target in targets.iter().skip(results.len()) {
+        warn!("{} {}", "SKIP".yellow(), target);
+    }
+    warn!(
+        "{} {} passed, {} failed, {} skipped [{:.3}sec]",
+        "Reels:".yellow(),
+        results.len() - failed,
+        failed,
+        skipped,
+        start.elapsed().as_secs_f32(),
+    );
+    // the failure with the highest exit code determines the exit code of the command
+    match results
+        .into_iter()
+        .filter_map(|(_, err)| err)
+        .max_by_key(exit_code)
+    {
+        None => Ok(()),
+        Some(err) => Err(err.context(format!("{} of {} reels failed", failed, targets.len()))),
+    }
+}
+
 pub fn cmd_vrecord(cmd: VirtualRecord, mut base_params: BaseParams) -> Result<(), Error> {
     use fr::vreel::*;
 
@@ -58,12 +276,27 @@ pub fn cmd_vrecord(cmd: VirtualRecord, mut base_params: BaseParams) -> Result<()
     base_params.timestamp = cmd.timestamp;
 
     let vreel = cmd.init()?;
-    let register = match vreel.cut {
+    let quarantine = match &vreel.path {
+        Some(path) => Quarantine::from_dir(path)?,
+        None => Quarantine::default(),
+    };
+    let mut register = match vreel.cut {
         VirtualCut::Register(r) => r,
         VirtualCut::MergeCuts(cuts) if cuts.is_empty() => Register::new(),
         VirtualCut::MergeCuts(cuts) => Register::try_from(cuts)?,
         VirtualCut::Cut(cut) => Register::try_from(cut)?,
     };
+    read_into(&mut register, cmd.merge_cut.clone())?;
+    for CutAssignment { name, value } in cmd.set.iter() {
+        register
+            .write_operation(name, value.clone())
+            .context(format!("--set {name}"))?;
+    }
+    let reel_oauth = vreel
+        .path
+        .as_ref()
+        .map(|dir| dir.join(format!("{}.oauth.json", vreel.name)));
+    init_oauth(&mut base_params, &mut register, reel_oauth)?;
 
     let frames = match vreel.frames {
         VirtualFrames::List(list) => list
@@ -79,21 +312,38 @@ pub fn cmd_vrecord(cmd: VirtualRecord, mut base_params: BaseParams) -> Result<()
             })
             .collect::<Result<Vec<MetaFrame>, _>>()?,
     };
+    let filter = FrameFilter::new(cmd.filter.clone(), cmd.tags.clone(), cmd.skip_tags.clone());
+    let frames = filter.apply(frames, &base_params)?;
 
     run_record(
         RecordRunner {
             duration: false,
+            max_duration: None,
             reel_name: vreel.name.into(),
             take_out: cmd.take_out,
             register,
+            quarantine,
             frames,
         },
         base_params,
     )
 }
 
-/// runs through a [fr::Reel] sequence using the [crate::Record] or [crate::VirtualRecord] structs
-pub fn run_record(mut runner: RecordRunner, base_params: BaseParams) -> Result<(), Error> {
+/// runs through a [fr::Reel] sequence using the [crate::Record] or [crate::VirtualRecord] structs,
+/// writing the cookie jar to `--cookie-out` once the record ends
+pub fn run_record(runner: RecordRunner, base_params: BaseParams) -> Result<(), Error> {
+    let cookie_out = base_params
+        .cookie_out
+        .clone()
+        .zip(base_params.cookie_jar.clone());
+    let result = run_frames(runner, base_params);
+    if let Some((path, jar)) = cookie_out {
+        jar.write(&path)?;
+    }
+    result
+}
+
+fn run_frames(mut runner: RecordRunner, mut base_params: BaseParams) -> Result<(), Error> {
     let start = Instant::now();
     let duration = runner.duration;
     let get_duration = || {
@@ -105,39 +355,269 @@ pub fn run_record(mut runner: RecordRunner, base_params: BaseParams) -> Result<(
         }
     };
 
-    for meta_frame in runner.frames.into_iter() {
+    let today = chrono::Local::now().date_naive();
+    // frames marked with a warning severity that failed to match
+    let mut warnings: Vec<String> = vec![];
+    // quarantined frames that failed to match
+    let mut quarantined: Vec<String> = vec![];
+    // frames skipped after the --max-duration budget was exceeded
+    let mut skipped: Vec<String> = vec![];
+    let mut metrics = RecordMetrics::new(&runner.reel_name);
+    // the Cut Variables read, written, or generated by the frames of the record
+    let mut used_vars: BTreeSet<String> = BTreeSet::new();
+    let input_vars: Vec<String> = runner.register.iter().map(|(k, _)| k.clone()).collect();
+    let timeout = base_params.timeout;
+    let remaining = || {
+        runner
+            .max_duration
+            .map(|max| max.saturating_sub(start.elapsed()))
+    };
+    // the run ID is always shown once it is sent to the servers under test
+    let run_id_level = match base_params.run_id_header {
+        Some(_) => Level::Warn,
+        None => Level::Info,
+    };
+    log::log!(
+        run_id_level,
+        "{} {}",
+        "Run ID:".yellow(),
+        base_params.run_id
+    );
+    // dumps the cut register to --cut-out upon SIGINT or SIGTERM
+    let abort_guard = AbortGuard::install(
+        base_params.cut_out.clone(),
+        base_params.cut_out_opts,
+        &runner.reel_name,
+        &runner.register,
+    )?;
+    let run_dir = runner
+        .take_out
+        .as_ref()
+        .map(|dir| RunDir::create(dir, &base_params.run_id))
+        .transpose()?;
+    let total = runner.frames.len();
+    // the durations of the frames run so far, estimating the time left in the record
+    let mut durations: Vec<Duration> = vec![];
+    // the register as of the first frame group of the reel manifest, restored whenever the group
+    // changes so that groups never read each other's writes
+    let mut setup_register: Option<Register> = None;
+    let mut group: Option<String> = None;
+    // frames taken ahead of their turn by --concurrency, reported in the order of the reel
+    let mut prefetched: HashMap<usize, Prefetched> = HashMap::new();
+    // the frame following the frame taken last, read while the request of that frame was in flight
+    let mut pipelined: Option<(usize, Pipelined)> = None;
+    let frames = std::mem::take(&mut runner.frames);
+    for (i, meta_frame) in frames.iter().enumerate() {
         // if cmd.output is Some, provide a take PathBuf
-        let output = runner
-            .take_out
+        let output = run_dir
             .as_ref()
-            .map(|dir| take_output(&dir, &&meta_frame.path));
+            .map(|dir| take_output(&dir.path, &PathBuf::from(meta_frame.get_filename())));
 
-        let mut info_str = format!("{} {:?}", "File:".yellow(), meta_frame.get_filename());
-        if let Some(alt_name) = meta_frame.alt_name {
+        let filename = meta_frame.get_filename();
+        let mut info_str = format!("{} {:?}", "File:".yellow(), filename);
+        if let Some(alt_name) = &meta_frame.alt_name {
             info_str = format!("{:45} | {} {}", info_str, "Name:".yellow(), alt_name);
         }
         warn!("{}{}", base_params.fmt_timestamp(), info_str,);
         warn!("{}", "=======================".green());
+        if meta_frame.group != group {
+            match &setup_register {
+                Some(setup) => runner.register = setup.clone(),
+                None => setup_register = Some(runner.register.clone()),
+            }
+            if let Some(name) = &meta_frame.group {
+                info!("{} {}", "Group:".yellow(), name);
+            }
+            group = meta_frame.group.clone();
+        }
+        abort_guard.update(&filename, &runner.register);
 
-        let frame = Frame::try_from(meta_frame.path)?;
+        let batchable = remaining() != Some(Duration::ZERO) && !prefetched.contains_key(&i);
+        if base_params.concurrency > 1 && batchable {
+            let mut batch_params = base_params.clone();
+            batch_params.timeout = budget_timeout(timeout, remaining());
+            prefetched = take_batch(&frames, i, &runner.register, &batch_params);
+        }
+        let (frame, prehydrated) = match (prefetched.get(&i), pipelined.take()) {
+            (Some(taken), _) => (taken.frame.clone(), None),
+            (None, Some((next, ahead))) if next == i => (ahead.frame?, ahead.prehydrated),
+            _ => (read_frame(meta_frame, &base_params)?, None),
+        };
+        used_vars.extend(frame.cut.variables().map(String::from));
+        if let Some(before) = &frame.before {
+            used_vars.extend(before.cut.variables().map(String::from));
+        }
+        metrics.annotate(&frame.meta);
+        let expired = !prefetched.contains_key(&i) && remaining() == Some(Duration::ZERO);
+        if expired && !frame.teardown {
+            warn!("{} --max-duration exceeded", "Skipped:".yellow());
+            metrics.push(&filename, FrameStatus::Skipped, Duration::ZERO);
+            skipped.push(filename);
+            continue;
+        }
+        // requests are cut short by the remaining budget, teardown frames keep the full timeout
+        base_params.timeout = match expired {
+            true => timeout,
+            false => budget_timeout(timeout, remaining()),
+        };
+        if let Some(description) = &frame.meta.description {
+            info!("{} {}", "Description:".yellow(), description);
+        }
         // Frame to be mutably borrowed
         let mut payload_frame = frame.clone();
+        runner
+            .register
+            .write_builtin("FRAME_NAME", Value::String(filename.clone()));
 
-        if let Err(e) = run_take(
-            &mut payload_frame,
-            &mut runner.register,
-            &base_params,
-            output,
-        ) {
+        base_params.progress = Some(Progress::new(i + 1, total, &durations));
+        let (result, elapsed, started_at) = match prefetched.remove(&i) {
+            Some(taken) => {
+                payload_frame = taken.payload;
+                merge_writes(&mut runner.register, &taken.base, taken.register);
+                let result = match output {
+                    Some(frame_out) => taken
+                        .result
+                        .and_then(|_| write_take(&payload_frame, frame_out)),
+                    None => taken.result,
+                };
+                (result, taken.elapsed, taken.started_at)
+            }
+            None => {
+                let frame_start = Instant::now();
+                let started_at = Utc::now();
+                let next = frames
+                    .get(i + 1)
+                    .filter(|next| next.group == meta_frame.group)
+                    .filter(|_| !base_params.interactive && remaining() != Some(Duration::ZERO));
+                let (result, ahead) = take_pipelined(
+                    &mut payload_frame,
+                    &mut runner.register,
+                    &base_params,
+                    output,
+                    prehydrated,
+                    next,
+                );
+                pipelined = ahead.map(|ahead| (i + 1, ahead));
+                (result, frame_start.elapsed(), started_at)
+            }
+        };
+        durations.push(elapsed);
+        if base_params.timestamp {
+            warn!(
+                "{} {}",
+                "Frame time:".yellow(),
+                frame_time(&filename, started_at, Utc::now())
+            );
+        }
+        if let Err(e) = result {
+            let mut push_error = |status: FrameStatus| {
+                let error = error_report(&e, &runner.register);
+                metrics.push_error(&filename, status, elapsed, error);
+            };
+            if remaining() == Some(Duration::ZERO) && !frame.teardown {
+                error!("{} {}", "Error:".red(), e);
+                warn!("{} --max-duration exceeded", "Skipped:".yellow());
+                push_error(FrameStatus::Skipped);
+                skipped.push(filename);
+                continue;
+            }
+            if frame.severity == Severity::Warning {
+                log_severity_warning(&frame, &e);
+                push_error(FrameStatus::Warning);
+                warnings.push(filename);
+                continue;
+            }
+            if let Some(entry) = runner.quarantine.get(&filename) {
+                if !entry.is_expired(today) {
+                    log_quarantined(&filename, entry, &e);
+                    push_error(FrameStatus::Quarantined);
+                    quarantined.push(filename);
+                    continue;
+                }
+                error!(
+                    "{} {} expired on {}",
+                    "Quarantine:".red(),
+                    filename,
+                    entry.expires
+                );
+            }
+            log_metadata(&frame.meta, Level::Error);
+            base_params.error_seed();
+            push_error(FrameStatus::Failed);
+            metrics.export(&base_params.metrics);
+            if let Some(dir) = &base_params.artifacts {
+                write_artifacts(
+                    dir,
+                    &filename,
+                    &payload_frame,
+                    &runner.register,
+                    &e,
+                    &base_params.generator,
+                )?;
+            }
+            if let Some(dir) = &base_params.dump_failures {
+                write_failure_dump(
+                    dir,
+                    &filename,
+                    &payload_frame,
+                    &runner.register,
+                    &base_params,
+                );
+            }
             get_duration();
+            if let Some(run_dir) = &run_dir {
+                run_dir.write(&runner.register, &runner.reel_name, &metrics, false)?;
+            }
             write_cut(
                 &base_params.cut_out,
+                base_params.cut_out_opts,
                 &runner.register,
                 &runner.reel_name,
                 true,
             )?;
+            if let Some(cmd) = &base_params.on_failure {
+                run_on_failure(
+                    cmd,
+                    &[
+                        ("DARK_REEL", runner.reel_name.clone()),
+                        ("DARK_FRAME", filename),
+                        ("DARK_RUN_ID", base_params.run_id.clone()),
+                        ("DARK_ERROR", runner.register.redact(format!("{e:#}"))),
+                        ("DARK_EXIT_CODE", exit_code(&e).to_string()),
+                        ("DARK_ARTIFACTS", path_var(&base_params.artifacts)),
+                        ("DARK_DUMP_FAILURES", path_var(&base_params.dump_failures)),
+                        ("DARK_TAKE_OUT", path_var(&run_dir.map(|dir| dir.path))),
+                    ],
+                );
+            }
             return Err(e);
         }
+        metrics.push(&filename, FrameStatus::Passed, elapsed);
+        if let Some(header) = promoted_header(&frame, &runner.register)
+            .context(format!("{filename}: promote_header"))?
+        {
+            info!("{} {:?}", "Promoted headers:".yellow(), header.keys());
+            base_params.promote_header(header);
+        }
+    }
+    metrics.export(&base_params.metrics);
+    if !skipped.is_empty() {
+        error!("{} {}", "Skipped:".red(), skipped.join(", "));
+        get_duration();
+        if let Some(run_dir) = &run_dir {
+            run_dir.write(&runner.register, &runner.reel_name, &metrics, false)?;
+        }
+        write_cut(
+            &base_params.cut_out,
+            base_params.cut_out_opts,
+            &runner.register,
+            &runner.reel_name,
+            true,
+        )?;
+        return Err(anyhow!(
+            "record exceeded --max-duration, {} frames were skipped",
+            skipped.len()
+        ));
     }
     warn!(
         "{}{}{}{}",
@@ -146,10 +626,28 @@ pub fn run_record(mut runner: RecordRunner, base_params: BaseParams) -> Result<(
         "Success 🎉 ".yellow(),
         "==========\n".green()
     );
+    if !warnings.is_empty() {
+        warn!(
+            "{} {}",
+            "Tolerated mismatches:".yellow(),
+            warnings.join(", ")
+        );
+    }
+    if !quarantined.is_empty() {
+        warn!("{} {}", "Quarantined:".yellow(), quarantined.join(", "));
+    }
+    let unused = unused_variables(input_vars, &used_vars);
+    if !unused.is_empty() {
+        warn!("{} {}", "Unused cut variables:".yellow(), unused.join(", "));
+    }
     get_duration();
+    if let Some(run_dir) = &run_dir {
+        run_dir.write(&runner.register, &runner.reel_name, &metrics, true)?;
+    }
 
     write_cut(
         &base_params.cut_out,
+        base_params.cut_out_opts,
         &runner.register,
         &runner.reel_name,
         false,
@@ -158,33 +656,507 @@ pub fn run_record(mut runner: RecordRunner, base_params: BaseParams) -> Result<(
     Ok(())
 }
 
+/// Pipelined is a frame read, and hydrated when possible, while the request of the frame before
+/// it was in flight. A frame that could not be read holds its error until the record reaches it.
+struct Pipelined {
+    frame: Result<Frame<'static>, Error>,
+    prehydrated: Option<Prehydrated>,
+}
+
+/// take_pipelined takes the frame while the next frame of the reel is read on another thread. The
+/// next frame is also hydrated from the register as of the start of the take when it references
+/// nothing written by the frame, its hydration is checked against the register once it is taken.
+fn take_pipelined(
+    frame: &mut Frame<'static>,
+    register: &mut Register,
+    base_params: &BaseParams,
+    output: Option<PathBuf>,
+    prehydrated: Option<Prehydrated>,
+    next: Option<&MetaFrame>,
+) -> (Result<(), Error>, Option<Pipelined>) {
+    let Some(next) = next else {
+        let result = run_prehydrated(frame, register, base_params, output, prehydrated);
+        return (result, None);
+    };
+    let written: BTreeSet<String> = std::iter::once(&frame.cut)
+        .chain(frame.before.as_ref().map(|b| &b.cut))
+        .flat_map(|set| set.written())
+        .map(String::from)
+        .collect();
+    let mut snapshot = register.clone();
+    snapshot.write_builtin("FRAME_NAME", Value::String(next.get_filename()));
+    let next_params = base_params.clone();
+    thread::scope(|scope| {
+        let worker = scope.spawn(move || {
+            let frame = match read_frame(next, &next_params) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    return Pipelined {
+                        frame: Err(e),
+                        prehydrated: None,
+                    }
+                }
+            };
+            let prehydrated = match frame.cut.variables().any(|var| written.contains(var)) {
+                true => None,
+                false => Prehydrated::new(&frame, &snapshot, &next_params).unwrap_or(None),
+            };
+            Pipelined {
+                frame: Ok(frame),
+                prehydrated,
+            }
+        });
+        let result = run_prehydrated(frame, register, base_params, output, prehydrated);
+        (result, worker.join().ok())
+    })
+}
+
+/// Prefetched is a frame taken by `--concurrency` ahead of its turn in a record, from a copy of the
+/// register as of the first frame of its batch
+struct Prefetched {
+    /// the frame as it was read
+    frame: Frame<'static>,
+    /// the frame as it was taken
+    payload: Frame<'static>,
+    base: Register,
+    register: Register,
+    result: Result<(), Error>,
+    elapsed: Duration,
+    started_at: DateTime<Utc>,
+}
+
+/// concurrent_batch returns the frames starting at the given index that can be taken at the same
+/// time under `--concurrent-frames`, at most `--concurrency` of them: consecutive frames of the same group that read nothing
+/// written by the frames before them in the batch and write distinct variables, frame-local
+/// variables aside as they never leave the register of their frame. Frames that
+/// generate variables, promote headers, or tear down are never batched, nor are the frames of the
+/// interactive mode.
+fn concurrent_batch(
+    frames: &[MetaFrame],
+    start: usize,
+    base_params: &BaseParams,
+) -> Vec<(usize, Frame<'static>)> {
+    let mut batch = vec![];
+    if !base_params.concurrent_frames || base_params.concurrency < 2 || base_params.interactive {
+        return batch;
+    }
+    let mut written: BTreeSet<String> = BTreeSet::new();
+    for (i, meta_frame) in frames
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(base_params.concurrency)
+    {
+        if meta_frame.group != frames[start].group {
+            break;
+        }
+        let frame = match read_frame(meta_frame, base_params) {
+            Ok(frame) if !frame.teardown && !promotes_header(&frame) => frame,
+            _ => break,
+        };
+        let sets: Vec<&InstructionSet> = std::iter::once(&frame.cut)
+            .chain(frame.before.as_ref().map(|b| &b.cut))
+            .collect();
+        let shared_writes = || {
+            sets.iter()
+                .flat_map(|set| set.written().filter(|var| !set.is_local(var)))
+        };
+        if sets.iter().any(|set| set.generated().next().is_some())
+            || sets
+                .iter()
+                .flat_map(|set| set.read())
+                .chain(shared_writes())
+                .any(|var| written.contains(var))
+        {
+            break;
+        }
+        written.extend(shared_writes().map(String::from));
+        batch.push((i, frame));
+    }
+    batch
+}
+
+fn promotes_header(frame: &Frame) -> bool {
+    frame
+        .get_request()
+        .get_etc()
+        .and_then(|etc| etc.get("promote_header").cloned())
+        .is_some_and(|promote| !promote.is_null())
+}
+
+/// take_batch takes the batch of frames starting at the given index as tasks of the shared
+/// runtime, each from a copy of the register, returning nothing unless there are at least two
+/// frames in the batch. The requests of a batch are sent even if an earlier frame of the batch
+/// fails, their takes are written once the record reaches them.
+fn take_batch(
+    frames: &[MetaFrame],
+    start: usize,
+    register: &Register,
+    base_params: &BaseParams,
+) -> HashMap<usize, Prefetched> {
+    let batch = concurrent_batch(frames, start, base_params);
+    if batch.len() < 2 {
+        return HashMap::new();
+    }
+    info!(
+        "{} {} frames taken concurrently",
+        "Batch:".yellow(),
+        batch.len()
+    );
+    let handles: Vec<_> = batch
+        .into_iter()
+        .map(|(i, frame)| {
+            let filename = frames[i].get_filename();
+            let mut base_params = base_params.clone();
+            base_params.progress = None;
+            let base = register.clone();
+            let mut taken = register.clone();
+            runtime().spawn_blocking(move || {
+                taken.write_builtin("FRAME_NAME", Value::String(filename));
+                let mut payload = frame.clone();
+                let frame_start = Instant::now();
+                let started_at = Utc::now();
+                let result = run_take(&mut payload, &mut taken, &base_params, None);
+                let prefetched = Prefetched {
+                    frame,
+                    payload,
+                    base,
+                    register: taken,
+                    result,
+                    elapsed: frame_start.elapsed(),
+                    started_at,
+                };
+                (i, prefetched)
+            })
+        })
+        .collect();
+    runtime().block_on(async {
+        let mut prefetched = HashMap::new();
+        for handle in handles {
+            let (i, taken) = joined(handle.await);
+            prefetched.insert(i, taken);
+        }
+        prefetched
+    })
+}
+
+/// merge_writes applies the variables written and removed by a frame taken from a copy of the base
+/// register to the register of the record
+fn merge_writes(register: &mut Register, base: &Register, taken: Register) {
+    for (var, _) in base.iter() {
+        if taken.get(var).is_none() {
+            register.remove(var);
+        }
+    }
+    for (var, value) in taken.iter() {
+        if base.get(var) != Some(value) {
+            register
+                .write_operation(var, value.clone())
+                .expect("variable names are checked when first written");
+        }
+        if taken.is_hidden(var) {
+            register.hide(var);
+        }
+    }
+}
+
+/// FrameFilter selects the frames of a record through the `--filter`, `--tags`, and `--skip-tags`
+/// options, a frame is run when its name matches the glob pattern, it holds any of the tags
+/// provided, and none of the skipped tags
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FrameFilter {
+    pattern: Option<String>,
+    tags: Vec<String>,
+    skip_tags: Vec<String>,
+}
+
+impl FrameFilter {
+    /// Creates a FrameFilter, tags can be repeated or comma separated
+    pub fn new(pattern: Option<String>, tags: Vec<String>, skip_tags: Vec<String>) -> Self {
+        let split = |tags: Vec<String>| -> Vec<String> {
+            tags.iter()
+                .flat_map(|t| t.split(','))
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(String::from)
+                .collect()
+        };
+        Self {
+            pattern,
+            tags: split(tags),
+            skip_tags: split(skip_tags),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pattern.is_none() && self.tags.is_empty() && self.skip_tags.is_empty()
+    }
+
+    /// matches returns true if the frame is selected by the filter, teardown frames are always
+    /// selected so that the cleanup of a filtered record still runs
+    pub fn matches(&self, meta_frame: &MetaFrame, base_params: &BaseParams) -> Result<bool, Error> {
+        let named = self.pattern.as_ref().is_none_or(|pattern| {
+            let filename = meta_frame.get_filename();
+            let names = std::iter::once(filename.as_str()).chain(meta_frame.alt_name.as_deref());
+            names.into_iter().any(|name| glob_match(pattern, name))
+        });
+        if named && self.tags.is_empty() && self.skip_tags.is_empty() {
+            return Ok(true);
+        }
+        let frame = read_frame(meta_frame, base_params).context(meta_frame.get_filename())?;
+        let holds = |tags: &[String]| tags.iter().any(|t| frame.tags.contains(t));
+        Ok(frame.teardown
+            || (named && (self.tags.is_empty() || holds(&self.tags)) && !holds(&self.skip_tags)))
+    }
+
+    /// apply returns the frames selected by the filter
+    pub fn apply(
+        &self,
+        frames: Vec<MetaFrame>,
+        base_params: &BaseParams,
+    ) -> Result<Vec<MetaFrame>, Error> {
+        if self.is_empty() {
+            return Ok(frames);
+        }
+        let total = frames.len();
+        let mut selected = vec![];
+        for meta_frame in frames {
+            if self.matches(&meta_frame, base_params)? {
+                selected.push(meta_frame);
+            }
+        }
+        info!(
+            "{} {} of {} frames selected",
+            "Filter:".yellow(),
+            selected.len(),
+            total
+        );
+        Ok(selected)
+    }
+}
+
+/// glob_match returns true if the whole text matches the pattern, where `*` matches any sequence
+/// of characters and `?` matches a single character
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // the position of the last `*` of the pattern and of the text it was matched against
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// run_on_failure runs the `--on-failure` shell command of a failed record with the environment
+/// variables given, waiting for it to exit so that it observes the state of the failure. A failed
+/// command is logged rather than replacing the error of the record.
+fn run_on_failure(cmd: &str, env: &[(&str, String)]) {
+    warn!("{} {}", "On failure:".yellow(), cmd);
+    let status = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .status();
+    match status {
+        Ok(status) if status.success() => (),
+        Ok(status) => error!("{} command exited with {}", "On failure:".red(), status),
+        Err(e) => error!("{} {}", "On failure:".red(), e),
+    }
+}
+
+/// path_var returns the path of an optional output as an environment variable value, empty when
+/// the output is not enabled
+fn path_var(path: &Option<PathBuf>) -> String {
+    path.as_ref()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// RunDir is the subdirectory created for every record inside its `-o` directory, named by the
+/// start time and the run ID of the record so that successive runs never overwrite each other:
+///
+/// - `<frame stem>.tk.json`: the take of every successful frame
+/// - `<reel_name>.cut.json`: the final cut register, hidden variables remain hidden
+/// - `report.json`: the result of the record and the status and duration of every frame run
+#[derive(Debug, PartialEq, Eq)]
+pub struct RunDir {
+    pub path: PathBuf,
+    run_id: String,
+}
+
+impl RunDir {
+    pub fn create<P: AsRef<Path>>(take_out: P, run_id: &str) -> Result<Self, Error> {
+        let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+        let prefix: String = run_id.chars().take(8).collect();
+        let path = take_out.as_ref().join(format!("{timestamp}-{prefix}"));
+        fs::create_dir_all(&path).context(format!("unable to create run directory {path:?}"))?;
+        warn!("{} {}", "Run directory:".yellow(), path.to_string_lossy());
+        Ok(Self {
+            path,
+            run_id: run_id.to_string(),
+        })
+    }
+
+    /// write writes the final cut register and the report of the record, passed is false when
+    /// the record was aborted
+    pub fn write(
+        &self,
+        register: &Register,
+        reel_name: &str,
+        metrics: &RecordMetrics,
+        passed: bool,
+    ) -> Result<(), Error> {
+        let cut_path = self.path.join(format!("{reel_name}.cut.json"));
+        write_atomic(&cut_path, register.to_string_hidden()? + "\n", false)
+            .context(format!("unable to write {cut_path:?}"))?;
+        let report_path = self.path.join("report.json");
+        let report = metrics.report(&self.run_id, passed);
+        write_atomic(&report_path, report.to_string_pretty()? + "\n", false)
+            .context(format!("unable to write {report_path:?}"))
+    }
+}
+
+/// frame_time formats the start and end timestamps and the duration of a frame on a single line
+/// printed when `--timestamp` is provided:
+///
+/// `2026-01-02T15:04:05.000Z -> 2026-01-02T15:04:05.250Z (0.250s) usr.01s.createuser.fr.json`
+fn frame_time(filename: &str, started: DateTime<Utc>, ended: DateTime<Utc>) -> String {
+    let duration = (ended - started).num_milliseconds() as f64 / 1000.0;
+    format!(
+        "{} -> {} ({duration:.3}s) {filename}",
+        started.to_rfc3339_opts(SecondsFormat::Millis, true),
+        ended.to_rfc3339_opts(SecondsFormat::Millis, true),
+    )
+}
+
+/// unused_variables returns the sorted names of the Cut Variables provided to a record that no frame
+/// read, wrote, or generated, hidden and ignored variables are never reported
+fn unused_variables<I>(input_vars: I, used_vars: &BTreeSet<String>) -> Vec<String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut unused: Vec<String> = input_vars
+        .into_iter()
+        .filter(|var| !var.starts_with('_') && !is_ignored(var) && !used_vars.contains(var))
+        .collect();
+    unused.sort();
+    unused
+}
+
+// log_quarantined reports the failure of a frame whose quarantine has not yet expired
+fn log_quarantined(filename: &str, entry: &QuarantineEntry, err: &Error) {
+    warn!("{} {}", "Warning:".yellow(), err);
+    let reason = entry
+        .reason
+        .as_ref()
+        .map(|r| format!(" | {} {}", "Reason:".yellow(), r))
+        .unwrap_or_default();
+    warn!(
+        "{} {} until {}{}",
+        "Quarantined:".yellow(),
+        filename,
+        entry.expires,
+        reason
+    );
+}
+
 // merge any found [PathBuf]s into the cut register destructively
 pub fn read_into(base_register: &mut Register, merge_cuts: Vec<String>) -> Result<(), Error> {
-    let mut err = Ok(());
-    // Merge any found PathBufs into the cut register destructively
     let merge_registers: Vec<Register> = merge_cuts
         .into_iter()
         .map(|c| {
             // if we're passing a json string such as '{"key": "value"}'
             if guess_json_obj(&c) {
-                return Ok(c);
+                return Register::from(c).context("--merge-cuts json string parse failure");
             }
-            fr::file_to_string(&c).map_err(|e| anyhow!("{} - {}", c, e))
+            Ok(Register::try_from(PathBuf::from(c))?)
         })
-        .scan(&mut err, filmreel::until_err)
-        .map(Register::from)
         .collect::<Result<Vec<Register>, _>>()?;
-    // TODO tidy up scan calling only on file_to_string errors
-    err?;
 
     base_register.destructive_merge(merge_registers);
 
     Ok(())
 }
 
+/// CutAssignment is a Cut Variable provided through `--set KEY=VAL`, the value is read as JSON when
+/// valid and as a string otherwise: `--set COUNT=3` is a number and `--set NAME=BIG_BEN` a string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CutAssignment {
+    pub name: String,
+    pub value: Value,
+}
+
+impl std::str::FromStr for CutAssignment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected KEY=VAL, found \"{s}\""))?;
+        Ok(Self {
+            name: name.to_string(),
+            value: serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string())),
+        })
+    }
+}
+
+/// CutOutMode determines how the final register is written to `--cut-out`, provided through
+/// `--cut-out-mode`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CutOutMode {
+    /// replace the contents of the existing cut file
+    #[default]
+    Overwrite,
+    /// merge the register into the existing cut file, keeping the variables absent from the
+    /// register
+    Merge,
+}
+
+impl FromStr for CutOutMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "overwrite" => Ok(Self::Overwrite),
+            "merge" => Ok(Self::Merge),
+            _ => Err(format!(
+                "unknown cut-out mode \"{s}\", expected one of: overwrite, merge"
+            )),
+        }
+    }
+}
+
+/// CutOutOpts holds the options used when writing a register to `--cut-out`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CutOutOpts {
+    /// keep a timestamped backup of the previous cut file, provided through `--cut-backup`
+    pub backup: bool,
+    pub mode: CutOutMode,
+}
+
 /// write_cut dumps the in memory [Register] to the [PathBuf] provided.
 pub fn write_cut<T>(
     cut_out: &Option<PathBuf>,
+    opts: CutOutOpts,
     cut_register: &Register,
     reel_name: T,
     failed_response: bool,
@@ -197,20 +1169,86 @@ where
         if failed_response {
             error!("{}", "take aborted! writing to --cut-out provided...".red());
         }
-        // write with a hidden cut if directory w,as provided
-        if path.is_dir() {
-            let dir_cut = &path.join(format!(".{reel_name}.cut.json"));
-            fs::write(dir_cut, cut_register.to_string_hidden()?)
-                .context("unable to write to --cut_out directory")?;
-        } else {
-            debug!("writing cut output to PathBuf...");
-            fs::write(path, cut_register.to_string_hidden()?)
-                .context("unable to write to cmd.get_cut_copy()")?;
+        if let Some(remote) = RemoteCut::from_path(path) {
+            let mut cut_register = cut_register.clone();
+            if opts.mode == CutOutMode::Merge {
+                if let Some(existing) = remote.read()? {
+                    let mut existing = Register::from(existing)
+                        .context("unable to merge into the existing --cut-out file")?;
+                    existing.destructive_merge([cut_register]);
+                    cut_register = existing;
+                }
+            }
+            return remote
+                .write(cut_register.to_string_hidden()? + "\n")
+                .context("unable to write to the remote --cut-out");
         }
+        // write with a hidden cut if directory w,as provided
+        let (path, context) = match path.is_dir() {
+            true => (
+                path.join(format!(".{reel_name}.cut.json")),
+                "unable to write to --cut_out directory",
+            ),
+            false => {
+                debug!("writing cut output to PathBuf...");
+                (path.clone(), "unable to write to cmd.get_cut_copy()")
+            }
+        };
+        let merged;
+        let cut_register = match opts.mode {
+            CutOutMode::Merge if path.is_file() => {
+                let mut existing = Register::try_from(path.clone())
+                    .context("unable to merge into the existing --cut-out file")?;
+                existing.destructive_merge([cut_register.clone()]);
+                merged = existing;
+                &merged
+            }
+            _ => cut_register,
+        };
+        write_atomic(&path, cut_register.to_string_hidden()? + "\n", opts.backup)
+            .context(context)?;
     }
     Ok(())
 }
 
+/// write_atomic writes to a temporary file next to the path provided before renaming it into
+/// place so that an interrupted write never leaves a partially written file behind. If backup is
+/// true an existing file is first copied to `<file>.<timestamp>.bak`.
+pub fn write_atomic<P, C>(path: P, contents: C, backup: bool) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+    C: AsRef<[u8]>,
+{
+    let path = path.as_ref();
+    let metadata = fs::metadata(path).ok();
+    // special files such as the pipe of a process substitution cannot be renamed over
+    if metadata.as_ref().is_some_and(|m| !m.is_file()) {
+        return Ok(fs::write(path, contents)?);
+    }
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{path:?} does not name a file"))?
+        .to_string_lossy();
+
+    if backup && metadata.is_some() {
+        let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ");
+        let backup_path = path.with_file_name(format!("{file_name}.{timestamp}.bak"));
+        fs::copy(path, &backup_path).context(format!("unable to back up {path:?}"))?;
+    }
+
+    let tmp_path = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+    let write_tmp = || -> std::io::Result<()> {
+        let mut tmp = fs::File::create(&tmp_path)?;
+        tmp.write_all(contents.as_ref())?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, path)
+    };
+    write_tmp().map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        Error::from(e)
+    })
+}
+
 /// take_output grabs a Record command's output directory and joins it with a MetaFrame's file stem
 pub fn take_output<P: AsRef<Path>>(dir: &P, file: &P) -> PathBuf {
     let frame_stem: &str = file
@@ -267,6 +1305,42 @@ fn parse_component(component: String) -> Result<(Reel, Register), Error> {
     ))
 }
 
+/// parse_duration parses the `--max-duration` cli argument, a number followed by one of the
+/// `ms`, `s`, `m`, or `h` units, a number without a unit is read as seconds
+fn parse_duration<T: AsRef<str>>(input: T) -> Result<Duration, Error> {
+    let input = input.as_ref().trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: u64 = number
+        .parse()
+        .context(format!("invalid duration => {input}"))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(number)),
+        "" | "s" => Ok(Duration::from_secs(number)),
+        "m" => Ok(Duration::from_secs(number * 60)),
+        "h" => Ok(Duration::from_secs(number * 60 * 60)),
+        _ => Err(anyhow!(
+            "invalid duration unit \"{unit}\", expected one of: ms, s, m, h"
+        )),
+    }
+}
+
+/// budget_timeout caps the request timeout in seconds to the remaining record budget, a timeout of
+/// `0` disables the request timeout
+fn budget_timeout(timeout: u64, remaining: Option<Duration>) -> u64 {
+    let Some(remaining) = remaining else {
+        return timeout;
+    };
+    // round up so that a sub-second budget still allows a request to be sent
+    let budget = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+    match timeout {
+        0 => budget,
+        t => t.min(budget),
+    }
+}
+
 type ParsedRange = Option<Range<u32>>;
 // parse_range parses the `"<start_u32>:<end_u32>"` provided to the `--range` cli argument
 // returning a range object
@@ -299,8 +1373,352 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{transport::tests::test_params, Command, SubCommand};
+    use argh::FromArgs;
     use rstest::*;
 
+    /// Echo answers every request with its uri after a delay, recording the uris sent and the
+    /// most requests in flight at once
+    #[derive(Default)]
+    struct Echo {
+        uris: std::sync::Mutex<Vec<String>>,
+        in_flight: std::sync::atomic::AtomicUsize,
+        max_in_flight: std::sync::atomic::AtomicUsize,
+    }
+
+    impl crate::transport::Transport for Echo {
+        fn send<'a>(
+            &self,
+            _params: crate::params::Params,
+            frame: Frame,
+        ) -> Result<fr::response::Response<'a>, Error> {
+            use std::sync::atomic::Ordering::SeqCst;
+            let uri = frame.get_request().get_uri();
+            let n = self.in_flight.fetch_add(1, SeqCst) + 1;
+            self.max_in_flight.fetch_max(n, SeqCst);
+            thread::sleep(Duration::from_millis(50));
+            self.in_flight.fetch_sub(1, SeqCst);
+            self.uris.lock().unwrap().push(uri.clone());
+            Ok(fr::response::Response {
+                body: Some(serde_json::json!({ "uri": uri })),
+                status: 200,
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn test_record_concurrency() {
+        use std::sync::{atomic::Ordering::SeqCst, Arc};
+
+        let frame = |uri: &str, cut: Value, body: Value| -> Frame<'static> {
+            serde_json::from_value(serde_json::json!({
+                "protocol": "HTTP",
+                "cut": cut,
+                "request": {"uri": uri},
+                "response": {"body": body, "status": 200}
+            }))
+            .unwrap()
+        };
+        let reel = Reel::from_frames(vec![
+            (
+                "usr.01s.a.fr.json",
+                frame(
+                    "GET /a",
+                    serde_json::json!({"to": {"A": "'response'.'body'.'uri'"}}),
+                    serde_json::json!({"uri": "${A}"}),
+                ),
+            ),
+            (
+                "usr.02s.b.fr.json",
+                frame(
+                    "GET /b",
+                    serde_json::json!({"to": {"B": "'response'.'body'.'uri'"}}),
+                    serde_json::json!({"uri": "${B}"}),
+                ),
+            ),
+            (
+                "usr.03s.c.fr.json",
+                frame(
+                    "GET /c/${A}/${B}",
+                    serde_json::json!({"from": ["A", "B"]}),
+                    serde_json::json!({"uri": "GET /c/GET /a/GET /b"}),
+                ),
+            ),
+        ])
+        .unwrap();
+        let frames: Vec<MetaFrame> = reel.into_iter().collect();
+        let batch = |concurrency: usize, concurrent_frames: bool| {
+            let args = Command::from_args(&["dark"], &["version"]).unwrap();
+            let mut base_params = BaseParams::from(&args);
+            base_params.concurrency = concurrency;
+            base_params.concurrent_frames = concurrent_frames;
+            concurrent_batch(&frames, 0, &base_params)
+                .into_iter()
+                .map(|(i, _)| i)
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(Vec::<usize>::new(), batch(1, true));
+        assert_eq!(Vec::<usize>::new(), batch(3, false));
+        assert_eq!(vec![0, 1], batch(3, true));
+
+        let transport = Arc::new(Echo::default());
+        let mut base_params = test_params(transport.clone());
+        base_params.concurrency = 3;
+        base_params.concurrent_frames = true;
+        run_record(
+            RecordRunner::new("usr", Register::new(), frames),
+            base_params,
+        )
+        .unwrap();
+
+        assert_eq!(2, transport.max_in_flight.load(SeqCst));
+        assert_eq!(
+            Some("GET /c/GET /a/GET /b"),
+            transport.uris.lock().unwrap().last().map(String::as_str)
+        );
+    }
+
+    /// Rewriting fixes the frame file at the path given while the request of the frame before it
+    /// is in flight
+    struct Rewriting {
+        path: PathBuf,
+        contents: &'static str,
+    }
+
+    impl crate::transport::Transport for Rewriting {
+        fn send<'a>(
+            &self,
+            _params: crate::params::Params,
+            _frame: Frame,
+        ) -> Result<fr::response::Response<'a>, Error> {
+            thread::sleep(Duration::from_millis(100));
+            fs::write(&self.path, self.contents).unwrap();
+            Ok(fr::response::Response {
+                status: 200,
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn test_record_pipelined_error() {
+        let dir = std::env::temp_dir().join(format!("dark_pipelined_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let frame =
+            r#"{"protocol": "HTTP", "request": {"uri": "GET /"}, "response": {"status": 200}}"#;
+        let first = dir.join("usr.01s.a.fr.json");
+        let second = dir.join("usr.02s.b.fr.json");
+        fs::write(&first, frame).unwrap();
+        fs::write(&second, "{").unwrap();
+        let frames = vec![
+            MetaFrame::try_from(&first).unwrap(),
+            MetaFrame::try_from(&second).unwrap(),
+        ];
+        let transport = std::sync::Arc::new(Rewriting {
+            path: second.clone(),
+            contents: frame,
+        });
+        let result = run_record(
+            RecordRunner::new("usr", Register::new(), frames),
+            test_params(transport),
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        // the frame read while the first request was in flight is not read again
+        let err = result.unwrap_err();
+        assert!(format!("{err:#}").starts_with("usr.02s.b.fr.json"));
+    }
+
+    #[test]
+    fn test_record_concurrency_takes() {
+        let frame = |uri: &str| -> Frame<'static> {
+            serde_json::from_value(serde_json::json!({
+                "protocol": "HTTP",
+                "request": {"uri": uri},
+                "response": {"body": {"uri": "GET /b"}, "status": 200}
+            }))
+            .unwrap()
+        };
+        let reel = Reel::from_frames(vec![
+            ("usr.01s.a.fr.json", frame("GET /a")),
+            ("usr.02s.b.fr.json", frame("GET /b")),
+        ])
+        .unwrap();
+        let take_out =
+            std::env::temp_dir().join(format!("dark_batch_takes_{}", std::process::id()));
+        let mut runner = RecordRunner::new("usr", Register::new(), reel.into_iter().collect());
+        runner.take_out = Some(take_out.clone());
+        let transport = std::sync::Arc::new(Echo::default());
+        let mut base_params = test_params(transport.clone());
+        base_params.concurrency = 2;
+        base_params.concurrent_frames = true;
+        assert!(run_record(runner, base_params).is_err());
+
+        // both requests of the batch are sent, but the take of a frame after the failed one is not
+        // written
+        assert_eq!(2, transport.uris.lock().unwrap().len());
+        let run_dir = fs::read_dir(&take_out)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        assert!(!run_dir.join("usr.02s.b.tk.json").exists());
+        fs::remove_dir_all(take_out).unwrap();
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = std::env::temp_dir().join(format!("dark_write_atomic_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("usr.cut.json");
+
+        write_atomic(&path, "{}", true).unwrap();
+        write_atomic(&path, r#"{"USER_ID": "BIG_BEN"}"#, true).unwrap();
+        let files: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|f| f != "usr.cut.json")
+            .collect();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(r#"{"USER_ID": "BIG_BEN"}"#, contents);
+        // only the backup of the first write remains, the temporary file is renamed away
+        assert_eq!(1, files.len());
+        assert!(files[0].starts_with("usr.cut.json.") && files[0].ends_with(".bak"));
+    }
+
+    #[rstest(
+        mode,
+        expected,
+        case(CutOutMode::Overwrite, r#"{"USER_ID": "BIG_BEN"}"#),
+        case(CutOutMode::Merge, r#"{"EMAIL": "ben@big.com", "USER_ID": "BIG_BEN"}"#)
+    )]
+    fn test_write_cut_mode(mode: CutOutMode, expected: &str) {
+        let dir = std::env::temp_dir().join(format!("dark_cut_{mode:?}_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("usr.cut.json");
+        fs::write(&path, r#"{"EMAIL": "ben@big.com", "USER_ID": "SMALL_BEN"}"#).unwrap();
+
+        let opts = CutOutOpts {
+            mode,
+            ..Default::default()
+        };
+        let register = fr::register!({"USER_ID" => serde_json::json!("BIG_BEN")});
+        write_cut(&Some(path.clone()), opts, &register, "usr", false).unwrap();
+        let written = Register::try_from(path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(serde_json::from_str::<Register>(expected).unwrap(), written);
+    }
+
+    #[test]
+    fn test_run_dir() {
+        let dir = std::env::temp_dir().join(format!("dark_run_dir_{}", std::process::id()));
+        let run_id = "9339a6c0-4566-4873-b9aa-4a747266595a";
+        let first = RunDir::create(&dir, run_id).unwrap();
+        let second = RunDir::create(&dir, "5c6bc009-4997-46f6-b90d-1c08da66e29e").unwrap();
+        assert_ne!(first, second);
+        assert!(first
+            .path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .ends_with("-9339a6c0"));
+
+        let mut metrics = RecordMetrics::new("usr");
+        metrics.annotate(&fr::frame::Metadata {
+            owner: Some("@users-team".to_string()),
+            ..Default::default()
+        });
+        metrics.push(
+            "usr.01s.createuser.fr.json",
+            FrameStatus::Failed,
+            Duration::from_millis(3),
+        );
+        let register = fr::register!({"USER_ID" => serde_json::json!("BIG_BEN"), "_TOKEN" => serde_json::json!("jWt")});
+        first.write(&register, "usr", &metrics, false).unwrap();
+        let cut = fs::read_to_string(first.path.join("usr.cut.json")).unwrap();
+        let report: Value =
+            serde_json::from_str(&fs::read_to_string(first.path.join("report.json")).unwrap())
+                .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(register.to_string_hidden().unwrap() + "\n", cut);
+        let mut report = report;
+        let frame = report["frames"][0].as_object_mut().unwrap();
+        for key in ["started_at", "ended_at"] {
+            let at = frame.remove(key).unwrap();
+            assert!(DateTime::parse_from_rfc3339(at.as_str().unwrap()).is_ok());
+        }
+        assert_eq!(
+            serde_json::json!({
+                "reel": "usr",
+                "run_id": run_id,
+                "result": "failed",
+                "frames": [
+                    {
+                        "frame": "usr.01s.createuser.fr.json",
+                        "status": "failed",
+                        "duration_ms": 3,
+                        "owner": "@users-team"
+                    }
+                ]
+            }),
+            report
+        );
+    }
+
+    #[test]
+    fn test_run_on_failure() {
+        let dir = std::env::temp_dir().join(format!("dark_on_failure_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("out.txt");
+        run_on_failure(
+            &format!(
+                "echo \"$DARK_REEL $DARK_FRAME [$DARK_ARTIFACTS]\" > {}",
+                out.display()
+            ),
+            &[
+                ("DARK_REEL", "usr".to_string()),
+                ("DARK_FRAME", "usr.01s.createuser.fr.json".to_string()),
+                ("DARK_ARTIFACTS", path_var(&None)),
+            ],
+        );
+        assert_eq!(
+            "usr usr.01s.createuser.fr.json []\n",
+            fs::read_to_string(&out).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_frame_time() {
+        let started = DateTime::parse_from_rfc3339("2026-01-02T15:04:05Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            "2026-01-02T15:04:05.000Z -> 2026-01-02T15:04:06.250Z (1.250s) usr.01s.createuser.fr.json",
+            frame_time(
+                "usr.01s.createuser.fr.json",
+                started,
+                started + chrono::Duration::milliseconds(1250)
+            )
+        );
+    }
+
+    #[test]
+    fn test_unused_variables() {
+        let used: BTreeSet<String> = ["USER_ID", "EMAIL"].iter().map(|v| v.to_string()).collect();
+        let input = ["USER_ID", "OLD_ID", "_TOKEN", "aborted", "ADDRESS", "EMAIL"];
+        assert_eq!(
+            vec!["ADDRESS".to_string(), "OLD_ID".to_string()],
+            unused_variables(input.iter().map(|v| v.to_string()), &used)
+        );
+    }
+
     #[rstest(input, expected,
         case("04:08", Ok::<ParsedRange, Error>(Some(4..9))),
         case(":10", Ok::<ParsedRange, Error>(Some(0..11))),
@@ -315,4 +1733,197 @@ mod tests {
             Err(err) => assert_eq!(expected.unwrap_err().to_string(), err.to_string()),
         }
     }
+
+    #[rstest(
+        input,
+        expected,
+        case("90", Some(Duration::from_secs(90))),
+        case("90s", Some(Duration::from_secs(90))),
+        case("10m", Some(Duration::from_secs(600))),
+        case("1h", Some(Duration::from_secs(3600))),
+        case("500ms", Some(Duration::from_millis(500))),
+        case("10d", None),
+        case("m", None)
+    )]
+    fn test_parse_duration(input: &str, expected: Option<Duration>) {
+        assert_eq!(expected, parse_duration(input).ok());
+    }
+
+    #[rstest(
+        input,
+        expected,
+        case("USER_ID=BIG_BEN", Some(("USER_ID", Value::from("BIG_BEN")))),
+        case("COUNT=3", Some(("COUNT", Value::from(3)))),
+        case("QUERY=a=b", Some(("QUERY", Value::from("a=b")))),
+        case("EMPTY=", Some(("EMPTY", Value::from("")))),
+        case("USER_ID", None)
+    )]
+    fn test_cut_assignment(input: &str, expected: Option<(&str, Value)>) {
+        assert_eq!(
+            expected.map(|(name, value)| CutAssignment {
+                name: name.to_string(),
+                value
+            }),
+            input.parse::<CutAssignment>().ok()
+        );
+    }
+
+    #[rstest(
+        timeout,
+        remaining,
+        expected,
+        case(30, None, 30),
+        case(30, Some(Duration::from_secs(10)), 10),
+        case(30, Some(Duration::from_millis(200)), 1),
+        case(0, Some(Duration::from_secs(45)), 45),
+        case(5, Some(Duration::from_secs(45)), 5)
+    )]
+    fn test_budget_timeout(timeout: u64, remaining: Option<Duration>, expected: u64) {
+        assert_eq!(expected, budget_timeout(timeout, remaining));
+    }
+
+    #[test]
+    fn test_reel_targets() {
+        let reels = ["./test_data&post".to_string(), "../test_data".to_string()];
+        let targets: Vec<String> = reel_targets(&reels)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            vec![
+                "./test_data&post",
+                "../test_data&grpc",
+                "../test_data&post",
+                "../test_data&stripe_subscription",
+                "../test_data&stripe_token",
+            ],
+            targets
+        );
+        assert!(reel_targets(&["./missing_dir".to_string()]).is_err());
+    }
+
+    #[rstest(
+        dir,
+        expected,
+        case("./test_data", "out/test_data/post"),
+        case("/tmp/reels/test_data", "out/tmp/reels/test_data/post"),
+        case("test_data/nested", "out/test_data/nested/post")
+    )]
+    fn test_take_out_dir(dir: &str, expected: &str) {
+        let target = ReelTarget {
+            dir: PathBuf::from(dir),
+            name: "post".to_string(),
+        };
+        assert_eq!(
+            PathBuf::from(expected),
+            target.take_out_dir(Path::new("out"))
+        );
+    }
+
+    #[test]
+    fn test_record_all_exit_code() {
+        let dir = std::env::temp_dir().join(format!("dark_record_all_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("bad.cut.json"), "{}").unwrap();
+        fs::write(
+            dir.join("bad.01s.frame.fr.json"),
+            "{\"protocol\": \"HTTP\"}",
+        )
+        .unwrap();
+
+        let dir_str = dir.to_string_lossy();
+        let missing = format!("{dir_str}&missing");
+        let bad = format!("{dir_str}&bad");
+        let args = Command::from_args(&["dark"], &["record-all", &missing, &bad]).unwrap();
+        let base_params = BaseParams::from(&args);
+        let cmd = match args.nested {
+            SubCommand::RecordAll(cmd) => cmd,
+            _ => unreachable!(),
+        };
+        let err = cmd_record_all(cmd, base_params).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+        // the missing cut file of the first reel exits with 1, the unreadable frame with 18
+        assert_eq!(18, exit_code(&err));
+        assert!(err.to_string().contains("2 of 2 reels failed"));
+    }
+
+    #[rstest(
+        pattern,
+        text,
+        expected,
+        case("*", "usr.01s.login.fr.json", true),
+        case("*login*", "usr.01s.login.fr.json", true),
+        case("usr.0?s.*", "usr.01s.login.fr.json", true),
+        case("usr.0?s.*", "usr.10s.login.fr.json", false),
+        case("*.logout.*", "usr.01s.login.fr.json", false),
+        case("usr.01s.login.fr.json", "usr.01s.login.fr.json", true),
+        case("login", "usr.01s.login.fr.json", false)
+    )]
+    fn test_glob_match(pattern: &str, text: &str, expected: bool) {
+        assert_eq!(expected, glob_match(pattern, text));
+    }
+
+    #[test]
+    fn test_frame_filter() {
+        let frame = |tags: Value| -> Frame<'static> {
+            serde_json::from_value(serde_json::json!({
+                "protocol": "HTTP",
+                "request": {"uri": "GET /"},
+                "response": {"status": 200},
+                "tags": tags
+            }))
+            .unwrap()
+        };
+        let mut teardown = frame(serde_json::json!(["slow"]));
+        teardown.teardown = true;
+        let reel = Reel::from_frames(vec![
+            ("usr.01s.login.fr.json", frame(serde_json::json!(["smoke"]))),
+            (
+                "usr.02s.search.fr.json",
+                frame(serde_json::json!(["smoke", "slow"])),
+            ),
+            ("usr.03s.export.fr.json", frame(serde_json::json!(["slow"]))),
+            ("usr.04s.logout.fr.json", frame(serde_json::json!([]))),
+            ("usr.05s.cleanup.fr.json", teardown),
+        ])
+        .unwrap();
+        let frames: Vec<MetaFrame> = reel.into_iter().collect();
+        let args = Command::from_args(&["dark"], &["version"]).unwrap();
+        let base_params = BaseParams::from(&args);
+        let selected = |filter: FrameFilter| -> Vec<String> {
+            filter
+                .apply(frames.clone(), &base_params)
+                .unwrap()
+                .iter()
+                .map(|f| f.name.clone())
+                .collect()
+        };
+
+        assert_eq!(5, selected(FrameFilter::default()).len());
+        assert_eq!(
+            vec!["login", "search", "cleanup"],
+            selected(FrameFilter::new(None, vec!["smoke".into()], vec![]))
+        );
+        assert_eq!(
+            vec!["login", "logout", "cleanup"],
+            selected(FrameFilter::new(None, vec![], vec!["slow".into()]))
+        );
+        assert_eq!(
+            vec!["login", "search", "export", "cleanup"],
+            selected(FrameFilter::new(None, vec!["smoke, slow".into()], vec![]))
+        );
+        assert_eq!(
+            vec!["login", "logout", "cleanup"],
+            selected(FrameFilter::new(Some("*.log*".into()), vec![], vec![]))
+        );
+        assert_eq!(
+            vec!["login", "cleanup"],
+            selected(FrameFilter::new(
+                Some("*.log*".into()),
+                vec!["smoke".into()],
+                vec![]
+            ))
+        );
+    }
 }
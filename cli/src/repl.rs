@@ -0,0 +1,308 @@
+use crate::{
+    params::BaseParams,
+    record::write_cut,
+    storage::{is_cut_file, read_register},
+    take::{read_frame, run_take},
+    Repl, ToStringHidden,
+};
+use anyhow::{anyhow, Context, Error};
+use colored::*;
+use filmreel::{cut::Register, reel::*};
+use log::{error, warn};
+use serde_json::Value;
+use std::{
+    io::{self, prelude::*},
+    path::PathBuf,
+    str::FromStr,
+};
+
+const REPL_HELP: &str = "\
+commands:
+  ls                 list the frames of the reel
+  take <frame>       run a frame by path, filename, name, or sequence number such as 01s
+  cut                print the cut register
+  get <VAR>          print a single cut variable
+  set <VAR> <value>  write a cut variable, the value is parsed as JSON and falls back to a string
+  unset <VAR>        remove a cut variable
+  save [file]        write the cut register to the file given or the session cut file
+  help               print this message
+  exit               save the session cut file and exit
+  abort              exit without saving";
+
+/// ReplCmd is a single line of input to the `dark repl` prompt
+#[derive(Debug, PartialEq)]
+pub enum ReplCmd {
+    List,
+    Take(String),
+    Cut,
+    Get(String),
+    Set(String, Value),
+    Unset(String),
+    Save(Option<PathBuf>),
+    Help,
+    Exit,
+    Abort,
+}
+
+impl FromStr for ReplCmd {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let (cmd, args) = line.split_once(' ').unwrap_or((line, ""));
+        let args = args.trim();
+        let arg = |name: &str| -> Result<String, Error> {
+            match args {
+                "" => Err(anyhow!("{cmd}: missing <{name}>")),
+                a => Ok(a.to_string()),
+            }
+        };
+        match cmd {
+            "ls" => Ok(Self::List),
+            "take" | "t" => Ok(Self::Take(arg("frame")?)),
+            "cut" => Ok(Self::Cut),
+            "get" => Ok(Self::Get(arg("VAR")?)),
+            "set" => {
+                let (var, value) = args
+                    .split_once(' ')
+                    .ok_or_else(|| anyhow!("set: expected <VAR> <value>"))?;
+                let value = value.trim();
+                let value = serde_json::from_str(value)
+                    .unwrap_or_else(|_| Value::String(value.to_string()));
+                Ok(Self::Set(var.to_string(), value))
+            }
+            "unset" => Ok(Self::Unset(arg("VAR")?)),
+            "save" => Ok(Self::Save((!args.is_empty()).then(|| PathBuf::from(args)))),
+            "help" | "?" => Ok(Self::Help),
+            "exit" | "quit" => Ok(Self::Exit),
+            "abort" => Ok(Self::Abort),
+            _ => Err(anyhow!("unknown command \"{cmd}\", try: help")),
+        }
+    }
+}
+
+/// ReplSession holds the cut register that takes are run against for the lifetime of `dark repl`
+pub struct ReplSession {
+    reel_name: String,
+    register: Register,
+    frames: Vec<MetaFrame>,
+    save_path: PathBuf,
+    base_params: BaseParams,
+}
+
+impl ReplSession {
+    /// find_frame resolves a frame by its path, its filename, its command name, or its sequence
+    /// number
+    pub fn find_frame(&self, query: &str) -> Result<MetaFrame, Error> {
+        let path = PathBuf::from(query);
+        if path.is_file() {
+            return Ok(MetaFrame::try_from(&path)?);
+        }
+        let matches: Vec<&MetaFrame> = self
+            .frames
+            .iter()
+            .filter(|mf| {
+                let filename = mf.get_filename();
+                filename == query
+                    || mf.name == query
+                    || filename.starts_with(&format!("{}.{query}.", mf.reel_name))
+            })
+            .collect();
+        match matches.as_slice() {
+            [metaframe] => Ok((*metaframe).clone()),
+            [] => Err(anyhow!("no frame found for \"{query}\"")),
+            _ => Err(anyhow!(
+                "\"{query}\" matches multiple frames: {}",
+                matches
+                    .iter()
+                    .map(|mf| mf.get_filename())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )),
+        }
+    }
+
+    /// execute runs a single command, returning false once the session should end
+    pub fn execute(&mut self, cmd: ReplCmd) -> Result<bool, Error> {
+        match cmd {
+            ReplCmd::List => {
+                for metaframe in self.frames.iter() {
+                    println!("{}", metaframe.get_filename());
+                }
+            }
+            ReplCmd::Take(query) => {
+                let metaframe = self.find_frame(&query)?;
+                let mut frame = read_frame(&metaframe, &self.base_params)?;
+                self.register
+                    .write_builtin("FRAME_NAME", Value::String(metaframe.get_filename()));
+                run_take(&mut frame, &mut self.register, &self.base_params, None)?;
+                warn!("{} {}", "Success 🎉".yellow(), metaframe.get_filename());
+            }
+            ReplCmd::Cut => println!("{}", self.register.to_string_hidden()?),
+            ReplCmd::Get(var) => match self.register.get(&var) {
+                Some(_) if self.register.is_hidden(&var) => println!("\"${{_HIDDEN}}\""),
+                Some(value) => println!("{}", serde_json::to_string_pretty(value)?),
+                None => return Err(anyhow!("{var} is not present in the cut register")),
+            },
+            ReplCmd::Set(var, value) => {
+                self.register.write_operation(&var, value)?;
+            }
+            ReplCmd::Unset(var) => {
+                self.register
+                    .remove(&var)
+                    .ok_or_else(|| anyhow!("{var} is not present in the cut register"))?;
+            }
+            ReplCmd::Save(path) => {
+                let path = path.unwrap_or_else(|| self.save_path.clone());
+                write_cut(
+                    &Some(path.clone()),
+                    self.base_params.cut_out_opts,
+                    &self.register,
+                    &self.reel_name,
+                    false,
+                )?;
+                warn!("{} {}", "Saved:".green(), path.to_string_lossy());
+            }
+            ReplCmd::Help => println!("{REPL_HELP}"),
+            ReplCmd::Exit => {
+                self.execute(ReplCmd::Save(None))?;
+                return Ok(false);
+            }
+            ReplCmd::Abort => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// cmd_repl loads a cut register and runs frames against it one prompt line at a time, the
+/// register is saved to `--cut-out` or the period prefixed copy of the cut file on exit
+pub fn cmd_repl(cmd: Repl, mut base_params: BaseParams) -> Result<(), Error> {
+    base_params.timeout = cmd.timeout;
+
+    let cut_file = cmd.get_cut_file();
+    let register = match is_cut_file(&cut_file) {
+        true => read_register(cut_file)?,
+        false => Register::new(),
+    };
+    let reel = Reel::new(&cmd.reel_path, &cmd.reel_name, None)?;
+    let mut session = ReplSession {
+        reel_name: cmd.reel_name.clone(),
+        register,
+        frames: reel.into_iter().collect(),
+        save_path: base_params
+            .cut_out
+            .clone()
+            .unwrap_or_else(|| cmd.get_cut_copy()),
+        base_params,
+    };
+
+    warn!(
+        "{} {} frames loaded, type {} for a list of commands",
+        "dark repl:".yellow(),
+        session.frames.len(),
+        "help".yellow()
+    );
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut line = String::new();
+    loop {
+        write!(stdout, "{} ", "dark>".green()).context("write to stdout failure")?;
+        stdout.flush().context("stdout flush failure")?;
+        line.clear();
+        // end of input is treated as exit
+        if stdin.read_line(&mut line)? == 0 {
+            println!();
+            session.execute(ReplCmd::Exit)?;
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        match line.parse::<ReplCmd>().and_then(|cmd| session.execute(cmd)) {
+            Ok(true) => (),
+            Ok(false) => return Ok(()),
+            Err(e) => error!("{} {:#}", "Error:".red(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Command;
+    use argh::FromArgs;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest(
+        line,
+        expected,
+        case("ls", Some(ReplCmd::List)),
+        case("take 01s", Some(ReplCmd::Take("01s".to_string()))),
+        case("  t createuser ", Some(ReplCmd::Take("createuser".to_string()))),
+        case("take", None),
+        case("set USER_ID 7", Some(ReplCmd::Set("USER_ID".to_string(), json!(7)))),
+        case(
+            "set NAME BIG BEN",
+            Some(ReplCmd::Set("NAME".to_string(), json!("BIG BEN")))
+        ),
+        case("set NAME", None),
+        case("save", Some(ReplCmd::Save(None))),
+        case("save ./out.cut.json", Some(ReplCmd::Save(Some(PathBuf::from("./out.cut.json"))))),
+        case("quit", Some(ReplCmd::Exit)),
+        case("rm", None)
+    )]
+    fn test_repl_cmd(line: &str, expected: Option<ReplCmd>) {
+        assert_eq!(expected, line.parse::<ReplCmd>().ok());
+    }
+
+    fn session() -> ReplSession {
+        let frames = [
+            "usr.01s.createuser.fr.json",
+            "usr.02s.getuser.fr.json",
+            "usr.03e.getuser.fr.json",
+        ]
+        .iter()
+        .map(|f| MetaFrame::try_from(&PathBuf::from(f)).unwrap())
+        .collect();
+        let args = Command::from_args(&["dark"], &["version"]).unwrap();
+        ReplSession {
+            reel_name: "usr".to_string(),
+            register: Register::new(),
+            frames,
+            save_path: PathBuf::from(".usr.cut.json"),
+            base_params: args.base_params(),
+        }
+    }
+
+    #[rstest(
+        query,
+        expected,
+        case("createuser", Some("usr.01s.createuser.fr.json")),
+        case("02s", Some("usr.02s.getuser.fr.json")),
+        case("usr.03e.getuser.fr.json", Some("usr.03e.getuser.fr.json")),
+        case("getuser", None),
+        case("04s", None)
+    )]
+    fn test_find_frame(query: &str, expected: Option<&str>) {
+        assert_eq!(
+            expected.map(String::from),
+            session().find_frame(query).ok().map(|mf| mf.get_filename())
+        );
+    }
+
+    #[test]
+    fn test_set_unset() {
+        let mut session = session();
+        session
+            .execute(ReplCmd::Set("USER_ID".to_string(), json!("BIG_BEN")))
+            .unwrap();
+        assert_eq!(Some(&json!("BIG_BEN")), session.register.get("USER_ID"));
+        session
+            .execute(ReplCmd::Unset("USER_ID".to_string()))
+            .unwrap();
+        assert!(session
+            .execute(ReplCmd::Unset("USER_ID".to_string()))
+            .is_err());
+    }
+}
@@ -0,0 +1,563 @@
+use crate::{Lint, Schema};
+use anyhow::{anyhow, Context, Error};
+use colored::*;
+use filmreel::{
+    cut::Register,
+    frame::Frame,
+    reel::{MetaFrame, Reel},
+    vreel::VirtualReel,
+};
+use jsonschema::JSONSchema;
+use log::{error, warn};
+use schemars::{schema::RootSchema, schema_for};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// The filmReel file formats that a JSON Schema can be generated for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaKind {
+    Frame,
+    Cut,
+    VirtualReel,
+}
+
+impl FromStr for SchemaKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "frame" => Ok(Self::Frame),
+            "cut" => Ok(Self::Cut),
+            "vreel" => Ok(Self::VirtualReel),
+            _ => Err(format!(
+                "unknown schema \"{s}\", expected one of: frame, cut, vreel"
+            )),
+        }
+    }
+}
+
+impl SchemaKind {
+    /// Infers the file format from the filename suffix
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let filename = path.as_ref().file_name()?.to_str()?;
+        if filename.ends_with(".fr.json") {
+            return Some(Self::Frame);
+        }
+        if filename.ends_with(".cut.json") {
+            return Some(Self::Cut);
+        }
+        if filename.ends_with(".vr.json") {
+            return Some(Self::VirtualReel);
+        }
+        None
+    }
+
+    /// Generates the JSON Schema from the serde types of the file format
+    pub fn root_schema(&self) -> RootSchema {
+        match self {
+            Self::Frame => schema_for!(Frame),
+            Self::Cut => schema_for!(Register),
+            Self::VirtualReel => schema_for!(VirtualReel),
+        }
+    }
+}
+
+/// cmd_schema prints the JSON Schema of the requested file format
+pub fn cmd_schema(cmd: Schema) -> Result<(), Error> {
+    println!("{}", serde_json::to_string_pretty(&cmd.kind.root_schema())?);
+    Ok(())
+}
+
+/// How long `dark lint --watch` waits between checks for changed files
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Severity distinguishes the diagnostics that fail a lint from those that are only reported
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Diagnostic is a single finding of a linted file, printed by `dark lint --json` as part of a
+/// line per file that editors can consume:
+///
+/// ```json
+/// {
+///   "file": "./test_data/post.01s.body.fr.json",
+///   "diagnostics": [
+///     {"severity": "error", "pointer": "/response/status", "line": 12, "column": 15, "message": "\"200\" is not of type \"integer\""}
+///   ]
+/// }
+/// ```
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// the JSON pointer of the offending value, absent if the finding concerns the whole file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+    /// the 1-based line of the offending value
+    pub line: usize,
+    /// the 1-based column of the offending value
+    pub column: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new<T: Into<String>>(severity: Severity, pointer: Option<String>, message: T) -> Self {
+        Self {
+            severity,
+            pointer,
+            line: 1,
+            column: 1,
+            message: message.into(),
+        }
+    }
+
+    fn error<T: Into<String>>(pointer: Option<String>, message: T) -> Self {
+        Self::new(Severity::Error, pointer, message)
+    }
+
+    /// locate sets the position of the diagnostic to the value found at its pointer
+    fn locate(mut self, json_string: &str) -> Self {
+        if let Some((line, column)) = self.pointer.as_deref().and_then(|p| locate(json_string, p)) {
+            self.line = line;
+            self.column = column;
+        }
+        self
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.pointer {
+            Some(p) if p.is_empty() => write!(f, "/ {}", self.message),
+            Some(p) => write!(f, "{p} {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// cmd_lint validates every file provided against the JSON Schema of its file format
+pub fn cmd_lint(cmd: Lint) -> Result<(), Error> {
+    if cmd.watch {
+        return watch_lint(&cmd);
+    }
+    let files = lint_targets(&cmd.files);
+    let mut failed = 0;
+    for path in files.iter() {
+        let diagnostics = lint_file(path)?;
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            failed += 1;
+        }
+        report(path, &diagnostics, cmd.json)?;
+    }
+    if failed > 0 {
+        return Err(anyhow!(
+            "{} of {} files failed linting",
+            failed,
+            files.len()
+        ));
+    }
+    Ok(())
+}
+
+/// watch_lint lints every file whenever any of them changes, reporting only the files whose
+/// diagnostics differ from the last report. A removed file is reported without diagnostics.
+fn watch_lint(cmd: &Lint) -> Result<(), Error> {
+    let mut modified: BTreeMap<PathBuf, Option<SystemTime>> = BTreeMap::new();
+    let mut reported: BTreeMap<PathBuf, Vec<Diagnostic>> = BTreeMap::new();
+    loop {
+        let stamps: BTreeMap<PathBuf, Option<SystemTime>> = lint_targets(&cmd.files)
+            .into_iter()
+            .map(|path| {
+                let stamp = fs::metadata(&path).and_then(|m| m.modified()).ok();
+                (path, stamp)
+            })
+            .collect();
+        if stamps != modified {
+            let mut linted = BTreeMap::new();
+            for path in stamps.keys() {
+                let diagnostics = lint_file(path)
+                    .unwrap_or_else(|e| vec![Diagnostic::error(None, format!("{e:#}"))]);
+                if reported.get(path) != Some(&diagnostics) {
+                    report(path, &diagnostics, cmd.json)?;
+                }
+                linted.insert(path.clone(), diagnostics);
+            }
+            for path in reported.keys().filter(|p| !linted.contains_key(*p)) {
+                report(path, &[], cmd.json)?;
+            }
+            reported = linted;
+            modified = stamps;
+        }
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+/// lint_targets returns the files provided with every directory replaced by the filmReel files it
+/// holds
+pub(crate) fn lint_targets(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let mut entries: Vec<PathBuf> = fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && SchemaKind::from_path(p).is_some())
+            .collect();
+        entries.sort();
+        files.extend(entries);
+    }
+    files
+}
+
+fn report(path: &Path, diagnostics: &[Diagnostic], json: bool) -> Result<(), Error> {
+    if json {
+        #[derive(Serialize)]
+        struct FileDiagnostics<'a> {
+            file: &'a Path,
+            diagnostics: &'a [Diagnostic],
+        }
+        println!(
+            "{}",
+            serde_json::to_string(&FileDiagnostics {
+                file: path,
+                diagnostics
+            })?
+        );
+        return Ok(());
+    }
+    if diagnostics.is_empty() {
+        warn!("{} {}", "OK:".green(), path.to_string_lossy());
+    }
+    for d in diagnostics {
+        match d.severity {
+            Severity::Error => error!("{} {}: {}", "Lint:".red(), path.to_string_lossy(), d),
+            Severity::Warning => warn!("{} {}: {}", "Lint:".yellow(), path.to_string_lossy(), d),
+        }
+    }
+    Ok(())
+}
+
+/// lint_file returns the schema violations of a single file, each positioned at the offending
+/// value. The Cut Variables read by a frame are also checked against the cut file of its reel.
+pub fn lint_file<P: AsRef<Path>>(path: P) -> Result<Vec<Diagnostic>, Error> {
+    let path = path.as_ref();
+    let kind = SchemaKind::from_path(path).ok_or_else(|| {
+        anyhow!("unable to infer the file format, expected a .fr.json, .cut.json, or .vr.json file => {path:?}")
+    })?;
+    let json_string =
+        filmreel::read_json_file(path).context(format!("lint read failure => {path:?}"))?;
+    let instance: Value = match serde_json::from_str(&json_string) {
+        Ok(v) => v,
+        Err(e) => {
+            let mut diagnostic = Diagnostic::error(None, e.to_string());
+            diagnostic.line = e.line();
+            diagnostic.column = e.column().max(1);
+            return Ok(vec![diagnostic]);
+        }
+    };
+
+    let mut diagnostics = lint_value(kind, &instance)?;
+    if kind == SchemaKind::Frame {
+        if let Some(known) = reel_variables(path) {
+            diagnostics.extend(unknown_variables(&instance, &known));
+        }
+    }
+    let mut diagnostics: Vec<Diagnostic> = diagnostics
+        .into_iter()
+        .map(|d| d.locate(&json_string))
+        .collect();
+    // serde_json keeps the last value of a duplicate key, leaving the first value silently unused
+    if let Some((pointer, line, column)) = filmreel::utils::find_duplicate_key(&json_string) {
+        let mut diagnostic = Diagnostic::error(
+            Some(pointer),
+            "duplicate object key, only the last value is kept",
+        );
+        diagnostic.line = line;
+        diagnostic.column = column.max(1);
+        diagnostics.push(diagnostic);
+    }
+    Ok(diagnostics)
+}
+
+fn lint_value(kind: SchemaKind, instance: &Value) -> Result<Vec<Diagnostic>, Error> {
+    let schema = serde_json::to_value(kind.root_schema())?;
+    let compiled = JSONSchema::compile(&schema).map_err(|e| anyhow!("invalid schema: {e}"))?;
+    let mut errors = match compiled.validate(instance) {
+        Ok(()) => vec![],
+        Err(errs) => errs
+            .map(|e| Diagnostic::error(Some(e.instance_path.to_string()), e.to_string()))
+            .collect(),
+    };
+    // frames are additionally checked for cut instruction conflicts that a schema cannot express
+    if errors.is_empty() && kind == SchemaKind::Frame {
+        if let Err(e) = Frame::new(&instance.to_string()) {
+            errors.push(Diagnostic::error(None, e.to_string()));
+        }
+    }
+    Ok(errors)
+}
+
+/// reel_variables returns the Cut Variables known to the reel of a frame: the variables of the reel
+/// cut file and the variables written or generated by any frame of the reel. None is returned if
+/// the reel has no cut file since its variables are then provided when the reel is run.
+fn reel_variables(path: &Path) -> Option<BTreeSet<String>> {
+    let meta_frame = MetaFrame::try_from(&path.to_path_buf()).ok()?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let register = Register::try_from(meta_frame.get_cut_file(dir)).ok()?;
+    let mut known: BTreeSet<String> = register.iter().map(|(k, _)| k.clone()).collect();
+    let reel = Reel::new(dir, &meta_frame.reel_name, None).ok()?;
+    for frame in reel.into_iter().filter_map(|m| m.to_frame().ok()) {
+        let sets = std::iter::once(&frame.cut).chain(frame.before.as_ref().map(|b| &b.cut));
+        for set in sets {
+            known.extend(set.written().map(String::from));
+            known.extend(set.generated().map(|(var, _)| var.to_string()));
+        }
+    }
+    Some(known)
+}
+
+/// unknown_variables warns of the Cut Variables read by a frame that are not known to its reel
+fn unknown_variables(instance: &Value, known: &BTreeSet<String>) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    for reads in ["/cut/from", "/before/cut/from"] {
+        let Some(Value::Array(vars)) = instance.pointer(reads) else {
+            continue;
+        };
+        for (i, var) in vars.iter().enumerate() {
+            match var.as_str() {
+                Some(var) if !known.contains(var) => diagnostics.push(Diagnostic::new(
+                    Severity::Warning,
+                    Some(format!("{reads}/{i}")),
+                    format!("unknown Cut Variable \"{var}\": it is neither in the reel cut file nor written by a frame of the reel"),
+                )),
+                _ => (),
+            }
+        }
+    }
+    diagnostics
+}
+
+/// locate returns the 1-based line and column of the value found at a JSON pointer of the JSON
+/// string provided
+fn locate(json_string: &str, pointer: &str) -> Option<(usize, usize)> {
+    let bytes = json_string.as_bytes();
+    let mut i = skip_ws(bytes, 0);
+    for token in pointer.split('/').skip(1) {
+        let token = token.replace("~1", "/").replace("~0", "~");
+        i = match bytes.get(i)? {
+            b'{' => {
+                let mut j = skip_ws(bytes, i + 1);
+                loop {
+                    let end = skip_string(bytes, j)?;
+                    let key: String = serde_json::from_str(&json_string[j..end]).ok()?;
+                    j = skip_ws(bytes, end);
+                    j = skip_ws(bytes, j + 1); // ':'
+                    if key == token {
+                        break j;
+                    }
+                    j = skip_ws(bytes, skip_value(bytes, j)?);
+                    j = skip_ws(bytes, j + 1); // ','
+                }
+            }
+            b'[' => {
+                let mut j = skip_ws(bytes, i + 1);
+                for _ in 0..token.parse::<usize>().ok()? {
+                    j = skip_ws(bytes, skip_value(bytes, j)?);
+                    if bytes.get(j) != Some(&b',') {
+                        return None;
+                    }
+                    j = skip_ws(bytes, j + 1);
+                }
+                if bytes.get(j) == Some(&b']') {
+                    return None;
+                }
+                j
+            }
+            _ => return None,
+        };
+    }
+    let before = &json_string[..i.min(json_string.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    Some((line, column))
+}
+
+fn skip_ws(bytes: &[u8], mut i: usize) -> usize {
+    while bytes.get(i).is_some_and(|b| b.is_ascii_whitespace()) {
+        i += 1;
+    }
+    i
+}
+
+/// skip_string returns the index following the string starting at i
+fn skip_string(bytes: &[u8], i: usize) -> Option<usize> {
+    if bytes.get(i) != Some(&b'"') {
+        return None;
+    }
+    let mut j = i + 1;
+    loop {
+        match bytes.get(j)? {
+            b'\\' => j += 2,
+            b'"' => return Some(j + 1),
+            _ => j += 1,
+        }
+    }
+}
+
+/// skip_value returns the index following the JSON value starting at i
+fn skip_value(bytes: &[u8], i: usize) -> Option<usize> {
+    match bytes.get(i)? {
+        b'"' => skip_string(bytes, i),
+        b'{' | b'[' => {
+            let mut depth = 0;
+            let mut j = i;
+            loop {
+                match bytes.get(j)? {
+                    b'"' => {
+                        j = skip_string(bytes, j)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(j + 1);
+                        }
+                    }
+                    _ => (),
+                }
+                j += 1;
+            }
+        }
+        _ => {
+            let mut j = i;
+            while bytes
+                .get(j)
+                .is_some_and(|b| !b",}]".contains(b) && !b.is_ascii_whitespace())
+            {
+                j += 1;
+            }
+            Some(j)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest(
+        kind,
+        instance,
+        expected,
+        case(
+            SchemaKind::Frame,
+            json!({"protocol": "HTTP", "request": {"uri": "GET /"}, "response": {"status": 200}}),
+            vec![]
+        ),
+        case(
+            SchemaKind::Frame,
+            json!({"protocol": "HTTP", "request": {"uri": "GET /"}, "response": {"status": "200"}}),
+            vec!["/response/status"]
+        ),
+        case(
+            SchemaKind::Frame,
+            json!({"protocol": "HTTP", "cut": {"form": []}, "request": {"uri": "GET /"}, "response": {"status": 200}}),
+            vec!["/cut"]
+        ),
+        case(SchemaKind::Cut, json!({"USER_ID": "BIG_BEN"}), vec![]),
+        case(
+            SchemaKind::VirtualReel,
+            json!({"name": "usr", "frames": ["usr.01s.fr.json"]}),
+            vec!["/"]
+        )
+    )]
+    fn test_lint_value(kind: SchemaKind, instance: Value, expected: Vec<&str>) {
+        let pointers: Vec<String> = lint_value(kind, &instance)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.to_string().split(' ').next().unwrap().to_string())
+            .collect();
+        assert_eq!(expected, pointers);
+    }
+
+    const FRAME: &str = r#"{
+  "protocol": "HTTP",
+  "cut": {"from": ["USER_ID", "a/b"]},
+  "request": {"uri": "GET /users/${USER_ID}", "body": {"tags": ["a", {"x": "}"}]}},
+  "response": {"status": 200}
+}"#;
+
+    #[rstest(
+        pointer,
+        expected,
+        case("", Some((1, 1))),
+        case("/protocol", Some((2, 15))),
+        case("/cut/from/1", Some((3, 31))),
+        case("/request/body/tags/1/x", Some((4, 76))),
+        case("/response", Some((5, 15))),
+        case("/response/body", None),
+        case("/cut/from/2", None)
+    )]
+    fn test_locate(pointer: &str, expected: Option<(usize, usize)>) {
+        assert_eq!(expected, locate(FRAME, pointer));
+    }
+
+    #[test]
+    fn test_unknown_variables() {
+        let instance = json!({
+            "cut": {"from": ["USER_ID", "ORDER_ID"]},
+            "before": {"cut": {"from": ["TOKEN"]}}
+        });
+        let known: BTreeSet<String> = ["USER_ID".to_string()].into();
+        let pointers: Vec<Option<String>> = unknown_variables(&instance, &known)
+            .into_iter()
+            .map(|d| d.pointer)
+            .collect();
+        assert_eq!(
+            vec![
+                Some("/cut/from/1".to_string()),
+                Some("/before/cut/from/0".to_string())
+            ],
+            pointers
+        );
+    }
+
+    #[rstest(
+        path,
+        expected,
+        case("usr.01s.createuser.fr.json", Some(SchemaKind::Frame)),
+        case("./usr.cut.json", Some(SchemaKind::Cut)),
+        case("post.vr.json", Some(SchemaKind::VirtualReel)),
+        case("post.json", None)
+    )]
+    fn test_from_path(path: &str, expected: Option<SchemaKind>) {
+        assert_eq!(expected, SchemaKind::from_path(path));
+    }
+}
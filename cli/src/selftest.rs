@@ -0,0 +1,220 @@
+use crate::{exit_code, params::BaseParams, record::*, take::run_take, Selftest};
+use anyhow::{anyhow, Context, Error};
+use colored::*;
+use filmreel::{cut::Register, frame::Frame, reel::Reel};
+use http::StatusCode;
+use log::{error, warn};
+use serde_json::{json, Map, Value};
+use std::{
+    io::{self, prelude::*, BufReader},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+/// The reel run against the self-test server, covering header, body, generate, and cut variable
+/// handling
+const SELFTEST_REEL: [(&str, &str); 3] = [
+    (
+        "selftest.01s.get.fr.json",
+        r#"{
+  "protocol": "HTTP",
+  "request": {"uri": "GET /get", "header": {"X-Selftest": "darkroom"}},
+  "response": {
+    "body": {"method": "GET", "path": "/get", "headers": {"x-selftest": "darkroom"}},
+    "validation": {"'response'.'body'.'headers'": {"partial": true}},
+    "status": 200
+  }
+}"#,
+    ),
+    (
+        "selftest.02s.post.fr.json",
+        r#"{
+  "protocol": "HTTP",
+  "cut": {"generate": {"NAME": "uuid"}, "to": {"ECHO": "'response'.'body'.'json'.'name'"}},
+  "request": {"uri": "POST /post", "body": {"name": "${NAME}"}},
+  "response": {"body": {"json": {"name": "${ECHO}"}}, "status": 200}
+}"#,
+    ),
+    (
+        "selftest.03s.status.fr.json",
+        r#"{
+  "protocol": "HTTP",
+  "cut": {"from": ["ECHO"]},
+  "request": {"uri": "GET /status/201?name=${ECHO}"},
+  "response": {"status": 201}
+}"#,
+    ),
+];
+
+/// A frame that never matches the response of the self-test server
+const MISMATCH_FRAME: &str = r#"{
+  "protocol": "HTTP",
+  "request": {"uri": "GET /status/500"},
+  "response": {"status": 200}
+}"#;
+
+/// Check is a single self-test check run against the self-test server
+type Check = fn(&BaseParams) -> Result<(), Error>;
+
+/// cmd_selftest runs the bundled reel against an embedded local server, then checks that a
+/// mismatch exits with the expected code, that a TLS client can be built, and that grpcurl can be
+/// found
+pub fn cmd_selftest(_: Selftest, mut base_params: BaseParams) -> Result<(), Error> {
+    let address = serve()?;
+    warn!("{} {}", "Self-test server:".yellow(), address);
+    base_params.address = Some(address);
+
+    let checks: [(&str, Check); 3] = [
+        ("record", record_check),
+        ("mismatch exit code", mismatch_check),
+        ("TLS client", tls_check),
+    ];
+    let mut failed = 0;
+    for (name, check) in checks {
+        match check(&base_params) {
+            Ok(()) => warn!("{} {}", "OK:".green(), name),
+            Err(e) => {
+                error!("{} {}: {:#}", "Failed:".red(), name, e);
+                failed += 1;
+            }
+        }
+    }
+    #[cfg(feature = "grpc")]
+    match crate::grpc::validate_grpcurl() {
+        Ok(()) => warn!("{} grpcurl", "OK:".green()),
+        Err(e) => warn!("{} {}, gRPC frames cannot be taken", "Warning:".yellow(), e),
+    }
+
+    if failed > 0 {
+        return Err(anyhow!("{failed} self-test checks failed"));
+    }
+    Ok(())
+}
+
+fn record_check(base_params: &BaseParams) -> Result<(), Error> {
+    let frames = SELFTEST_REEL
+        .iter()
+        .map(|(name, frame)| Ok((name, Frame::new(frame)?)))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let reel = Reel::from_frames(frames)?;
+    run_record(
+        RecordRunner::new("selftest", Register::new(), reel.into_iter().collect()),
+        base_params.clone(),
+    )
+}
+
+fn mismatch_check(base_params: &BaseParams) -> Result<(), Error> {
+    let mut frame = Frame::new(MISMATCH_FRAME)?;
+    // the diff of the expected mismatch is not printed
+    let level = log::max_level();
+    log::set_max_level(log::LevelFilter::Off);
+    let result = run_take(&mut frame, &mut Register::new(), base_params, None);
+    log::set_max_level(level);
+    match result {
+        Ok(()) => Err(anyhow!("the mismatched frame passed")),
+        Err(e) if exit_code(&e) == 2 => Ok(()),
+        Err(e) => {
+            let code = exit_code(&e);
+            Err(e.context(format!("expected exit code 2, found {code}")))
+        }
+    }
+}
+
+fn tls_check(_: &BaseParams) -> Result<(), Error> {
+    reqwest::Client::builder()
+        .https_only(true)
+        .build()
+        .context("unable to initialize the TLS backend")?;
+    Ok(())
+}
+
+/// serve starts the self-test server on a random loopback port, returning its address. The
+/// server runs until the process exits.
+pub fn serve() -> Result<String, Error> {
+    let listener =
+        TcpListener::bind("127.0.0.1:0").context("unable to bind the self-test server")?;
+    let address = format!("http://{}", listener.local_addr()?);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            thread::spawn(move || handle(stream));
+        }
+    });
+    Ok(address)
+}
+
+fn handle(mut stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+    );
+
+    let mut headers = Map::new();
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let Some((name, value)) = line.trim_end().split_once(':') else {
+            break;
+        };
+        let name = name.trim().to_ascii_lowercase();
+        if name == "content-length" {
+            content_length = value.trim().parse().unwrap_or_default();
+        }
+        headers.insert(name, Value::String(value.trim().to_string()));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let (status, body) = route(method, target, headers, &body);
+    let reason = StatusCode::from_u16(status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or_default();
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// route returns the status and body of the self-test endpoints:
+///
+/// - `GET /get`: the method, path, and lowercase headers of the request
+/// - `POST /post`: the JSON body of the request as `"json"`
+/// - `/status/<code>`: an empty response with the status code given
+fn route(method: &str, target: &str, headers: Map<String, Value>, body: &[u8]) -> (u16, String) {
+    let path = target.split('?').next().unwrap_or_default();
+    match (method, path) {
+        ("GET", "/get") => (
+            200,
+            json!({"method": method, "path": path, "headers": headers}).to_string(),
+        ),
+        ("POST", "/post") => {
+            let body: Value = serde_json::from_slice(body).unwrap_or_default();
+            (200, json!({"json": body}).to_string())
+        }
+        (_, path) => match path.strip_prefix("/status/").map(str::parse) {
+            Some(Ok(status)) => (status, String::new()),
+            _ => (404, json!({"error": "not found"}).to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Command;
+    use argh::FromArgs;
+
+    #[test]
+    fn test_selftest() {
+        let args = Command::from_args(&["dark"], &["selftest"]).unwrap();
+        cmd_selftest(Selftest {}, BaseParams::from(&args)).unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{dump::RequestDump, params::Params};
+use anyhow::{anyhow, Context, Error};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use filmreel::{frame::Request, response::Response};
+use serde_json::{json, Map, Value};
+use sqlx::{
+    any::{AnyArguments, AnyRow},
+    query::Query,
+    Any, AnyConnection, Column, Connection, Row,
+};
+use std::time::Duration;
+
+/// query_params returns the bind parameters of the query, provided as an array `"body"`. A single
+/// non-array body is bound as the only parameter.
+fn query_params(body: Option<Value>) -> Vec<Value> {
+    match body {
+        None => vec![],
+        Some(Value::Array(params)) => params,
+        Some(param) => vec![param],
+    }
+}
+
+fn bind<'q>(
+    query: Query<'q, Any, AnyArguments<'q>>,
+    param: Value,
+) -> Result<Query<'q, Any, AnyArguments<'q>>, Error> {
+    Ok(match param {
+        Value::Null => query.bind(None::<String>),
+        Value::Bool(b) => query.bind(b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => query.bind(i),
+            None => query.bind(n.as_f64().ok_or_else(|| anyhow!("{n} is out of range"))?),
+        },
+        Value::String(s) => query.bind(s),
+        // objects and arrays are bound as their JSON text
+        param => query.bind(param.to_string()),
+    })
+}
+
+/// row_value converts a row into an object keyed by column name, blobs are base64 encoded
+fn row_value(row: &AnyRow) -> Result<Value, Error> {
+    let mut object = Map::new();
+    for (i, column) in row.columns().iter().enumerate() {
+        let value = if let Ok(v) = row.try_get::<Option<i64>, _>(i) {
+            json!(v)
+        } else if let Ok(v) = row.try_get::<Option<f64>, _>(i) {
+            json!(v)
+        } else if let Ok(v) = row.try_get::<Option<bool>, _>(i) {
+            json!(v)
+        } else if let Ok(v) = row.try_get::<Option<String>, _>(i) {
+            json!(v)
+        } else {
+            let blob = row
+                .try_get::<Option<Vec<u8>>, _>(i)
+                .context(format!("unsupported type of column \"{}\"", column.name()))?;
+            json!(blob.map(|b| STANDARD.encode(b)))
+        };
+        object.insert(column.name().to_string(), value);
+    }
+    Ok(Value::Object(object))
+}
+
+/// without_password removes the password from the userinfo of a database url, the client of a
+/// dumped request then reads it from its environment, such as `PGPASSWORD`, or prompts for it
+fn without_password(url: &str) -> String {
+    let (scheme, rest) = match url.split_once("://") {
+        Some(parts) => parts,
+        None => return url.to_string(),
+    };
+    let (authority, path) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+    match authority.rsplit_once('@') {
+        Some((userinfo, host)) => {
+            let user = userinfo.split_once(':').map_or(userinfo, |(user, _)| user);
+            format!("{scheme}://{user}@{host}{path}")
+        }
+        None => url.to_string(),
+    }
+}
+
+/// dump_request describes the query of a Frame Request, replayed with the client of the database
+/// url scheme, the password of the database url is left out
+pub fn dump_request(prm: &Params, req: Request) -> Result<RequestDump, Error> {
+    let query = req.get_uri();
+    let url = without_password(&prm.address);
+    let command = match url.split_once("://").map(|(scheme, _)| scheme) {
+        Some("postgres" | "postgresql") => vec!["psql".to_string(), url, "-c".to_string(), query],
+        Some("mysql" | "mariadb") => vec![
+            "mysqlsh".to_string(),
+            "--sql".to_string(),
+            url,
+            "-e".to_string(),
+            query,
+        ],
+        Some("sqlite") => vec![
+            "sqlite3".to_string(),
+            url.trim_start_matches("sqlite://").to_string(),
+            query,
+        ],
+        _ => return Err(anyhow!("unsupported database url => {url}")),
+    };
+    Ok(RequestDump {
+        request: json!({
+            "protocol": "SQL",
+            "address": without_password(&prm.address),
+            "uri": req.get_uri(),
+            "body": req.to_val_payload()?,
+        }),
+        command,
+    })
+}
+
+/// request runs the query held by the uri of a Frame Request against the database url of the
+/// request entrypoint, with the values of an array `"body"` bound as its parameters. The response
+/// body is the array of rows returned, each row an object keyed by column name, with a status of
+/// 0.
+///
+/// Queries are sent through the sqlx `Any` driver: parameters are written with the placeholders
+/// of the database (`$1` for PostgreSQL, `?` for MySQL and SQLite) and columns of types other than
+/// integers, floats, booleans, text, and blobs must be cast, such as `SELECT id::text`.
+pub async fn request<'a>(prm: Params<'_>, req: Request) -> Result<Response<'a>, Error> {
+    let query = req.get_uri();
+    let params = query_params(req.to_val_payload()?);
+
+    sqlx::any::install_default_drivers();
+    let connect = AnyConnection::connect(&prm.address);
+    let mut conn = match prm.timeout {
+        0 => connect.await,
+        timeout => tokio::time::timeout(Duration::from_secs(timeout), connect)
+            .await
+            .map_err(|_| anyhow!("database connection timed out after {timeout}s"))?,
+    }
+    .context("unable to connect to the database")?;
+
+    let mut sql_query = sqlx::query(&query);
+    for param in params {
+        sql_query = bind(sql_query, param)?;
+    }
+    let rows = sql_query
+        .fetch_all(&mut conn)
+        .await
+        .context(format!("query failure => {query}"))?;
+    conn.close().await?;
+
+    Ok(Response {
+        body: Some(Value::Array(
+            rows.iter().map(row_value).collect::<Result<_, _>>()?,
+        )),
+        status: 0,
+        content_type: None,
+        header: None,
+        etc: Some(json!({})),
+        validation: None,
+        ttfb: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest(
+        url,
+        expected,
+        case("postgres://app:s3cr3t@db:5432/users", "postgres://app@db:5432/users"),
+        case("mysql://app:p@ss@db/users?ssl=true", "mysql://app@db/users?ssl=true"),
+        case("postgres://app@db/users", "postgres://app@db/users"),
+        case("sqlite:///tmp/test.db", "sqlite:///tmp/test.db")
+    )]
+    fn test_without_password(url: &str, expected: &str) {
+        assert_eq!(expected, without_password(url));
+    }
+
+    #[test]
+    fn test_request() {
+        let dir = std::env::temp_dir().join(format!("darkroom_sql_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let prm = Params {
+            address: format!("sqlite://{}?mode=rwc", dir.join("test.db").display()),
+            timeout: 5,
+            ..Default::default()
+        };
+        let run = |frame_request: Value| {
+            let req: Request = serde_json::from_value(frame_request).unwrap();
+            crate::transport::runtime()
+                .block_on(request(prm.clone(), req))
+                .unwrap()
+                .body
+                .unwrap()
+        };
+
+        run(json!({
+            "uri": "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, score REAL, avatar BLOB)"
+        }));
+        assert_eq!(
+            json!([{"id": 1}]),
+            run(json!({
+                "uri": "INSERT INTO users (name, score, avatar) VALUES (?, ?, ?) RETURNING id",
+                "body": ["BIG BEN", 9.5, null]
+            }))
+        );
+        assert_eq!(
+            json!([{"id": 1, "name": "BIG BEN", "score": 9.5, "avatar": null}]),
+            run(json!({"uri": "SELECT * FROM users WHERE name = ?", "body": "BIG BEN"}))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
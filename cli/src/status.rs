@@ -0,0 +1,142 @@
+use std::{
+    io::{self, IsTerminal, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// The time a request is in flight before its status line is drawn, so that fast frames do not
+/// flicker
+const STATUS_DELAY: Duration = Duration::from_millis(500);
+const STATUS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Progress is the position of a frame within a record along with the estimated time left in the
+/// record when the frame started
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// the 1-based position of the frame
+    pub frame: usize,
+    pub total: usize,
+    /// the mean duration of the frames already run times the frames left, None for the first frame
+    pub eta: Option<Duration>,
+}
+
+impl Progress {
+    /// new estimates the time left in a record from the durations of the frames already run
+    pub fn new(frame: usize, total: usize, durations: &[Duration]) -> Self {
+        let eta = match durations.len() {
+            0 => None,
+            n => Some(durations.iter().sum::<Duration>() / n as u32 * (total + 1 - frame) as u32),
+        };
+        Self { frame, total, eta }
+    }
+}
+
+/// StatusDetails is what the status line of an in-flight frame reports
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StatusDetails {
+    pub label: String,
+    /// the current attempt and the number of attempts of a frame with retry attempts
+    pub attempt: Option<(u32, u32)>,
+    pub progress: Option<Progress>,
+}
+
+impl StatusDetails {
+    /// render formats the status line for the time elapsed since the frame started:
+    /// `⏳ usr.02s.getuser.fr.json | 3.2s | attempt 2/3 | frame 2/5, ETA 14s`
+    pub fn render(&self, elapsed: Duration) -> String {
+        let mut line = format!("⏳ {} | {:.1}s", self.label, elapsed.as_secs_f32());
+        if let Some((attempt, times)) = self.attempt {
+            line.push_str(&format!(" | attempt {attempt}/{times}"));
+        }
+        if let Some(progress) = self.progress {
+            line.push_str(&format!(" | frame {}/{}", progress.frame, progress.total));
+            if let Some(eta) = progress.eta {
+                line.push_str(&format!(", ETA {}s", eta.saturating_sub(elapsed).as_secs()));
+            }
+        }
+        line
+    }
+}
+
+/// StatusLine redraws a single line on stderr while the request of a frame is in flight so that a
+/// slow request does not look like a hang, the line is cleared once the StatusLine is dropped
+pub struct StatusLine {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<bool>>,
+}
+
+impl StatusLine {
+    /// show starts drawing the status line, nothing is drawn unless stderr is a terminal
+    pub fn show(details: StatusDetails, started: Instant) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        if !io::stderr().is_terminal() {
+            return Self { stop, handle: None };
+        }
+        let stopped = stop.clone();
+        let handle = thread::spawn(move || {
+            let mut drawn = false;
+            while !stopped.load(Ordering::Relaxed) {
+                let elapsed = started.elapsed();
+                if elapsed >= STATUS_DELAY {
+                    eprint!("\r\x1b[2K{}", details.render(elapsed));
+                    let _ = io::stderr().flush();
+                    drawn = true;
+                }
+                thread::park_timeout(STATUS_INTERVAL);
+            }
+            drawn
+        });
+        Self {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for StatusLine {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let handle = match self.handle.take() {
+            Some(handle) => handle,
+            None => return,
+        };
+        handle.thread().unpark();
+        if let Ok(true) = handle.join() {
+            eprint!("\r\x1b[2K");
+            let _ = io::stderr().flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest(
+        details,
+        expected,
+        case(
+            StatusDetails {
+                label: "usr.01s.createuser.fr.json".to_string(),
+                ..Default::default()
+            },
+            "⏳ usr.01s.createuser.fr.json | 3.2s"
+        ),
+        case(
+            StatusDetails {
+                label: "usr.02s.getuser.fr.json".to_string(),
+                attempt: Some((2, 3)),
+                progress: Some(Progress::new(2, 5, &[Duration::from_secs(4)])),
+            },
+            "⏳ usr.02s.getuser.fr.json | 3.2s | attempt 2/3 | frame 2/5, ETA 12s"
+        )
+    )]
+    fn test_render(details: StatusDetails, expected: &str) {
+        assert_eq!(expected, details.render(Duration::from_millis(3200)));
+    }
+}
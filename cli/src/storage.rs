@@ -0,0 +1,177 @@
+use anyhow::{anyhow, Context, Error};
+use filmreel::cut::Register;
+#[cfg(feature = "http")]
+use reqwest::{Client, StatusCode};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// RemoteCut is a cut register stored outside of the local filesystem, named by a URL passed to
+/// `--cut` or `--cut-out`:
+///
+/// - `https://` or `http://`: read with a GET request and written with a PUT request, such as a
+///   presigned URL
+/// - `s3://<bucket>/<key>`: read and written using the `aws` command line tool
+/// - `gs://<bucket>/<object>` or `gcs://<bucket>/<object>`: read and written using the `gcloud`
+///   command line tool
+///
+/// Credentials are resolved by the command line tool used, a remote cut is never backed up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteCut {
+    Http(String),
+    S3(String),
+    Gcs(String),
+}
+
+impl RemoteCut {
+    /// from_path returns the remote location named by the path provided or None if the path is
+    /// local
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let path = path.as_ref().to_str()?;
+        let (scheme, location) = path.split_once("://")?;
+        if location.is_empty() {
+            return None;
+        }
+        match scheme {
+            "http" | "https" => Some(Self::Http(path.to_string())),
+            "s3" => Some(Self::S3(path.to_string())),
+            "gs" | "gcs" => Some(Self::Gcs(format!("gs://{location}"))),
+            _ => None,
+        }
+    }
+
+    /// read returns the contents of the remote cut or None if there is no object at the location
+    pub fn read(&self) -> Result<Option<String>, Error> {
+        match self {
+            #[cfg(feature = "http")]
+            Self::Http(url) => crate::transport::runtime().block_on(async {
+                let response = Client::new()
+                    .get(url)
+                    .send()
+                    .await
+                    .context(format!("GET {url} failure"))?;
+                if response.status() == StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+                Ok(Some(response.error_for_status()?.text().await?))
+            }),
+            #[cfg(not(feature = "http"))]
+            Self::Http(_) => Err(crate::missing_feature("http")),
+            Self::S3(url) => run_cli("aws", &["s3", "cp", url, "-"], None),
+            Self::Gcs(url) => run_cli("gcloud", &["storage", "cat", url], None),
+        }
+    }
+
+    /// write replaces the contents of the remote cut
+    pub fn write(&self, contents: String) -> Result<(), Error> {
+        match self {
+            #[cfg(not(feature = "http"))]
+            Self::Http(_) => return Err(crate::missing_feature("http")),
+            #[cfg(feature = "http")]
+            Self::Http(url) => {
+                let request = Client::new()
+                    .put(url)
+                    .header("Content-Type", "application/json")
+                    .body(contents);
+                crate::transport::runtime()
+                    .block_on(request.send())
+                    .context(format!("PUT {url} failure"))?
+                    .error_for_status()?;
+            }
+            Self::S3(url) => {
+                run_cli("aws", &["s3", "cp", "-", url], Some(&contents))?;
+            }
+            Self::Gcs(url) => {
+                run_cli("gcloud", &["storage", "cp", "-", url], Some(&contents))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// run_cli runs a storage command line tool, writing the input provided to its stdin. Returns None
+/// when the tool reports that the object does not exist.
+fn run_cli(program: &str, args: &[&str], input: Option<&str>) -> Result<Option<String>, Error> {
+    if which::which(program).is_err() {
+        return Err(anyhow!("`{program}` was not found! Check your PATH!"));
+    }
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context(format!("unable to run `{program}`"))?;
+    if let Some(input) = input {
+        child
+            .stdin
+            .take()
+            .expect("piped stdin")
+            .write_all(input.as_bytes())
+            .context(format!("unable to write to `{program}` stdin"))?;
+    }
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        return Ok(Some(String::from_utf8(output.stdout)?));
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    const NOT_FOUND: [&str; 3] = ["(404)", "NoSuchKey", "matched no objects"];
+    if input.is_none() && NOT_FOUND.iter().any(|m| stderr.contains(m)) {
+        return Ok(None);
+    }
+    Err(anyhow!(
+        "`{program} {}` failure: {}",
+        args.join(" "),
+        stderr.trim()
+    ))
+}
+
+/// is_cut_file reports whether a cut register can be read from the path provided, remote cuts are
+/// only checked once they are read
+pub fn is_cut_file<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().is_file() || RemoteCut::from_path(path).is_some()
+}
+
+/// read_register reads a cut register from a local file or a [`RemoteCut`]
+pub fn read_register(path: PathBuf) -> Result<Register, Error> {
+    let remote = match RemoteCut::from_path(&path) {
+        Some(remote) => remote,
+        None => return Ok(Register::try_from(path)?),
+    };
+    let contents = remote
+        .read()?
+        .ok_or_else(|| anyhow!("no cut file found at {}", path.to_string_lossy()))?;
+    Register::from(contents).context(format!("{} parse failure", path.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest(
+        path,
+        expected,
+        case(
+            "https://ci.example.com/usr.cut.json",
+            Some(RemoteCut::Http("https://ci.example.com/usr.cut.json".to_string()))
+        ),
+        case(
+            "s3://bucket/run/usr.cut.json",
+            Some(RemoteCut::S3("s3://bucket/run/usr.cut.json".to_string()))
+        ),
+        case(
+            "gcs://bucket/usr.cut.json",
+            Some(RemoteCut::Gcs("gs://bucket/usr.cut.json".to_string()))
+        ),
+        case("gs://", None),
+        case("./usr.cut.json", None),
+        case("ftp://host/usr.cut.json", None)
+    )]
+    fn test_remote_cut_from_path(path: &str, expected: Option<RemoteCut>) {
+        assert_eq!(expected, RemoteCut::from_path(path));
+    }
+}
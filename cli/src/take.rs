@@ -1,37 +1,170 @@
 use crate::{
-    grpc, http,
-    params::{BaseParams, Params},
+    artifacts::{strip_ansi, write_artifacts},
+    diff::{json_diff, DiffFormat},
+    dump::{sanitized_request, write_failure_dump, LogRequests},
+    echo::Echo,
+    explain::Explanation,
+    generate::random_uuid,
+    oauth::{init_oauth, refresh_oauth},
+    params::{Attempts, BaseParams, Params},
     record::write_cut,
-    Take, ToStringPretty, ToTakeColouredJson, ToTakeHiddenColouredJson,
+    status::{StatusDetails, StatusLine},
+    storage::{is_cut_file, read_register},
+    transport::{runtime, FixtureTransport},
+    Take, ToStringCanonical, ToStringPretty, ToTakeColouredJson, ToTakeHiddenColouredJson,
+    ValidateResponse,
 };
 use anyhow::{anyhow, Context, Error};
 use colored::*;
 use colored_diff::PrettyDifference;
-use filmreel as fr;
 use filmreel::{
-    cut::Register,
-    frame::{Frame, Protocol},
+    cut::{is_builtin, Register},
+    frame::{Frame, Metadata, Protocol, Request, Scope, Severity, FRAME_VERSION},
     reel::MetaFrame,
     response::Response,
 };
-use log::{debug, error, info, warn};
-use prettytable::*;
+use log::{debug, error, info, log, warn, Level};
+#[cfg(feature = "pretty")]
+use prettytable::{Cell, Row, Table};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
 use std::{
     fs,
     io::{self, prelude::*},
     path::PathBuf,
+    sync::{Arc, Mutex},
     thread, time,
 };
+use tokio::{sync::Semaphore, task::JoinError};
 
-// run_request decides which protocol to use for sending a hydrated Frame Request
-pub fn run_request<'a>(params: Params, frame: Frame) -> Result<Response<'a>, Error> {
-    let request_fn = match frame.protocol {
-        Protocol::HTTP => http::request,
-        Protocol::GRPC => grpc::request,
+/// The request and response fields outside the frame format that are supported by darkroom,
+/// these are permitted by `--strict`
+pub const FRAME_EXTENSIONS: &[&str] = &[
+    "attempts",
+    "auth",
+    "echo",
+    "env",
+    "form",
+    "proto",
+    "import_path",
+    "query",
+    "query_encoding",
+    "path_params",
+    "promote_header",
+    "follow_location",
+    "max_ttfb_ms",
+    "redirect",
+    "retry_connect",
+    "sse",
+    "tcp",
+    "tls",
+];
+
+/// read_frame reads the Frame of a MetaFrame, rejecting unknown fields when `--strict` is
+/// provided and frames newer than the supported frame version, a missing protocol is inferred
+/// from the request uri
+pub fn read_frame(
+    metaframe: &MetaFrame,
+    base_params: &BaseParams,
+) -> Result<Frame<'static>, Error> {
+    let frame = match &metaframe.frame {
+        Some(frame) => Ok(frame.clone()),
+        None => {
+            let extensions = base_params.strict.then_some(FRAME_EXTENSIONS);
+            Frame::try_from_inferred(metaframe.path.clone(), extensions).map(|(frame, inferred)| {
+                if let Some(protocol) = inferred {
+                    warn!(
+                        "{} {} has no protocol, inferred {:?} from the request uri",
+                        "Warning:".yellow(),
+                        metaframe.get_filename(),
+                        protocol
+                    );
+                }
+                frame
+            })
+        }
+    };
+    let frame = frame.context(metaframe.get_filename())?;
+    if frame.version.unwrap_or_default() > FRAME_VERSION {
+        return Err(anyhow!(
+            "{}: frame version {} is newer than the supported version {}, upgrade darkroom",
+            metaframe.get_filename(),
+            frame.version.unwrap_or_default(),
+            FRAME_VERSION
+        ));
+    }
+    Ok(frame)
+}
+
+/// promoted_header returns the `"request"["promote_header"]` map of a frame, hydrated with the
+/// values written by the frame so that a captured token can become a default header for the
+/// frames that follow:
+///
+/// ```json
+/// "promote_header": {"Authorization": "Bearer ${TOKEN}"}
+/// ```
+pub fn promoted_header(
+    frame: &Frame,
+    register: &Register,
+) -> Result<Option<Map<String, Value>>, Error> {
+    let mut promote = match frame
+        .get_request()
+        .get_etc()
+        .and_then(|mut etc| etc.get_mut("promote_header").map(Value::take))
+    {
+        Some(Value::Null) | None => return Ok(None),
+        Some(v @ Value::Object(_)) => v,
+        Some(_) => {
+            return Err(anyhow!(
+                "request[\"promote_header\"] must be a key value map"
+            ))
+        }
     };
-    request_fn(params, frame.get_request())
+    let mut set = frame.cut.clone();
+    set.hydrate_writes = true;
+    Frame::hydrate_val(&set, &mut promote, register, false)?;
+    Ok(promote.as_object().cloned())
+}
+
+/// Mismatch holds the context of a failed response comparison so that it can be retrieved from
+/// the returned [`Error`] with `Error::downcast_ref`
+#[derive(Debug)]
+pub struct Mismatch {
+    /// the pretty JSON of the actual response
+    pub response: String,
+    /// the uncoloured diff or expected/actual listing of the mismatch
+    pub diff: String,
+    msg: &'static str,
 }
 
+impl Mismatch {
+    fn new(
+        expected: &Response,
+        actual: &Response,
+        register: &Register,
+        msg: &'static str,
+    ) -> Result<Self, Error> {
+        let response = register.redact(actual.to_string_pretty()?);
+        Ok(Self {
+            diff: format!(
+                "Expected:\n{}\nActual:\n{}\n",
+                register.redact(expected.to_string_pretty()?),
+                response
+            ),
+            response,
+            msg,
+        })
+    }
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
 // process_response grabs the expected Response from the given Frame and attempts to match the values
 // present in the payload Response printing a "Value Mismatch" diff to stdout and returning an
 // error if there is not a complete match
@@ -42,61 +175,127 @@ pub fn process_response<'a, 'b>(
     mut payload_response: Response<'b>,
     output: Option<PathBuf>,
 ) -> Result<&'a Register, Error> {
+    if !frame.response_any_of.is_empty() {
+        match select_response(params, frame, cut_register, &payload_response) {
+            Some(0) => (),
+            Some(i) => {
+                info!("{} response_any_of[{}]", "Matched:".yellow(), i - 1);
+                frame.response = frame.response_any_of[i - 1].clone();
+            }
+            None => warn!(
+                "{} reporting the mismatch against \"response\"",
+                "No response_any_of variant matched,".yellow()
+            ),
+        }
+    }
+
+    let mut explanation = params.explain.then(|| Explanation::new(&frame.response));
+
     // ----------------------------------------------------------------------------
     // apply validation transformations before read and write operations are called
+    if params.normalize_field_names && frame.protocol == Protocol::GRPC {
+        frame.response.apply_field_names(&mut payload_response);
+    }
     frame.response.apply_validation(&mut payload_response)?;
+    frame.response.apply_content_type(&mut payload_response);
+    frame.response.apply_header(&mut payload_response);
     // ----------------------------------------------------------------------------
 
+    if let Some(explanation) = explanation.as_mut() {
+        explanation.record_writes(&frame.response, &frame.cut, &payload_response)?;
+    }
     let payload_matches = frame
         .response
         .match_payload_response(&frame.cut, &payload_response)
         .map_err(Error::from)
         .or_else(|e| {
-            log_mismatch(params, &frame.response, &payload_response)
-                .context("fn log_mismatch failure")?;
-            Err(e)
+            const MSG: &str = "request/response form mismatch";
+            log_explanation(
+                &explanation,
+                &frame.response,
+                &payload_response,
+                cut_register,
+            )?;
+            let mut mismatch =
+                Mismatch::new(&frame.response, &payload_response, cut_register, MSG)?;
+            match params.diff_format {
+                DiffFormat::Text => {
+                    log_mismatch(params, &frame.response, &payload_response, cut_register)
+                        .context("fn log_mismatch failure")?
+                }
+                DiffFormat::Json => {
+                    mismatch.diff =
+                        cut_register.redact(json_diff(MSG, &frame.response, &payload_response)?);
+                    error!("{}", mismatch.diff);
+                }
+            }
+            Err(e.context(mismatch))
         })?;
 
     // If there are valid matches for write operations
     if let Some(matches) = payload_matches {
         debug!("writing to cut register...");
+        // values written from sensitive response fields are hidden unless revealed by the frame
+        let sensitive: Vec<&str> = frame.cut.sensitive_writes().collect();
         for (k, v) in matches {
-            cut_register.write_operation(k, v)?;
+            if sensitive.contains(&k) {
+                cut_register.hide(k);
+            }
+            let before = match frame.cut.is_local(k) {
+                true => cut_register.write_local(k, v)?,
+                false => cut_register.write_operation(k, v)?,
+            };
+            log_write(
+                k,
+                cut_register.is_hidden(k),
+                before.as_ref(),
+                cut_register.get(k),
+            );
         }
 
-        // For now simply run hydrate again to hydrate the newly written cut variables into the
-        // Response
-        frame.cut.hydrate_writes = true;
-
-        if let Some(response_body) = &mut frame.response.body {
-            Frame::hydrate_val(&frame.cut, response_body, cut_register, false)?;
-        }
-        if let Some(etc) = &mut frame.response.etc {
-            Frame::hydrate_val(&frame.cut, etc, cut_register, false)?;
-        }
+        hydrate_writes(frame, cut_register)?;
     }
 
     if frame.response != payload_response {
+        const MSG: &str = "request/response mismatch";
         params.error_timestamp();
-        error!(
-            "{}",
-            PrettyDifference {
-                expected: &frame.response.to_string_pretty()?,
-                actual: &payload_response.to_string_pretty()?,
+        log_explanation(
+            &explanation,
+            &frame.response,
+            &payload_response,
+            cut_register,
+        )?;
+        if params.diff_format == DiffFormat::Json {
+            let diff = cut_register.redact(json_diff(MSG, &frame.response, &payload_response)?);
+            error!("{}", diff);
+            return Err(Mismatch {
+                response: cut_register.redact(payload_response.to_string_pretty()?),
+                diff,
+                msg: MSG,
             }
-        );
+            .into());
+        }
+        // hidden values are redacted before diffing so that a differing secret is not printed
+        let diff = PrettyDifference {
+            expected: &cut_register.redact(frame.response.to_string_pretty()?),
+            actual: &cut_register.redact(payload_response.to_string_pretty()?),
+        }
+        .to_string();
+        error!("{}", params.diff_limit.limit_diff(&diff));
         error!(
             "{}{}{}",
             "= ".red(),
             "Value Mismatch 🤷".yellow(),
             "===".red()
         );
-        return Err(anyhow!("request/response mismatch"));
+        return Err(Mismatch {
+            response: cut_register.redact(payload_response.to_string_pretty()?),
+            diff: strip_ansi(diff),
+            msg: MSG,
+        }
+        .into());
     }
 
-    // remove lowercase values
-    cut_register.flush_ignored();
-
     info!(
         "{}{}{}",
         "= ".green(),
@@ -106,31 +305,235 @@ pub fn process_response<'a, 'b>(
 
     // If an output was specified create a take file
     if let Some(frame_out) = output {
-        debug!("creating take receipt...");
-        fs::write(frame_out, frame.to_string_pretty()?)?;
+        write_take(frame, frame_out)?;
     }
 
     Ok(cut_register)
 }
 
-/// run_take
+/// write_take writes the take receipt of a frame that matched its expected response
+pub fn write_take(frame: &Frame, frame_out: PathBuf) -> Result<(), Error> {
+    debug!("creating take receipt...");
+    // receipts name the frame layout that they were written with so that they diff cleanly
+    let mut receipt = frame.clone();
+    receipt.version = Some(FRAME_VERSION);
+    fs::write(frame_out, receipt.to_string_canonical()?)?;
+    Ok(())
+}
+
+/// hydrate_writes hydrates the cut variables written by the payload into the expected response
+fn hydrate_writes(frame: &mut Frame, cut_register: &Register) -> Result<(), Error> {
+    // For now simply run hydrate again to hydrate the newly written cut variables into the
+    // Response
+    frame.cut.hydrate_writes = true;
+
+    if let Some(response_body) = &mut frame.response.body {
+        Frame::hydrate_val(&frame.cut, response_body, cut_register, false)?;
+    }
+    for header in frame
+        .response
+        .header
+        .iter_mut()
+        .flat_map(|h| h.values_mut())
+    {
+        Frame::hydrate_val(&frame.cut, header, cut_register, false)?;
+    }
+    if let Some(etc) = &mut frame.response.etc {
+        Frame::hydrate_val(&frame.cut, etc, cut_register, false)?;
+    }
+    Ok(())
+}
+
+/// select_response returns the index of the first response of [`Frame::responses`] that the
+/// payload matches, each candidate is checked against copies of the frame and register so that
+/// nothing is written or logged
+fn select_response<'a>(
+    params: &Params,
+    frame: &Frame<'a>,
+    cut_register: &Register,
+    payload: &Response<'a>,
+) -> Option<usize> {
+    frame.responses().position(|candidate| {
+        let mut frame = frame.clone();
+        frame.response = candidate.clone();
+        matches_response(params, frame, cut_register.clone(), payload.clone()).unwrap_or(false)
+    })
+}
+
+fn matches_response<'a>(
+    params: &Params,
+    mut frame: Frame<'a>,
+    mut cut_register: Register,
+    mut payload: Response<'a>,
+) -> Result<bool, Error> {
+    if params.normalize_field_names && frame.protocol == Protocol::GRPC {
+        frame.response.apply_field_names(&mut payload);
+    }
+    frame.response.apply_validation(&mut payload)?;
+    frame.response.apply_content_type(&mut payload);
+    frame.response.apply_header(&mut payload);
+    if let Some(matches) = frame
+        .response
+        .match_payload_response(&frame.cut, &payload)?
+    {
+        for (k, v) in matches {
+            match frame.cut.is_local(k) {
+                true => cut_register.write_local(k, v)?,
+                false => cut_register.write_operation(k, v)?,
+            };
+        }
+        hydrate_writes(&mut frame, &cut_register)?;
+    }
+    Ok(frame.response == payload)
+}
+
+/// print_interactive prints the titled columns of the interactive mode side by side
+#[cfg(feature = "pretty")]
+fn print_interactive(columns: [(String, String); 3]) {
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        columns.iter().map(|(title, _)| Cell::new(title)).collect(),
+    ));
+    table.add_row(Row::new(
+        columns.iter().map(|(_, json)| Cell::new(json)).collect(),
+    ));
+    table.printstd();
+}
+
+/// the columns of the interactive mode are printed one after the other without the "pretty"
+/// feature
+#[cfg(not(feature = "pretty"))]
+fn print_interactive(columns: [(String, String); 3]) {
+    for (title, json) in columns {
+        println!("{title}\n{json}");
+    }
+}
+
+/// Prehydrated is a frame hydrated ahead of its take, while the request of the frame before it was
+/// in flight. It is only taken if the Cut Variables and builtin variables read by the frame still
+/// hold the values it was hydrated with, the frame is hydrated once more otherwise.
+pub struct Prehydrated {
+    frame: Frame<'static>,
+    /// the names of the Cut Variables and builtin variables read by the frame
+    reads: Vec<String>,
+    /// the variables read by the frame as of its hydration
+    variables: Register,
+    idempotency_key: String,
+}
+
+impl Prehydrated {
+    /// Hydrates the frame from the register given, frames generating variables or sending a
+    /// `"before"` request are never hydrated ahead of their take
+    pub fn new(
+        frame: &Frame<'static>,
+        register: &Register,
+        base_params: &BaseParams,
+    ) -> Result<Option<Self>, Error> {
+        if frame.cut.generated().next().is_some() || frame.before.is_some() {
+            return Ok(None);
+        }
+        let mut register = register.clone();
+        let idempotency_key = random_uuid();
+        write_take_builtins(&mut register, base_params, &idempotency_key);
+        let mut hydrated = frame.clone();
+        hydrated.hydrate(&register, false)?;
+        // builtin variables are read without being declared by the cut instructions
+        let builtins = register.read_match(&serde_json::to_string(frame)?)?;
+        let reads: Vec<String> = frame
+            .cut
+            .variables()
+            .chain(
+                builtins
+                    .iter()
+                    .filter_map(|mat| mat.name())
+                    .filter(|var| is_builtin(var)),
+            )
+            .map(String::from)
+            .collect();
+        Ok(Some(Self {
+            frame: hydrated,
+            variables: register.subset(&reads),
+            reads,
+            idempotency_key,
+        }))
+    }
+}
+
+/// run_take takes the `"before"` request of a frame ahead of the frame itself, see [`take_frame`]
+pub fn run_take(
+    frame: &mut Frame<'static>,
+    register: &mut Register,
+    base_params: &BaseParams,
+    output: Option<PathBuf>,
+) -> Result<(), Error> {
+    run_prehydrated(frame, register, base_params, output, None)
+}
+
+/// run_prehydrated is [`run_take`] sending the frame hydrated ahead of the take, if any
+pub fn run_prehydrated(
+    frame: &mut Frame<'static>,
+    register: &mut Register,
+    base_params: &BaseParams,
+    output: Option<PathBuf>,
+    prehydrated: Option<Prehydrated>,
+) -> Result<(), Error> {
+    let result = match frame.before_frame() {
+        Some(before) => run_before(before, frame.cut.scope, register, base_params),
+        None => Ok(()),
+    }
+    .and_then(|_| take_frame(frame, register, base_params, output, prehydrated));
+    // the frame-local variables of the frame and of its before request never outlive the frame
+    let cleared = register.clear_locals();
+    if !cleared.is_empty() {
+        info!(
+            "{} {}",
+            "Cleared frame-local variables:".yellow(),
+            cleared.join(", ")
+        );
+    }
+    result
+}
+
+/// run_before takes the `"before"` request of a frame, the ignored variables that it writes are
+/// kept for as long as the scope of the frame
+fn run_before(
+    mut before: Frame<'static>,
+    scope: Scope,
+    register: &mut Register,
+    base_params: &BaseParams,
+) -> Result<(), Error> {
+    info!("{} request", "Before:".yellow());
+    before.cut.scope = scope;
+    take_frame(&mut before, register, base_params, None, None).context("\"before\" request failure")
+}
+
+/// take_frame
 /// 1. initializes cli settings for the take using base_params
 /// 2. performs a single frame hydration using a given json file
 /// 3. initializes frame specific settings for the take using base_params.init(frame.get_request())
-/// 4. runs a request and processes the response, multiple times if attempts are present in the Params object
+/// 4. runs a request and processes the response, multiple times if attempts are present in the Params object,
+///    see [`take_attempts`] for the attempts sent concurrently with `--concurrency`
 /// 5. Outputs a diff to stdout and returns an error if there is a mismatch:
-///    - Form Mismatch: output during run_request when the returned JSON does not match the
-///     expected structure
+///    - Form Mismatch: output during Transport::send when the returned JSON does not match the
+///      expected structure
 ///    - Value Mismatch: output during process_response when the returned JSON values do not
-///    match
-pub fn run_take<'a>(
-    frame: &'a mut Frame<'a>,
-    register: &'a mut Register,
-    base_params: &'a BaseParams,
+///      match
+fn take_frame(
+    frame: &mut Frame<'static>,
+    register: &mut Register,
+    base_params: &BaseParams,
     output: Option<PathBuf>,
+    prehydrated: Option<Prehydrated>,
 ) -> Result<(), Error> {
     let interactive = base_params.interactive;
     let verbose = base_params.verbose;
+    // generated cut variables are written before hydration so that they can be read by the frame
+    base_params.generator.write_into(&frame.cut, register)?;
+    let idempotency_key = match &prehydrated {
+        Some(prehydrated) => prehydrated.idempotency_key.clone(),
+        None => random_uuid(),
+    };
+    write_take_builtins(register, base_params, &idempotency_key);
     let mut unhydrated_frame: Option<Frame> = None;
     // hidden_frame is meant to sanitize ${_HIDDEN} variables
     let hidden_frame: Option<Frame> = if interactive || verbose {
@@ -142,35 +545,51 @@ pub fn run_take<'a>(
         None
     };
 
+    let body_limit = base_params.verbose_body_limit;
     info!("[{}] frame:", "Unhydrated".red());
-    info!("{}", frame.to_coloured_tk_json()?);
+    info!(
+        "{}",
+        summarized(frame, body_limit)?.0.to_coloured_tk_json()?
+    );
     info!("{}", "=======================".magenta());
     info!("HYDRATING...");
     info!("{}", "=======================".magenta());
-    frame.hydrate(register, false)?;
+    // every attempt is hydrated from the unhydrated frame with its own ${@ATTEMPT}
+    let retry_frame = frame.clone();
+    match prehydrated.filter(|p| p.variables == register.subset(&p.reads)) {
+        Some(prehydrated) => {
+            debug!("taking the frame hydrated ahead of its take...");
+            *frame = prehydrated.frame;
+        }
+        None => frame.hydrate(register, false)?,
+    }
     // init params after hydration so that  cut register params can be pulled otherwise this can
     // happen: Params { address: "${ADDRESS}", }
-    let params = base_params.init(frame.get_request())?;
+    let mut params = base_params.init(frame.get_request())?;
 
     if interactive {
         let mut stdin = io::stdin();
         let mut stdout = io::stdout();
-        let mut table = Table::new();
-        table.add_row(row![
-            format!("[{}] frame", "Unhydrated".red()),
-            format!("[{}]", "Cut Register".yellow()),
-            format!("[{}] frame", "Hydrated".green()),
-        ]);
-
         let hidden = hidden_frame.ok_or_else(|| anyhow!("None for interactive hidden_frame"))?;
-        table.add_row(row![
-            unhydrated_frame
-                .expect("None for unhydrated_frame")
+        print_interactive([
+            (
+                format!("[{}] frame", "Unhydrated".red()),
+                summarized(
+                    &unhydrated_frame.expect("None for unhydrated_frame"),
+                    body_limit,
+                )?
+                .0
                 .to_coloured_tk_json()?,
-            register.to_hidden_tk_json()?,
-            hidden.to_coloured_tk_json()?,
+            ),
+            (
+                format!("[{}]", "Cut Register".yellow()),
+                register.subset(frame.cut.variables()).to_hidden_tk_json()?,
+            ),
+            (
+                format!("[{}] frame", "Hydrated".green()),
+                summarized(&hidden, body_limit)?.0.to_coloured_tk_json()?,
+            ),
         ]);
-        table.printstd();
         write!(stdout, "Press {} to continue...", "ENTER".yellow()).expect("write to stdout panic");
         stdout.flush().expect("stdout flush panic");
 
@@ -178,26 +597,99 @@ pub fn run_take<'a>(
         let _ = stdin.read(&mut [0u8]).expect("read stdin panic");
     } else if verbose {
         let hidden = hidden_frame.ok_or_else(|| anyhow!("None for interactive hidden_frame"))?;
-        info!("{} {}", "Request URI:".yellow(), frame.get_request_uri()?);
+        // the hydrated uri and the effective headers can hold hidden values
+        info!(
+            "{} {}",
+            "Request URI:".yellow(),
+            register.redact(frame.get_request_uri()?)
+        );
+        if let Some(header) = &params.header {
+            info!("{} {}", "Request Header:".yellow(), register.redact(header));
+        }
+        info!("[{}] frame variables:", "Cut Register".yellow());
+        info!(
+            "{}",
+            register.subset(frame.cut.variables()).to_hidden_tk_json()?
+        );
         info!("[{}] frame:", "Hydrated".green());
+        let (hidden, summaries) = summarized(&hidden, body_limit)?;
         info!("{}", hidden.to_coloured_tk_json()?);
+        for summary in summaries {
+            info!(
+                "[{}] {} bytes, sha256 {}, first {} lines:",
+                summary.pointer.yellow(),
+                summary.bytes,
+                summary.sha256,
+                SUMMARY_LINES
+            );
+            info!("{}", summary.head);
+        }
     }
 
-    if let Some(attempts) = params.attempts {
+    let started = time::Instant::now();
+    let mut status = StatusDetails {
+        label: match register.get_builtin("FRAME_NAME").and_then(Value::as_str) {
+            Some(name) => name.to_string(),
+            None => register.redact(frame.get_request().get_uri()),
+        },
+        attempt: None,
+        progress: base_params.progress,
+    };
+    if let Some(attempts) = params.attempts.clone() {
+        if base_params.concurrent_attempts
+            && base_params.concurrency > 1
+            && !interactive
+            && is_safe(frame)
+        {
+            let attempt = Attempt {
+                frame: frame.clone(),
+                unhydrated: retry_frame,
+                register: register.clone(),
+            };
+            let (taken, result) =
+                take_attempts(base_params, &attempts, attempt, &status, started, output);
+            *frame = taken.frame;
+            *register = taken.register;
+            return result;
+        }
         for n in 1..attempts.times {
+            let delay = attempts.delay(n);
             warn!(
                 "attempt [{}/{}] | interval [{}{}]",
                 n.to_string().yellow(),
                 attempts.times,
-                attempts.ms.to_string().yellow(),
+                delay.as_millis().to_string().yellow(),
                 "ms",
             );
-            if let Ok(response) = run_request(params.clone(), frame.clone()) {
+            status.attempt = Some((n, attempts.times));
+            if let Ok(response) = send_refreshing(
+                base_params,
+                &mut params,
+                frame,
+                &retry_frame,
+                register,
+                &status,
+                started,
+            ) {
+                if !attempts.retries_status(response.status) {
+                    warn!(
+                        "attempt [{}/{}] | status {} is not retried",
+                        n.to_string().red(),
+                        attempts.times,
+                        response.status.to_string().red(),
+                    );
+                    return process_response(&params, frame, register, response, output)
+                        .map(|_| ());
+                }
                 if process_response(&params, frame, register, response, output.clone()).is_ok() {
                     return Ok(());
                 }
             }
-            thread::sleep(time::Duration::from_millis(attempts.ms));
+            thread::sleep(delay);
+            register.write_builtin("ATTEMPT", Value::String((n + 1).to_string()));
+            *frame = retry_frame.clone();
+            frame.hydrate(register, false)?;
+            params = base_params.init(frame.get_request())?;
         }
         // for final retry attempt do not swallow error propagation
         warn!(
@@ -205,42 +697,366 @@ pub fn run_take<'a>(
             attempts.times.to_string().red(),
             attempts.times
         );
+        status.attempt = Some((attempts.times, attempts.times));
     }
 
-    let response = run_request(params.clone(), frame.clone())?;
+    let response = send_refreshing(
+        base_params,
+        &mut params,
+        frame,
+        &retry_frame,
+        register,
+        &status,
+        started,
+    )?;
     match process_response(&params, frame, register, response, output) {
         Ok(_) => Ok(()),
         Err(e) => Err(e),
     }
 }
 
+/// Attempt is a retry attempt of a take sent concurrently by [`take_attempts`], hydrated from
+/// its own copy of the register
+struct Attempt {
+    frame: Frame<'static>,
+    /// the frame as it was read, hydrated once more if the OAuth access token is refreshed
+    unhydrated: Frame<'static>,
+    register: Register,
+}
+
+/// is_safe returns whether the frame is an HTTP request with a method that has no side effects,
+/// the only frames whose retry attempts are sent without waiting for the attempts before them
+fn is_safe(frame: &Frame) -> bool {
+    let uri = frame.get_request().get_uri();
+    let method = uri.split_once(' ').map_or("", |(method, _)| method);
+    frame.protocol == Protocol::HTTP && matches!(method, "GET" | "HEAD" | "OPTIONS")
+}
+
+/// Dispatched tracks the retry attempts sent by [`take_attempts`] until the take ends
+#[derive(Default)]
+struct Dispatched {
+    ended: bool,
+    sent: Vec<u32>,
+}
+
+/// take_attempts sends the retry attempts of a frame at most `--concurrency` at a time on the
+/// shared runtime under `--concurrent-attempts`. Every attempt is sent once the intervals of the
+/// attempts before it have elapsed, without waiting for their responses, and the responses are
+/// processed in the order of the attempts: the first attempt matching the frame, or responding
+/// with a status that is not retried, ends the take. The attempts not sent yet are then never
+/// sent, while the attempts already in flight are not cancelled and their responses are ignored.
+/// The attempt ending the take is returned along with the outcome of the take.
+fn take_attempts(
+    base_params: &BaseParams,
+    attempts: &Attempts,
+    first: Attempt,
+    status: &StatusDetails,
+    started: time::Instant,
+    output: Option<PathBuf>,
+) -> (Attempt, Result<(), Error>) {
+    // a frame is sent at least once
+    let times = attempts.times.max(1);
+    let permits = Arc::new(Semaphore::new(base_params.concurrency));
+    let dispatched = Arc::new(Mutex::new(Dispatched::default()));
+    let mut offset = time::Duration::ZERO;
+    let mut handles = vec![];
+    for n in 1..=times {
+        let mut attempt = Attempt {
+            frame: first.frame.clone(),
+            unhydrated: first.unhydrated.clone(),
+            register: first.register.clone(),
+        };
+        let base_params = base_params.clone();
+        let permits = permits.clone();
+        let dispatched = dispatched.clone();
+        let mut status = status.clone();
+        status.attempt = Some((n, times));
+        let start = offset;
+        let send_at = tokio::time::Instant::now() + start;
+        offset += attempts.delay(n);
+        handles.push(runtime().spawn(async move {
+            tokio::time::sleep_until(send_at).await;
+            let _permit = permits
+                .acquire_owned()
+                .await
+                .expect("attempt permits closed");
+            let attempt = tokio::task::spawn_blocking(move || {
+                {
+                    let mut dispatched = dispatched.lock().expect("attempts lock poisoned");
+                    if dispatched.ended {
+                        return (attempt, Err(anyhow!("the take ended before attempt {n}")));
+                    }
+                    dispatched.sent.push(n);
+                }
+                warn!(
+                    "attempt [{}/{}] | sent after [{}{}]",
+                    n.to_string().yellow(),
+                    times,
+                    start.as_millis().to_string().yellow(),
+                    "ms",
+                );
+                // the first attempt was hydrated by the take
+                if n > 1 {
+                    attempt
+                        .register
+                        .write_builtin("ATTEMPT", Value::String(n.to_string()));
+                    attempt.frame = attempt.unhydrated.clone();
+                    if let Err(e) = attempt.frame.hydrate(&attempt.register, false) {
+                        return (attempt, Err(e.into()));
+                    }
+                }
+                let response =
+                    base_params
+                        .init(attempt.frame.get_request())
+                        .and_then(|mut params| {
+                            send_refreshing(
+                                &base_params,
+                                &mut params,
+                                &mut attempt.frame,
+                                &attempt.unhydrated,
+                                &mut attempt.register,
+                                &status,
+                                started,
+                            )
+                        });
+                (attempt, response)
+            });
+            joined(attempt.await)
+        }));
+    }
+
+    let mut pending = handles.into_iter();
+    let mut n = 0;
+    while let Some(handle) = pending.next() {
+        n += 1;
+        let last = n == times;
+        let (mut attempt, response) = joined(runtime().block_on(handle));
+        let response = match response {
+            Ok(response) => response,
+            Err(e) if last => return (attempt, Err(e)),
+            Err(_) => continue,
+        };
+        let retried = attempts.retries_status(response.status);
+        if !retried && !last {
+            warn!(
+                "attempt [{}/{}] | status {} is not retried",
+                n.to_string().red(),
+                times,
+                response.status.to_string().red(),
+            );
+        }
+        let result = base_params
+            .init(attempt.frame.get_request())
+            .and_then(|params| {
+                let frame = &mut attempt.frame;
+                process_response(
+                    &params,
+                    frame,
+                    &mut attempt.register,
+                    response,
+                    output.clone(),
+                )
+            })
+            .map(|_| ());
+        if last || !retried || result.is_ok() {
+            let mut dispatched = dispatched.lock().expect("attempts lock poisoned");
+            dispatched.ended = true;
+            for sent in dispatched.sent.iter().filter(|sent| **sent > n) {
+                warn!(
+                    "attempt [{}/{}] | already sent, its response is ignored",
+                    sent.to_string().yellow(),
+                    times,
+                );
+            }
+            pending.for_each(|handle| handle.abort());
+            return (attempt, result);
+        }
+    }
+    unreachable!("the last attempt ends the take")
+}
+
+/// joined returns the output of a finished task, resuming the panic of a task that panicked
+pub(crate) fn joined<T>(result: Result<T, JoinError>) -> T {
+    result.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+// write_take_builtins writes the builtin variables of the first attempt of a take
+fn write_take_builtins(register: &mut Register, base_params: &BaseParams, idempotency_key: &str) {
+    register.write_builtin("RUN_ID", Value::String(base_params.run_id.clone()));
+    // ${@ATTEMPT} is a string so that it can be embedded in a URI or an idempotency key
+    register.write_builtin("ATTEMPT", Value::String("1".to_string()));
+    // ${@IDEMPOTENCY_KEY} is written once per take so that every retry attempt reuses it
+    register.write_builtin(
+        "IDEMPOTENCY_KEY",
+        Value::String(idempotency_key.to_string()),
+    );
+}
+
+// send_refreshing sends the request of a frame, a frame reading the OAuth access token is hydrated
+// from the unhydrated frame and sent once more after a 401 response refreshes the token
+fn send_refreshing<'a, 'b, 'f>(
+    base_params: &'b BaseParams,
+    params: &mut Params<'b>,
+    frame: &mut Frame<'f>,
+    unhydrated: &Frame<'f>,
+    register: &mut Register,
+    status: &StatusDetails,
+    started: time::Instant,
+) -> Result<Response<'a>, Error> {
+    let response = send_request(base_params, params, frame, register, status, started)?;
+    if !refresh_oauth(base_params, register, frame, response.status)? {
+        return Ok(response);
+    }
+    *frame = unhydrated.clone();
+    frame.hydrate(register, false)?;
+    *params = base_params.init(frame.get_request())?;
+    send_request(base_params, params, frame, register, status, started)
+}
+
+// send_request sends the request of a frame through the transport of the BaseParams, showing a
+// status line while the request is in flight outside of interactive mode
+fn send_request<'a>(
+    base_params: &BaseParams,
+    params: &Params,
+    frame: &Frame,
+    register: &Register,
+    status: &StatusDetails,
+    started: time::Instant,
+) -> Result<Response<'a>, Error> {
+    // cookies are only stored and sent by HTTP frames
+    let cookie_jar = base_params
+        .cookie_jar
+        .as_ref()
+        .filter(|_| frame.protocol == Protocol::HTTP);
+    let mut params = params.clone();
+    if let Some(jar) = cookie_jar {
+        params.header = jar.insert_into(&params.address, params.header)?;
+    }
+    if base_params.log_requests == Some(LogRequests::Sanitized) {
+        match sanitized_request(&params, frame, register) {
+            // logged at the warn level so that the preview is shown without --verbose
+            Ok(request) => warn!("{} {}", "Request:".yellow(), request),
+            Err(e) => warn!("{} {:#}", "Log requests:".yellow(), e),
+        }
+    }
+    let _status_line =
+        (!base_params.interactive).then(|| StatusLine::show(status.clone(), started));
+    let address = params.address.clone();
+    let header = params.header.clone();
+    let response = base_params.transport.send(params, frame.clone())?;
+    if let Some(jar) = cookie_jar {
+        jar.store(&address, response.header.as_ref());
+    }
+    let request = frame.get_request();
+    if let Some(ttfb) = response.ttfb {
+        info!("{} {}ms", "TTFB:".yellow(), ttfb.as_millis());
+        if let Some(max) = max_ttfb(&request)? {
+            if ttfb > max {
+                return Err(anyhow!(
+                    "time to first byte of {}ms exceeded max_ttfb_ms of {}ms",
+                    ttfb.as_millis(),
+                    max.as_millis()
+                ));
+            }
+        }
+    }
+    if let Some(echo) = Echo::from_request(&request)? {
+        let diffs = echo.verify(
+            header.as_deref(),
+            request.to_val_payload()?.as_ref(),
+            &response,
+        )?;
+        if !diffs.is_empty() {
+            return Err(anyhow!(
+                "request echo mismatch:\n{}",
+                register.redact(diffs.join("\n"))
+            ));
+        }
+    }
+    Ok(response)
+}
+
+/// max_ttfb reads the `"request"["max_ttfb_ms"]` upper bound of the time to first byte of the
+/// response, only measured for HTTP frames
+fn max_ttfb(req: &Request) -> Result<Option<time::Duration>, Error> {
+    match req
+        .get_etc()
+        .as_ref()
+        .and_then(|etc| etc.get("max_ttfb_ms"))
+    {
+        None | Some(Value::Null) => Ok(None),
+        Some(ms) => ms
+            .as_u64()
+            .map(|ms| Some(time::Duration::from_millis(ms)))
+            .ok_or_else(|| anyhow!("request[\"max_ttfb_ms\"] must be a positive integer")),
+    }
+}
+
 /// cmd_take runs a single take using the darkroom::Take struct
-pub fn cmd_take(cmd: Take, base_params: BaseParams) -> Result<(), Error> {
+pub fn cmd_take(cmd: Take, mut base_params: BaseParams) -> Result<(), Error> {
     let metaframe = MetaFrame::try_from(&cmd.frame)?;
 
     // set up cut register
     let mut cut_register: Register;
 
     let cut_file = cmd.get_cut_file()?;
-    if cmd.no_cut || !cut_file.exists() && !cmd.merge_cuts.is_empty() {
+    if cmd.no_cut || !is_cut_file(&cut_file) && !cmd.merge_cuts.is_empty() {
         cut_register = Register::new();
     } else {
-        let cut_str = fr::file_to_string(cut_file)?;
-        cut_register = Register::from(cut_str)?;
+        cut_register = read_register(cut_file)?;
     }
 
     // Frame to be mutably borrowed
-    let frame = Frame::try_from(cmd.frame).context(metaframe.get_filename())?;
+    let frame = read_frame(&metaframe, &base_params)?;
     let mut payload_frame = frame.clone();
     crate::record::read_into(&mut cut_register, cmd.merge_cuts)?;
+    let reel_oauth = metaframe
+        .path
+        .parent()
+        .map(|dir| dir.join(format!("{}.oauth.json", metaframe.reel_name)));
+    init_oauth(&mut base_params, &mut cut_register, reel_oauth)?;
+    cut_register.write_builtin("FRAME_NAME", Value::String(metaframe.get_filename()));
     if let Err(e) = run_take(
         &mut payload_frame,
         &mut cut_register,
         &base_params,
         cmd.take_out,
     ) {
+        if frame.severity == Severity::Warning {
+            log_severity_warning(&frame, &e);
+            write_cut(
+                &base_params.cut_out,
+                base_params.cut_out_opts,
+                &cut_register,
+                metaframe.reel_name,
+                false,
+            )?;
+            return Ok(());
+        }
+        log_metadata(&frame.meta, Level::Error);
+        base_params.error_seed();
+        if let Some(dir) = &base_params.artifacts {
+            write_artifacts(
+                dir,
+                &metaframe.get_filename(),
+                &payload_frame,
+                &cut_register,
+                &e,
+                &base_params.generator,
+            )?;
+        }
+        if let Some(dir) = &base_params.dump_failures {
+            write_failure_dump(
+                dir,
+                &metaframe.get_filename(),
+                &payload_frame,
+                &cut_register,
+                &base_params,
+            );
+        }
         write_cut(
             &base_params.cut_out,
+            base_params.cut_out_opts,
             &cut_register,
             metaframe.reel_name,
             true,
@@ -250,6 +1066,7 @@ pub fn cmd_take(cmd: Take, base_params: BaseParams) -> Result<(), Error> {
 
     write_cut(
         &base_params.cut_out,
+        base_params.cut_out_opts,
         &cut_register,
         metaframe.reel_name,
         false,
@@ -265,27 +1082,204 @@ pub fn cmd_take(cmd: Take, base_params: BaseParams) -> Result<(), Error> {
     Ok(())
 }
 
+pub fn cmd_validate_response(
+    cmd: ValidateResponse,
+    mut base_params: BaseParams,
+) -> Result<(), Error> {
+    let metaframe = MetaFrame::try_from(&cmd.frame)?;
+    let mut cut_register = Register::new();
+    if cmd.merge_cuts.is_empty() {
+        let dir = fs::canonicalize(&cmd.frame)?;
+        let cut_file = metaframe.get_cut_file(dir.parent().unwrap());
+        if cut_file.is_file() {
+            cut_register = read_register(cut_file)?;
+        }
+    }
+    crate::record::read_into(&mut cut_register, cmd.merge_cuts)?;
+
+    base_params.transport = Arc::new(FixtureTransport::from_path(&cmd.response)?);
+    // the request is never sent so any address satisfies a frame without an entrypoint
+    base_params
+        .address
+        .get_or_insert_with(|| "localhost".to_string());
+
+    let mut frame = read_frame(&metaframe, &base_params)?;
+    cut_register.write_builtin("FRAME_NAME", Value::String(metaframe.get_filename()));
+    run_take(&mut frame, &mut cut_register, &base_params, None)?;
+
+    write_cut(
+        &base_params.cut_out,
+        base_params.cut_out_opts,
+        &cut_register,
+        metaframe.reel_name,
+        false,
+    )?;
+    warn!(
+        "{}{}{}",
+        "= ".green(),
+        "Valid 🎉 ".yellow(),
+        "============\n".green()
+    );
+    Ok(())
+}
+
+/// log_metadata surfaces the annotations of a failed Frame so that the failure points to
+/// further context
+pub fn log_metadata(meta: &Metadata, level: Level) {
+    if let Some(description) = &meta.description {
+        log!(level, "{} {}", "Description:".yellow(), description);
+    }
+    if let Some(owner) = &meta.owner {
+        log!(level, "{} {}", "Owner:".yellow(), owner);
+    }
+    if let Some(link) = &meta.link {
+        log!(level, "{} {}", "Link:".yellow(), link);
+    }
+}
+
+/// log_severity_warning reports the mismatch of a Frame marked with [`Severity::Warning`],
+/// such a mismatch does not fail the run
+pub fn log_severity_warning(frame: &Frame, err: &Error) {
+    log_metadata(&frame.meta, Level::Warn);
+    warn!("{} {}", "Warning:".yellow(), err);
+    warn!(
+        "{}{}{}",
+        "= ".yellow(),
+        "Mismatch Tolerated ⚠️ ".yellow(),
+        "=\n".yellow()
+    );
+}
+
+/// The number of leading lines printed for a body summarized in verbose output
+const SUMMARY_LINES: usize = 20;
+
+/// BodySummary stands in for a frame body larger than `--verbose-body-limit` bytes in verbose
+/// output
+#[derive(Debug, PartialEq)]
+struct BodySummary {
+    pointer: &'static str,
+    bytes: usize,
+    sha256: String,
+    /// the first [`SUMMARY_LINES`] lines of the pretty printed body
+    head: String,
+}
+
+// summarized serializes a frame, replacing every request and response body larger than limit bytes
+// with a placeholder holding its size and digest, a limit of 0 keeps every body
+fn summarized(frame: &Frame, limit: usize) -> Result<(Value, Vec<BodySummary>), Error> {
+    let mut frame = serde_json::to_value(frame)?;
+    let mut summaries = vec![];
+    if limit == 0 {
+        return Ok((frame, summaries));
+    }
+    for pointer in ["/request/body", "/response/body"] {
+        let body = match frame.pointer_mut(pointer) {
+            Some(body) => body,
+            None => continue,
+        };
+        let pretty = serde_json::to_string_pretty(body)?;
+        if pretty.len() <= limit {
+            continue;
+        }
+        let sha256: String = Sha256::digest(pretty.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        *body = Value::String(format!(
+            "<{} bytes, sha256 {}>",
+            pretty.len(),
+            &sha256[..16]
+        ));
+        summaries.push(BodySummary {
+            pointer,
+            bytes: pretty.len(),
+            sha256,
+            head: pretty
+                .lines()
+                .take(SUMMARY_LINES)
+                .collect::<Vec<&str>>()
+                .join("\n"),
+        });
+    }
+    Ok((frame, summaries))
+}
+
+// log_write describes a single cut register write with the value held before and after the write,
+// hidden variables have both values obscured
+fn log_write(name: &str, hidden: bool, before: Option<&Value>, after: Option<&Value>) {
+    let fmt_value = |value: Option<&Value>| match value {
+        Some(_) if hidden => "${_HIDDEN}".to_string(),
+        Some(v) => v.to_string(),
+        None => "<unset>".to_string(),
+    };
+    info!(
+        "{} {}: {} {} {}",
+        "Cut Write:".yellow(),
+        name,
+        fmt_value(before).bright_black(),
+        "->".yellow(),
+        fmt_value(after),
+    );
+}
+
+// log_explanation provides the `--explain` output of a mismatch ahead of its diff
+fn log_explanation(
+    explanation: &Option<Explanation>,
+    frame_response: &Response,
+    payload_response: &Response,
+    register: &Register,
+) -> Result<(), Error> {
+    let explanation = match explanation {
+        Some(explanation) => explanation,
+        None => return Ok(()),
+    };
+    error!(
+        "{}{}{}",
+        "= ".red(),
+        "Explain 🔎 ".yellow(),
+        "==========".red()
+    );
+    error!(
+        "{}",
+        register.redact(
+            explanation
+                .render(frame_response, payload_response)
+                .context("fn log_explanation failure")?
+        )
+    );
+    Ok(())
+}
+
 // log_mismatch provides the "Form Mismatch" diff when the returned payload Response does not match
 // the expected object structure of the Frame Response
 fn log_mismatch(
     params: &Params,
     frame_response: &Response,
     payload_response: &Response,
+    register: &Register,
 ) -> Result<(), Error> {
     params.error_timestamp();
     error!("{}\n", "Expected:".magenta());
     error!(
         "{}\n",
-        frame_response
-            .to_coloured_tk_json()
-            .context("log_mismatch \"Expected:\" serialization")?
+        params.diff_limit.limit_lines(
+            register.redact(
+                frame_response
+                    .to_coloured_tk_json()
+                    .context("log_mismatch \"Expected:\" serialization")?
+            )
+        )
     );
     error!("{}\n", "Actual:".magenta());
     error!(
         "{}\n",
-        payload_response
-            .to_coloured_tk_json()
-            .context("log_mismatch \"Actual:\"  serialization")?
+        params.diff_limit.limit_lines(
+            register.redact(
+                payload_response
+                    .to_coloured_tk_json()
+                    .context("log_mismatch \"Actual:\"  serialization")?
+            )
+        )
     );
     error!(
         "{}{}{}",
@@ -299,8 +1293,17 @@ fn log_mismatch(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use filmreel::register;
+    use crate::transport::{
+        tests::{test_params, Canned, Flaky},
+        Transport,
+    };
+    use filmreel::{register, FrError};
+    use rstest::*;
     use serde_json::json;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
 
     #[test]
     fn test_process_response() {
@@ -327,9 +1330,12 @@ mod tests {
         .unwrap();
         let payload_response = Response {
             body: Some(json!("created user: BIG_BEN")),
+            content_type: Some("application/json".to_string()),
+            header: None,
             etc: Some(json!({})),
             validation: None,
             status: 200,
+            ttfb: None,
         };
         let mut register = Register::default();
         let params = Params::default();
@@ -337,4 +1343,393 @@ mod tests {
             process_response(&params, &mut frame, &mut register, payload_response, None).unwrap();
         assert_eq!(*processed_register, register!({"USER_ID"=>"BIG_BEN"}));
     }
+
+    #[test]
+    fn test_process_response_sensitive() {
+        let mut frame: Frame = serde_json::from_value(json!({
+            "protocol": "HTTP",
+            "cut": {
+                "to": {
+                    "SESSION": "'response'.'header'.'Set-Cookie'",
+                    "ACCESS_TOKEN": "'response'.'body'.'access_token'",
+                    "TOKEN_TYPE": "'response'.'body'.'token_type'"
+                },
+                "reveal": ["TOKEN_TYPE"]
+            },
+            "request": {"uri": "POST /login"},
+            "response": {
+                "header": {"Set-Cookie": "${SESSION}"},
+                "body": {"access_token": "${ACCESS_TOKEN}", "token_type": "${TOKEN_TYPE}"},
+                "status": 200
+            }
+        }))
+        .unwrap();
+        let payload_response = Response {
+            header: json!({"set-cookie": "sid=s3ss"}).as_object().cloned(),
+            body: Some(json!({"access_token": "jWt", "token_type": "Bearer"})),
+            status: 200,
+            ..Default::default()
+        };
+        let mut register = Register::default();
+        let processed_register = process_response(
+            &Params::default(),
+            &mut frame,
+            &mut register,
+            payload_response,
+            None,
+        )
+        .unwrap();
+        assert!(processed_register.is_hidden("SESSION"));
+        assert!(processed_register.is_hidden("ACCESS_TOKEN"));
+        assert!(!processed_register.is_hidden("TOKEN_TYPE"));
+        assert_eq!("${_HIDDEN} Bearer", processed_register.redact("jWt Bearer"));
+    }
+
+    #[test]
+    fn test_process_response_header() {
+        let mut frame: Frame = serde_json::from_value(json!({
+            "protocol": "HTTP",
+            "cut": {
+                "from": ["REQUEST_ID"],
+                "to": {"USER_URL": "'response'.'header'.'Location'"}
+            },
+            "request": {"uri": "POST /users"},
+            "response": {
+                "header": {"Location": "${USER_URL}", "X-Request-Id": "${REQUEST_ID}"},
+                "status": 201
+            }
+        }))
+        .unwrap();
+        let payload_response = Response {
+            header: Some(
+                json!({"location": "/users/BIG_BEN", "x-request-id": "abc", "date": "now"})
+                    .as_object()
+                    .cloned()
+                    .unwrap(),
+            ),
+            status: 201,
+            ..Default::default()
+        };
+        let mut register = register!({"REQUEST_ID"=>"abc"});
+        frame.hydrate(&register, false).unwrap();
+        let processed_register = process_response(
+            &Params::default(),
+            &mut frame,
+            &mut register,
+            payload_response.clone(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            *processed_register,
+            register!({"REQUEST_ID"=>"abc", "USER_URL"=>"/users/BIG_BEN"})
+        );
+
+        let mut register = register!({"REQUEST_ID"=>"xyz"});
+        frame.response.header = Some(
+            json!({"Location": "${USER_URL}", "X-Request-Id": "xyz"})
+                .as_object()
+                .cloned()
+                .unwrap(),
+        );
+        assert!(process_response(
+            &Params::default(),
+            &mut frame,
+            &mut register,
+            payload_response,
+            None,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_process_response_any_of() {
+        let mut frame: Frame = serde_json::from_value(json!({
+            "protocol": "HTTP",
+            "cut": {"to": {"USER_ID": "'response'.'body'.'id'"}},
+            "request": {"uri": "POST /users"},
+            "response": {"body": {"id": "${USER_ID}"}, "status": 200},
+            "response_any_of": [
+                {"body": {"error": "exists"}, "status": 409},
+                {"body": {"id": "${USER_ID}", "created": false}, "status": 200}
+            ]
+        }))
+        .unwrap();
+        let payload_response = Response {
+            body: Some(json!({"id": "BIG_BEN", "created": false})),
+            status: 200,
+            ..Default::default()
+        };
+        let mut register = Register::default();
+        let processed_register = process_response(
+            &Params::default(),
+            &mut frame,
+            &mut register,
+            payload_response,
+            None,
+        )
+        .unwrap();
+        assert_eq!(*processed_register, register!({"USER_ID"=>"BIG_BEN"}));
+        assert_eq!(
+            Some(json!({"id": "BIG_BEN", "created": false})),
+            frame.response.body
+        );
+
+        let payload_response = Response {
+            body: Some(json!({"error": "missing"})),
+            status: 409,
+            ..Default::default()
+        };
+        assert!(process_response(
+            &Params::default(),
+            &mut frame,
+            &mut Register::default(),
+            payload_response,
+            None,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_summarized() {
+        let frame: Frame = serde_json::from_value(json!({
+            "protocol": "HTTP",
+            "request": {"uri": "POST /users", "body": {"name": "BIG_BEN"}},
+            "response": {"body": {"users": (0..50).collect::<Vec<u32>>()}, "status": 200}
+        }))
+        .unwrap();
+        let (summarized_frame, summaries) = summarized(&frame, 64).unwrap();
+        assert_eq!(
+            Some(&json!({"name": "BIG_BEN"})),
+            summarized_frame.pointer("/request/body")
+        );
+        assert_eq!(1, summaries.len());
+        let summary = &summaries[0];
+        assert_eq!("/response/body", summary.pointer);
+        assert_eq!(SUMMARY_LINES, summary.head.lines().count());
+        assert_eq!(
+            Some(&json!(format!(
+                "<{} bytes, sha256 {}>",
+                summary.bytes,
+                &summary.sha256[..16]
+            ))),
+            summarized_frame.pointer("/response/body")
+        );
+
+        let (unsummarized, summaries) = summarized(&frame, 0).unwrap();
+        assert_eq!(serde_json::to_value(&frame).unwrap(), unsummarized);
+        assert!(summaries.is_empty());
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let mismatch = Error::from(Mismatch {
+            response: String::new(),
+            diff: String::new(),
+            msg: "request/response mismatch",
+        });
+        assert_eq!(2, crate::exit_code(&mismatch));
+
+        // form mismatches report the underlying filmReel error
+        let form_mismatch =
+            Error::from(FrError::SelectorNotFound("'response'.'body'".into())).context(mismatch);
+        assert_eq!(14, crate::exit_code(&form_mismatch));
+        assert_eq!(1, crate::exit_code(&anyhow!("unknown")));
+    }
+
+    #[test]
+    fn test_max_ttfb() {
+        let max_ttfb = |request: Value| max_ttfb(&serde_json::from_value(request).unwrap());
+        assert_eq!(
+            Some(time::Duration::from_millis(250)),
+            max_ttfb(json!({"uri": "GET /", "max_ttfb_ms": 250})).unwrap()
+        );
+        assert_eq!(None, max_ttfb(json!({"uri": "GET /"})).unwrap());
+        assert!(max_ttfb(json!({"uri": "GET /", "max_ttfb_ms": "250"})).is_err());
+    }
+
+    #[test]
+    fn test_attempt_hydration() {
+        let transport = Arc::new(Flaky::default());
+        let base_params = test_params(transport.clone());
+
+        let mut frame: Frame = serde_json::from_value(json!({
+            "protocol": "HTTP",
+            "request": {"uri": "GET /try/${@ATTEMPT}", "attempts": {"times": 3, "ms": 0}},
+            "response": {"status": 200}
+        }))
+        .unwrap();
+        run_take(&mut frame, &mut Register::new(), &base_params, None).unwrap();
+        assert_eq!(
+            vec!["GET /try/1", "GET /try/2", "GET /try/3"],
+            *transport.uris.lock().unwrap()
+        );
+    }
+
+    /// Slow answers every request after a delay, recording the most requests ever in flight
+    #[derive(Default)]
+    struct Slow {
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+        uris: Mutex<Vec<String>>,
+    }
+
+    impl Transport for Slow {
+        fn send<'a>(&self, _params: Params, frame: Frame) -> Result<Response<'a>, Error> {
+            let uri = frame.get_request().get_uri();
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            let status = if uri.ends_with("/3") { 200 } else { 500 };
+            self.uris.lock().unwrap().push(uri);
+            Ok(Response {
+                status,
+                ..Default::default()
+            })
+        }
+    }
+
+    #[rstest(
+        uri,
+        concurrent_attempts,
+        overlapping,
+        case("GET /try/${@ATTEMPT}", true, true),
+        case("GET /try/${@ATTEMPT}", false, false),
+        case("POST /try/${@ATTEMPT}", true, false)
+    )]
+    fn test_concurrent_attempts(uri: &str, concurrent_attempts: bool, overlapping: bool) {
+        let transport = Arc::new(Slow::default());
+        let mut base_params = test_params(transport.clone());
+        base_params.concurrency = 3;
+        base_params.concurrent_attempts = concurrent_attempts;
+
+        let mut frame: Frame = serde_json::from_value(json!({
+            "protocol": "HTTP",
+            "request": {"uri": uri, "attempts": {"times": 3, "ms": 0}},
+            "response": {"status": 200}
+        }))
+        .unwrap();
+        run_take(&mut frame, &mut Register::new(), &base_params, None).unwrap();
+
+        let mut uris = transport.uris.lock().unwrap().clone();
+        uris.sort();
+        assert_eq!(3, uris.len());
+        assert!(uris[2].ends_with("/try/3"));
+        // only the attempts of safe requests are sent before the attempt ahead of them is answered
+        assert_eq!(
+            overlapping,
+            transport.max_in_flight.load(Ordering::SeqCst) > 1
+        );
+    }
+
+    #[test]
+    fn test_idempotency_key() {
+        let transport = Arc::new(Flaky::default());
+        let base_params = test_params(transport.clone());
+
+        let frame: Frame = serde_json::from_value(json!({
+            "protocol": "HTTP",
+            "request": {"uri": "POST /orders/${@IDEMPOTENCY_KEY}", "attempts": {"times": 3, "ms": 0}},
+            "response": {"status": 200}
+        }))
+        .unwrap();
+        let mut register = Register::new();
+        run_take(&mut frame.clone(), &mut register, &base_params, None).unwrap();
+        run_take(&mut frame.clone(), &mut register, &base_params, None).unwrap();
+
+        let uris = transport.uris.lock().unwrap();
+        assert_eq!(4, uris.len());
+        // retries reuse the key of their take while a new take gets a new key
+        assert!(uris[0] == uris[1] && uris[1] == uris[2]);
+        assert_ne!(uris[2], uris[3]);
+        assert!(!uris[3].contains("${"));
+    }
+
+    #[test]
+    fn test_take_frame_locals() {
+        let transport = Arc::new(Canned::new(Response {
+            body: Some(json!({"id": "BIG_BEN"})),
+            status: 201,
+            ..Default::default()
+        }));
+        let base_params = test_params(transport.clone());
+
+        let mut frame: Frame = serde_json::from_value(json!({
+            "protocol": "HTTP",
+            "cut": {
+                "generate": {"REQUEST_KEY": "uuid"},
+                "to": {"USER_ID": "'response'.'body'.'id'"},
+                "local": ["REQUEST_KEY", "USER_ID"]
+            },
+            "request": {"uri": "POST /users/${REQUEST_KEY}"},
+            "response": {"body": {"id": "${USER_ID}"}, "status": 201}
+        }))
+        .unwrap();
+        let mut register: Register = register!({"REQUEST_KEY"=> "shared"});
+        run_take(&mut frame, &mut register, &base_params, None).unwrap();
+
+        let uris = transport.uris.lock().unwrap();
+        assert!(!uris[0].contains("${") && !uris[0].ends_with("/shared"));
+        // the frame-local variables never reach the shared register
+        assert_eq!(Some(&json!("shared")), register.get("REQUEST_KEY"));
+        assert_eq!(None, register.get("USER_ID"));
+    }
+
+    #[test]
+    fn test_prehydrated() {
+        let transport = Arc::new(Canned::new(Response {
+            status: 200,
+            ..Default::default()
+        }));
+        let base_params = test_params(transport.clone());
+
+        let frame = |uri: &str| -> Frame<'static> {
+            serde_json::from_value(json!({
+                "protocol": "HTTP",
+                "cut": {"from": ["USER_ID"]},
+                "request": {"uri": uri},
+                "response": {"status": 200}
+            }))
+            .unwrap()
+        };
+        // the frame hydrated ahead of its take is marked to tell it apart from a frame hydrated
+        // during the take
+        let marked = frame("GET /users/${USER_ID}/${@FRAME_NAME}?ahead");
+        let frame = frame("GET /users/${USER_ID}/${@FRAME_NAME}");
+        let mut ahead = register!({"USER_ID"=> "BIG_BEN"});
+        ahead.write_builtin("FRAME_NAME", json!("now"));
+        let take = |register: &mut Register| {
+            let prehydrated = Prehydrated::new(&marked, &ahead, &base_params).unwrap();
+            run_prehydrated(
+                &mut frame.clone(),
+                register,
+                &base_params,
+                None,
+                prehydrated,
+            )
+            .unwrap();
+        };
+
+        // the frame hydrated ahead of its take is sent while its variables are unchanged
+        let mut register = register!({"USER_ID"=> "BIG_BEN"});
+        register.write_builtin("FRAME_NAME", json!("now"));
+        take(&mut register);
+        // and hydrated once more when a builtin variable changed
+        register.write_builtin("FRAME_NAME", json!("later"));
+        take(&mut register);
+        // or when a Cut Variable changed
+        let mut register = register!({"USER_ID"=> "LIL_BEN"});
+        register.write_builtin("FRAME_NAME", json!("now"));
+        take(&mut register);
+
+        assert_eq!(
+            vec![
+                "GET /users/BIG_BEN/now?ahead",
+                "GET /users/BIG_BEN/later",
+                "GET /users/LIL_BEN/now"
+            ],
+            *transport.uris.lock().unwrap()
+        );
+    }
 }
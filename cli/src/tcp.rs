@@ -0,0 +1,349 @@
+use crate::{dump::RequestDump, params::Params};
+use anyhow::{anyhow, Context, Error};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use filmreel::{frame::Request, response::Response};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    fmt::Write as _,
+    io::{ErrorKind, Read, Write},
+    net::{Shutdown, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+/// Encoding is how the bytes of a TCP frame are written in its request and response bodies,
+/// provided through `"request"["tcp"]["encoding"]`:
+///
+/// - `raw`: the body string is sent as UTF-8 and the bytes read back are kept as text
+/// - `hex` or `base64`: the body string is decoded before it is sent and the bytes read back are
+///   encoded the same way
+/// - `json`: the body is sent as a single line of JSON and every line read back is parsed as JSON
+///   into an array
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Encoding {
+    #[default]
+    Raw,
+    Hex,
+    Base64,
+    Json,
+}
+
+/// TcpOpts reads `"request"["tcp"]`, the bytes sent back are otherwise read until the server
+/// closes the connection:
+///
+/// ```json
+/// "tcp": {"encoding": "hex", "lines": 1, "bytes": 64, "timeout": 2.5}
+/// ```
+///
+/// Reading stops once `lines` newline terminated lines or `bytes` bytes were read, or once
+/// `timeout` seconds have passed, keeping the bytes read so far.
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+struct TcpOpts {
+    encoding: Encoding,
+    lines: Option<usize>,
+    bytes: Option<usize>,
+    timeout: Option<f64>,
+}
+
+impl TcpOpts {
+    fn from_request(req: &Request) -> Result<Self, Error> {
+        let opts: Self = match req.get_etc().as_ref().and_then(|etc| etc.get("tcp")) {
+            Some(Value::Null) | None => return Ok(Self::default()),
+            Some(tcp) => serde_json::from_value(tcp.clone()).context(
+                "request[\"tcp\"] must be an object of: {\"encoding\", \"lines\", \"bytes\", \"timeout\"}",
+            )?,
+        };
+        if opts
+            .timeout
+            .is_some_and(|secs| !secs.is_finite() || secs < 0.0)
+        {
+            return Err(anyhow!(
+                "request[\"tcp\"][\"timeout\"] must be a non-negative number of seconds"
+            ));
+        }
+        Ok(opts)
+    }
+
+    /// is_complete reports whether the bytes read reached the `lines` or `bytes` bound
+    fn is_complete(&self, read: &[u8]) -> bool {
+        self.bytes.is_some_and(|max| read.len() >= max)
+            || self
+                .lines
+                .is_some_and(|max| read.iter().filter(|b| **b == b'\n').count() >= max)
+    }
+
+    /// truncate drops the bytes read past the `lines` or `bytes` bound
+    fn truncate(&self, read: &mut Vec<u8>) {
+        if let Some(max) = self.lines {
+            if let Some((end, _)) = read
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| **b == b'\n')
+                .nth(max.saturating_sub(1))
+            {
+                read.truncate(if max == 0 { 0 } else { end + 1 });
+            }
+        }
+        if let Some(max) = self.bytes {
+            read.truncate(max);
+        }
+    }
+}
+
+/// encode_body returns the bytes sent for the request body
+fn encode_body(body: Option<&Value>, encoding: Encoding) -> Result<Vec<u8>, Error> {
+    match (body, encoding) {
+        (None, _) => Ok(vec![]),
+        (Some(body), Encoding::Json) => Ok(format!("{body}\n").into_bytes()),
+        (Some(Value::String(body)), Encoding::Raw) => Ok(body.as_bytes().to_vec()),
+        (Some(Value::String(body)), Encoding::Hex) => decode_hex(body),
+        (Some(Value::String(body)), Encoding::Base64) => STANDARD
+            .decode(body)
+            .context("request body is not valid base64"),
+        (Some(_), _) => Err(anyhow!(
+            "request body of a TCP frame must be a string unless request[\"tcp\"][\"encoding\"] is \"json\""
+        )),
+    }
+}
+
+/// decode_body returns the response body holding the bytes read back, None if no bytes were read
+fn decode_body(bytes: &[u8], encoding: Encoding) -> Result<Option<Value>, Error> {
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+    let body = match encoding {
+        Encoding::Raw => Value::String(String::from_utf8_lossy(bytes).into_owned()),
+        Encoding::Hex => Value::String(bytes.iter().fold(String::new(), |mut hex, b| {
+            let _ = write!(hex, "{b:02x}");
+            hex
+        })),
+        Encoding::Base64 => Value::String(STANDARD.encode(bytes)),
+        Encoding::Json => String::from_utf8_lossy(bytes)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .context(format!("line {} of the response is not valid JSON", i + 1))
+            })
+            .collect::<Result<Vec<Value>, Error>>()
+            .map(Value::Array)?,
+    };
+    Ok(Some(body))
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, Error> {
+    let digits: Vec<char> = hex.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(anyhow!(
+            "request body is not valid hex: odd number of digits"
+        ));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair: String = pair.iter().collect();
+            u8::from_str_radix(&pair, 16)
+                .map_err(|_| anyhow!("request body is not valid hex: \"{pair}\""))
+        })
+        .collect()
+}
+
+/// split_address splits the `host:port` address of a TCP frame, an optional `tcp://` scheme is
+/// ignored
+fn split_address(address: &str) -> Result<(&str, &str), Error> {
+    let address = address.trim_start_matches("tcp://");
+    address
+        .rsplit_once(':')
+        .filter(|(host, port)| !host.is_empty() && !port.is_empty())
+        .ok_or_else(|| anyhow!("TCP address must be <host>:<port> => {address}"))
+}
+
+fn connect(address: &str, timeout: Option<Duration>) -> Result<TcpStream, Error> {
+    let (host, port) = split_address(address)?;
+    let port: u16 = port
+        .parse()
+        .context(format!("invalid TCP port => {address}"))?;
+    let mut last_err = None;
+    for addr in (host, port)
+        .to_socket_addrs()
+        .context(format!("unable to resolve {address}"))?
+    {
+        let connected = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+            None => TcpStream::connect(addr),
+        };
+        match connected {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(match last_err {
+        Some(e) => Error::from(e).context(format!("unable to connect to {address}")),
+        None => anyhow!("{address} did not resolve to any address"),
+    })
+}
+
+/// dump_request describes the bytes sent to the TCP address of a Frame Request, replayed with
+/// `nc`
+pub fn dump_request(prm: &Params, req: Request) -> Result<RequestDump, Error> {
+    let opts = TcpOpts::from_request(&req)?;
+    let payload = encode_body(req.to_val_payload()?.as_ref(), opts.encoding)?;
+    let (host, port) = split_address(&prm.address)?;
+    Ok(RequestDump {
+        request: json!({
+            "protocol": "TCP",
+            "address": prm.address,
+            "uri": req.get_uri(),
+            "body": req.to_val_payload()?,
+        }),
+        command: vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            r#"printf %s "$1" | base64 -d | nc -N "$2" "$3""#.to_string(),
+            "sh".to_string(),
+            STANDARD.encode(payload),
+            host.to_string(),
+            port.to_string(),
+        ],
+    })
+}
+
+/// request connects to the TCP address of a Frame Request, writes the bytes of its body, and
+/// reads the bytes sent back into a response with a status of 0. The write side of the
+/// connection is closed once the body is sent.
+pub fn request<'a>(prm: Params, req: Request) -> Result<Response<'a>, Error> {
+    let opts = TcpOpts::from_request(&req)?;
+    let payload = encode_body(req.to_val_payload()?.as_ref(), opts.encoding)?;
+    let client_timeout = match prm.timeout {
+        0 => None,
+        timeout => Some(Duration::from_secs(timeout)),
+    };
+
+    let mut stream = connect(&prm.address, client_timeout)?;
+    stream
+        .write_all(&payload)
+        .and_then(|_| stream.shutdown(Shutdown::Write))
+        .context("TCP write failure")?;
+
+    let deadline = opts
+        .timeout
+        .map(|secs| Instant::now() + Duration::from_secs_f64(secs));
+    let mut read = vec![];
+    let mut buf = [0u8; 4096];
+    while !opts.is_complete(&read) {
+        let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        if remaining == Some(Duration::ZERO) {
+            break;
+        }
+        stream.set_read_timeout(remaining.into_iter().chain(client_timeout).min())?;
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => read.extend_from_slice(&buf[..n]),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                if deadline.is_some_and(|d| Instant::now() >= d) {
+                    break;
+                }
+                return Err(anyhow!("TCP read timed out after {}s", prm.timeout));
+            }
+            Err(e) => return Err(Error::from(e).context("TCP read failure")),
+        }
+    }
+    opts.truncate(&mut read);
+
+    Ok(Response {
+        body: decode_body(&read, opts.encoding)?,
+        status: 0,
+        content_type: None,
+        header: None,
+        etc: Some(json!({})),
+        validation: None,
+        ttfb: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use std::{io::BufRead, net::TcpListener, thread};
+
+    #[rstest(
+        body,
+        encoding,
+        expected,
+        case(None, Encoding::Raw, Some(b"".to_vec())),
+        case(Some(json!("PING\r\n")), Encoding::Raw, Some(b"PING\r\n".to_vec())),
+        case(Some(json!("de ad BE EF")), Encoding::Hex, Some(vec![0xde, 0xad, 0xbe, 0xef])),
+        case(Some(json!("3q2+7w==")), Encoding::Base64, Some(vec![0xde, 0xad, 0xbe, 0xef])),
+        case(Some(json!({"cmd": "ping"})), Encoding::Json, Some(b"{\"cmd\":\"ping\"}\n".to_vec())),
+        case(Some(json!("abc")), Encoding::Hex, None),
+        case(Some(json!({"cmd": "ping"})), Encoding::Raw, None)
+    )]
+    fn test_encode_body(body: Option<Value>, encoding: Encoding, expected: Option<Vec<u8>>) {
+        assert_eq!(expected, encode_body(body.as_ref(), encoding).ok());
+    }
+
+    #[rstest(
+        bytes,
+        encoding,
+        expected,
+        case(b"", Encoding::Json, None),
+        case(b"PONG\n", Encoding::Raw, Some(json!("PONG\n"))),
+        case(&[0xde, 0xad, 0xbe, 0xef], Encoding::Hex, Some(json!("deadbeef"))),
+        case(&[0xde, 0xad, 0xbe, 0xef], Encoding::Base64, Some(json!("3q2+7w=="))),
+        case(b"{\"n\": 1}\r\n\n{\"n\": 2}\n", Encoding::Json, Some(json!([{"n": 1}, {"n": 2}])))
+    )]
+    fn test_decode_body(bytes: &[u8], encoding: Encoding, expected: Option<Value>) {
+        assert_eq!(expected, decode_body(bytes, encoding).unwrap());
+    }
+
+    #[rstest(
+        request,
+        expected,
+        case(json!({"uri": "PING"}), Some(TcpOpts::default())),
+        case(
+            json!({"uri": "PING", "tcp": {"lines": 1, "timeout": 2.5}}),
+            Some(TcpOpts {lines: Some(1), timeout: Some(2.5), ..Default::default()})
+        ),
+        case(json!({"uri": "PING", "tcp": {"timeout": -1}}), None)
+    )]
+    fn test_tcp_opts(request: Value, expected: Option<TcpOpts>) {
+        let request: Request = serde_json::from_value(request).unwrap();
+        assert_eq!(expected, TcpOpts::from_request(&request).ok());
+    }
+
+    #[test]
+    fn test_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        // a line protocol server that keeps the connection open after replying
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut stream = stream;
+            write!(stream, "{{\"echo\": {}}}\n{{\"n\": 2}}\n", line.trim()).unwrap();
+            thread::sleep(Duration::from_secs(1));
+        });
+
+        let req: Request = serde_json::from_value(json!({
+            "uri": "ECHO",
+            "body": {"n": 1},
+            "tcp": {"encoding": "json", "lines": 1}
+        }))
+        .unwrap();
+        let prm = Params {
+            address,
+            timeout: 5,
+            ..Default::default()
+        };
+        let response = request(prm, req).unwrap();
+        assert_eq!(Some(json!([{"echo": {"n": 1}}])), response.body);
+        assert_eq!(0, response.status);
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+#[cfg(feature = "exec")]
+use crate::exec;
+#[cfg(feature = "grpc")]
+use crate::grpc;
+#[cfg(feature = "http")]
+use crate::http;
+#[cfg(feature = "sql")]
+use crate::sql;
+#[cfg(feature = "tcp")]
+use crate::tcp;
+use crate::{missing_feature, params::Params};
+use anyhow::{Context, Error};
+use filmreel::{
+    frame::{Frame, Protocol},
+    response::Response,
+};
+use serde_json::Value;
+use std::{fs, path::Path, sync::OnceLock};
+use tokio::runtime::{Builder, Runtime};
+
+/// runtime returns the tokio runtime shared by the asynchronous transports and by the frames and
+/// retry attempts dispatched concurrently through `--concurrency`, started on first use
+pub fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("unable to start the tokio runtime")
+    })
+}
+
+/// Transport sends the hydrated request of a frame and returns the response received. Takes and
+/// records send every request through the transport of their [`crate::params::BaseParams`], a
+/// custom transport lets embedders and tests run frames without opening sockets:
+///
+/// ```no_run
+/// use darkroom::{params::Params, transport::Transport, Frame};
+/// use filmreel::response::Response;
+///
+/// struct Canned;
+///
+/// impl Transport for Canned {
+///     fn send<'a>(&self, _params: Params, _frame: Frame) -> Result<Response<'a>, anyhow::Error> {
+///         Ok(Response {
+///             status: 200,
+///             ..Default::default()
+///         })
+///     }
+/// }
+/// ```
+pub trait Transport: Send + Sync {
+    fn send<'a>(&self, params: Params, frame: Frame) -> Result<Response<'a>, Error>;
+}
+
+/// NetworkTransport is the default [`Transport`], sending HTTP requests with reqwest, gRPC
+/// requests with grpcurl, TCP requests over a plain socket, SQL queries with sqlx, and command
+/// lines with `sh`. HTTP clients are reused by every frame sent through the same transport.
+///
+/// HTTP, gRPC, and SQL requests are sent asynchronously on the shared [`runtime`], `send` blocks
+/// the calling thread until the response is received.
+#[derive(Debug, Default)]
+pub struct NetworkTransport {
+    #[cfg(feature = "http")]
+    clients: http::ClientCache,
+}
+
+impl Transport for NetworkTransport {
+    #[cfg_attr(
+        not(all(feature = "http", feature = "grpc", feature = "tcp", feature = "exec")),
+        allow(unused_variables)
+    )]
+    fn send<'a>(&self, params: Params, frame: Frame) -> Result<Response<'a>, Error> {
+        match frame.protocol {
+            #[cfg(feature = "http")]
+            Protocol::HTTP => {
+                runtime().block_on(http::request(&self.clients, params, frame.get_request()))
+            }
+            #[cfg(feature = "grpc")]
+            Protocol::GRPC => runtime().block_on(grpc::request(params, frame.get_request())),
+            #[cfg(feature = "tcp")]
+            Protocol::TCP => tcp::request(params, frame.get_request()),
+            #[cfg(feature = "sql")]
+            Protocol::SQL => runtime().block_on(sql::request(params, frame.get_request())),
+            #[cfg(feature = "exec")]
+            Protocol::Exec => exec::request(params, frame.get_request()),
+            #[allow(unreachable_patterns)]
+            protocol => Err(missing_feature(protocol_feature(&protocol))),
+        }
+    }
+}
+
+/// FixtureTransport is a [`Transport`] answering every request with a saved response, used by
+/// `dark validate-response` to validate a frame without any network access
+#[derive(Debug, Clone)]
+pub struct FixtureTransport(pub Response<'static>);
+
+impl FixtureTransport {
+    /// from_path reads a response object, or the `"response"` object of a frame or take file
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).context(format!("unable to read {path:?}"))?;
+        let mut value: Value =
+            serde_json::from_str(&contents).context(format!("{path:?} is not valid JSON"))?;
+        if let Some(response) = value.get_mut("response") {
+            value = response.take();
+        }
+        let response = serde_json::from_value(value)
+            .context(format!("{path:?} does not hold a filmReel response"))?;
+        Ok(Self(response))
+    }
+}
+
+impl Transport for FixtureTransport {
+    fn send<'a>(&self, _params: Params, _frame: Frame) -> Result<Response<'a>, Error> {
+        Ok(self.0.clone())
+    }
+}
+
+/// protocol_feature returns the cargo feature that sends the requests of a protocol
+pub(crate) fn protocol_feature(protocol: &Protocol) -> &'static str {
+    match protocol {
+        Protocol::HTTP => "http",
+        Protocol::GRPC => "grpc",
+        Protocol::TCP => "tcp",
+        Protocol::SQL => "sql",
+        Protocol::Exec => "exec",
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{params::BaseParams, take::run_take, Command};
+    use argh::FromArgs;
+    use filmreel::{register, Register};
+    use rstest::*;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    /// test_params returns the BaseParams of `dark version` sending its requests through the
+    /// transport provided
+    pub(crate) fn test_params(transport: Arc<dyn Transport>) -> BaseParams {
+        let args = Command::from_args(&["dark"], &["version"]).unwrap();
+        let mut base_params = BaseParams::from(&args);
+        base_params.address = Some("localhost:8000".to_string());
+        base_params.transport = transport;
+        base_params
+    }
+
+    /// Canned returns the same response to every request, recording the uri of each request
+    pub(crate) struct Canned {
+        pub(crate) uris: Mutex<Vec<String>>,
+        response: Response<'static>,
+    }
+
+    impl Canned {
+        pub(crate) fn new(response: Response<'static>) -> Self {
+            Self {
+                uris: Mutex::new(vec![]),
+                response,
+            }
+        }
+    }
+
+    impl Transport for Canned {
+        fn send<'a>(&self, _params: Params, frame: Frame) -> Result<Response<'a>, Error> {
+            self.uris
+                .lock()
+                .unwrap()
+                .push(frame.get_request().get_uri());
+            Ok(self.response.clone())
+        }
+    }
+
+    /// Flaky fails every request until the third attempt
+    #[derive(Default)]
+    pub(crate) struct Flaky {
+        pub(crate) uris: Mutex<Vec<String>>,
+    }
+
+    impl Transport for Flaky {
+        fn send<'a>(&self, _params: Params, frame: Frame) -> Result<Response<'a>, Error> {
+            let mut uris = self.uris.lock().unwrap();
+            uris.push(frame.get_request().get_uri());
+            Ok(Response {
+                status: if uris.len() < 3 { 500 } else { 200 },
+                ..Default::default()
+            })
+        }
+    }
+
+    #[rstest(
+        contents,
+        case(r#"{"body": {"id": "BIG_BEN"}, "status": 201}"#),
+        case(r#"{"request": {"uri": "POST /users"}, "response": {"body": {"id": "BIG_BEN"}, "status": 201}}"#)
+    )]
+    fn test_fixture_transport(contents: &str) {
+        let path = std::env::temp_dir().join(format!(
+            "darkroom_fixture_{}_{}.json",
+            std::process::id(),
+            contents.len()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        let fixture = FixtureTransport::from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            Response {
+                body: Some(json!({"id": "BIG_BEN"})),
+                status: 201,
+                ..Default::default()
+            },
+            fixture.0
+        );
+    }
+
+    #[test]
+    fn test_take_with_transport() {
+        let transport = Arc::new(Canned::new(Response {
+            body: Some(json!({"id": "BIG_BEN"})),
+            status: 201,
+            ..Default::default()
+        }));
+        let base_params = test_params(transport.clone());
+
+        let mut frame: Frame = serde_json::from_value(json!({
+            "protocol": "HTTP",
+            "cut": {"from": ["NAME"], "to": {"USER_ID": "'response'.'body'.'id'"}},
+            "request": {"uri": "POST /users/${NAME}"},
+            "response": {"body": {"id": "${USER_ID}"}, "status": 201}
+        }))
+        .unwrap();
+        let mut register: Register = register!({"NAME"=> "ben"});
+        run_take(&mut frame, &mut register, &base_params, None).unwrap();
+
+        assert_eq!(vec!["POST /users/ben"], *transport.uris.lock().unwrap());
+        assert_eq!(Some(&json!("BIG_BEN")), register.get("USER_ID"));
+    }
+}
@@ -0,0 +1,283 @@
+use crate::{
+    error::FrError,
+    frame::{Frame, InstructionSet, Latency, Metadata, Protocol, Request, Severity, StreamMode},
+    response::Response,
+};
+use serde_json::{json, Map, Value};
+use std::borrow::Cow;
+
+/// Constructs a [`Request`] programmatically.
+///
+/// ```edition2018
+/// use filmreel::builder::RequestBuilder;
+/// use serde_json::json;
+///
+/// let request = RequestBuilder::new("POST /users")
+///     .body(json!({"name": "${USER_NAME}"}))
+///     .header("Authorization", "Bearer ${USER_TOKEN}")
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequestBuilder {
+    request: Request,
+}
+
+impl RequestBuilder {
+    /// Creates a new RequestBuilder, the uri holds the method and path for HTTP requests and the
+    /// fully qualified method name for gRPC requests
+    pub fn new<T: Into<String>>(uri: T) -> Self {
+        Self {
+            request: Request {
+                body: None,
+                uri: Value::String(uri.into()),
+                etc: Some(json!({})),
+                header: None,
+                entrypoint: None,
+                stream: None,
+            },
+        }
+    }
+
+    /// Sets the request body
+    pub fn body(mut self, body: Value) -> Self {
+        self.request.body = Some(body);
+        self
+    }
+
+    /// Inserts a single request header
+    pub fn header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let header = self
+            .request
+            .header
+            .get_or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(map) = header {
+            map.insert(key.into(), Value::String(value.into()));
+        }
+        self
+    }
+
+    /// Sets the address the request uri is joined to
+    pub fn entrypoint<T: Into<String>>(mut self, entrypoint: T) -> Self {
+        self.request.entrypoint = Some(Value::String(entrypoint.into()));
+        self
+    }
+
+    /// Sets how an array body of gRPC messages is streamed to the server
+    pub fn stream(mut self, mode: StreamMode) -> Self {
+        self.request.stream = Some(mode);
+        self
+    }
+
+    /// Inserts an additional request field such as `"query"`, `"form"` or `"follow_location"`
+    pub fn field<K: Into<String>>(mut self, key: K, value: Value) -> Self {
+        if let Some(Value::Object(map)) = &mut self.request.etc {
+            map.insert(key.into(), value);
+        }
+        self
+    }
+
+    pub fn build(self) -> Request {
+        self.request
+    }
+}
+
+/// Constructs a [`Frame`] programmatically, allowing integration tests to assemble frames in code.
+///
+/// ```edition2018
+/// use filmreel::{
+///     builder::{FrameBuilder, RequestBuilder},
+///     frame::Protocol,
+///     Response,
+/// };
+/// use serde_json::json;
+///
+/// let frame = FrameBuilder::new(Protocol::HTTP)
+///     .read("USER_NAME")
+///     .write("USER_ID", "'response'.'body'.'id'")
+///     .request(RequestBuilder::new("POST /users").body(json!({"name": "${USER_NAME}"})))
+///     .response(Response {
+///         body: Some(json!({"id": "${USER_ID}"})),
+///         status: 201,
+///         ..Default::default()
+///     })
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameBuilder<'a> {
+    protocol: Protocol,
+    cut: InstructionSet<'a>,
+    request: Option<Request>,
+    response: Response<'a>,
+    response_any_of: Vec<Response<'a>>,
+    severity: Severity,
+    teardown: bool,
+    latency: Option<Latency>,
+    tags: Vec<String>,
+    meta: Metadata,
+}
+
+impl<'a> FrameBuilder<'a> {
+    pub fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            cut: InstructionSet::default(),
+            request: None,
+            response: Response::default(),
+            response_any_of: vec![],
+            severity: Severity::default(),
+            teardown: false,
+            latency: None,
+            tags: vec![],
+            meta: Metadata::default(),
+        }
+    }
+
+    /// Adds a read instruction for the given Cut Variable
+    pub fn read<T: Into<Cow<'a, str>>>(mut self, var: T) -> Self {
+        self.cut.reads.insert(var.into());
+        self
+    }
+
+    /// Adds a write instruction storing the value found at the selector in the given Cut Variable
+    pub fn write<K, V>(mut self, var: K, selector: V) -> Self
+    where
+        K: Into<Cow<'a, str>>,
+        V: Into<Cow<'a, str>>,
+    {
+        self.cut.writes.insert(var.into(), selector.into());
+        self
+    }
+
+    pub fn request<T: Into<Request>>(mut self, request: T) -> Self {
+        self.request = Some(request.into());
+        self
+    }
+
+    pub fn response(mut self, response: Response<'a>) -> Self {
+        self.response = response;
+        self
+    }
+
+    /// Adds an alternative response, see [`Frame::response_any_of`]
+    pub fn response_any_of(mut self, response: Response<'a>) -> Self {
+        self.response_any_of.push(response);
+        self
+    }
+
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Marks the Frame as a teardown frame, see [`Frame::teardown`]
+    pub fn teardown(mut self, teardown: bool) -> Self {
+        self.teardown = teardown;
+        self
+    }
+
+    /// Sets the latency budgets of the Frame, see [`Frame::latency`]
+    pub fn latency(mut self, latency: Latency) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Adds a tag to the Frame, see [`Frame::tags`]
+    pub fn tag<T: Into<String>>(mut self, tag: T) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn meta(mut self, meta: Metadata) -> Self {
+        self.meta = meta;
+        self
+    }
+
+    /// Builds the Frame, running the same validations as [`Frame::new`]
+    pub fn build(self) -> Result<Frame<'a>, FrError> {
+        let frame = Frame {
+            version: None,
+            protocol: self.protocol,
+            cut: self.cut,
+            request: self
+                .request
+                .ok_or(FrError::FrameParse("FrameBuilder is missing a request"))?,
+            response: self.response,
+            response_any_of: self.response_any_of,
+            before: None,
+            severity: self.severity,
+            teardown: self.teardown,
+            latency: self.latency,
+            tags: self.tags,
+            meta: self.meta,
+        };
+        frame.validate()?;
+        Ok(frame)
+    }
+}
+
+impl From<RequestBuilder> for Request {
+    fn from(builder: RequestBuilder) -> Self {
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_JSON: &str = r#"
+{
+  "protocol": "HTTP",
+  "cut": {
+    "from": ["USER_NAME"],
+    "to": {"USER_ID": "'response'.'body'.'id'"}
+  },
+  "request": {
+    "uri": "POST /users",
+    "body": {"name": "${USER_NAME}"},
+    "header": {"Authorization": "Bearer jWt"},
+    "query": {"verbose": true}
+  },
+  "response": {
+    "body": {"id": "${USER_ID}"},
+    "status": 201
+  }
+}
+    "#;
+
+    #[test]
+    fn test_frame_builder() {
+        let frame = FrameBuilder::new(Protocol::HTTP)
+            .read("USER_NAME")
+            .write("USER_ID", "'response'.'body'.'id'")
+            .request(
+                RequestBuilder::new("POST /users")
+                    .body(json!({"name": "${USER_NAME}"}))
+                    .header("Authorization", "Bearer jWt")
+                    .field("query", json!({"verbose": true})),
+            )
+            .response(Response {
+                body: Some(json!({"id": "${USER_ID}"})),
+                status: 201,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        assert_eq!(Frame::new(FRAME_JSON).unwrap(), frame);
+    }
+
+    #[test]
+    fn test_frame_builder_err() {
+        let frame = FrameBuilder::new(Protocol::HTTP)
+            .read("USER_ID")
+            .write("USER_ID", "'response'.'body'.'id'")
+            .request(RequestBuilder::new("GET /users"))
+            .build();
+        assert!(frame.is_err());
+    }
+}
@@ -1,18 +1,39 @@
-use crate::{error::FrError, utils::ordered_val_map};
+use crate::{
+    error::{FrError, WithPath},
+    utils::ordered_val_map,
+    ToStringHidden,
+};
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::{collections::HashMap, convert::TryFrom, ops::Range, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    ops::Range,
+    path::PathBuf,
+};
 
 /// Holds Cut Variables and their corresponding values stored in a series of
 /// key/value pairs.
 ///
 /// [Cut Register](https://github.com/mkatychev/filmReel/blob/master/cut.md#cut-register)
 #[derive(Serialize, Clone, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Register {
     #[serde(serialize_with = "ordered_val_map", flatten)]
     vars: Variables,
+    /// the `@` prefixed variables provided by the runner, these are never written to a cut file
+    #[serde(skip)]
+    builtins: Variables,
+    /// the Cut Variables hidden regardless of their name, such as the values written from
+    /// sensitive response fields
+    #[serde(skip)]
+    hidden: HashSet<String>,
+    /// the frame-local overlay of the Frame being taken, read ahead of `vars` and cleared once the
+    /// Frame completes, these are never written to a cut file
+    #[serde(skip)]
+    locals: Variables,
 }
 
 const VAR_NAME_ERR: &str = "Only alphanumeric characters, dashes, and underscores are permitted \
@@ -48,8 +69,20 @@ impl Register {
         self.vars.insert(key.to_string(), val)
     }
 
-    /// Removes a single key value
-    fn remove(&mut self, key: &str) -> Option<Value> {
+    /// Marks a Cut Variable as hidden, its value is then presented as `${_HIDDEN}` just like the
+    /// value of an underscore prefixed Cut Variable
+    pub fn hide<T: ToString>(&mut self, key: T) {
+        self.hidden.insert(key.to_string());
+    }
+
+    /// Returns true if the Cut Variable name starts with an underscore or was marked as hidden
+    /// through [`Register::hide`]
+    pub fn is_hidden(&self, key: &str) -> bool {
+        key.starts_with('_') || self.hidden.contains(key)
+    }
+
+    /// Removes a single Cut Variable, returning its value if it was present
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
         self.vars.remove(key)
     }
 
@@ -57,18 +90,26 @@ impl Register {
     ///
     /// [Cut Variable](https://github.com/mkatychev/filmReel/blob/master/cut.md#cut-variable)
     pub fn get_key_value<K: AsRef<str>>(&self, key: K) -> Option<(&String, &Value)> {
-        self.vars.get_key_value(key.as_ref())
+        let key = key.as_ref();
+        if is_builtin(key) {
+            return self.builtins.get_key_value(key);
+        }
+        self.locals
+            .get_key_value(key)
+            .or_else(|| self.vars.get_key_value(key))
     }
 
     /// Gets a reference to the string slice value for the given var name.
     ///
     /// [Cut Variable](https://github.com/mkatychev/filmReel/blob/master/cut.md#cut-variable)
     pub fn get<K: AsRef<str>>(&self, key: K) -> Option<&Value> {
-        self.vars.get(key.as_ref())
+        let key = key.as_ref();
+        self.locals.get(key).or_else(|| self.vars.get(key))
     }
 
-    /// An iterator visiting all Cut Variables in arbitrary order.
-    pub fn iter(&self) -> std::collections::hash_map::Iter<String, Value> {
+    /// An iterator visiting all Cut Variables in arbitrary order, the frame-local overlay is not
+    /// visited.
+    pub fn iter(&self) -> std::collections::hash_map::Iter<'_, String, Value> {
         self.vars.iter()
     }
 
@@ -76,7 +117,25 @@ impl Register {
     ///
     /// [Cut Variable](https://github.com/mkatychev/filmReel/blob/master/cut.md#cut-variable)
     pub fn contains_key(&self, key: &str) -> bool {
-        self.vars.contains_key(key)
+        self.locals.contains_key(key) || self.vars.contains_key(key)
+    }
+
+    /// Returns a new [`Register`] holding only the Cut Variables named in `keys`
+    pub fn subset<I, K>(&self, keys: I) -> Register
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        let mut output = Register::new();
+        for key in keys.into_iter() {
+            if let Some((k, v)) = self.get_key_value(key) {
+                output.insert(k, v.clone());
+                if self.hidden.contains(k) {
+                    output.hide(k);
+                }
+            }
+        }
+        output
     }
 
     /// Merges foreign [`Register`] structs into the caller,
@@ -113,20 +172,21 @@ impl Register {
         for (k, v) in other.iter() {
             self.insert(k.to_string(), v.clone());
         }
+        self.hidden.extend(other.hidden);
     }
 
     /// Returns a vector of Match enums enums found in the string provided for
     /// use in cut operations.
     ///
     /// [Read Operation](https://github.com/mkatychev/filmReel/blob/master/cut.md#read-operation)
-    pub fn read_match(&self, json_string: &str) -> Result<Vec<Match>, FrError> {
+    pub fn read_match(&self, json_string: &str) -> Result<Vec<Match<'_>>, FrError> {
         lazy_static! {
             static ref VAR_MATCH: Regex = Regex::new(
                 r"(?x)
-                (?P<esc_char>\\)?          # escape character
-                (?P<leading_b>\$\{)        # leading brace
-                (?P<cut_var>[A-Za-z_0-9]+) # Cut Variable
-                (?P<trailing_b>})?         # trailing brace
+                (?P<esc_char>\\)?            # escape character
+                (?P<leading_b>\$\{)          # leading brace
+                (?P<cut_var>@?[A-Za-z_0-9]+) # Cut Variable or builtin variable
+                (?P<trailing_b>})?           # trailing brace
                 "
             )
             .unwrap();
@@ -189,7 +249,7 @@ impl Register {
                     name.to_string(),
                 ));
             }
-            if hide_vars && name.starts_with('_') {
+            if hide_vars && self.is_hidden(name) {
                 let expected = format!("{}{}{}", "${", name, "}");
                 if let Value::String(val) = value {
                     if val.contains(&expected) {
@@ -273,41 +333,140 @@ impl Register {
     ///
     /// Returns an [`Err`] if the key value is does not consist solely of characters, dashes, and underscores.
     pub fn write_operation(&mut self, key: &str, val: Value) -> Result<Option<Value>, FrError> {
-        lazy_static! {
-            // Permit only alphachars dashes and underscores for variable names
-            static ref KEY_CHECK: Regex = Regex::new(r"^[A-Za-z_0-9]+$").unwrap();
-        }
-        if !KEY_CHECK.is_match(key) {
-            return Err(FrError::FrameParsef(VAR_NAME_ERR, key.to_string()));
-        }
+        check_var_name(key)?;
         Ok(self.insert(key, val))
     }
 
-    /// Flushes lowercase/ignored variable patters
-    pub fn flush_ignored(&mut self) {
-        lazy_static! {
-        // if key value consists of only lowercase letters and underscores
-            static ref KEY_IGNORE: Regex = Regex::new(r"^[a-z_0-9]+$").unwrap();
-        }
-        let mut remove: Vec<String> = vec![];
-        for (k, _) in self.vars.iter() {
-            if KEY_IGNORE.is_match(k) {
-                remove.push(k.to_owned());
-            }
+    /// Inserts a Value entry into the frame-local overlay, shadowing any Cut Variable of the same
+    /// name until [`Register::clear_locals`] is called
+    pub fn write_local(&mut self, key: &str, val: Value) -> Result<Option<Value>, FrError> {
+        check_var_name(key)?;
+        Ok(self.locals.insert(key.to_string(), val))
+    }
+
+    /// Returns true if the Cut Variable is held by the frame-local overlay
+    pub fn is_local(&self, key: &str) -> bool {
+        self.locals.contains_key(key)
+    }
+
+    /// Clears the frame-local overlay, returning the sorted names of the cleared variables
+    pub fn clear_locals(&mut self) -> Vec<String> {
+        let mut cleared: Vec<String> = self.locals.drain().map(|(k, _)| k).collect();
+        cleared.sort();
+        cleared
+    }
+
+    /// Sets a builtin variable that every Frame can read as `${@NAME}` without declaring it in its
+    /// read instructions, such as the `${@RUN_ID}` of a darkroom session
+    pub fn write_builtin(&mut self, name: &str, val: Value) -> Option<Value> {
+        self.builtins.insert(format!("@{name}"), val)
+    }
+
+    /// Returns the value of a builtin variable set through [`Register::write_builtin`]
+    pub fn get_builtin(&self, name: &str) -> Option<&Value> {
+        self.builtins.get(&format!("@{name}"))
+    }
+
+    /// Replaces every occurrence of a hidden Cut Variable value in the text provided with
+    /// `${_HIDDEN}`, preventing values such as captured tokens from being printed. Both the raw
+    /// and the JSON escaped forms of string values are replaced.
+    pub fn redact<T: AsRef<str>>(&self, text: T) -> String {
+        let mut secrets: Vec<String> = self
+            .vars
+            .iter()
+            .chain(self.locals.iter())
+            .filter(|(k, _)| self.is_hidden(k))
+            .filter_map(|(_, v)| v.as_str())
+            .filter(|v| !v.is_empty())
+            .flat_map(|v| {
+                let escaped = serde_json::to_string(v).expect("serialization error");
+                [v.to_string(), escaped[1..escaped.len() - 1].to_string()]
+            })
+            .collect();
+        // replace the longest values first so that overlapping values are fully redacted
+        secrets.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        secrets.dedup();
+
+        let mut text = text.as_ref().to_string();
+        for secret in secrets {
+            text = text.replace(&secret, "${_HIDDEN}");
         }
-        for k in remove.iter() {
-            self.remove(k);
+        text
+    }
+
+    /// Returns a copy of the Register holding every value as a single-quoted shell word, used to
+    /// hydrate the command line of an Exec Frame so that a value cannot inject shell syntax
+    pub fn shell_quoted(&self) -> Self {
+        let quote = |vars: &Variables| -> Variables {
+            vars.iter()
+                .map(|(k, v)| (k.clone(), Value::String(shell_quote(v))))
+                .collect()
+        };
+        Self {
+            vars: quote(&self.vars),
+            builtins: quote(&self.builtins),
+            hidden: self.hidden.clone(),
+            locals: quote(&self.locals),
         }
     }
+
+    /// Flushes lowercase/ignored variable patterns, returning the names of the flushed variables
+    pub fn flush_ignored(&mut self) -> Vec<String> {
+        let keys: Vec<String> = self.vars.keys().cloned().collect();
+        self.flush_ignored_from(keys)
+    }
+
+    /// Flushes the lowercase/ignored variable patterns found in the provided variable names,
+    /// returning the sorted names of the flushed variables
+    pub fn flush_ignored_from<I, K>(&mut self, keys: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        let mut flushed: Vec<String> = keys
+            .into_iter()
+            .filter(|k| is_ignored(k.as_ref()))
+            .filter_map(|k| self.remove(k.as_ref()).map(|_| k.as_ref().to_string()))
+            .collect();
+        flushed.sort();
+        flushed
+    }
+}
+
+/// Returns an [`Err`] if the Cut Variable name does not consist solely of alphanumeric characters
+/// and underscores
+fn check_var_name(key: &str) -> Result<(), FrError> {
+    lazy_static! {
+        // Permit only alphachars dashes and underscores for variable names
+        static ref KEY_CHECK: Regex = Regex::new(r"^[A-Za-z_0-9]+$").unwrap();
+    }
+    if !KEY_CHECK.is_match(key) {
+        return Err(FrError::FrameParsef(VAR_NAME_ERR, key.to_string()));
+    }
+    Ok(())
+}
+
+/// Returns true if the variable name is that of a builtin variable, such as `@RUN_ID`
+pub fn is_builtin(var_name: &str) -> bool {
+    var_name.starts_with('@')
+}
+
+/// Returns true if the variable name consists of only lowercase letters, digits, and underscores
+pub fn is_ignored(var_name: &str) -> bool {
+    lazy_static! {
+        // if key value consists of only lowercase letters and underscores
+        static ref KEY_IGNORE: Regex = Regex::new(r"^[a-z_0-9]+$").unwrap();
+    }
+    KEY_IGNORE.is_match(var_name)
 }
 
 impl TryFrom<PathBuf> for Register {
     type Error = FrError;
 
     fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
-        let buf = crate::file_to_reader(path)?;
+        let json_string = crate::read_json_file(&path)?;
 
-        let register = serde_json::from_reader(buf)?;
+        let register = serde_json::from_str(&json_string).with_path(&path)?;
         Ok(register)
     }
 }
@@ -415,6 +574,31 @@ macro_rules! register {
     }}
 }
 
+impl ToStringHidden for Register {
+    /// Pretty formatting for Register serialization, the values of hidden Cut Variables are
+    /// presented as `${_HIDDEN}`, see [`Register::is_hidden`]
+    fn to_string_hidden(&self) -> Result<String, FrError> {
+        let mut vars = self.vars.clone();
+        for (k, v) in vars.iter_mut() {
+            if self.is_hidden(k) {
+                *v = Value::String("${_HIDDEN}".to_string());
+            }
+        }
+        let mut val = serde_json::to_value(vars)?;
+        val.sort_all_objects();
+        Ok(serde_json::to_string_pretty(&val)?)
+    }
+}
+
+/// shell_quote wraps a value in single quotes, strings are quoted as-is and other values as JSON
+fn shell_quote(value: &Value) -> String {
+    let raw = match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    };
+    format!("'{}'", raw.replace('\'', r"'\''"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -430,11 +614,15 @@ mod tests {
         let mut kv_vec = vec![];
 
         for (k, v) in reg.iter() {
-            kv_vec.push((k, v));
+            kv_vec.push((k.as_str(), v.clone()));
         }
+        kv_vec.sort_by(|a, b| a.0.cmp(b.0));
         assert_eq!(
-            [(&"FIRST_NAME", "Primus"), (&"RESPONSE", "ALRIGHT")].sort(),
-            kv_vec.sort_by(|a, b| a.0.cmp(b.0))
+            vec![
+                ("FIRST_NAME", json!("Primus")),
+                ("RESPONSE", json!("ALRIGHT"))
+            ],
+            kv_vec
         );
     }
 
@@ -551,10 +739,7 @@ mod tests {
             "FIRST_NAME"=> "Slim",
             "LAST_NAME"=> "Shady"
         });
-        assert_eq!(
-            expected,
-            reg.read_match(&mut input.to_string()).unwrap_err()
-        )
+        assert_eq!(expected, reg.read_match(input).unwrap_err())
     }
 
     #[rstest(
@@ -584,6 +769,80 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_subset() {
+        let reg = register!({
+            "FIRST_NAME"=> "Primus",
+            "LAST_NAME"=> "Secundus",
+            "RESPONSE"=> "ALRIGHT"
+        });
+        assert_eq!(
+            register!({"FIRST_NAME"=> "Primus", "RESPONSE"=> "ALRIGHT"}),
+            reg.subset(["FIRST_NAME", "RESPONSE", "MISSING"])
+        );
+    }
+
+    #[test]
+    fn test_flush_ignored_from() {
+        let mut reg = register!({
+            "KEY"=> "VALUE",
+            "ignored"=> "VALUE",
+            "from_cut"=> "VALUE"
+        });
+        assert_eq!(
+            vec!["ignored".to_string()],
+            reg.flush_ignored_from(["KEY", "ignored"])
+        );
+        assert_eq!(register!({"KEY"=> "VALUE", "from_cut"=> "VALUE"}), reg);
+    }
+
+    #[test]
+    fn test_redact() {
+        let mut reg = register!({
+            "_TOKEN"=> "jWt",
+            "_QUOTED"=> "a\"b",
+            "USER"=> "jWt_user",
+            "SESSION"=> "s3ss"
+        });
+        reg.hide("SESSION");
+        assert_eq!(
+            r#"{"token": "Bearer ${_HIDDEN}", "user": "${_HIDDEN}_user", "q": "${_HIDDEN}", "s": "${_HIDDEN}"}"#,
+            reg.redact(r#"{"token": "Bearer jWt", "user": "jWt_user", "q": "a\"b", "s": "s3ss"}"#)
+        );
+        let hidden: Value = serde_json::from_str(&reg.to_string_hidden().unwrap()).unwrap();
+        assert_eq!(
+            json!({
+                "_TOKEN": "${_HIDDEN}",
+                "_QUOTED": "${_HIDDEN}",
+                "USER": "jWt_user",
+                "SESSION": "${_HIDDEN}"
+            }),
+            hidden
+        );
+        assert!(reg.subset(["SESSION"]).is_hidden("SESSION"));
+    }
+
+    #[test]
+    fn test_locals() {
+        let mut reg = register!({"KEY"=> "global", "OTHER"=> "other"});
+        reg.write_local("KEY", json!("local")).unwrap();
+        reg.write_local("idempotency_key", json!("abc")).unwrap();
+        assert_eq!(Some(&json!("local")), reg.get("KEY"));
+        assert!(reg.is_local("idempotency_key"));
+
+        let mut val = json!("${KEY}-${idempotency_key}-${OTHER}");
+        for mat in reg.read_match(val.as_str().unwrap()).unwrap() {
+            reg.read_operation(mat, &mut val, false).unwrap();
+        }
+        assert_eq!(json!("local-abc-other"), val);
+        // the overlay is never serialized to the cut file
+        let cut: Value = serde_json::from_str(&reg.to_string_pretty()).unwrap();
+        assert_eq!(json!({"KEY": "global", "OTHER": "other"}), cut);
+
+        assert_eq!(vec!["KEY", "idempotency_key"], reg.clear_locals());
+        assert_eq!(register!({"KEY"=> "global", "OTHER"=> "other"}), reg);
+    }
+
     #[test]
     fn test_write_op() {
         let mut reg = register!({
@@ -3,7 +3,7 @@ use colored::*;
 use pest::error::Error as PestError;
 use serde_hashkey::Error as HashKeyError;
 use serde_json::error::{Category, Error as SerdeError};
-use std::{error::Error, fmt, path::Path};
+use std::{error::Error, fmt, io, path::Path};
 
 /// An error that occurred during parsing or hydrating a filmReel file
 #[derive(Debug, PartialEq)]
@@ -16,12 +16,79 @@ pub enum FrError {
     WriteInstruction(&'static str),
     ReadInstructionf(&'static str, String),
     ReelParse(&'static str),
+    /// a selector query that did not return a selection
+    SelectorNotFound(String),
+    /// a value that did not have the type required by the operation
+    TypeMismatch(&'static str, String),
     Serde(String),
     Parse(String),
     File(String, String),
+    /// a filepath that does not exist
+    FileMissing(String),
     Pest(Box<PestError<Rule>>),
 }
 
+/// A stable machine-readable code for each category of [`FrError`], allowing callers to branch on
+/// the kind of failure without parsing error messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    FrameParse,
+    ReelParse,
+    ReadInstruction,
+    WriteInstruction,
+    SelectorNotFound,
+    TypeMismatch,
+    Serde,
+    Parse,
+    File,
+    FileMissing,
+    SelectorSyntax,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FrameParse => "frame_parse",
+            Self::ReelParse => "reel_parse",
+            Self::ReadInstruction => "read_instruction",
+            Self::WriteInstruction => "write_instruction",
+            Self::SelectorNotFound => "selector_not_found",
+            Self::TypeMismatch => "type_mismatch",
+            Self::Serde => "serde",
+            Self::Parse => "parse",
+            Self::File => "file",
+            Self::FileMissing => "file_missing",
+            Self::SelectorSyntax => "selector_syntax",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FrError {
+    /// Returns the [`ErrorCode`] of the error
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::FrameParse(_) | Self::FrameParsef(..) => ErrorCode::FrameParse,
+            Self::ReelParse(_) | Self::ReelParsef(..) => ErrorCode::ReelParse,
+            Self::ReadInstruction(_) | Self::ReadInstructionf(..) => ErrorCode::ReadInstruction,
+            Self::WriteInstruction(_) => ErrorCode::WriteInstruction,
+            Self::SelectorNotFound(_) => ErrorCode::SelectorNotFound,
+            Self::TypeMismatch(..) => ErrorCode::TypeMismatch,
+            Self::Serde(_) => ErrorCode::Serde,
+            Self::Parse(_) => ErrorCode::Parse,
+            Self::File(..) => ErrorCode::File,
+            Self::FileMissing(_) => ErrorCode::FileMissing,
+            Self::Pest(_) => ErrorCode::SelectorSyntax,
+        }
+    }
+}
+
 impl Error for FrError {
     fn description(&self) -> &str {
         "Error related to filmReel"
@@ -76,6 +143,19 @@ impl fmt::Display for FrError {
                 errorf!(f, "ReadInstructionError", msg, item);
                 Ok(())
             }
+            FrError::SelectorNotFound(query) => {
+                errorf!(
+                    f,
+                    "SelectorNotFoundError",
+                    "selector did not return a selection",
+                    query
+                );
+                Ok(())
+            }
+            FrError::TypeMismatch(msg, item) => {
+                errorf!(f, "TypeMismatchError", msg, item);
+                Ok(())
+            }
             FrError::Serde(msg) => {
                 writeln!(f, "SerdeError {} {}", "-->".red(), msg)?;
                 Ok(())
@@ -92,6 +172,10 @@ impl fmt::Display for FrError {
                 errorf!(f, "FileError", msg, item);
                 Ok(())
             }
+            FrError::FileMissing(path) => {
+                errorf!(f, "FileError", "file not found", path);
+                Ok(())
+            }
         }
     }
 }
@@ -103,10 +187,35 @@ pub trait WithPath<T, P> {
 
 impl<T, E, P> WithPath<T, P> for Result<T, E>
 where
-    E: Error,
+    E: Error + 'static,
     P: AsRef<Path>,
 {
     fn with_path(self, path: P) -> Result<T, FrError> {
-        self.map_err(|e| FrError::File(path.as_ref().to_string_lossy().to_string(), e.to_string()))
+        self.map_err(|e| {
+            let path = path.as_ref().to_string_lossy().to_string();
+            match (&e as &dyn Error).downcast_ref::<io::Error>() {
+                Some(io_err) if io_err.kind() == io::ErrorKind::NotFound => {
+                    FrError::FileMissing(path)
+                }
+                _ => FrError::File(path, e.to_string()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_path_code() {
+        let missing = std::fs::File::open("./missing.fr.json").with_path("./missing.fr.json");
+        assert_eq!(
+            FrError::FileMissing("./missing.fr.json".to_string()),
+            missing.unwrap_err()
+        );
+
+        let dir = std::fs::read_to_string(".").with_path(".");
+        assert_eq!(ErrorCode::File, dir.unwrap_err().code());
     }
 }
@@ -1,46 +1,134 @@
 use crate::{
-    cut::Register,
-    error::FrError,
-    response::Response,
-    utils::{ordered_set, ordered_str_map},
+    cut::{is_builtin, is_ignored, Register},
+    error::{FrError, WithPath},
+    response::{Response, Validation},
+    utils::{check_duplicate_keys, ordered_set, ordered_str_map},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{error::Error as SerdeError, json, to_value, Value};
 use std::{
     borrow::Cow,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     convert::TryFrom,
     path::PathBuf,
+    time::Duration,
 };
 
 /// Represents the entire deserialized frame file.
 ///
 /// [Frame spec](https://github.com/mkatychev/filmReel/blob/master/frame.md#frame)
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
 pub struct Frame<'a> {
+    /// the version of the frame layout, frames without a version predate versioning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
     pub protocol: Protocol,
     #[serde(default, skip_serializing_if = "InstructionSet::is_empty")]
     pub cut: InstructionSet<'a>, // Both the reads and writes can be optional
     pub(crate) request: Request,
     pub response: Response<'a>,
+    /// alternative responses, a payload matching either the response or any one of these variants
+    /// passes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub response_any_of: Vec<Response<'a>>,
+    /// a request taken before the request of the frame
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<Before<'a>>,
+    #[serde(default, skip_serializing_if = "Severity::is_error")]
+    pub severity: Severity,
+    /// teardown frames are still taken after a record exceeds its time budget
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub teardown: bool,
+    /// latency budgets of the request, enforced over repeated takes of the frame
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<Latency>,
+    /// labels selecting the frame through `dark record --tags` and `--skip-tags`, such as `"smoke"`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(flatten)]
+    pub meta: Metadata,
 }
 
+/// The current version of the frame layout
+pub const FRAME_VERSION: u32 = 1;
+
 const MISSING_VAR_ERR: &str = "Variable is not present in InstructionSet";
 const DUPE_VAR_REFERENCE_ERR: &str =
     "Cut Variables cannot be referenced by both read and write instructions";
+const DUPE_GENERATE_REFERENCE_ERR: &str =
+    "Generated Cut Variables cannot be referenced by read or write instructions";
 const DUPE_KEY_UPON_HYDRATION_ERR: &str = "Hydrated key produced a duplicate key value";
 const INVALID_KEY_HYDRATION_ERR: &str =
     "Key attempted to be hydrated with a non-string cut variable";
+const UNKNOWN_FIELD_ERR: &str =
+    "Unknown Frame field, fields outside the Frame format must be allowlisted";
+const INVALID_SELECTOR_HYDRATION_ERR: &str =
+    "Selector attempted to be hydrated with a non-string cut variable";
+const UNINFERRED_PROTOCOL_ERR: &str =
+    "Frame is missing a protocol that could not be inferred from the request uri";
+const STREAM_PROTOCOL_ERR: &str = "Request streaming is only supported by gRPC Frames";
+const STREAM_BODY_ERR: &str = "Streamed request body must be an array of messages";
 
 impl<'a> Frame<'a> {
     /// Creates a new Frame object running post deserialization validations
     pub fn new(json_string: &str) -> Result<Self, FrError> {
+        check_duplicate_keys(json_string)?;
         let frame: Self = serde_json::from_str(json_string)?;
-        frame.cut.validate()?;
-        frame.response.validate()?;
+        frame.validate()?;
         Ok(frame)
     }
 
+    /// Runs the post deserialization validations of the instruction sets and responses
+    pub(crate) fn validate(&self) -> Result<(), FrError> {
+        self.cut.validate()?;
+        self.request.validate(&self.protocol)?;
+        for response in self.responses() {
+            response.validate()?;
+        }
+        if let Some(before) = &self.before {
+            before.cut.validate()?;
+            before
+                .request
+                .validate(before.protocol.as_ref().unwrap_or(&self.protocol))?;
+            before.response.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the `"before"` request as a Frame of its own, taken with the protocol of the
+    /// Frame unless the request provides one. The request can read the Cut Variables read by the
+    /// Frame that it does not write.
+    pub fn before_frame(&self) -> Option<Frame<'a>> {
+        let mut before = self.before.clone()?;
+        let inherited: Vec<Cow<'a, str>> = self
+            .cut
+            .reads
+            .iter()
+            .filter(|var| !before.cut.writes.contains_key(*var))
+            .cloned()
+            .collect();
+        before.cut.reads.extend(inherited);
+        Some(Frame {
+            version: self.version,
+            protocol: before.protocol.unwrap_or_else(|| self.protocol.clone()),
+            cut: before.cut,
+            request: before.request,
+            response: before.response,
+            response_any_of: vec![],
+            before: None,
+            severity: Severity::Error,
+            teardown: self.teardown,
+            latency: None,
+            tags: vec![],
+            meta: Metadata::default(),
+        })
+    }
+
     /// Serializes the Frame struct to a serde_json::Value
     pub fn to_value(&self) -> Value {
         to_value(self).expect("serialization error")
@@ -58,6 +146,11 @@ impl<'a> Frame<'a> {
         Ok(unst.replace('\"', ""))
     }
 
+    /// Returns the response followed by every alternative of `"response_any_of"`
+    pub fn responses(&self) -> impl Iterator<Item = &Response<'a>> {
+        std::iter::once(&self.response).chain(self.response_any_of.iter())
+    }
+
     /// Returns a Value object from the response body, used for response comparisons and writing to
     /// the cut register
     pub fn get_response_value(&self) -> Result<Value, SerdeError> {
@@ -71,8 +164,15 @@ impl<'a> Frame<'a> {
         if let Some(request_body) = &mut self.request.body {
             Self::hydrate_val(&set, request_body, reg, hide)?;
         }
-        if let Some(response_body) = &mut self.response.body {
-            Self::hydrate_val(&set, response_body, reg, hide)?;
+        for response in std::iter::once(&mut self.response).chain(self.response_any_of.iter_mut()) {
+            if let Some(response_body) = &mut response.body {
+                Self::hydrate_val(&set, response_body, reg, hide)?;
+            }
+            if let Some(header) = &mut response.header {
+                for val in header.values_mut() {
+                    Self::hydrate_val(&set, val, reg, hide)?;
+                }
+            }
         }
         if let Some(header) = &mut self.request.header {
             Self::hydrate_val(&set, header, reg, hide)?;
@@ -81,14 +181,72 @@ impl<'a> Frame<'a> {
             Self::hydrate_val(&set, etc, reg, hide)?;
         }
 
-        // URI and entrypoint is given an explicit read operation
-        Self::hydrate_str(&set, &mut self.request.uri, reg, hide)?;
+        // URI and entrypoint is given an explicit read operation, the command line of an Exec
+        // Frame is run by `sh -c` and thus reads shell-quoted values
+        match self.protocol {
+            Protocol::Exec => {
+                Self::hydrate_str(&set, &mut self.request.uri, &reg.shell_quoted(), hide)?
+            }
+            _ => Self::hydrate_str(&set, &mut self.request.uri, reg, hide)?,
+        };
         if let Some(entrypoint) = &mut self.request.entrypoint {
             Self::hydrate_str(&set, entrypoint, reg, hide)?;
         }
+
+        // write selectors and validation keys are hydrated before they are compiled into
+        // selectors so that indices and keys known only from prior frames can be selected
+        for selector in self.cut.writes.values_mut() {
+            Self::hydrate_selector(&set, selector, reg, hide)?;
+        }
+        for response in std::iter::once(&mut self.response).chain(self.response_any_of.iter_mut()) {
+            if let Some(validation) = &mut response.validation {
+                let mut hydrated = Validation::new();
+                for (mut selector, mut validator) in std::mem::take(validation).into_iter() {
+                    Self::hydrate_selector(&set, &mut selector, reg, hide)?;
+                    // the reference time is read as though it were a `"${VAR}"` value
+                    if let Some(within) = &mut validator.within_seconds_of {
+                        let mut time = Value::String(format!("${{{}}}", within.var));
+                        if Self::hydrate_str(&set, &mut time, reg, false)? {
+                            within.time = Some(time);
+                        }
+                    }
+                    if let Some(compare) = &mut validator.compare {
+                        let mut value = Value::String(format!("${{{}}}", compare.var));
+                        if Self::hydrate_str(&set, &mut value, reg, false)? {
+                            compare.value = Some(value);
+                        }
+                    }
+                    hydrated.insert(selector, validator);
+                }
+                *validation = hydrated;
+            }
+        }
         Ok(())
     }
 
+    /// Performs a Register.read_operation on a selector query, ensuring the result remains a string
+    fn hydrate_selector(
+        set: &InstructionSet,
+        selector: &mut Cow<'a, str>,
+        reg: &Register,
+        hide: bool,
+    ) -> Result<(), FrError> {
+        let mut val = Value::String(selector.to_string());
+        if !Self::hydrate_str(set, &mut val, reg, hide)? {
+            return Ok(());
+        }
+        match val {
+            Value::String(hydrated) => {
+                *selector = Cow::Owned(hydrated);
+                Ok(())
+            }
+            _ => Err(FrError::TypeMismatch(
+                INVALID_SELECTOR_HYDRATION_ERR,
+                selector.to_string(),
+            )),
+        }
+    }
+
     /// Traverses a given serde::Value enum attempting to modify found Strings
     /// for the moment this method also works as a Frame.init() check, emitting FrameParseErrors
     pub fn hydrate_val(
@@ -118,7 +276,12 @@ impl<'a> Frame<'a> {
                         Value::String(new_key) => {
                             replace_keys.push((keys.get(key).unwrap(), new_key));
                         }
-                        _ => return Err(FrError::FrameParse(INVALID_KEY_HYDRATION_ERR)),
+                        _ => {
+                            return Err(FrError::TypeMismatch(
+                                INVALID_KEY_HYDRATION_ERR,
+                                key.to_string(),
+                            ))
+                        }
                     }
                 }
                 // newly generated keys will now be inserted into the map
@@ -161,12 +324,18 @@ impl<'a> Frame<'a> {
             // Check if the InstructionSet has the given variable
             for mat in matches.into_iter() {
                 if let Some(n) = mat.name() {
+                    // builtin variables are read without being declared
+                    if is_builtin(n) {
+                        reg.read_operation(mat, val, hide)?;
+                        continue;
+                    }
                     if !set.contains(n) {
                         return Err(FrError::FrameParsef(MISSING_VAR_ERR, n.to_string()));
                     }
                     // Now that the cut var is confirmed to exist in the entire instruction set
-                    // perform read operation only if cut var is present in read instructions
-                    if set.reads.contains(n) {
+                    // perform read operation only if cut var is present in read or generate
+                    // instructions
+                    if set.reads.contains(n) || set.generate.contains_key(n) {
                         reg.read_operation(mat, val, hide)?;
                         continue;
                     }
@@ -186,23 +355,303 @@ impl<'a> TryFrom<PathBuf> for Frame<'a> {
     type Error = FrError;
 
     fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
-        let buf = crate::file_to_reader(path)?;
+        let json_string = crate::read_json_file(&path)?;
+        check_duplicate_keys(&json_string).with_path(&path)?;
 
-        let frame: Frame = serde_json::from_reader(buf)?;
+        let frame: Frame = serde_json::from_str(&json_string).with_path(&path)?;
         Ok(frame)
     }
 }
 
+impl<'a> Frame<'a> {
+    /// Reads the Frame at the given path, failing on the first field that is not part of the Frame
+    /// format. The `extensions` allowlist holds the additional request and response fields
+    /// that are permitted, such as the `"query"` field of an HTTP request.
+    pub fn try_from_strict(path: PathBuf, extensions: &[&str]) -> Result<Self, FrError> {
+        let json_string = crate::read_json_file(&path)?;
+        check_duplicate_keys(&json_string).with_path(&path)?;
+
+        let value: Value = serde_json::from_str(&json_string).with_path(&path)?;
+        check_fields(&value, extensions)?;
+        let frame: Frame = serde_json::from_value(value).with_path(&path)?;
+        Ok(frame)
+    }
+
+    /// Reads the Frame at the given path, inferring the protocol from the request uri when the
+    /// `"protocol"` field is omitted. The inferred protocol is returned alongside the Frame so that
+    /// it can be reported. Unknown fields are rejected when an `extensions` allowlist is provided,
+    /// as with [`Frame::try_from_strict`].
+    pub fn try_from_inferred(
+        path: PathBuf,
+        extensions: Option<&[&str]>,
+    ) -> Result<(Self, Option<Protocol>), FrError> {
+        let json_string = crate::read_json_file(&path)?;
+        check_duplicate_keys(&json_string).with_path(&path)?;
+        let mut value: Value = serde_json::from_str(&json_string).with_path(&path)?;
+        if let Some(extensions) = extensions {
+            check_fields(&value, extensions)?;
+        }
+        let inferred = Self::infer_protocol(&mut value)?;
+        // deserialize from the string whenever possible to retain line and column errors
+        let frame: Frame = match (&inferred, extensions) {
+            (None, None) => serde_json::from_str(&json_string),
+            _ => serde_json::from_value(value),
+        }
+        .with_path(&path)?;
+        Ok((frame, inferred))
+    }
+
+    /// Inserts the protocol inferred from the request uri if the frame JSON has no `"protocol"`
+    /// field, returning the inferred protocol
+    pub fn infer_protocol(frame: &mut Value) -> Result<Option<Protocol>, FrError> {
+        let map = match frame.as_object_mut() {
+            Some(map) if !map.contains_key("protocol") => map,
+            // let deserialization report on non-object frames
+            _ => return Ok(None),
+        };
+        let uri = map
+            .get("request")
+            .and_then(|r| r.get("uri"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let protocol = Protocol::infer(uri)
+            .ok_or_else(|| FrError::FrameParsef(UNINFERRED_PROTOCOL_ERR, uri.to_string()))?;
+        map.insert("protocol".to_string(), to_value(&protocol)?);
+        Ok(Some(protocol))
+    }
+}
+
+// check_fields returns an error naming the first key of a Frame object that is neither a known
+// field nor an allowlisted extension
+fn check_fields(value: &Value, extensions: &[&str]) -> Result<(), FrError> {
+    const FRAME_FIELDS: &[&str] = &[
+        "version",
+        "protocol",
+        "cut",
+        "request",
+        "response",
+        "response_any_of",
+        "before",
+        "severity",
+        "teardown",
+        "latency",
+        "tags",
+        "description",
+        "owner",
+        "link",
+    ];
+    const CUT_FIELDS: &[&str] = &["from", "to", "scope", "generate", "reveal", "local"];
+    const REQUEST_FIELDS: &[&str] = &["body", "uri", "header", "entrypoint", "stream"];
+    const RESPONSE_FIELDS: &[&str] = &["body", "content_type", "header", "validation", "status"];
+    const BEFORE_FIELDS: &[&str] = &["protocol", "cut", "request", "response"];
+
+    let check = |prefix: &str, obj: Option<&Value>, fields: &[&str], allow: &[&str]| {
+        let Some(Value::Object(map)) = obj else {
+            return Ok(());
+        };
+        match map
+            .keys()
+            .find(|k| !fields.contains(&k.as_str()) && !allow.contains(&k.as_str()))
+        {
+            Some(key) => Err(FrError::FrameParsef(
+                UNKNOWN_FIELD_ERR,
+                format!("{prefix}{key}"),
+            )),
+            None => Ok(()),
+        }
+    };
+    check("", Some(value), FRAME_FIELDS, &[])?;
+    check("cut.", value.get("cut"), CUT_FIELDS, &[])?;
+    check("request.", value.get("request"), REQUEST_FIELDS, extensions)?;
+    check(
+        "response.",
+        value.get("response"),
+        RESPONSE_FIELDS,
+        extensions,
+    )?;
+    if let Some(before) = value.get("before") {
+        check("before.", Some(before), BEFORE_FIELDS, &[])?;
+        check("before.cut.", before.get("cut"), CUT_FIELDS, &[])?;
+        check(
+            "before.request.",
+            before.get("request"),
+            REQUEST_FIELDS,
+            extensions,
+        )?;
+        check(
+            "before.response.",
+            before.get("response"),
+            RESPONSE_FIELDS,
+            extensions,
+        )?;
+    }
+    if let Some(Value::Array(variants)) = value.get("response_any_of") {
+        for (i, variant) in variants.iter().enumerate() {
+            check(
+                &format!("response_any_of.{i}."),
+                Some(variant),
+                RESPONSE_FIELDS,
+                extensions,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// A request taken before the request of its Frame, such as fetching a CSRF token, so that
+/// setup requests that are not contracts of their own do not need a frame of their own. The values
+/// written by its cut instructions can be read when hydrating the Frame and the variables read by
+/// the Frame can be read by the request:
+///
+/// ```json
+/// "before": {
+///   "cut": {"to": {"csrf": "'response'.'body'.'token'"}},
+///   "request": {"uri": "GET /csrf"},
+///   "response": {"body": {"token": "${csrf}"}, "status": 200}
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
+pub struct Before<'a> {
+    /// defaults to the protocol of the Frame
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<Protocol>,
+    #[serde(default, skip_serializing_if = "InstructionSet::is_empty")]
+    pub cut: InstructionSet<'a>,
+    pub(crate) request: Request,
+    pub response: Response<'a>,
+}
+
+/// Latency budgets in milliseconds for percentiles of the request duration of a Frame, checked
+/// against the durations of repeated takes of the Frame:
+///
+/// ```json
+/// "latency": {"p50": 100, "p95": 250, "p99": 800}
+/// ```
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
+#[serde(deny_unknown_fields)]
+pub struct Latency {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p50: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p95: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p99: Option<u64>,
+}
+
+impl Latency {
+    /// Returns the percentile, the budget, and the observed duration of every budget exceeded by
+    /// the durations provided, no budget is exceeded by an empty list of durations
+    pub fn exceeded(&self, durations: &[Duration]) -> Vec<(u8, Duration, Duration)> {
+        [(50, self.p50), (95, self.p95), (99, self.p99)]
+            .into_iter()
+            .filter_map(|(p, budget)| {
+                let budget = Duration::from_millis(budget?);
+                let observed = percentile(durations, p)?;
+                (observed > budget).then_some((p, budget, observed))
+            })
+            .collect()
+    }
+}
+
+/// Returns the nearest-rank percentile of the durations provided
+pub fn percentile(durations: &[Duration], p: u8) -> Option<Duration> {
+    if durations.is_empty() {
+        return None;
+    }
+    let mut sorted = durations.to_vec();
+    sorted.sort();
+    let rank = (usize::from(p) * sorted.len()).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+/// Optional Frame annotations, these are never compared against a payload and are only surfaced
+/// when reporting on the Frame so that failures can point to additional context.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
+pub struct Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none() && self.owner.is_none() && self.link.is_none()
+    }
+}
+
+/// Determines whether a Frame mismatch fails the entire run:
+///
+/// - `"error"`: a mismatch fails the run
+/// - `"warning"`: a mismatch is reported but the run continues
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn is_error(&self) -> bool {
+        *self == Self::Error
+    }
+}
+
 /// Represents the protocol used to send the frame payload.
 ///
 /// [Protocol example](https://github.com/mkatychev/filmReel/blob/master/frame.md#frame-nomenclature)
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Protocol {
     #[serde(rename(serialize = "gRPC", deserialize = "gRPC"))]
     #[allow(clippy::upper_case_acronyms)]
     GRPC,
     #[allow(clippy::upper_case_acronyms)]
     HTTP,
+    /// raw bytes sent over a TCP connection to the request entrypoint, never inferred
+    #[allow(clippy::upper_case_acronyms)]
+    TCP,
+    /// a query held by the request uri, run against the database url of the request entrypoint,
+    /// never inferred
+    #[allow(clippy::upper_case_acronyms)]
+    SQL,
+    /// a command line held by the request uri, run by `sh` with the request body piped to its
+    /// stdin, never inferred
+    Exec,
+}
+
+impl Protocol {
+    /// Infers the protocol from the shape of a request uri: `VERB /path` is HTTP and
+    /// `package.Service/Method` is gRPC
+    pub fn infer(uri: &str) -> Option<Self> {
+        if let Some((method, path)) = uri.split_once(' ') {
+            let is_method = !method.is_empty() && method.chars().all(|c| c.is_ascii_uppercase());
+            return (is_method && !path.trim().is_empty()).then_some(Self::HTTP);
+        }
+        let (service, method) = uri.split_once('/')?;
+        let is_service = service.contains('.') && !service.starts_with('.');
+        let is_method = !method.is_empty() && !method.contains('/');
+        (is_service && is_method).then_some(Self::GRPC)
+    }
 }
 
 /// Contains read and write instructions for the [`crate::Register`],
@@ -226,19 +675,128 @@ pub struct InstructionSet<'a> {
     pub(crate) writes: HashMap<Cow<'a, str>, Cow<'a, str>>,
     #[serde(skip_serializing, default)]
     pub hydrate_writes: bool,
+    #[serde(skip_serializing_if = "Scope::is_frame")]
+    pub scope: Scope,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) generate: BTreeMap<Cow<'a, str>, Generate>,
+    /// the Cut Variables written from sensitive response fields that are not hidden
+    #[serde(
+        skip_serializing_if = "HashSet::is_empty",
+        serialize_with = "ordered_set"
+    )]
+    pub(crate) reveal: HashSet<Cow<'a, str>>,
+    /// the Cut Variables written or generated into the frame-local overlay of the register
+    #[serde(
+        rename(serialize = "local", deserialize = "local"),
+        skip_serializing_if = "HashSet::is_empty",
+        serialize_with = "ordered_set"
+    )]
+    pub(crate) locals: HashSet<Cow<'a, str>>,
+}
+
+// the derived schema cannot provide defaults for the borrowed fields serialized through
+// `serialize_with`, so the schema is derived from an owned mirror of the deserialized fields
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for InstructionSet<'_> {
+    fn schema_name() -> String {
+        "InstructionSet".to_string()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        /// Contains read and write instructions for the Cut Register.
+        ///
+        /// [Cut Instruction Set](https://github.com/mkatychev/filmReel/blob/master/frame.md#cut-instruction-set)
+        #[derive(schemars::JsonSchema)]
+        #[schemars(default, deny_unknown_fields)]
+        #[allow(dead_code)]
+        struct InstructionSet {
+            /// Cut Variables read into the Frame
+            from: HashSet<String>,
+            /// Cut Variables written from the response, mapped to the selector of their value
+            to: HashMap<String, String>,
+            scope: Scope,
+            generate: BTreeMap<String, Generate>,
+            /// Cut Variables written from sensitive response fields that are not hidden
+            reveal: HashSet<String>,
+            /// Cut Variables written or generated for the Frame alone, never shared with the
+            /// following Frames or written to the cut file
+            local: HashSet<String>,
+        }
+
+        impl Default for InstructionSet {
+            fn default() -> Self {
+                Self {
+                    from: HashSet::new(),
+                    to: HashMap::new(),
+                    scope: Scope::Frame,
+                    generate: BTreeMap::new(),
+                    reveal: HashSet::new(),
+                    local: HashSet::new(),
+                }
+            }
+        }
+
+        InstructionSet::json_schema(gen)
+    }
 }
 
 impl<'a> InstructionSet<'a> {
     fn is_empty(&self) -> bool {
-        self.reads.is_empty() && self.writes.is_empty()
+        self.reads.is_empty()
+            && self.writes.is_empty()
+            && self.generate.is_empty()
+            && self.reveal.is_empty()
+            && self.locals.is_empty()
+            && self.scope.is_frame()
     }
 
     fn contains(&self, var: &str) -> bool {
-        self.reads.contains(var) || self.writes.contains_key(var)
+        self.reads.contains(var) || self.writes.contains_key(var) || self.generate.contains_key(var)
+    }
+
+    /// Returns the Cut Variable names referenced by either read, write, or generate instructions
+    pub fn variables(&self) -> impl Iterator<Item = &str> {
+        self.reads
+            .iter()
+            .chain(self.writes.keys())
+            .chain(self.generate.keys())
+            .map(|var| var.as_ref())
     }
 
-    /// Ensures no Cut Variables are present in both read and write instructions
-    fn validate(&self) -> Result<(), FrError> {
+    /// Returns the names of the Cut Variables read by the read instructions
+    pub fn read(&self) -> impl Iterator<Item = &str> {
+        self.reads.iter().map(|var| var.as_ref())
+    }
+
+    /// Returns the names of the Cut Variables written by the write instructions
+    pub fn written(&self) -> impl Iterator<Item = &str> {
+        self.writes.keys().map(|var| var.as_ref())
+    }
+
+    /// Returns true if the Cut Variable is written or generated into the frame-local overlay of
+    /// the register: either listed in `"local"` or an ignored variable of a `"frame"` scope
+    pub fn is_local(&self, var: &str) -> bool {
+        self.locals.contains(var) || (self.scope.is_frame() && is_ignored(var))
+    }
+
+    /// Returns the names of the Cut Variables written from a sensitive response field that are not
+    /// listed in `"reveal"`: the `Authorization` and `Set-Cookie` headers and the fields named
+    /// like `*token*` or `*secret*`
+    pub fn sensitive_writes(&self) -> impl Iterator<Item = &str> {
+        self.writes
+            .iter()
+            .filter(|(var, query)| !self.reveal.contains(*var) && is_sensitive_query(query))
+            .map(|(var, _)| var.as_ref())
+    }
+
+    /// Returns the generate instructions ordered by Cut Variable name
+    pub fn generated(&self) -> impl Iterator<Item = (&str, &Generate)> {
+        self.generate.iter().map(|(k, v)| (k.as_ref(), v))
+    }
+
+    /// Ensures no Cut Variables are present in both read and write instructions and that
+    /// generated Cut Variables are not referenced by either
+    pub(crate) fn validate(&self) -> Result<(), FrError> {
         let writes_set: HashSet<Cow<str>> = self.writes.keys().cloned().collect();
         let intersection = self.reads.intersection(&writes_set).next();
 
@@ -248,14 +806,79 @@ impl<'a> InstructionSet<'a> {
                 format!("{intersection:?}"),
             ));
         }
+        if let Some(var) = self
+            .generate
+            .keys()
+            .find(|k| self.reads.contains(*k) || self.writes.contains_key(*k))
+        {
+            return Err(FrError::FrameParsef(
+                DUPE_GENERATE_REFERENCE_ERR,
+                var.to_string(),
+            ));
+        }
         Ok(())
     }
 }
 
+// is_sensitive_query returns true if the last field selected by a write query is the
+// Authorization or Set-Cookie header or a field named like `*token*` or `*secret*`
+fn is_sensitive_query(query: &str) -> bool {
+    let field = query
+        .rsplit('.')
+        .find(|segment| segment.starts_with('\''))
+        .map(|segment| segment.trim_matches('\'').to_lowercase());
+    match field.as_deref() {
+        Some("authorization" | "set-cookie") => true,
+        Some(field) => field.contains("token") || field.contains("secret"),
+        None => false,
+    }
+}
+
+/// The kind of value produced for a generated Cut Variable, generated values are written to the
+/// [`crate::Register`] before the Frame is hydrated:
+///
+/// ```json
+/// "cut": {"generate": {"ORDER_ID": "uuid", "AMOUNT": "int"}}
+/// ```
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Generate {
+    /// a random version 4 UUID string
+    Uuid,
+    /// a random unsigned 32 bit integer
+    Int,
+    /// the current UTC time as an RFC 3339 string, such as the reference time of a
+    /// `"within_seconds_of"` validation
+    Timestamp,
+}
+
+/// Determines how long ignored (lowercase) Cut Variables written by a Frame are kept in the
+/// [`crate::Register`]:
+///
+/// - `"frame"`: ignored variables written or generated by the Frame are held by the frame-local
+///   overlay of the register, cleared once the Frame completes
+/// - `"reel"`: ignored variables written by the Frame are kept for the remainder of the Reel
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    #[default]
+    Frame,
+    Reel,
+}
+
+impl Scope {
+    fn is_frame(&self) -> bool {
+        *self == Self::Frame
+    }
+}
+
 /// Encapsulates the request payload to be sent.
 ///
 /// [Request Object](https://github.com/mkatychev/filmReel/blob/master/frame.md#request)
 #[derive(Serialize, Clone, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Request {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub(crate) body: Option<Value>,
@@ -267,6 +890,8 @@ pub struct Request {
     pub(crate) header: Option<Value>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub(crate) entrypoint: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) stream: Option<StreamMode>,
 }
 
 impl Request {
@@ -299,6 +924,44 @@ impl Request {
         }
         None
     }
+
+    /// Returns the streaming mode and the messages of a streamed gRPC request, a request whose
+    /// body is an array is streamed from the client unless `"stream"` provides another mode.
+    /// Returns None for a unary request.
+    pub fn get_stream(&self) -> Result<Option<(StreamMode, &[Value])>, FrError> {
+        match (&self.body, self.stream) {
+            (Some(Value::Array(messages)), mode) => {
+                Ok(Some((mode.unwrap_or(StreamMode::Client), messages)))
+            }
+            (_, Some(mode)) => Err(FrError::FrameParsef(STREAM_BODY_ERR, format!("{mode:?}"))),
+            (_, None) => Ok(None),
+        }
+    }
+
+    /// Ensures `"stream"` is only provided to gRPC requests, the body is checked once hydrated
+    /// since a Cut Variable can hold the array of messages
+    pub(crate) fn validate(&self, protocol: &Protocol) -> Result<(), FrError> {
+        match (self.stream, protocol) {
+            (Some(mode), protocol) if *protocol != Protocol::GRPC => Err(FrError::FrameParsef(
+                STREAM_PROTOCOL_ERR,
+                format!("{mode:?}"),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The streaming mode of a gRPC request whose `"body"` is an array of messages:
+///
+/// - `"client"`: every message is streamed to the server before the response is received
+/// - `"bidi"`: sends and receives are interleaved, each message is sent once the server has
+///   replied to the previous one and the response body holds the array of messages received
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum StreamMode {
+    Client,
+    Bidi,
 }
 
 impl Default for Request {
@@ -309,6 +972,7 @@ impl Default for Request {
             etc: Some(json!({})),
             header: None,
             entrypoint: None,
+            stream: None,
         }
     }
 }
@@ -365,8 +1029,37 @@ mod tests {
     use super::*;
     use crate::register;
     use pretty_assertions::assert_eq;
+    use rstest::*;
     use serde_json::json;
 
+    #[rstest(
+        uri,
+        expected,
+        case("POST /users", Some(Protocol::HTTP)),
+        case("GET https://example.com/users", Some(Protocol::HTTP)),
+        case("user_api.User/CreateUser", Some(Protocol::GRPC)),
+        case("grpc.health.v1.Health/Check", Some(Protocol::GRPC)),
+        case("post /users", None),
+        case("/users", None),
+        case("User/CreateUser", None),
+        case("", None)
+    )]
+    fn test_infer(uri: &str, expected: Option<Protocol>) {
+        assert_eq!(expected, Protocol::infer(uri));
+    }
+
+    #[test]
+    fn test_infer_protocol() {
+        let mut frame = json!({"request": {"uri": "user_api.User/CreateUser"}});
+        assert_eq!(
+            Some(Protocol::GRPC),
+            Frame::infer_protocol(&mut frame).unwrap()
+        );
+        assert_eq!(json!("gRPC"), frame["protocol"]);
+        assert_eq!(None, Frame::infer_protocol(&mut frame).unwrap());
+        assert!(Frame::infer_protocol(&mut json!({"request": {"uri": "/users"}})).is_err());
+    }
+
     const FRAME_JSON: &str = r#"
 {
   "protocol": "gRPC",
@@ -418,6 +1111,7 @@ mod tests {
         frame.hydrate(&reg, false).unwrap();
         assert_eq!(
             Frame {
+                version: None,
                 protocol: Protocol::GRPC,
                 cut: InstructionSet {
                     reads: from![
@@ -432,6 +1126,7 @@ mod tests {
                     ],
                     writes: HashMap::new(),
                     hydrate_writes: false,
+                    ..Default::default()
                 },
                 request: Request {
                     body: Some(json!({
@@ -441,6 +1136,7 @@ mod tests {
                     })),
                     header: Some(json!({"Authorization": "Bearer jWt"})),
                     entrypoint: Some(json!("localhost:8080")),
+                    stream: None,
                     uri: json!("user_api.User/CreateUser"),
                     etc: Some(json!({})),
                 },
@@ -450,10 +1146,39 @@ mod tests {
                     status: 0,
                     ..Default::default()
                 },
+                severity: Severity::Error,
+                response_any_of: vec![],
+                before: None,
+                teardown: false,
+                latency: None,
+                tags: vec![],
+                meta: Metadata::default(),
             },
             frame
         );
     }
+    #[test]
+    fn test_hydrate_exec() {
+        let reg = register!({
+            "NAME"=> "x'; rm -rf ~; echo '",
+            "COUNT"=> 2
+        });
+        let mut frame: Frame = serde_json::from_value(json!({
+            "protocol": "Exec",
+            "cut": {"from": ["NAME", "COUNT"]},
+            "request": {"uri": "greet ${NAME} --times ${COUNT}", "body": "${NAME}"},
+            "response": {"status": 0}
+        }))
+        .unwrap();
+        frame.hydrate(&reg, false).unwrap();
+        assert_eq!(
+            json!(r"greet 'x'\''; rm -rf ~; echo '\''' --times '2'"),
+            frame.request.uri
+        );
+        // only the command line is quoted
+        assert_eq!(Some(json!("x'; rm -rf ~; echo '")), frame.request.body);
+    }
+
     const KEY_VAR_JSON: &str = r#"
 {
   "protocol": "gRPC",
@@ -489,11 +1214,13 @@ mod tests {
         frame.hydrate(&reg, false).unwrap();
         assert_eq!(
             Frame {
+                version: None,
                 protocol: Protocol::GRPC,
                 cut: InstructionSet {
                     reads: from!["KEY", "KEY_2"],
                     writes: HashMap::new(),
                     hydrate_writes: false,
+                    ..Default::default()
                 },
                 request: Request {
                     body: Some(json!({})),
@@ -510,18 +1237,292 @@ mod tests {
                     status: 0,
                     ..Default::default()
                 },
+                severity: Severity::Error,
+                response_any_of: vec![],
+                before: None,
+                teardown: false,
+                latency: None,
+                tags: vec![],
+                meta: Metadata::default(),
             },
             frame
         );
     }
 
+    const SELECTOR_VAR_JSON: &str = r#"
+{
+  "protocol": "HTTP",
+  "cut": {
+    "from": [
+      "INDEX",
+      "KEY"
+    ],
+    "to": {
+      "USER_ID": "'response'.'body'.'users'.[${INDEX}].'id'"
+    }
+  },
+  "request": {
+    "uri": "GET /users"
+  },
+  "response": {
+    "validation": {
+      "'response'.'body'.'${KEY}'": {
+        "unordered": true
+      }
+    },
+    "body": {
+      "users": [{"id": "${USER_ID}"}]
+    },
+    "status": 200
+  }
+}
+    "#;
+
+    #[test]
+    fn test_selector_hydrate() {
+        let reg = register!({
+            "INDEX"=> "0",
+            "KEY"=> "users"
+        });
+        let mut frame: Frame = Frame::new(SELECTOR_VAR_JSON).unwrap();
+        frame.hydrate(&reg, false).unwrap();
+        assert_eq!(
+            to!({"USER_ID"=> "'response'.'body'.'users'.[0].'id'"}),
+            frame.cut.writes
+        );
+        assert_eq!(
+            vec!["'response'.'body'.'users'"],
+            frame
+                .response
+                .validation
+                .unwrap()
+                .keys()
+                .collect::<Vec<&Cow<str>>>()
+        );
+    }
+
+    #[rstest(
+        frame,
+        expected,
+        case(r#"{"request": {"uri": "GET /", "query": {}}, "response": {}}"#, None),
+        case(
+            r#"{"request": {"uri": "GET /", "heders": {}}}"#,
+            Some("request.heders")
+        ),
+        case(r#"{"cut": {"form": []}}"#, Some("cut.form")),
+        case(r#"{"cut": {"to": {}, "reveal": [], "local": []}}"#, None),
+        case(r#"{"descripton": "typo"}"#, Some("descripton")),
+        case(
+            r#"{"response_any_of": [{"status": 200}, {"stauts": 409}]}"#,
+            Some("response_any_of.1.stauts")
+        ),
+        case(
+            r#"{"before": {"request": {"uri": "GET /", "query": {}}, "respone": {}}}"#,
+            Some("before.respone")
+        )
+    )]
+    fn test_check_fields(frame: &str, expected: Option<&str>) {
+        let value: Value = serde_json::from_str(frame).unwrap();
+        assert_eq!(
+            expected.map(|e| FrError::FrameParsef(UNKNOWN_FIELD_ERR, e.to_string())),
+            check_fields(&value, &["query"]).err()
+        );
+    }
+
+    #[test]
+    fn test_before_frame() {
+        let frame = Frame::new(
+            r#"
+{
+  "protocol": "HTTP",
+  "cut": {"from": ["HOST", "csrf"]},
+  "before": {
+    "cut": {"to": {"csrf": "'response'.'body'"}},
+    "request": {"uri": "GET /csrf"},
+    "response": {"body": "${csrf}", "status": 200}
+  },
+  "request": {"uri": "POST /users", "header": {"X-CSRF-Token": "${csrf}"}},
+  "response": {"status": 200},
+  "teardown": true
+}
+    "#,
+        )
+        .unwrap();
+        let before = frame.before_frame().unwrap();
+        assert_eq!(Protocol::HTTP, before.protocol);
+        assert!(before.teardown);
+        let mut variables: Vec<&str> = before.cut.variables().collect();
+        variables.sort();
+        assert_eq!(vec!["HOST", "csrf"], variables);
+        assert_eq!(vec!["csrf"], before.cut.written().collect::<Vec<&str>>());
+        assert_eq!("GET /csrf", before.request.get_uri());
+        assert_eq!(None, before.before_frame());
+    }
+
+    #[test]
+    fn test_builtin_hydrate() {
+        let mut reg = register!({"USER_ID"=> "BIG_BEN"});
+        reg.write_builtin("RUN_ID", Value::String("a1b2".to_string()));
+        let mut frame = Frame::new(
+            r#"
+{
+  "protocol": "HTTP",
+  "cut": {"from": ["USER_ID"]},
+  "request": {"uri": "GET /users/${USER_ID}", "header": {"X-Run-Id": "${@RUN_ID}"}},
+  "response": {"status": 200}
+}
+    "#,
+        )
+        .unwrap();
+        frame.hydrate(&reg, false).unwrap();
+        assert_eq!(
+            Some(&json!({"X-Run-Id": "a1b2"})),
+            frame.request.get_header().as_ref()
+        );
+        // builtin variables are never written to a cut file
+        assert_eq!(
+            json!({"USER_ID": "BIG_BEN"}),
+            serde_json::to_value(&reg).unwrap()
+        );
+    }
+
+    #[rstest(
+        latency,
+        expected,
+        case(json!({"p50": 50}), vec![]),
+        case(json!({"p50": 49, "p99": 100}), vec![(50, 49, 50)]),
+        case(json!({"p95": 90, "p99": 99}), vec![(95, 90, 100), (99, 99, 100)])
+    )]
+    fn test_latency_exceeded(latency: Value, expected: Vec<(u8, u64, u64)>) {
+        let latency: Latency = serde_json::from_value(latency).unwrap();
+        let durations: Vec<Duration> = (1..=10).map(|n| Duration::from_millis(n * 10)).collect();
+        let ms = Duration::from_millis;
+        assert_eq!(
+            expected
+                .into_iter()
+                .map(|(p, budget, observed)| (p, ms(budget), ms(observed)))
+                .collect::<Vec<_>>(),
+            latency.exceeded(&durations)
+        );
+        assert!(latency.exceeded(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_try_from_path_err() {
+        let path = std::env::temp_dir().join("usr.01s.malformed.fr.json");
+        std::fs::write(&path, "{\n  \"protocol\": \"HTTP\",\n}").unwrap();
+        match Frame::try_from(path.clone()).unwrap_err() {
+            FrError::File(err_path, msg) => {
+                assert_eq!(path.to_string_lossy(), err_path);
+                assert!(msg.contains("line 3 column 1"), "{}", msg);
+            }
+            err => panic!("unexpected error: {err:?}"),
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_selector_hydrate_err() {
+        let reg = register!({
+            "INDEX"=> 0,
+            "KEY"=> "users"
+        });
+        let mut frame: Frame = Frame::new(SELECTOR_VAR_JSON).unwrap();
+        assert_eq!(
+            FrError::TypeMismatch(
+                INVALID_SELECTOR_HYDRATION_ERR,
+                "'response'.'body'.'users'.[${INDEX}].'id'".to_string()
+            ),
+            frame.hydrate(&reg, false).unwrap_err()
+        );
+    }
+
+    #[rstest(
+        frame,
+        expected,
+        case(
+            r#"{"protocol": "gRPC", "request": {"uri": "a.B/C", "body": {}}, "response": {"status": 0}}"#,
+            Ok(None)
+        ),
+        case(
+            r#"{"protocol": "gRPC", "request": {"uri": "a.B/C", "body": [{"n": 1}]}, "response": {"status": 0}}"#,
+            Ok(Some((StreamMode::Client, vec![json!({"n": 1})])))
+        ),
+        case(
+            r#"{"protocol": "gRPC", "request": {"uri": "a.B/C", "body": [], "stream": "bidi"}, "response": {"status": 0}}"#,
+            Ok(Some((StreamMode::Bidi, vec![])))
+        ),
+        case(
+            r#"{"protocol": "gRPC", "request": {"uri": "a.B/C", "body": "${MSGS}", "stream": "bidi"}, "response": {"status": 0}}"#,
+            Err(FrError::FrameParsef(STREAM_BODY_ERR, "Bidi".to_string()))
+        )
+    )]
+    fn test_get_stream(frame: &str, expected: Result<Option<(StreamMode, Vec<Value>)>, FrError>) {
+        let frame = Frame::new(frame).unwrap();
+        assert_eq!(
+            expected,
+            frame
+                .request
+                .get_stream()
+                .map(|s| s.map(|(mode, messages)| (mode, messages.to_vec())))
+        );
+    }
+
+    #[test]
+    fn test_stream_protocol() {
+        assert_eq!(
+            FrError::FrameParsef(STREAM_PROTOCOL_ERR, "Client".to_string()),
+            Frame::new(
+                r#"{"protocol": "HTTP", "request": {"uri": "POST /", "body": [], "stream": "client"}, "response": {"status": 200}}"#
+            )
+            .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_instruction_set_validate_generate() {
+        let mut set = InstructionSet {
+            reads: from!["ORDER_ID"],
+            ..Default::default()
+        };
+        set.generate.insert("ORDER_ID".into(), Generate::Uuid);
+        assert_eq!(
+            FrError::FrameParsef(DUPE_GENERATE_REFERENCE_ERR, "ORDER_ID".to_string()),
+            set.validate().unwrap_err()
+        );
+    }
+
     #[test]
     fn test_instruction_set_validate() {
         let set = InstructionSet {
             reads: from!["USER_ID"],
             writes: to! ({"USER_ID"=> "'response'.'body'.'id'"}),
             hydrate_writes: false,
+            ..Default::default()
         };
         assert!(set.validate().is_err());
     }
+
+    #[test]
+    fn test_sensitive_writes() {
+        let set: InstructionSet = serde_json::from_value(json!({
+            "to": {
+                "AUTH": "'response'.'header'.'authorization'",
+                "COOKIE": "'response'.'header'.'Set-Cookie'",
+                "ACCESS_TOKEN": "'response'.'body'.'data'.[0].'access_token'",
+                "TOKEN_TYPE": "'response'.'body'.'token_type'",
+                "CLIENT_SECRET": "'response'.'body'.'clientSecret'",
+                "USER_ID": "'response'.'body'.'id'",
+                "BODY": "'response'.'body'"
+            },
+            "reveal": ["TOKEN_TYPE"]
+        }))
+        .unwrap();
+        let mut sensitive: Vec<&str> = set.sensitive_writes().collect();
+        sensitive.sort();
+        assert_eq!(
+            vec!["ACCESS_TOKEN", "AUTH", "CLIENT_SECRET", "COOKIE"],
+            sensitive
+        );
+    }
 }
@@ -18,6 +18,7 @@ filmreel = "0.7"
 
 */
 
+pub mod builder;
 pub mod cut;
 mod error;
 pub mod frame;
@@ -30,7 +31,7 @@ pub mod vreel;
 mod serde_tests;
 
 pub use cut::Register;
-pub use error::{FrError, WithPath};
+pub use error::{ErrorCode, FrError, WithPath};
 pub use frame::Frame;
 pub use reel::{MetaFrame, Reel};
 pub use response::Response;
@@ -49,6 +50,18 @@ where
     Ok(json_string)
 }
 
+/// Reads the frame, cut, or vreel file at the given path. With the `jsonc` feature enabled the
+/// file may hold `//` and `/* */` comments and trailing commas, see [`utils::strip_jsonc`].
+pub fn read_json_file<P>(path: P) -> Result<String, FrError>
+where
+    P: AsRef<Path>,
+{
+    let json_string = fs::read_to_string(&path).with_path(&path)?;
+    #[cfg(feature = "jsonc")]
+    let json_string = utils::strip_jsonc(&json_string);
+    Ok(json_string)
+}
+
 // Convenience in converting a Path to a BufReader
 pub fn file_to_reader<P>(path: P) -> Result<io::BufReader<fs::File>, FrError>
 where
@@ -60,6 +73,7 @@ where
 }
 
 pub trait ToStringHidden: ToStringPretty {
+    /// Pretty formatting that presents hidden values as `${_HIDDEN}`
     fn to_string_hidden(&self) -> Result<String, FrError>;
 }
 
@@ -67,6 +81,12 @@ pub trait ToStringPretty {
     fn to_string_pretty(&self) -> Result<String, FrError>;
 }
 
+/// Serialization for files kept under version control such as take receipts, the keys of every
+/// object are sorted regardless of the map types serialized and the output ends with a newline
+pub trait ToStringCanonical {
+    fn to_string_canonical(&self) -> Result<String, FrError>;
+}
+
 impl<T> ToStringPretty for T
 where
     T: ?Sized + Serialize,
@@ -76,26 +96,14 @@ where
     }
 }
 
-impl<T> ToStringHidden for T
+impl<T> ToStringCanonical for T
 where
     T: ?Sized + Serialize,
 {
-    /// Pretty formatting for Register serialization, any cut variable names starting with an underscore are
-    /// presented as `${_HIDDEN}` in stdout
-    fn to_string_hidden(&self) -> Result<String, FrError> {
-        let val = match serde_json::to_value(self)? {
-            serde_json::Value::Object(mut map) => {
-                for (k, v) in map.iter_mut() {
-                    if k.starts_with('_') {
-                        *v = serde_json::Value::String("${_HIDDEN}".to_string());
-                    }
-                }
-                serde_json::Value::Object(map)
-            }
-            i => i,
-        };
-        let str_val = serde_json::to_string_pretty(&val)?;
-        Ok(str_val)
+    fn to_string_canonical(&self) -> Result<String, FrError> {
+        let mut val = serde_json::to_value(self)?;
+        val.sort_all_objects();
+        Ok(serde_json::to_string_pretty(&val)? + "\n")
     }
 }
 
@@ -1,7 +1,11 @@
-use crate::error::FrError;
+use crate::{
+    error::{FrError, WithPath},
+    frame::Frame,
+};
 use glob::glob;
+use serde::Deserialize;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
     convert::TryFrom,
     ffi::OsStr,
     iter::FromIterator,
@@ -21,6 +25,82 @@ pub struct Reel {
 const SEQUENCE_DUPE_ERR: &str = "Associated frames cannot share the same sequence number";
 const METAFRAME_DELIMIT_ERR: &str =
     "Frame filename mast have exactly 3 period delimited sections preceding '.fr.json'";
+const SHARED_FRAME_ERR: &str = "Shared frame in reel manifest is not a file";
+const GROUP_DUPE_ERR: &str = "Sequence number cannot belong to more than one frame group";
+const GROUP_SPLIT_ERR: &str = "Frames of a frame group must be consecutive in the reel";
+
+/// ReelManifest is the optional `<reel_name>.manifest.json` file of a reel directory, listing
+/// frames of other reel directories that are run as frames of the reel. Each key is the local
+/// sequence number of the shared frame and each value is the frame path relative to the reel
+/// directory:
+///
+/// ```json
+/// {
+///   "frames": {
+///     "00s": "../auth/auth.01s.login.fr.json"
+///   }
+/// }
+/// ```
+///
+/// The shared frame keeps its command name, so the frame above runs as `usr.00s.login.fr.json`
+/// in the `usr` reel. Unlike a component reel, only the frames listed are run and the cut file of
+/// the other reel directory is not read.
+///
+/// `"groups"` names sets of consecutive frames by sequence number, every group starts from the
+/// cut register as it was after the frames preceding the first group, so the writes of one group
+/// are never read by another:
+///
+/// ```json
+/// {
+///   "groups": {
+///     "orders": ["02s", "03s"],
+///     "invoices": ["04s", "05se"]
+///   }
+/// }
+/// ```
+///
+/// Frames following the last group also start from the register of the frames preceding the first
+/// group.
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ReelManifest {
+    #[serde(default)]
+    pub frames: BTreeMap<String, PathBuf>,
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<String>>,
+}
+
+impl ReelManifest {
+    /// read returns the manifest of the reel, if the reel directory holds one
+    fn read<P>(dir: P, reel_name: &str) -> Result<Option<Self>, FrError>
+    where
+        P: AsRef<Path>,
+    {
+        let manifest_path = dir.as_ref().join(format!("{reel_name}.manifest.json"));
+        if !manifest_path.is_file() {
+            return Ok(None);
+        }
+        serde_json::from_str(&crate::read_json_file(&manifest_path)?)
+            .map(Some)
+            .with_path(&manifest_path)
+    }
+
+    /// group_of returns the name of the frame group that a sequence number belongs to
+    fn group_of(&self, step: &str) -> Result<Option<String>, FrError> {
+        let mut groups = self
+            .groups
+            .iter()
+            .filter(|(_, steps)| steps.iter().any(|s| s == step))
+            .map(|(name, _)| name);
+        match (groups.next(), groups.next()) {
+            (Some(first), Some(second)) => Err(FrError::ReelParsef(
+                GROUP_DUPE_ERR,
+                format!("{step} in {first} and {second}"),
+            )),
+            (group, _) => Ok(group.cloned()),
+        }
+    }
+}
 
 impl Reel {
     /// A new reel is created from a provided Path or PathBuf
@@ -30,10 +110,18 @@ impl Reel {
     {
         let dir_glob = Self::get_frame_dir_glob(&dir, reel_name);
 
-        let mut frames = Self::get_metaframes(dir_glob, range)?;
+        let mut frames = Self::get_metaframes(dir_glob, range.clone())?;
+        let manifest = ReelManifest::read(&dir, reel_name)?.unwrap_or_default();
+        frames.extend(Self::get_shared_metaframes(
+            &dir, &manifest, reel_name, range,
+        )?);
+        for frame in frames.iter_mut() {
+            frame.group = manifest.group_of(&frame.step)?;
+        }
 
-        // sort by string value since sorting by f32 is not idiomatic
-        frames.sort_by(|a, b| a.path.cmp(&b.path));
+        // sort by string value since sorting by f32 is not idiomatic, shared frames are sorted by
+        // their local filename rather than their path
+        frames.sort_by_key(|f| f.get_filename());
 
         let reel = Self {
             dir: PathBuf::from(dir.as_ref().to_str().expect("None Reel dir")),
@@ -43,6 +131,30 @@ impl Reel {
         Ok(reel)
     }
 
+    /// Creates a reel from in-memory Frames, each name is a frame filename such as
+    /// `"usr.01s.createuser.fr.json"` providing the sequence number and frame type
+    pub fn from_frames<T>(frames: Vec<(T, Frame<'static>)>) -> Result<Self, FrError>
+    where
+        T: AsRef<str>,
+    {
+        let mut frames = frames
+            .into_iter()
+            .map(|(name, frame)| {
+                let mut meta_frame = MetaFrame::try_from(&PathBuf::from(name.as_ref()))?;
+                meta_frame.frame = Some(frame);
+                Ok(meta_frame)
+            })
+            .collect::<Result<Vec<MetaFrame>, FrError>>()?;
+        frames.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let reel = Self {
+            dir: PathBuf::new(),
+            frames,
+        };
+        reel.validate()?;
+        Ok(reel)
+    }
+
     /// convenience function to get default associated cut file
     pub fn get_default_cut_path(&self) -> PathBuf {
         let reel_name = self.frames[0].reel_name.clone();
@@ -69,6 +181,19 @@ impl Reel {
                 ));
             }
         }
+        // ensure that no frame group resumes after another frame was run
+        let mut ended: HashSet<&String> = HashSet::new();
+        for (frame, next) in self.frames.iter().zip(self.frames.iter().skip(1)) {
+            if let Some(group) = next.group.as_ref().filter(|g| ended.contains(g)) {
+                return Err(FrError::ReelParsef(
+                    GROUP_SPLIT_ERR,
+                    format!("{group} resumes at {}", next.get_filename()),
+                ));
+            }
+            if frame.group != next.group {
+                ended.extend(frame.group.as_ref());
+            }
+        }
         Ok(())
     }
 
@@ -111,13 +236,45 @@ impl Reel {
         // PathBuf -> PathBuf
         // -> PathBuf
         {
-            let frame = MetaFrame::try_from(&entry)?;
+            let frame = MetaFrame::try_from(&entry).map_err(|e| with_frame_path(e, &entry))?;
             if permit_frame(frame.step_f32.trunc() as u32) {
                 frames.push(frame);
             }
         }
         Ok(frames)
     }
+
+    /// get_shared_metaframes returns the MetaFrames listed in the manifest of the reel
+    fn get_shared_metaframes<P>(
+        dir: P,
+        manifest: &ReelManifest,
+        reel_name: &str,
+        range: Option<Range<u32>>,
+    ) -> Result<Vec<MetaFrame>, FrError>
+    where
+        P: AsRef<Path>,
+    {
+        let manifest_path = dir.as_ref().join(format!("{reel_name}.manifest.json"));
+        let mut frames = Vec::new();
+        for (step, path) in manifest.frames.iter() {
+            let path = dir.as_ref().join(path);
+            if !path.is_file() {
+                return Err(FrError::ReelParsef(
+                    SHARED_FRAME_ERR,
+                    path.to_string_lossy().to_string(),
+                ));
+            }
+            let frame = MetaFrame::shared(&path, reel_name, step)
+                .map_err(|e| with_frame_path(e, &manifest_path))?;
+            if range
+                .as_ref()
+                .is_none_or(|r| r.contains(&(frame.step_f32.trunc() as u32)))
+            {
+                frames.push(frame);
+            }
+        }
+        Ok(frames)
+    }
 }
 
 impl IntoIterator for Reel {
@@ -144,15 +301,22 @@ impl IntoIterator for Reel {
 ///                     └─ Frame suffix // .fr.json
 /// ```
 ///
+/// A sequence number can end with a lowercase parallel group marker: `usr.03s_a.getuser.fr.json`
+/// and `usr.03s_b.getorder.fr.json` share the sequence number `3` and are safe to run concurrently.
 #[derive(Clone, PartialEq, Debug)]
 pub struct MetaFrame {
     pub reel_name: String,
     pub frame_type: FrameType,
     pub alt_name: Option<String>,
     pub name: String,
+    pub parallel: Option<String>,
     pub path: PathBuf,
     pub step_f32: f32,
     step: String,
+    /// the frame group of the reel manifest that the frame belongs to, see [`ReelManifest`]
+    pub group: Option<String>,
+    /// an in-memory Frame used in place of reading the frame from [`MetaFrame::path`]
+    pub frame: Option<Frame<'static>>,
 }
 
 impl TryFrom<&PathBuf> for MetaFrame {
@@ -171,7 +335,8 @@ impl TryFrom<&PathBuf> for MetaFrame {
 
         let reel_name = String::from(reel_parts.remove(0));
         let sequence_number = reel_parts.remove(0);
-        let (seq, fr_type) = parse_sequence(sequence_number)?;
+        let (sequence, parallel) = parse_parallel(sequence_number);
+        let (seq, fr_type) = parse_sequence(sequence)?;
         let name = reel_parts.remove(0);
 
         // only three indices should be present when split on '.'
@@ -183,19 +348,57 @@ impl TryFrom<&PathBuf> for MetaFrame {
             path: p.clone(),
             alt_name: None,
             name: name.to_string(),
+            parallel,
             reel_name,
             step_f32: seq,
             step: sequence_number.to_string(),
             frame_type: fr_type,
+            group: None,
+            frame: None,
         })
     }
 }
 
 impl MetaFrame {
+    /// Creates the MetaFrame of a frame shared from another reel directory, run at the sequence
+    /// number provided as a frame of the reel provided
+    pub fn shared(path: &PathBuf, reel_name: &str, step: &str) -> Result<Self, FrError> {
+        let shared = Self::try_from(path)?;
+        let (sequence, parallel) = parse_parallel(step);
+        let (seq, fr_type) = parse_sequence(sequence)?;
+        Ok(Self {
+            reel_name: reel_name.to_string(),
+            frame_type: fr_type,
+            parallel,
+            step_f32: seq,
+            step: step.to_string(),
+            ..shared
+        })
+    }
+
     fn is_success(&self) -> bool {
         self.frame_type == FrameType::Success
     }
 
+    /// Returns the in-memory Frame if present, otherwise the Frame is read from the MetaFrame path
+    pub fn to_frame(&self) -> Result<Frame<'static>, FrError> {
+        match &self.frame {
+            Some(frame) => Ok(frame.clone()),
+            None => Frame::try_from(self.path.clone()),
+        }
+    }
+
+    /// Returns true if both frames belong to different parallel groups of the same sequence
+    /// number, meaning that they can be run concurrently
+    pub fn is_concurrent_with(&self, other: &Self) -> bool {
+        match (&self.parallel, &other.parallel) {
+            (Some(group), Some(other_group)) => {
+                self.step_f32 == other.step_f32 && group != other_group
+            }
+            _ => false,
+        }
+    }
+
     // get_filename returns the str representation of the MetaFrame.path file stem
     pub fn get_filename(&self) -> String {
         format!("{}.{}.{}.fr.json", self.reel_name, self.step, self.name)
@@ -211,6 +414,19 @@ impl MetaFrame {
     }
 }
 
+// parse_parallel splits the trailing parallel group marker from a sequence number:
+// `"03s_a" => ("03s", Some("a"))`
+fn parse_parallel(seq: &str) -> (&str, Option<String>) {
+    match seq.rsplit_once('_') {
+        Some((sequence, group))
+            if !group.is_empty() && group.chars().all(|ch| ch.is_ascii_lowercase()) =>
+        {
+            (sequence, Some(group.to_string()))
+        }
+        _ => (seq, None),
+    }
+}
+
 fn parse_sequence(seq: &str) -> Result<(f32, FrameType), FrError> {
     let mut seq_chars: Vec<char> = Vec::new();
     let mut type_str: String = String::new();
@@ -256,6 +472,17 @@ fn parse_sequence(seq: &str) -> Result<(f32, FrameType), FrError> {
     Ok((seq_f32, frame_type))
 }
 
+/// with_frame_path names the path of a frame file whose name failed to parse, keeping the
+/// ReelParse variant of the error so that its error code is preserved
+fn with_frame_path(err: FrError, path: &Path) -> FrError {
+    let path = path.to_string_lossy();
+    match err {
+        FrError::ReelParse(msg) => FrError::ReelParsef(msg, path.to_string()),
+        FrError::ReelParsef(msg, item) => FrError::ReelParsef(msg, format!("{item} in {path}")),
+        err => err,
+    }
+}
+
 /// [Frame Types](https://github.com/mkatychev/filmReel/blob/master/Reel.md#frame-type)
 #[derive(Clone, PartialEq, Debug)]
 pub enum FrameType {
@@ -279,6 +506,7 @@ impl<T: AsRef<str>> From<T> for FrameType {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ErrorCode;
     use rstest::*;
 
     #[rstest(input, expected,
@@ -294,6 +522,28 @@ mod tests {
         }
     }
 
+    #[rstest(input, expected,
+        case("03s_a", ("03s", Some("a"))),
+        case("10s_1_b", ("10s_1", Some("b"))),
+        case("10s_1", ("10s_1", None)),
+        case("02se", ("02se", None)),
+        )]
+    fn test_parse_parallel(input: &str, expected: (&str, Option<&str>)) {
+        let (seq, group) = parse_parallel(input);
+        assert_eq!(expected, (seq, group.as_deref()));
+    }
+
+    #[test]
+    fn test_is_concurrent_with() {
+        let frame = |p: &str| MetaFrame::try_from(&PathBuf::from(p)).unwrap();
+        let group_a = frame("./usr.03s_a.getuser.fr.json");
+        assert_eq!(Some("a".to_string()), group_a.parallel);
+        assert!(group_a.is_concurrent_with(&frame("./usr.03s_b.getorder.fr.json")));
+        assert!(!group_a.is_concurrent_with(&frame("./usr.03s_a.getorder.fr.json")));
+        assert!(!group_a.is_concurrent_with(&frame("./usr.04s_b.getorder.fr.json")));
+        assert!(!group_a.is_concurrent_with(&frame("./usr.03s.getorder.fr.json")));
+    }
+
     #[test]
     fn test_metaframe_try_from() {
         let try_path = MetaFrame::try_from(&PathBuf::from("./reel_name.01s.frame_name.fr.json"))
@@ -303,15 +553,118 @@ mod tests {
                 frame_type: FrameType::Success,
                 name: "frame_name".to_string(),
                 alt_name: None,
+                parallel: None,
                 path: PathBuf::from("./reel_name.01s.frame_name.fr.json"),
                 reel_name: "reel_name".to_string(),
                 step: "01s".to_string(),
                 step_f32: 1.0,
+                group: None,
+                frame: None,
             },
             try_path
         );
     }
 
+    #[test]
+    fn test_from_frames() {
+        let frame = Frame::new(
+            r#"{"protocol": "HTTP", "request": {"uri": "GET /"}, "response": {"status": 200}}"#,
+        )
+        .unwrap();
+        let reel = Reel::from_frames(vec![
+            ("usr.02s.second.fr.json", frame.clone()),
+            ("usr.01s.first.fr.json", frame.clone()),
+        ])
+        .unwrap();
+        let names: Vec<String> = reel.frames.iter().map(|f| f.name.clone()).collect();
+        assert_eq!(vec!["first", "second"], names);
+        assert_eq!(frame, reel.frames[0].to_frame().unwrap());
+
+        let dupe = Reel::from_frames(vec![
+            ("usr.01s.first.fr.json", frame.clone()),
+            ("usr.01s.second.fr.json", frame),
+        ]);
+        assert!(dupe.is_err());
+    }
+
+    #[test]
+    fn test_shared_frames() {
+        let dir = std::env::temp_dir().join(format!("filmreel_shared_{}", std::process::id()));
+        let (reel_dir, shared_dir) = (dir.join("usr"), dir.join("auth"));
+        std::fs::create_dir_all(&reel_dir).unwrap();
+        std::fs::create_dir_all(&shared_dir).unwrap();
+        let frame =
+            r#"{"protocol": "HTTP", "request": {"uri": "GET /"}, "response": {"status": 200}}"#;
+        for path in [
+            shared_dir.join("auth.01s.login.fr.json"),
+            reel_dir.join("usr.01s.createuser.fr.json"),
+            reel_dir.join("usr.02s.getuser.fr.json"),
+        ] {
+            std::fs::write(path, frame).unwrap();
+        }
+        std::fs::write(
+            reel_dir.join("usr.manifest.json"),
+            r#"{"frames": {"00s": "../auth/auth.01s.login.fr.json", "01s_5": "../auth/auth.01s.login.fr.json"}}"#,
+        )
+        .unwrap();
+
+        let reel = Reel::new(&reel_dir, "usr", None).unwrap();
+        let names: Vec<String> = reel.frames.iter().map(|f| f.get_filename()).collect();
+        assert_eq!(
+            vec![
+                "usr.00s.login.fr.json",
+                "usr.01s.createuser.fr.json",
+                "usr.01s_5.login.fr.json",
+                "usr.02s.getuser.fr.json"
+            ],
+            names
+        );
+        assert_eq!(
+            reel_dir.join("../auth/auth.01s.login.fr.json"),
+            reel.frames[0].path
+        );
+
+        let reel = Reel::new(&reel_dir, "usr", Some(1..2)).unwrap();
+        assert_eq!(2, reel.frames.len());
+
+        std::fs::write(
+            reel_dir.join("usr.manifest.json"),
+            r#"{"frames": {"01s": "../auth/auth.01s.login.fr.json"}}"#,
+        )
+        .unwrap();
+        assert!(Reel::new(&reel_dir, "usr", None).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_frame_groups() {
+        let dir = std::env::temp_dir().join(format!("filmreel_groups_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let frame =
+            r#"{"protocol": "HTTP", "request": {"uri": "GET /"}, "response": {"status": 200}}"#;
+        for step in ["01s", "02s", "03s", "04se"] {
+            std::fs::write(dir.join(format!("usr.{step}.frame.fr.json")), frame).unwrap();
+        }
+        let manifest = |groups: &str| {
+            std::fs::write(
+                dir.join("usr.manifest.json"),
+                format!(r#"{{"groups": {groups}}}"#),
+            )
+            .unwrap();
+            Reel::new(&dir, "usr", None)
+        };
+
+        let reel = manifest(r#"{"orders": ["02s", "03s"], "invoices": ["04se"]}"#).unwrap();
+        let groups: Vec<Option<&str>> = reel.frames.iter().map(|f| f.group.as_deref()).collect();
+        assert_eq!(
+            vec![None, Some("orders"), Some("orders"), Some("invoices")],
+            groups
+        );
+        assert!(manifest(r#"{"orders": ["02s", "04se"]}"#).is_err());
+        assert!(manifest(r#"{"orders": ["02s"], "invoices": ["02s"]}"#).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_validate() {
         let reel = Reel {
@@ -323,6 +676,25 @@ mod tests {
         };
         assert!(reel.validate().is_ok());
     }
+    #[test]
+    fn test_get_metaframes_err() {
+        let dir = std::env::temp_dir().join(format!("reel_parse_err_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("reel.01x.frame.fr.json");
+        std::fs::write(&path, "{}").unwrap();
+
+        let err = Reel::get_metaframes(dir.join("reel.*.*.fr.json"), None).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(ErrorCode::ReelParse, err.code());
+        assert_eq!(
+            FrError::ReelParsef(
+                "Unrecognized frame type in frame sequence",
+                path.to_string_lossy().to_string()
+            ),
+            err
+        );
+    }
+
     #[test]
     fn test_validate_err() {
         let reel = Reel {
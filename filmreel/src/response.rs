@@ -4,34 +4,46 @@ use crate::{
     frame::*,
     utils::{new_mut_selector, select_value, MutSelector},
 };
-use serde::{Deserialize, Serialize};
+use serde::{ser::SerializeMap, Deserialize, Serialize};
 use serde_hashkey::{
     to_key_with_ordered_float as to_key, Error as HashError, Key, OrderedFloatPolicy as Hash,
 };
 use serde_json::{json, to_value, Map, Value};
 use std::{
     borrow::Cow,
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, HashMap, VecDeque},
+    time::Duration,
 };
 
 const INVALID_INSTRUCTION_TYPE_ERR: &str =
     "Frame write instruction did not correspond to a string object";
 
-const MISSING_SELECTION_ERR: &str = "selection missing from Frame body";
-
 /// Encapsulates the expected response payload.
 ///
 /// [Request Object](https://github.com/mkatychev/filmReel/blob/master/frame.md#request)
 #[derive(Serialize, Clone, Deserialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Response<'a> {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub body: Option<Value>,
+    /// the media type of the response body, only asserted when present in the Frame
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// the response headers keyed by name, only the headers named in the Frame are asserted. A
+    /// header sent more than once holds the array of its values.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<Map<String, Value>>,
     //
     #[serde(flatten, skip_serializing_if = "Option::is_none")]
     pub etc: Option<Value>, // https://github.com/serde-rs/serde/issues/1626
     #[serde(skip_serializing)]
     pub validation: Option<Validation<'a>>,
     pub status: u32,
+    /// the time to first byte of the response, the time elapsed until its headers were received,
+    /// never compared against a payload
+    #[serde(skip)]
+    #[cfg_attr(feature = "schema", schemars(skip))]
+    pub ttfb: Option<Duration>,
 }
 
 impl<'a> Response<'a> {
@@ -68,27 +80,9 @@ impl<'a> Response<'a> {
 
         let mut write_matches: HashMap<&str, Value> = HashMap::new();
         for (k, query) in set.writes.iter() {
-            // ensure frame jql query returns a string object
-            let frame_str = match select_value(&frame_response, query) {
-                Ok(Value::String(v)) => Ok(v),
-                Ok(_) => Err(FrError::FrameParsef(
-                    INVALID_INSTRUCTION_TYPE_ERR,
-                    query.to_string(),
-                )),
-                Err(e) => Err(e),
-            }?;
-            let payload_val = select_value(&payload_response, query)?;
-
-            if let Value::String(payload_str) = &payload_val {
-                let write_match = Register::write_match(k, &frame_str, payload_str)?;
-                if let Some(mat) = write_match {
-                    write_matches.insert(k, to_value(mat)?);
-                }
-                continue;
+            if let Some(mat) = match_write(k, query, &frame_response, &payload_response)? {
+                write_matches.insert(k, mat);
             }
-            // handle non string payload values returned by the jql query
-            Register::expect_standalone_var(k, &frame_str)?;
-            write_matches.insert(k, payload_val);
         }
 
         if write_matches.iter().next().is_some() {
@@ -98,6 +92,29 @@ impl<'a> Response<'a> {
         Ok(None)
     }
 
+    /// Returns the outcome of every write instruction found in the frame InstructionSet ordered by
+    /// Cut Variable name: the Cut Variable, its selector, and the value matched or the reason the
+    /// instruction failed to match
+    pub fn explain_writes<'s>(
+        &self,
+        set: &'s InstructionSet,
+        payload_response: &Response,
+    ) -> Result<Vec<WriteOutcome<'s>>, FrError> {
+        let frame_response: Value = self.to_frame_value()?;
+        let payload_response: Value = payload_response.to_frame_value()?;
+
+        let mut writes: Vec<WriteOutcome> = set
+            .writes
+            .iter()
+            .map(|(k, query)| {
+                let mat = match_write(k, query, &frame_response, &payload_response);
+                (k.as_ref(), query.as_ref(), mat)
+            })
+            .collect();
+        writes.sort_by(|a, b| a.0.cmp(b.0));
+        Ok(writes)
+    }
+
     /// Applies the validations using the BTree key as the Value selector
     pub fn apply_validation(&mut self, other: &mut Self) -> Result<(), FrError> {
         if self.body.is_none() || other.body.is_none() || self.validation.is_none() {
@@ -105,11 +122,50 @@ impl<'a> Response<'a> {
         }
         for (k, v) in self.validation.as_ref().unwrap().iter() {
             // if no validator operations are needed
-            if !v.partial && !v.unordered {
+            if !v.partial
+                && !v.unordered
+                && !v.subset
+                && !v.coerce
+                && !v.null_as_absent
+                && v.within_seconds_of.is_none()
+                && v.compare.is_none()
+            {
                 continue;
             }
 
             let selector = new_mut_selector(strip_query(k))?;
+            if v.null_as_absent {
+                v.apply_null_as_absent(
+                    k,
+                    &selector,
+                    self.body.as_mut().unwrap(),
+                    other.body.as_mut().unwrap(),
+                )?;
+            }
+            if v.coerce {
+                v.apply_coerce(
+                    k,
+                    &selector,
+                    self.body.as_mut().unwrap(),
+                    other.body.as_mut().unwrap(),
+                )?;
+            }
+            if let Some(within) = &v.within_seconds_of {
+                within.apply(
+                    k,
+                    &selector,
+                    self.body.as_mut().unwrap(),
+                    other.body.as_mut().unwrap(),
+                )?;
+            }
+            if let Some(compare) = &v.compare {
+                compare.apply(
+                    k,
+                    &selector,
+                    self.body.as_mut().unwrap(),
+                    other.body.as_mut().unwrap(),
+                )?;
+            }
             if v.unordered {
                 v.apply_unordered(
                     k,
@@ -126,6 +182,14 @@ impl<'a> Response<'a> {
                     other.body.as_mut().unwrap(),
                 )?;
             }
+            if v.subset {
+                v.apply_subset(
+                    k,
+                    &selector,
+                    self.body.as_mut().unwrap(),
+                    other.body.as_mut().unwrap(),
+                )?;
+            }
         }
 
         // for comparison's sake set validation to None once applying is finished
@@ -133,6 +197,128 @@ impl<'a> Response<'a> {
 
         Ok(())
     }
+
+    /// Renames the fields of the other Response body that differ from the fields of this Response
+    /// body only by their casing, such as the `user_id` proto name and the `userId` JSON name of
+    /// a protobuf field
+    pub fn apply_field_names(&self, other: &mut Self) {
+        if let (Some(body), Some(other_body)) = (&self.body, &mut other.body) {
+            rename_fields(body, other_body);
+        }
+    }
+
+    /// Drops the Content-Type of the other Response unless it is asserted by this Response,
+    /// otherwise the media type parameters (such as `charset`) are removed
+    pub fn apply_content_type(&self, other: &mut Self) {
+        other.content_type = match (&self.content_type, other.content_type.take()) {
+            (None, _) | (_, None) => None,
+            (Some(_), Some(actual)) => Some(media_type(&actual)),
+        };
+    }
+
+    /// Drops the headers of the other Response that are not asserted by this Response, header
+    /// names are matched case-insensitively and renamed to the casing of this Response
+    pub fn apply_header(&self, other: &mut Self) {
+        other.header = match (&self.header, other.header.take()) {
+            (None, _) => None,
+            (Some(_), None) => Some(Map::new()),
+            (Some(expected), Some(mut actual)) => Some(
+                expected
+                    .keys()
+                    .filter_map(|name| {
+                        let key = actual
+                            .keys()
+                            .find(|k| k.eq_ignore_ascii_case(name))?
+                            .clone();
+                        actual.remove(&key).map(|v| (name.clone(), v))
+                    })
+                    .collect(),
+            ),
+        };
+    }
+}
+
+/// match_write returns the value that a single write instruction selects from the payload
+/// response, the frame selection must be a string holding the Cut Variable declaration
+fn match_write(
+    var_name: &str,
+    query: &str,
+    frame_response: &Value,
+    payload_response: &Value,
+) -> Result<Option<Value>, FrError> {
+    // ensure frame jql query returns a string object
+    let frame_str = match select_value(frame_response, query) {
+        Ok(Value::String(v)) => Ok(v),
+        Ok(_) => Err(FrError::TypeMismatch(
+            INVALID_INSTRUCTION_TYPE_ERR,
+            query.to_string(),
+        )),
+        Err(e) => Err(e),
+    }?;
+    let payload_val = select_value(payload_response, query)?;
+
+    if let Value::String(payload_str) = &payload_val {
+        return Register::write_match(var_name, &frame_str, payload_str)?
+            .map(to_value)
+            .transpose()
+            .map_err(FrError::from);
+    }
+    // handle non string payload values returned by the jql query
+    Register::expect_standalone_var(var_name, &frame_str)?;
+    Ok(Some(payload_val))
+}
+
+fn rename_fields(expected: &Value, actual: &mut Value) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            let renames: Vec<(String, String)> = actual
+                .keys()
+                .filter(|k| !expected.contains_key(*k))
+                .filter_map(|k| {
+                    expected
+                        .keys()
+                        .find(|e| !actual.contains_key(*e) && json_name(e) == json_name(k))
+                        .map(|e| (k.clone(), e.clone()))
+                })
+                .collect();
+            for (from, to) in renames {
+                if let Some(v) = actual.remove(&from) {
+                    actual.insert(to, v);
+                }
+            }
+            for (k, v) in actual.iter_mut() {
+                if let Some(e) = expected.get(k) {
+                    rename_fields(e, v);
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            // elements beyond the expected elements are renamed after the first expected element
+            for (i, v) in actual.iter_mut().enumerate() {
+                if let Some(e) = expected.get(i).or_else(|| expected.first()) {
+                    rename_fields(e, v);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Returns the lowerCamelCase JSON name of a protobuf field name: `"user_id" => "userId"`
+fn json_name(field: &str) -> String {
+    let mut name = String::with_capacity(field.len());
+    let mut upper = false;
+    for c in field.chars() {
+        match c {
+            '_' => upper = true,
+            c if upper => {
+                name.extend(c.to_uppercase());
+                upper = false;
+            }
+            c => name.push(c),
+        }
+    }
+    name
 }
 
 // For now selector queries are only used on the response body
@@ -151,13 +337,27 @@ fn strip_query(query: &str) -> &str {
     body_query
 }
 
+/// Returns the lowercase media type of a Content-Type header value without its parameters:
+/// `"application/json; charset=utf-8" => "application/json"`
+pub fn media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
 impl Default for Response<'_> {
     fn default() -> Self {
         Self {
             body: None,
+            content_type: None,
+            header: None,
             etc: Some(json!({})),
             validation: None,
             status: 0,
+            ttfb: None,
         }
     }
 }
@@ -168,23 +368,269 @@ impl Default for Response<'_> {
 /// should always be[`Option::None`]
 impl<'a> PartialEq for Response<'a> {
     fn eq(&self, other: &Self) -> bool {
-        self.body.eq(&other.body) && self.etc.eq(&other.etc) && self.status.eq(&other.status)
+        self.body.eq(&other.body)
+            && self.content_type.eq(&other.content_type)
+            && self.header.eq(&other.header)
+            && self.etc.eq(&other.etc)
+            && self.status.eq(&other.status)
     }
 }
 
 impl<'a> Eq for Response<'a> {}
 
-type Validation<'a> = BTreeMap<Cow<'a, str>, Validator>;
+pub(crate) type Validation<'a> = BTreeMap<Cow<'a, str>, Validator>;
+
+/// WriteOutcome is the Cut Variable, the selector, and the match of a single write instruction
+pub type WriteOutcome<'a> = (&'a str, &'a str, Result<Option<Value>, FrError>);
 
 /// Validator represents one validation ruleset applied to a single JSON selection
 #[derive(Serialize, Clone, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
 #[serde(default)]
 pub struct Validator {
     partial: bool,
     unordered: bool,
+    /// every expected array element must equal a distinct element of the actual array, in any
+    /// order and with any other elements in between
+    subset: bool,
+    /// treat numbers and booleans as equal to their string representation: `"42" == 42`
+    coerce: bool,
+    /// treat an object key holding `null` as equal to an absent key
+    null_as_absent: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) within_seconds_of: Option<WithinSecondsOf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) compare: Option<CompareVar>,
+}
+
+/// WithinSecondsOf matches a response timestamp that is within `delta` seconds of the time held by
+/// a Cut Variable, for timestamps produced by the server that cannot be known exactly:
+///
+/// ```json
+/// "validation": {"'response'.'body'.'created_at'": {"within_seconds_of": {"var": "REQUEST_TIME", "delta": 5}}}
+/// ```
+///
+/// Timestamps are either RFC 3339 strings or numbers of seconds since the Unix epoch.
+#[derive(Serialize, Clone, Deserialize, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
+pub struct WithinSecondsOf {
+    /// the name of the Cut Variable holding the reference time
+    pub var: String,
+    pub delta: f64,
+    /// the reference time read from the [`Register`] during hydration
+    #[serde(skip)]
+    pub(crate) time: Option<Value>,
+}
+
+impl WithinSecondsOf {
+    /// Replaces the timestamp selected from the other body with the expected value when it is
+    /// within `delta` seconds of the reference time, a timestamp outside of the tolerance is left
+    /// to be reported as a mismatch
+    fn apply(
+        &self,
+        query: &str,
+        selector: &MutSelector,
+        self_body: &mut Value,
+        other_body: &mut Value,
+    ) -> Result<(), FrError> {
+        let time = self
+            .time
+            .as_ref()
+            .ok_or_else(|| FrError::ReadInstructionf(UNREAD_TIME_ERR, self.var.clone()))?;
+        let reference = parse_timestamp(time)
+            .ok_or_else(|| FrError::TypeMismatch(INVALID_TIME_ERR, time.to_string()))?;
+        let selection =
+            selector(self_body).ok_or_else(|| FrError::SelectorNotFound(query.to_string()))?;
+        let other_selection = match selector(other_body) {
+            Some(other_selection) => other_selection,
+            None => return Ok(()),
+        };
+        match parse_timestamp(other_selection) {
+            Some(actual) if (actual - reference).abs() <= self.delta => {
+                other_selection.clone_from(selection);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// CompareVar matches a response value against the value held by a Cut Variable, such as an
+/// `email` written by a prior frame, using the `op` comparison operator:
+///
+/// ```json
+/// "validation": {"'response'.'body'.'email'": {"compare": {"var": "EMAIL", "op": "eq"}}}
+/// ```
+#[derive(Serialize, Clone, Deserialize, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
+pub struct CompareVar {
+    /// the name of the Cut Variable holding the value compared against
+    pub var: String,
+    #[serde(default)]
+    pub op: CompareOp,
+    /// the value read from the [`Register`] during hydration
+    #[serde(skip)]
+    pub(crate) value: Option<Value>,
+}
+
+/// CompareOp is the operator of a [`CompareVar`] validation:
+///
+/// - `"eq"`: the response value equals the variable
+/// - `"ne"`: the response value differs from the variable
+/// - `"contains"`: the response string contains the variable as a substring, the response array
+///   contains the variable as an element, or the response object contains every entry of the
+///   variable object
+#[derive(Serialize, Clone, Copy, Deserialize, Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum CompareOp {
+    #[default]
+    Eq,
+    Ne,
+    Contains,
+}
+
+impl CompareOp {
+    fn compare(&self, actual: &Value, expected: &Value) -> bool {
+        match self {
+            Self::Eq => actual == expected,
+            Self::Ne => actual != expected,
+            Self::Contains => match (actual, expected) {
+                (Value::String(a), Value::String(e)) => a.contains(e.as_str()),
+                (Value::Array(a), e) => a.contains(e),
+                (Value::Object(a), Value::Object(e)) => {
+                    e.iter().all(|(k, v)| a.get(k).is_some_and(|a| a == v))
+                }
+                _ => false,
+            },
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Eq => "eq",
+            Self::Ne => "ne",
+            Self::Contains => "contains",
+        }
+    }
+}
+
+impl CompareVar {
+    /// Replaces the value selected from the other body with the expected value when the
+    /// comparison with the Cut Variable holds, a failed comparison is left to be reported as a
+    /// mismatch
+    fn apply(
+        &self,
+        query: &str,
+        selector: &MutSelector,
+        self_body: &mut Value,
+        other_body: &mut Value,
+    ) -> Result<(), FrError> {
+        let value = self
+            .value
+            .as_ref()
+            .ok_or_else(|| FrError::ReadInstructionf(UNREAD_COMPARE_ERR, self.var.clone()))?;
+        let selection =
+            selector(self_body).ok_or_else(|| FrError::SelectorNotFound(query.to_string()))?;
+        if let Some(other_selection) = selector(other_body) {
+            if self.op.compare(other_selection, value) {
+                other_selection.clone_from(selection);
+            }
+        }
+        Ok(())
+    }
+}
+
+const SUBSET_SELECTION_ERR: &str = "subset validation selectors must point to a JSON array";
+const UNREAD_TIME_ERR: &str = "within_seconds_of variable was not read from the Cut Register";
+const INVALID_TIME_ERR: &str =
+    "within_seconds_of variable is neither an RFC 3339 timestamp nor a number of seconds";
+const UNREAD_COMPARE_ERR: &str = "compare variable was not read from the Cut Register";
+
+/// Returns the number of seconds since the Unix epoch of an RFC 3339 string or a number
+fn parse_timestamp(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => chrono::DateTime::parse_from_rfc3339(s)
+            .map(|t| t.timestamp_micros() as f64 / 1_000_000.0)
+            .ok()
+            .or_else(|| s.parse().ok()),
+        _ => None,
+    }
 }
 
 impl Validator {
+    /// Returns the names of the validation rules enabled by the Validator
+    pub fn rules(&self) -> Vec<String> {
+        let mut rules: Vec<String> = [
+            (self.partial, "partial"),
+            (self.unordered, "unordered"),
+            (self.subset, "subset"),
+            (self.coerce, "coerce"),
+            (self.null_as_absent, "null_as_absent"),
+        ]
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, rule)| rule.to_string())
+        .collect();
+        if let Some(within) = &self.within_seconds_of {
+            rules.push(format!(
+                "within_seconds_of ${{{}}} ±{}s",
+                within.var, within.delta
+            ));
+        }
+        if let Some(compare) = &self.compare {
+            rules.push(format!(
+                "compare {} ${{{}}}",
+                compare.op.as_str(),
+                compare.var
+            ));
+        }
+        rules
+    }
+
+    fn apply_coerce(
+        &self,
+        query: &str,
+        selector: &MutSelector,
+        self_body: &mut Value,
+        other_body: &mut Value,
+    ) -> Result<(), FrError> {
+        let selection =
+            selector(self_body).ok_or_else(|| FrError::SelectorNotFound(query.to_string()))?;
+        if let Some(other_selection) = selector(other_body) {
+            coerce(selection, other_selection);
+        }
+        Ok(())
+    }
+
+    fn apply_null_as_absent(
+        &self,
+        query: &str,
+        selector: &MutSelector,
+        self_body: &mut Value,
+        other_body: &mut Value,
+    ) -> Result<(), FrError> {
+        let selection =
+            selector(self_body).ok_or_else(|| FrError::SelectorNotFound(query.to_string()))?;
+        if let Some(other_selection) = selector(other_body) {
+            null_as_absent(selection, other_selection);
+        }
+        Ok(())
+    }
+
     fn apply_partial(
         &self,
         query: &str,
@@ -192,8 +638,8 @@ impl Validator {
         self_body: &mut Value,
         other_body: &mut Value,
     ) -> Result<(), FrError> {
-        let selection = selector(self_body)
-            .ok_or_else(|| FrError::ReadInstructionf(MISSING_SELECTION_ERR, query.to_string()))?;
+        let selection =
+            selector(self_body).ok_or_else(|| FrError::SelectorNotFound(query.to_string()))?;
         match selection {
             Value::Object(o) => {
                 let preserve_keys = o.keys().collect::<Vec<&String>>();
@@ -259,6 +705,41 @@ impl Validator {
         Ok(())
     }
 
+    fn apply_subset(
+        &self,
+        query: &str,
+        selector: &MutSelector,
+        self_body: &mut Value,
+        other_body: &mut Value,
+    ) -> Result<(), FrError> {
+        let selection =
+            selector(self_body).ok_or_else(|| FrError::SelectorNotFound(query.to_string()))?;
+        let self_selection = match selection {
+            Value::Array(self_selection) => self_selection,
+            _ => return Err(FrError::ReadInstruction(SUBSET_SELECTION_ERR)),
+        };
+        let other_selection = match selector(other_body) {
+            Some(Value::Array(o)) => o,
+            _ => return Ok(()),
+        };
+
+        // unlike apply_unordered, elements are hashed whole so that each expected element is
+        // only matched by an equal element
+        let mut other_idx_map: HashMap<Key<Hash>, usize> = HashMap::new();
+        for v in other_selection.iter() {
+            *other_idx_map.entry(to_key(v)?).or_default() += 1;
+        }
+        for v in self_selection.iter() {
+            match other_idx_map.get_mut(&to_key(v)?) {
+                Some(count) if *count > 0 => *count -= 1,
+                // an expected element is missing, leave the mismatch to be reported
+                _ => return Ok(()),
+            }
+        }
+        other_selection.clone_from(self_selection);
+        Ok(())
+    }
+
     fn apply_unordered(
         &self,
         query: &str,
@@ -266,8 +747,8 @@ impl Validator {
         self_body: &mut Value,
         other_body: &mut Value,
     ) -> Result<(), FrError> {
-        let selection = selector(self_body)
-            .ok_or_else(|| FrError::ReadInstructionf(MISSING_SELECTION_ERR, query.to_string()))?;
+        let selection =
+            selector(self_body).ok_or_else(|| FrError::SelectorNotFound(query.to_string()))?;
         match selection {
             Value::Object(_) => Ok(()),
             Value::Array(self_selection) => {
@@ -276,33 +757,27 @@ impl Validator {
                     _ => return Ok(()),
                 };
                 // https://gist.github.com/daboross/976978d8200caf86e02acb6805961195
-                let mut other_idx_map: HashMap<Key<Hash>, Vec<usize>> = HashMap::new();
-                other_selection
-                    .iter()
-                    .enumerate()
-                    .try_for_each(|(i, v)| match hash_value(v) {
-                        Ok(k) => {
-                            // if .entry(k) returns a Vec, push i to it
-                            // if .entry(k) returns None, insert Vec::new() then push i to it
-                            other_idx_map.entry(k).or_default().push(i);
-                            Ok(())
-                        }
-                        Err(e) => Err(e),
-                    })?;
-
-                // elements removed form OtherIdxMap are put in
-                // placeholder_indices so that Other can be later drained
-                // of the appropriate Value::Null elements using `other_selection.retain(...);`
-                let mut placeholder_indices: HashSet<usize> = HashSet::new();
+                let mut other_idx_map: HashMap<Key<Hash>, VecDeque<usize>> =
+                    HashMap::with_capacity(other_selection.len());
+                for (i, v) in other_selection.iter().enumerate() {
+                    other_idx_map
+                        .entry(hash_value(v)?)
+                        .or_default()
+                        .push_back(i);
+                }
+
                 // sink collects successful matches of elements from Other
                 // in the sequence (aka ordered but non-consecutive) they are present in Self
-                // sink is then prepended to other_selection
+                // sink is then followed by the unmatched elements of Other
                 // thus all successful matches (those that are found in both Self and Other)
                 // will found at the front of other_selection in sequence
-                let mut sink: BTreeMap<usize, Value> = BTreeMap::new();
+                let mut sink: Vec<Value> = Vec::new();
+                // matched marks the elements moved from Other into the sink, leaving a Null
+                // placeholder behind so that the indices of OtherIdxMap remain valid
+                let mut matched = vec![false; other_selection.len()];
                 /*
-                remove from other_selection starting with the last index of other
-                and insert into the index of where it is found in self
+                take from other_selection the first unmatched index of an equal element
+                and push it to the sink in the order it is found in self
                 ----------------
                 Self:  [A, B, C, C]
                 Other: [B, A, C, C]
@@ -312,31 +787,25 @@ impl Validator {
                 Expected iterations:
                                                                     Sink[       ];Other[B,   A,   C,   C   ];IdxMap{B:[0],A:[1]C:[2,3]}
                 i=0 v=A:
-                OtherIdxMap[A].remove(0)->1;Null->Other[1]->Sink[0];Sink[A      ];Other[B,   Null,C,   C   ];IdxMap{B:[0],C:[2,3]     }
+                OtherIdxMap[A].pop_front()->1;Null->Other[1]->Sink;Sink[A      ];Other[B,   Null,C,   C   ];IdxMap{B:[0],C:[2,3]     }
                 i=1 v=B:
-                OtherIdxMap[B].remove(0)->0;Null->Other[0]->Sink[1];Sink[A,B    ];Other[Null,Null,C,   C   ];IdxMap{C:[2,3]           }
+                OtherIdxMap[B].pop_front()->0;Null->Other[0]->Sink;Sink[A,B    ];Other[Null,Null,C,   C   ];IdxMap{C:[2,3]           }
                 i=2 v=C:
-                OtherIdxMap[C].remove(0)->2;Null->Other[2]->Sink[2];Sink[A,B,C  ];Other[Null,Null,Null,C   ];IdxMap{C:[3]             }
+                OtherIdxMap[C].pop_front()->2;Null->Other[2]->Sink;Sink[A,B,C  ];Other[Null,Null,Null,C   ];IdxMap{C:[3]             }
                 i=3 v=C:
-                OtherIdxMap[C].remove(0)->3;Null->Other[3]->Sink[3];Sink[A,B,C,C];Other[Null,Null,Null,Null];IdxMap{                  }
+                OtherIdxMap[C].pop_front()->3;Null->Other[3]->Sink;Sink[A,B,C,C];Other[Null,Null,Null,Null];IdxMap{                  }
                 ----------------
                 */
-                for (to_idx, v) in self_selection.iter().enumerate() {
-                    let v_hash = hash_value(v)?;
-
-                    if let Some(other_indices) = other_idx_map.get_mut(&v_hash) {
-                        // remove idx from Vec so that it is not reused
-                        let from_idx = other_indices.remove(0);
-                        // retain reference of key to be removed so we can swap it
-                        // with a Null when doing substitution
-                        let mut to_value = Value::Null;
-                        // swap places with the Null in the match_sink so that values in
-                        // Other maintain a valid index reference
-                        std::mem::swap(&mut to_value, &mut other_selection[from_idx]);
-                        sink.insert(to_idx, to_value);
-                        // make sure to remove the Null after iteration
-                        placeholder_indices.insert(from_idx);
-                    }
+                for v in self_selection.iter() {
+                    let from_idx = match other_idx_map
+                        .get_mut(&hash_value(v)?)
+                        .and_then(VecDeque::pop_front)
+                    {
+                        Some(i) => i,
+                        None => continue,
+                    };
+                    sink.push(other_selection[from_idx].take());
+                    matched[from_idx] = true;
                 }
 
                 // we've found no intersections; return early
@@ -344,12 +813,15 @@ impl Validator {
                     return Ok(());
                 }
 
-                // retain other_selection elements where index is not in placeholder_indices
-                let mut i = 0;
-                other_selection.retain(|_| (!placeholder_indices.contains(&i), i += 1).0);
-                // https://stackoverflow.com/questions/47037573/how-to-prepend-a-slice-to-a-vec#answer-47037876
-                other_selection.splice(0..0, sink.into_values());
-
+                // follow the matches with the other_selection elements that were not matched
+                sink.reserve(other_selection.len() - matched.iter().filter(|m| **m).count());
+                sink.extend(
+                    other_selection
+                        .drain(..)
+                        .zip(matched)
+                        .filter_map(|(v, m)| (!m).then_some(v)),
+                );
+                *other_selection = sink;
                 Ok(())
             }
             _ => Err(FrError::ReadInstruction(
@@ -359,6 +831,64 @@ impl Validator {
     }
 }
 
+/// coerce replaces the scalars of the other Value that equal the scalar found at the same position
+/// of the expected Value once both are compared as strings
+fn coerce(expected: &Value, other: &mut Value) {
+    match (expected, other) {
+        (Value::Object(expected), Value::Object(other)) => {
+            for (k, v) in other.iter_mut() {
+                if let Some(e) = expected.get(k) {
+                    coerce(e, v);
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(other)) => {
+            for (e, v) in expected.iter().zip(other.iter_mut()) {
+                coerce(e, v);
+            }
+        }
+        (expected, other) if coerced_eq(expected, other) => other.clone_from(expected),
+        _ => (),
+    }
+}
+
+/// null_as_absent makes the null keys of the other Value match the expected Value: a null key
+/// absent from the expected Value is removed and an expected null key is added where it is absent
+fn null_as_absent(expected: &Value, other: &mut Value) {
+    match (expected, other) {
+        (Value::Object(expected), Value::Object(other)) => {
+            other.retain(|k, v| !v.is_null() || expected.contains_key(k));
+            for (k, e) in expected.iter() {
+                match other.get_mut(k) {
+                    Some(v) => null_as_absent(e, v),
+                    None if e.is_null() => {
+                        other.insert(k.clone(), Value::Null);
+                    }
+                    None => (),
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(other)) => {
+            for (e, v) in expected.iter().zip(other.iter_mut()) {
+                null_as_absent(e, v);
+            }
+        }
+        _ => (),
+    }
+}
+
+fn coerced_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(n), Value::String(s)) | (Value::String(s), Value::Number(n)) => {
+            s.trim().parse::<f64>().ok() == n.as_f64()
+        }
+        (Value::Bool(bool), Value::String(s)) | (Value::String(s), Value::Bool(bool)) => {
+            *s == bool.to_string()
+        }
+        _ => false,
+    }
+}
+
 /// hash_value hashes [Value::Object] variants using only the key elements
 /// thus partial equality can be done for the sake of ordering:
 /// `[{"this":false}, false] ~= [false, {"this":true}]`
@@ -367,24 +897,36 @@ impl Validator {
 /// `{"this":false }` will be hashed as `{"this":null}`
 fn hash_value(value: &Value) -> Result<Key<Hash>, HashError> {
     if let Value::Object(obj_map) = value {
-        let null_map: Map<String, Value> =
-            obj_map.keys().map(|k| (k.clone(), Value::Null)).collect();
-
-        return to_key(&null_map);
+        return to_key(&NullMap(obj_map));
     }
     to_key(value)
 }
 
+/// NullMap serializes the keys of an object with every value as null, hashing an object by its
+/// keys without cloning them
+struct NullMap<'a>(&'a Map<String, Value>);
+
+impl Serialize for NullMap<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for k in self.0.keys() {
+            map.serialize_entry(k, &Value::Null)?;
+        }
+        map.end()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{from, to};
+    use crate::{from, register, to};
     use rstest::*;
     use serde_json::json;
 
     #[test]
     fn test_match_payload_response() {
         let frame = Frame {
+            version: None,
             protocol: Protocol::GRPC,
             cut: InstructionSet {
                 reads: from![],
@@ -394,6 +936,7 @@ mod tests {
                     "ignore"=> "'response'.'body'.'array'.[0].'ignore'"
                 }),
                 hydrate_writes: true,
+                ..Default::default()
             },
             request: Request {
                 ..Default::default()
@@ -407,6 +950,13 @@ mod tests {
                 status: 0,
                 ..Default::default()
             },
+            severity: Severity::Error,
+            response_any_of: vec![],
+            before: None,
+            teardown: false,
+            latency: None,
+            tags: vec![],
+            meta: Metadata::default(),
         };
 
         let payload_response = Response {
@@ -490,6 +1040,186 @@ mod tests {
         }
     }
 
+    #[rstest(
+        created_at,
+        should_match,
+        case(json!("2024-05-01T12:00:03Z"), true),
+        case(json!("2024-05-01T13:59:57+02:00"), true),
+        case(json!(1714564805.5), false),
+        case(json!(1714564795), true),
+        case(json!("yesterday"), false)
+    )]
+    fn test_within_seconds_of(created_at: Value, should_match: bool) {
+        let mut frame = Frame::new(
+            r#"
+{
+  "protocol": "HTTP",
+  "cut": {"from": ["REQUEST_TIME"]},
+  "request": {"uri": "POST /users"},
+  "response": {
+    "body": {"id": 1, "created_at": "<within 5s of REQUEST_TIME>"},
+    "validation": {
+      "'response'.'body'.'created_at'": {
+        "within_seconds_of": {"var": "REQUEST_TIME", "delta": 5}
+      }
+    },
+    "status": 200
+  }
+}
+    "#,
+        )
+        .unwrap();
+        frame
+            .hydrate(&register!({"REQUEST_TIME"=>"2024-05-01T12:00:00Z"}), false)
+            .unwrap();
+        let mut payload = Response {
+            body: Some(json!({"id": 1, "created_at": created_at})),
+            status: 200,
+            ..Default::default()
+        };
+        frame.response.apply_validation(&mut payload).unwrap();
+        assert_eq!(should_match, frame.response == payload);
+    }
+
+    #[rstest(
+        op,
+        email,
+        should_match,
+        case("eq", json!("ben@example.com"), true),
+        case("eq", json!("bob@example.com"), false),
+        case("ne", json!("bob@example.com"), true),
+        case("ne", json!("ben@example.com"), false),
+        case("contains", json!("Ben <ben@example.com>"), true),
+        case("contains", json!(["bob@example.com", "ben@example.com"]), true),
+        case("contains", json!(null), false)
+    )]
+    fn test_compare(op: &str, email: Value, should_match: bool) {
+        let mut frame = Frame::new(&format!(
+            r#"
+{{
+  "protocol": "HTTP",
+  "cut": {{"from": ["ID", "EMAIL"]}},
+  "request": {{"uri": "GET /users/${{ID}}"}},
+  "response": {{
+    "body": {{"id": "${{ID}}", "email": "<{op} EMAIL>"}},
+    "validation": {{
+      "'response'.'body'.'email'": {{"compare": {{"var": "EMAIL", "op": "{op}"}}}}
+    }},
+    "status": 200
+  }}
+}}
+    "#
+        ))
+        .unwrap();
+        frame
+            .hydrate(&register!({"ID"=>"1", "EMAIL"=>"ben@example.com"}), false)
+            .unwrap();
+        let mut payload = Response {
+            body: Some(json!({"id": "1", "email": email})),
+            status: 200,
+            ..Default::default()
+        };
+        frame.response.apply_validation(&mut payload).unwrap();
+        assert_eq!(should_match, frame.response == payload);
+    }
+
+    #[test]
+    fn test_apply_field_names() {
+        let expected = Response {
+            body: Some(json!({
+                "user_id": "${USER_ID}",
+                "createdAt": 1,
+                "roles": [{"role_name": "admin"}],
+                "status": "ACTIVE"
+            })),
+            status: 0,
+            ..Default::default()
+        };
+        let mut actual = Response {
+            body: Some(json!({
+                "userId": "BIG_BEN",
+                "created_at": 1,
+                "roles": [{"roleName": "admin"}, {"roleName": "user"}],
+                "status": "ACTIVE",
+                "lastName": "Ben"
+            })),
+            status: 0,
+            ..Default::default()
+        };
+        expected.apply_field_names(&mut actual);
+        assert_eq!(
+            Some(json!({
+                "user_id": "BIG_BEN",
+                "createdAt": 1,
+                "roles": [{"role_name": "admin"}, {"role_name": "user"}],
+                "status": "ACTIVE",
+                "lastName": "Ben"
+            })),
+            actual.body
+        );
+    }
+
+    #[rstest(
+        selector,
+        actual,
+        should_match,
+        case("'response'.'body'", json!({"id": "42", "admin": "true", "tags": [1, "2"]}), true),
+        case("'response'.'body'", json!({"id": "42.0", "admin": true, "tags": ["1", 2]}), true),
+        case("'response'.'body'.'tags'", json!({"id": "42", "admin": true, "tags": [1, 2]}), false),
+        case("'response'.'body'", json!({"id": "41", "admin": "True", "tags": [1, 2]}), false)
+    )]
+    fn test_coerce_validation(selector: &str, actual: Value, should_match: bool) {
+        let mut expected = Response {
+            body: Some(json!({"id": 42, "admin": true, "tags": ["1", 2]})),
+            validation: Some(
+                [(
+                    Cow::from(selector),
+                    serde_json::from_value(json!({"coerce": true})).unwrap(),
+                )]
+                .into(),
+            ),
+            status: 200,
+            ..Default::default()
+        };
+        let mut actual = Response {
+            body: Some(actual),
+            status: 200,
+            ..Default::default()
+        };
+        expected.apply_validation(&mut actual).unwrap();
+        assert_eq!(should_match, expected == actual);
+    }
+
+    #[rstest(
+        actual,
+        should_match,
+        case(json!({"id": 1, "items": [{"note": null}]}), true),
+        case(json!({"id": 1, "name": null, "items": [{}]}), true),
+        case(json!({"id": 1, "name": "", "items": [{}]}), false),
+        case(json!({"id": 1, "items": [{"note": "n"}]}), false)
+    )]
+    fn test_null_as_absent_validation(actual: Value, should_match: bool) {
+        let mut expected = Response {
+            body: Some(json!({"id": 1, "email": null, "items": [{}]})),
+            validation: Some(
+                [(
+                    Cow::from("'response'.'body'"),
+                    serde_json::from_value(json!({"null_as_absent": true})).unwrap(),
+                )]
+                .into(),
+            ),
+            status: 200,
+            ..Default::default()
+        };
+        let mut actual = Response {
+            body: Some(actual),
+            status: 200,
+            ..Default::default()
+        };
+        expected.apply_validation(&mut actual).unwrap();
+        assert_eq!(should_match, expected == actual);
+    }
+
     const UNORDERED_FRAME: &str = r#"
 {
   "validation": {
@@ -675,4 +1405,110 @@ mod tests {
         // even it if is not a _full_ match against our initial frame
         pretty_assertions::assert_eq!(other_frame, expected_frame);
     }
+
+    #[rstest(
+        expected,
+        actual,
+        should_match,
+        case(json!(["A", "C"]), json!(["C", "B", "A"]), true),
+        case(json!(["A", "A"]), json!(["A", "B", "A"]), true),
+        case(json!(["A", "A"]), json!(["A", "B"]), false),
+        case(json!([{"id": 1}, {"id": 3}]), json!([{"id": 3}, {"id": 2}, {"id": 1}]), true),
+        case(json!([{"id": 1}, {"id": 4}]), json!([{"id": 3}, {"id": 2}, {"id": 1}]), false),
+        case(json!([]), json!(["A"]), true)
+    )]
+    fn test_subset_validation(expected: Value, actual: Value, should_match: bool) {
+        let mut frame = Response {
+            body: Some(expected),
+            validation: serde_json::from_value(json!({"'response'.'body'": {"subset": true}}))
+                .unwrap(),
+            status: 200,
+            ..Default::default()
+        };
+        let mut other_frame = Response {
+            body: Some(actual),
+            status: 200,
+            ..Default::default()
+        };
+        frame.apply_validation(&mut other_frame).unwrap();
+        assert_eq!(should_match, frame == other_frame);
+    }
+
+    #[test]
+    fn test_unordered_validation_large() {
+        // duplicated elements share the same queue of indices in other_idx_map
+        let expected: Vec<Value> = (0..50_000)
+            .map(|i| json!(format!("item_{}", i % 10)))
+            .collect();
+        let mut frame = Response {
+            body: Some(Value::Array(expected.clone())),
+            validation: serde_json::from_value(json!({"'response'.'body'": {"unordered": true}}))
+                .unwrap(),
+            status: 200,
+            ..Default::default()
+        };
+        let mut other_frame = Response {
+            body: Some(Value::Array(expected.into_iter().rev().collect())),
+            status: 200,
+            ..Default::default()
+        };
+
+        frame.apply_validation(&mut other_frame).unwrap();
+        assert_eq!(frame.body, other_frame.body);
+    }
+
+    #[rstest(
+        expected,
+        actual,
+        applied,
+        case(None, Some("application/json"), None),
+        case(Some("application/json"), None, None),
+        case(
+            Some("application/json"),
+            Some("Application/JSON; charset=utf-8"),
+            Some("application/json")
+        )
+    )]
+    fn test_apply_content_type(
+        expected: Option<&str>,
+        actual: Option<&str>,
+        applied: Option<&str>,
+    ) {
+        let frame = Response {
+            content_type: expected.map(String::from),
+            ..Default::default()
+        };
+        let mut other = Response {
+            content_type: actual.map(String::from),
+            ..Default::default()
+        };
+        frame.apply_content_type(&mut other);
+        assert_eq!(applied.map(String::from), other.content_type);
+    }
+
+    #[rstest(
+        expected,
+        actual,
+        applied,
+        case(None, Some(json!({"date": "now"})), None),
+        case(Some(json!({"Location": "/"})), None, Some(json!({}))),
+        case(
+            Some(json!({"Location": "/", "Set-Cookie": ["a=1", "b=2"]})),
+            Some(json!({"date": "now", "location": "/", "set-cookie": ["a=1", "b=2"]})),
+            Some(json!({"Location": "/", "Set-Cookie": ["a=1", "b=2"]}))
+        )
+    )]
+    fn test_apply_header(expected: Option<Value>, actual: Option<Value>, applied: Option<Value>) {
+        let as_map = |v: Option<Value>| v.and_then(|v| v.as_object().cloned());
+        let frame = Response {
+            header: as_map(expected),
+            ..Default::default()
+        };
+        let mut other = Response {
+            header: as_map(actual),
+            ..Default::default()
+        };
+        frame.apply_header(&mut other);
+        assert_eq!(as_map(applied), other.header);
+    }
 }
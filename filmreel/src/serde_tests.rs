@@ -42,6 +42,15 @@ test_ser_de!(protocol_grpc, Protocol::GRPC, PROTOCOL_GRPC_JSON);
 const PROTOCOL_HTTP_JSON: &str = r#""HTTP""#;
 test_ser_de!(protocol_http, Protocol::HTTP, PROTOCOL_HTTP_JSON);
 
+const PROTOCOL_TCP_JSON: &str = r#""TCP""#;
+test_ser_de!(protocol_tcp, Protocol::TCP, PROTOCOL_TCP_JSON);
+
+const PROTOCOL_SQL_JSON: &str = r#""SQL""#;
+test_ser_de!(protocol_sql, Protocol::SQL, PROTOCOL_SQL_JSON);
+
+const PROTOCOL_EXEC_JSON: &str = r#""Exec""#;
+test_ser_de!(protocol_exec, Protocol::Exec, PROTOCOL_EXEC_JSON);
+
 const REQUEST_JSON: &str = r#"
 {
   "body": {
@@ -77,6 +86,7 @@ test_ser_de!(
         body: Some(json!({})),
         header: Some(json!({"Authorization": "${USER_TOKEN}"})),
         entrypoint: None,
+        stream: None,
         etc: Some(json!({"id": "007"})),
         uri: json!("POST /logout/${USER_ID}"),
     },
@@ -138,10 +148,57 @@ test_ser_de!(
             "DATETIME" => ".response.body.timestamp"
         }),
         hydrate_writes: false,
+        ..Default::default()
     },
     INSTRUCTION_SET_JSON
 );
 
+const INSTRUCTION_SET_SCOPE_JSON: &str = r#"
+{
+  "to": {
+    "session_id": ".response.body.session_id"
+  },
+  "scope": "reel"
+}
+    "#;
+test_ser_de!(
+    instruction_set_scope,
+    InstructionSet {
+        writes: to!({
+            "session_id" => ".response.body.session_id"
+        }),
+        scope: Scope::Reel,
+        ..Default::default()
+    },
+    INSTRUCTION_SET_SCOPE_JSON
+);
+
+const INSTRUCTION_SET_GENERATE_JSON: &str = r#"
+{
+  "from": [
+    "USER_ID"
+  ],
+  "generate": {
+    "AMOUNT": "int",
+    "ORDER_ID": "uuid"
+  }
+}
+    "#;
+test_ser_de!(
+    instruction_set_generate,
+    InstructionSet {
+        reads: from!["USER_ID"],
+        generate: [
+            ("AMOUNT".into(), Generate::Int),
+            ("ORDER_ID".into(), Generate::Uuid)
+        ]
+        .into_iter()
+        .collect(),
+        ..Default::default()
+    },
+    INSTRUCTION_SET_GENERATE_JSON
+);
+
 const FRAME_JSON: &str = r#"
 {
   "protocol": "HTTP",
@@ -175,6 +232,7 @@ const FRAME_JSON: &str = r#"
 test_ser_de!(
     frame,
     Frame {
+        version: None,
         protocol: Protocol::HTTP,
         cut: InstructionSet {
             reads: from!["USER_ID", "USER_TOKEN"],
@@ -183,6 +241,7 @@ test_ser_de!(
                 "DATETIME" => ".response.body.timestamp"
             }),
             hydrate_writes: false,
+            ..Default::default()
         },
         request: Request {
             body: Some(json!({})),
@@ -200,6 +259,13 @@ test_ser_de!(
             status: 200,
             ..Default::default()
         },
+        severity: Severity::Error,
+        response_any_of: vec![],
+        before: None,
+        teardown: false,
+        latency: None,
+        tags: vec![],
+        meta: Metadata::default(),
     },
     FRAME_JSON
 );
@@ -217,6 +283,7 @@ const SIMPLE_FRAME_JSON: &str = r#"
 test_ser_de!(
     simple_frame,
     Frame {
+        version: None,
         protocol: Protocol::HTTP,
         cut: InstructionSet::default(),
         request: Request {
@@ -228,6 +295,85 @@ test_ser_de!(
             status: 200,
             ..Default::default()
         },
+        severity: Severity::Error,
+        response_any_of: vec![],
+        before: None,
+        teardown: false,
+        latency: None,
+        tags: vec![],
+        meta: Metadata::default(),
     },
     SIMPLE_FRAME_JSON
 );
+
+const META_FRAME_JSON: &str = r#"
+{
+  "protocol": "HTTP",
+  "description": "logs out the current user",
+  "owner": "auth-team",
+  "link": "https://example.com/runbooks/logout",
+  "severity": "warning",
+  "request": {
+    "uri": "POST /logout/${USER_ID}"
+  },
+  "response": {
+    "status": 200
+  }
+}
+    "#;
+test_ser_de!(
+    meta_frame,
+    Frame {
+        version: None,
+        protocol: Protocol::HTTP,
+        cut: InstructionSet::default(),
+        request: Request {
+            uri: json!("POST /logout/${USER_ID}"),
+            ..Default::default()
+        },
+
+        response: Response {
+            status: 200,
+            ..Default::default()
+        },
+        response_any_of: vec![],
+        before: None,
+        severity: Severity::Warning,
+        teardown: false,
+        latency: None,
+        tags: vec![],
+        meta: Metadata {
+            description: Some("logs out the current user".to_string()),
+            owner: Some("auth-team".to_string()),
+            link: Some("https://example.com/runbooks/logout".to_string()),
+        },
+    },
+    META_FRAME_JSON
+);
+
+#[test]
+fn test_to_string_canonical() {
+    use crate::ToStringCanonical;
+
+    let frame: Frame = serde_json::from_str(
+        r#"{"request": {"uri": "GET /", "header": {"b": 1, "a": 2}}, "response": {"status": 200}, "protocol": "HTTP"}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        r#"{
+  "protocol": "HTTP",
+  "request": {
+    "header": {
+      "a": 2,
+      "b": 1
+    },
+    "uri": "GET /"
+  },
+  "response": {
+    "status": 200
+  }
+}
+"#,
+        frame.to_string_canonical().unwrap()
+    );
+}
@@ -1,13 +1,20 @@
 use crate::error::FrError;
 use pest::Parser;
 use pest_derive::*;
-use serde::{Serialize, Serializer};
+use serde::{
+    de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor},
+    Serialize, Serializer,
+};
 use serde_json::Value;
 use std::{
     borrow::Cow,
+    cell::RefCell,
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
 };
 
+const DUPLICATE_KEY_ERR: &str = "JSON object holds a duplicate key";
+
 /// Serializes a HashMap into a BTreeMap, sorting key order for serialization.
 pub fn ordered_str_map<S>(
     map: &HashMap<Cow<str>, Cow<str>>,
@@ -58,10 +65,7 @@ pub fn select_value(val: &Value, query: &str) -> Result<Value, FrError> {
             Value::String(_) => Ok(v.clone()),
             v => Ok(v.clone()),
         },
-        None => Err(FrError::ReadInstructionf(
-            "get_jql_value did not return a selection: {}",
-            query.to_string(),
-        )),
+        None => Err(FrError::SelectorNotFound(query.to_string())),
     }
 }
 
@@ -179,12 +183,201 @@ pub fn new_selector(query: &str) -> Result<Selector, FrError> {
     Ok(selector_fn)
 }
 
+/// Returns the JSON pointer, line, and column of the first object key repeated within the same
+/// object, serde_json otherwise silently keeps the value of the last duplicate key. Invalid JSON is
+/// left to be reported by deserialization.
+pub fn find_duplicate_key(json_string: &str) -> Option<(String, usize, usize)> {
+    let duplicate = RefCell::new(None);
+    let mut de = serde_json::Deserializer::from_str(json_string);
+    let err = UniqueKeys {
+        pointer: String::new(),
+        duplicate: &duplicate,
+    }
+    .deserialize(&mut de)
+    .err()?;
+    duplicate
+        .into_inner()
+        .map(|pointer| (pointer, err.line(), err.column()))
+}
+
+/// Returns an error naming the JSON pointer of the first duplicate object key, see
+/// [`find_duplicate_key`]
+pub fn check_duplicate_keys(json_string: &str) -> Result<(), FrError> {
+    match find_duplicate_key(json_string) {
+        Some((pointer, line, _)) => Err(FrError::FrameParsef(
+            DUPLICATE_KEY_ERR,
+            format!("{pointer} at line {line}"),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// UniqueKeys walks a JSON value without retaining it, recording the JSON pointer of the first
+/// duplicate object key found
+struct UniqueKeys<'a> {
+    pointer: String,
+    duplicate: &'a RefCell<Option<String>>,
+}
+
+impl UniqueKeys<'_> {
+    fn child<T: fmt::Display>(&self, token: T) -> Self {
+        let token = token.to_string().replace('~', "~0").replace('/', "~1");
+        UniqueKeys {
+            pointer: format!("{}/{token}", self.pointer),
+            duplicate: self.duplicate,
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for UniqueKeys<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for UniqueKeys<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut i = 0;
+        while seq.next_element_seed(self.child(i))?.is_some() {
+            i += 1;
+        }
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut keys = HashSet::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let child = self.child(&key);
+            if !keys.insert(key) {
+                *self.duplicate.borrow_mut() = Some(child.pointer);
+                return Err(de::Error::custom(DUPLICATE_KEY_ERR));
+            }
+            map.next_value_seed(child)?;
+        }
+        Ok(())
+    }
+}
+
+/// Blanks out the `//` and `/* */` comments and the trailing commas of JSONC text so that it can
+/// be parsed as JSON. Removed characters are replaced by spaces, newlines are kept, so that the
+/// line and column of a parse error still point into the original text.
+pub fn strip_jsonc(jsonc: &str) -> String {
+    let mut chars: Vec<char> = jsonc.chars().collect();
+    let mut i = 0;
+    // index of the last comma outside of a string, blanked if a closing bracket follows it
+    let mut comma: Option<usize> = None;
+    while i < chars.len() {
+        match (chars[i], chars.get(i + 1)) {
+            ('"', _) => {
+                comma = None;
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+            }
+            ('/', Some('/')) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    chars[i] = ' ';
+                    i += 1;
+                }
+                continue;
+            }
+            ('/', Some('*')) => {
+                let end = chars[i + 2..]
+                    .windows(2)
+                    .position(|w| w == ['*', '/'])
+                    .map_or(chars.len(), |p| i + 2 + p + 2);
+                for ch in chars[i..end].iter_mut().filter(|ch| **ch != '\n') {
+                    *ch = ' ';
+                }
+                i = end;
+                continue;
+            }
+            (',', _) => comma = Some(i),
+            ('}' | ']', _) => {
+                if let Some(c) = comma.take() {
+                    chars[c] = ' ';
+                }
+            }
+            (ch, _) if ch.is_whitespace() => (),
+            _ => comma = None,
+        }
+        i += 1;
+    }
+    chars.into_iter().collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use rstest::*;
     use serde_json::value::Index;
 
+    #[rstest(
+        jsonc,
+        expected,
+        case("{\"a\": 1, // one\n}", "{\"a\": 1        \n}"),
+        case("[1, /* two */ 2,]", "[1,           2 ]"),
+        case("{\"url\": \"http://a/*b*/\", }", "{\"url\": \"http://a/*b*/\"  }"),
+        case("{\"a\": \"\\\"//\"}", "{\"a\": \"\\\"//\"}"),
+        case("/* a\nb */{}", "    \n    {}")
+    )]
+    fn test_strip_jsonc(jsonc: &str, expected: &str) {
+        assert_eq!(expected, strip_jsonc(jsonc));
+    }
+
+    #[rstest(
+        json_string,
+        expected,
+        case(r#"{"a": 1, "b": {"c": 2}}"#, None),
+        case(r#"{"a": 1, "a": 2}"#, Some(("/a", 1))),
+        case("{\n  \"a\": {\"b\": [{\"c\": 1},\n {\"c/d\": 1, \"c/d\": 2}]}\n}", Some(("/a/b/1/c~1d", 3))),
+        case(r#"[{"a": 1}, {"a": 1}]"#, None),
+        case(r#"{"a": 1, "b": "#, None)
+    )]
+    fn test_find_duplicate_key(json_string: &str, expected: Option<(&str, usize)>) {
+        assert_eq!(
+            expected.map(|(p, l)| (p.to_string(), l)),
+            find_duplicate_key(json_string).map(|(p, l, _)| (p, l))
+        );
+        assert_eq!(
+            expected.is_none(),
+            check_duplicate_keys(json_string).is_ok()
+        );
+    }
+
     const OBJ_JSON: &str = r#"
 {
   "key": {
@@ -1,8 +1,16 @@
-use crate::{cut::Register, error::FrError};
+use crate::{
+    cut::Register,
+    error::{FrError, WithPath},
+};
 use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, collections::BTreeMap, convert::TryFrom, path::PathBuf};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
 pub struct VirtualReel<'a> {
     pub name: Cow<'a, str>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -50,13 +58,14 @@ impl<'a> TryFrom<PathBuf> for VirtualReel<'a> {
     type Error = FrError;
 
     fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
-        let buf = crate::file_to_reader(path)?;
-        let vreel = serde_json::from_reader(buf)?;
+        let json_string = crate::read_json_file(&path)?;
+        let vreel = serde_json::from_str(&json_string).with_path(&path)?;
         Ok(vreel)
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum VirtualCut {
     MergeCuts(Vec<PathBuf>),
@@ -83,6 +92,7 @@ pub enum VirtualCut {
 ///
 ///  The example above will run `usr.01s.createuser.fr.json` *after* `usr.04s.validateuser.fr.json`
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum VirtualFrames<'a> {
     RenamedList(BTreeMap<Cow<'a, str>, PathBuf>),
@@ -98,9 +108,7 @@ macro_rules! vframes {
     ([$($val: expr),+]) => ({
         use ::std::path::PathBuf;
 
-        let mut vec = Vec::new();
-        $(vec.push(PathBuf::from($val));)*
-        VirtualFrames::List(vec)
+        VirtualFrames::List(vec![$(PathBuf::from($val)),*])
     });
     ({$( $key: expr => $val: expr ),*}) => {{
         use ::std::collections::BTreeMap;